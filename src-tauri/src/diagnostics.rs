@@ -0,0 +1,497 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tokio::net::TcpStream;
+use url::Url;
+
+use crate::network::{
+    current_proxy_settings, effective_network_config, EffectiveNetworkConfig, ProxySettingsReport,
+};
+use crate::platforms::common::http_client::DEFAULT_USER_AGENT;
+use crate::platforms::common::request_id::{last_error, LastError};
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::types::StreamVariant;
+use crate::platforms::common::user_agent::user_agent_for;
+use crate::platforms::common::SupportedPlatformRust;
+use crate::StreamUrlStore;
+
+const CANARY_TIMEOUT_SECONDS: u64 = 8;
+
+// 每个平台一个轻量的探测地址（只关心能否连通，不解析内容）
+const PLATFORM_CANARIES: &[(&str, &str)] = &[
+    ("douyu", "https://www.douyu.com/"),
+    (
+        "bilibili",
+        "https://api.live.bilibili.com/room/v1/Room/room_init?id=1",
+    ),
+    ("huya", "https://www.huya.com/"),
+    ("douyin", "https://live.douyin.com/"),
+];
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PlatformConnectivity {
+    pub platform: String,
+    pub reachable: bool,
+    pub latency_ms: Option<u64>,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ConnectivityReport {
+    pub platforms: Vec<PlatformConnectivity>,
+    pub proxy: ProxySettingsReport,
+}
+
+async fn probe_platform(
+    client: &reqwest::Client,
+    platform: &str,
+    url: &str,
+) -> PlatformConnectivity {
+    let started = Instant::now();
+    match client.get(url).send().await {
+        Ok(resp) => PlatformConnectivity {
+            platform: platform.to_string(),
+            // 能拿到任何 HTTP 响应即说明网络可达（403 等风控返回也算）
+            reachable: true,
+            latency_ms: Some(started.elapsed().as_millis() as u64),
+            status: Some(resp.status().as_u16()),
+            error: None,
+        },
+        Err(e) => PlatformConnectivity {
+            platform: platform.to_string(),
+            reachable: false,
+            latency_ms: None,
+            status: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+async fn probe_proxy(proxy_url: &str) -> Result<(), String> {
+    let parsed = Url::parse(proxy_url).map_err(|e| format!("Invalid proxy url: {}", e))?;
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| "Proxy url has no host".to_string())?
+        .to_string();
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    match tokio::time::timeout(
+        Duration::from_secs(CANARY_TIMEOUT_SECONDS),
+        TcpStream::connect((host.as_str(), port)),
+    )
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(format!(
+            "Failed to connect to proxy {}:{}: {}",
+            host, port, e
+        )),
+        Err(_) => Err(format!("Timed out connecting to proxy {}:{}", host, port)),
+    }
+}
+
+#[tauri::command]
+pub async fn diagnose_connectivity() -> Result<ConnectivityReport, String> {
+    run_connectivity_probes().await
+}
+
+async fn run_connectivity_probes() -> Result<ConnectivityReport, String> {
+    // 使用新建的客户端，按 API 链路的代理设置发起请求，反映真实的网络路径
+    let client = crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
+        .user_agent(DEFAULT_USER_AGENT)
+        .timeout(Duration::from_secs(CANARY_TIMEOUT_SECONDS))
+        .build()
+        .map_err(|e| format!("Failed to build client: {}", e))?;
+
+    let mut proxy = current_proxy_settings();
+    let proxy_target = proxy
+        .https_proxy
+        .clone()
+        .or_else(|| proxy.http_proxy.clone())
+        .or_else(|| proxy.all_proxy.clone());
+
+    let platform_probes = futures_util::future::join_all(
+        PLATFORM_CANARIES
+            .iter()
+            .map(|(platform, url)| probe_platform(&client, platform, url)),
+    );
+    let proxy_probe = async {
+        match proxy_target.as_deref() {
+            Some(target) => Some(probe_proxy(target).await),
+            None => None,
+        }
+    };
+    let (platforms, proxy_result) = tokio::join!(platform_probes, proxy_probe);
+
+    if let Some(result) = proxy_result {
+        proxy.proxy_reachable = Some(result.is_ok());
+        proxy.proxy_error = result.err();
+    }
+    // 连接测试用的是真实地址，之后的日志与报告只使用脱敏后的副本
+    let proxy = proxy.masked();
+
+    for item in &platforms {
+        println!(
+            "[Diagnostics] {} reachable={} latency={:?}ms status={:?} error={:?}",
+            item.platform, item.reachable, item.latency_ms, item.status, item.error
+        );
+    }
+    println!(
+        "[Diagnostics] proxy http={:?} https={:?} all={:?} reachable={:?}",
+        proxy.http_proxy, proxy.https_proxy, proxy.all_proxy, proxy.proxy_reachable
+    );
+
+    Ok(ConnectivityReport { platforms, proxy })
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct StreamDiagnostics {
+    pub status: Option<i32>,
+    pub stream_url: Option<String>,
+    pub upstream_url: Option<String>,
+    pub available_streams: Vec<StreamVariant>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LocalProxyDiagnostics {
+    pub flv_proxy_running: bool,
+    pub current_upstream: Option<String>,
+    pub current_request_id: Option<String>,
+}
+
+// “复制流信息”诊断包：前端直接把 JSON 粘贴到 issue 里
+#[derive(Serialize, Clone, Debug)]
+pub struct DiagnosticsBundle {
+    pub app_version: String,
+    pub os: String,
+    pub platform: String,
+    pub room_id: String,
+    pub normalized_room_id: String,
+    pub stream: StreamDiagnostics,
+    pub request_headers: BTreeMap<String, String>,
+    pub network: EffectiveNetworkConfig,
+    pub local_proxy: LocalProxyDiagnostics,
+    pub connectivity: Option<ConnectivityReport>,
+    pub connectivity_error: Option<String>,
+    pub last_error: Option<LastError>,
+    pub collected_at: i64,
+}
+
+fn platform_referer(platform: &SupportedPlatformRust) -> &'static str {
+    match platform {
+        SupportedPlatformRust::Douyu => "https://www.douyu.com/",
+        SupportedPlatformRust::Bilibili => "https://live.bilibili.com/",
+        SupportedPlatformRust::Douyin => "https://live.douyin.com/",
+        SupportedPlatformRust::Huya => "https://www.huya.com/",
+    }
+}
+
+// 只保留 Cookie 名称，值全部打码
+fn mask_cookie(cookie: &str) -> String {
+    cookie
+        .split(';')
+        .filter_map(|pair| {
+            let name = pair.split('=').next()?.trim();
+            (!name.is_empty()).then(|| format!("{}=***", name))
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[tauri::command]
+pub async fn collect_diagnostics(
+    app_handle: AppHandle,
+    platform: String,
+    room_id: String,
+    quality: Option<String>,
+    cookie: Option<String>,
+) -> Result<DiagnosticsBundle, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let room_id = validate_room_id(&platform, &room_id)?;
+    let mut normalized_room_id = match platform {
+        SupportedPlatformRust::Douyin => {
+            crate::platforms::douyin::web_api::normalize_douyin_live_id(&room_id)
+        }
+        _ => room_id.clone(),
+    };
+
+    let mut request_headers = BTreeMap::new();
+    request_headers.insert("User-Agent".to_string(), user_agent_for(&platform));
+    request_headers.insert(
+        "Referer".to_string(),
+        platform_referer(&platform).to_string(),
+    );
+    if let Some(c) = cookie.as_deref().filter(|c| !c.trim().is_empty()) {
+        request_headers.insert("Cookie".to_string(), mask_cookie(c));
+    }
+
+    // 解析不写入存储也不启动代理，诊断时可以对任意平台重新解析
+    let stream = {
+        let quality = quality.unwrap_or_else(|| "原画".to_string());
+        match crate::room::resolve_stream(&app_handle, &platform, &room_id, &quality, cookie).await
+        {
+            Ok(info) => {
+                if let Some(id) = info.normalized_room_id.clone() {
+                    normalized_room_id = id;
+                }
+                StreamDiagnostics {
+                    status: info.status,
+                    stream_url: info.stream_url,
+                    upstream_url: info.upstream_url,
+                    available_streams: info.available_streams.unwrap_or_default(),
+                    error: info.error_message,
+                }
+            }
+            Err(e) => StreamDiagnostics {
+                status: None,
+                stream_url: None,
+                upstream_url: None,
+                available_streams: Vec::new(),
+                error: Some(e),
+            },
+        }
+    };
+
+    let local_proxy = {
+        let store = app_handle.state::<StreamUrlStore>();
+        let url = store.url.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let request_id = store
+            .request_id
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        LocalProxyDiagnostics {
+            flv_proxy_running: app_handle
+                .state::<crate::proxy::ProxyServerHandle>()
+                .0
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .is_some(),
+            current_upstream: (!url.is_empty()).then_some(url),
+            current_request_id: (!request_id.is_empty()).then_some(request_id),
+        }
+    };
+
+    let (connectivity, connectivity_error) = match run_connectivity_probes().await {
+        Ok(report) => (Some(report), None),
+        Err(e) => (None, Some(e)),
+    };
+
+    println!(
+        "[Diagnostics] collected bundle for {} {} (stream error: {:?})",
+        platform.as_str(),
+        room_id,
+        stream.error
+    );
+    Ok(DiagnosticsBundle {
+        app_version: app_handle.package_info().version.to_string(),
+        os: std::env::consts::OS.to_string(),
+        platform: platform.as_str().to_string(),
+        room_id,
+        normalized_room_id,
+        stream,
+        request_headers,
+        network: effective_network_config(),
+        local_proxy,
+        connectivity,
+        connectivity_error,
+        last_error: last_error(),
+        collected_at: chrono::Utc::now().timestamp_millis(),
+    })
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ProxyTaskSnapshot {
+    pub running: bool,
+    pub port: Option<u16>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DanmakuListenersSnapshot {
+    pub douyu_rooms: Vec<String>,
+    pub douyin_rooms: Vec<String>,
+    pub huya_rooms: Vec<String>,
+    pub bilibili_rooms: Vec<String>,
+    // "platform:room_id" -> 订阅窗口数
+    pub subscribers: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct StateSnapshot {
+    pub generated_at: String,
+    pub flv_proxy: ProxyTaskSnapshot,
+    pub static_proxy: ProxyTaskSnapshot,
+    // 只保留 scheme/host/path，防盗链参数等查询串不导出
+    pub stream_url: Option<String>,
+    pub stream_token: Option<String>,
+    pub now_playing: Option<crate::room::NowPlaying>,
+    pub danmaku: DanmakuListenersSnapshot,
+    pub recordings_active: usize,
+    pub recordings_total: usize,
+    pub bilibili_logged_in: bool,
+    pub bilibili_cookie: Option<String>,
+    pub bilibili_w_webid_present: bool,
+    // 关注列表保存在前端，后端没有对应状态，这里不导出
+    pub network: serde_json::Value,
+}
+
+fn strip_query(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) => format!(
+            "{}://{}{}",
+            parsed.scheme(),
+            parsed.host_str().unwrap_or_default(),
+            parsed.path()
+        ),
+        Err(_) => "<unparseable>".to_string(),
+    }
+}
+
+// 代理地址中可能带有 user:pass@，递归替换所有字符串里的凭据
+pub(crate) fn mask_credentials(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(s) => {
+            if let Ok(parsed) = Url::parse(s) {
+                if !parsed.username().is_empty() || parsed.password().is_some() {
+                    *s = crate::network::mask_proxy_url(s);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(mask_credentials),
+        serde_json::Value::Object(map) => map.values_mut().for_each(mask_credentials),
+        _ => {}
+    }
+}
+
+fn proxy_task_snapshot(task: Option<&crate::proxy::ProxyServerTask>) -> ProxyTaskSnapshot {
+    ProxyTaskSnapshot {
+        running: task.is_some(),
+        port: task.map(|t| t.port),
+    }
+}
+
+// 连接仍存活的房间，按房间号排序
+fn alive_rooms(senders: &crate::platforms::common::types::DanmakuStopSenders) -> Vec<String> {
+    let mut rooms: Vec<String> = senders
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .filter(|(_, tx)| !tx.is_closed())
+        .map(|(room_id, _)| room_id.clone())
+        .collect();
+    rooms.sort();
+    rooms
+}
+
+// 汇总所有托管状态的脱敏快照，供排查问题时一次性导出。
+// 仅在 debug 构建或开启 set_debug_raw_responses 后可用
+#[tauri::command]
+pub fn dump_state(app_handle: AppHandle) -> Result<StateSnapshot, String> {
+    if !cfg!(debug_assertions) && !crate::platforms::common::debug_raw::enabled() {
+        return Err("dump_state is only available in debug builds or with debug mode on".into());
+    }
+
+    let flv_proxy = {
+        let handle = app_handle.state::<crate::proxy::ProxyServerHandle>();
+        let task = handle.0.lock().unwrap_or_else(|e| e.into_inner());
+        proxy_task_snapshot(task.as_ref())
+    };
+    let static_proxy = {
+        let handle = app_handle.state::<crate::proxy::StaticProxyServerHandle>();
+        let task = handle.0.lock().unwrap_or_else(|e| e.into_inner());
+        proxy_task_snapshot(task.as_ref())
+    };
+    let (stream_url, stream_token) = {
+        let store = app_handle.state::<StreamUrlStore>();
+        let url = store.url.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let token = store
+            .request_id
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        (
+            (!url.is_empty()).then(|| strip_query(&url)),
+            (!token.is_empty()).then_some(token),
+        )
+    };
+    let now_playing = app_handle
+        .state::<crate::room::NowPlayingState>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+
+    let mut douyu_rooms: Vec<String> = app_handle
+        .state::<crate::DouyuDanmakuHandles>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .keys()
+        .cloned()
+        .collect();
+    douyu_rooms.sort();
+    let danmaku = DanmakuListenersSnapshot {
+        douyu_rooms,
+        douyin_rooms: alive_rooms(
+            &app_handle
+                .state::<crate::platforms::common::DouyinDanmakuState>()
+                .0,
+        ),
+        huya_rooms: alive_rooms(
+            &app_handle
+                .state::<crate::platforms::common::HuyaDanmakuState>()
+                .0,
+        ),
+        bilibili_rooms: alive_rooms(
+            &app_handle
+                .state::<crate::platforms::common::BilibiliDanmakuState>()
+                .0,
+        ),
+        subscribers: crate::platforms::common::danmaku_subscribers::snapshot()
+            .into_iter()
+            .collect(),
+    };
+
+    let recordings = crate::recording::list_recordings(app_handle.state());
+    let recordings_active = recordings
+        .iter()
+        .filter(|r| r.status == crate::recording::RecordingStatus::Recording)
+        .count();
+
+    let bilibili = app_handle.state::<crate::platforms::bilibili::state::BilibiliState>();
+    let bilibili_cookie = bilibili
+        .cookie
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    let bilibili_w_webid_present = bilibili
+        .w_webid
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_some();
+
+    let mut network = serde_json::to_value(effective_network_config()).unwrap_or_default();
+    mask_credentials(&mut network);
+
+    Ok(StateSnapshot {
+        generated_at: chrono::Local::now().to_rfc3339(),
+        flv_proxy,
+        static_proxy,
+        stream_url,
+        stream_token,
+        now_playing,
+        danmaku,
+        recordings_active,
+        recordings_total: recordings.len(),
+        bilibili_logged_in: bilibili_cookie
+            .as_deref()
+            .is_some_and(|c| c.contains("SESSDATA=")),
+        bilibili_cookie: bilibili_cookie.as_deref().map(mask_cookie),
+        bilibili_w_webid_present,
+        network,
+    })
+}
@@ -0,0 +1,309 @@
+use serde::Serialize;
+
+// 只解析 FLV 头与第一个 script tag（onMetaData），拿到真实分辨率/帧率/编码后即停止
+const MAX_SNIFF_BYTES: usize = 256 * 1024;
+const FLV_TAG_HEADER_LEN: usize = 11;
+const FLV_TAG_SCRIPT: u8 = 18;
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct FlvMetadata {
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    pub framerate: Option<f64>,
+    pub video_codec_id: Option<f64>,
+    pub audio_codec_id: Option<f64>,
+}
+
+pub enum SniffResult {
+    NeedMore,
+    Found(FlvMetadata),
+    // 数据不是 FLV、首个 tag 不是 script tag 或缺少 onMetaData
+    Absent,
+}
+
+#[derive(Default)]
+pub struct FlvMetadataSniffer {
+    buf: Vec<u8>,
+}
+
+impl FlvMetadataSniffer {
+    pub fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> SniffResult {
+        self.buf.extend_from_slice(chunk);
+        match parse_flv_metadata(&self.buf) {
+            Some(result) => result,
+            None if self.buf.len() >= MAX_SNIFF_BYTES => SniffResult::Absent,
+            None => SniffResult::NeedMore,
+        }
+    }
+}
+
+// 返回 None 表示数据还不够
+fn parse_flv_metadata(data: &[u8]) -> Option<SniffResult> {
+    if data.len() < 9 {
+        return None;
+    }
+    if &data[0..3] != b"FLV" {
+        return Some(SniffResult::Absent);
+    }
+    let header_len = u32::from_be_bytes([data[5], data[6], data[7], data[8]]) as usize;
+    // 跳过 PreviousTagSize0
+    let tag_start = header_len + 4;
+    if data.len() < tag_start + FLV_TAG_HEADER_LEN {
+        return None;
+    }
+    let tag = &data[tag_start..];
+    if tag[0] & 0x1f != FLV_TAG_SCRIPT {
+        return Some(SniffResult::Absent);
+    }
+    let data_size = ((tag[1] as usize) << 16) | ((tag[2] as usize) << 8) | tag[3] as usize;
+    if tag.len() < FLV_TAG_HEADER_LEN + data_size {
+        return None;
+    }
+    let body = &tag[FLV_TAG_HEADER_LEN..FLV_TAG_HEADER_LEN + data_size];
+    Some(match parse_on_metadata(body) {
+        Some(meta) => SniffResult::Found(meta),
+        None => SniffResult::Absent,
+    })
+}
+
+struct Amf0Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Amf0Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let slice = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(slice)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        self.take(8)
+            .map(|b| f64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+    }
+
+    fn short_string(&mut self) -> Option<String> {
+        let len = self.u16()? as usize;
+        self.take(len)
+            .map(|b| String::from_utf8_lossy(b).into_owned())
+    }
+
+    // 读取一个 AMF0 值；只关心数字，其余类型跳过
+    fn value(&mut self, depth: usize) -> Option<Option<f64>> {
+        if depth > 8 {
+            return None;
+        }
+        match self.u8()? {
+            0x00 => self.f64().map(Some),
+            0x01 => self.take(1).map(|_| None),
+            0x02 => self.short_string().map(|_| None),
+            0x03 => self.properties(depth + 1, |_, _| {}).map(|_| None),
+            0x05 | 0x06 => Some(None),
+            0x08 => {
+                self.u32()?;
+                self.properties(depth + 1, |_, _| {}).map(|_| None)
+            }
+            0x0a => {
+                let count = self.u32()?;
+                for _ in 0..count {
+                    self.value(depth + 1)?;
+                }
+                Some(None)
+            }
+            0x0b => self.take(10).map(|_| None),
+            0x0c => {
+                let len = self.u32()? as usize;
+                self.take(len).map(|_| None)
+            }
+            _ => None,
+        }
+    }
+
+    // 读取 key/value 对直到 object-end 标记 (00 00 09)
+    fn properties<F: FnMut(&str, Option<f64>)>(
+        &mut self,
+        depth: usize,
+        mut on_prop: F,
+    ) -> Option<()> {
+        loop {
+            let key = self.short_string()?;
+            if key.is_empty() {
+                // 空 key 之后应为 0x09；部分推流端省略结束标记，此时直接结束
+                let _ = self.u8();
+                return Some(());
+            }
+            let value = self.value(depth)?;
+            on_prop(&key, value);
+        }
+    }
+}
+
+fn parse_on_metadata(body: &[u8]) -> Option<FlvMetadata> {
+    let mut reader = Amf0Reader { data: body, pos: 0 };
+    if reader.u8()? != 0x02 || reader.short_string()? != "onMetaData" {
+        return None;
+    }
+    let mut meta = FlvMetadata::default();
+    let mut on_prop = |key: &str, value: Option<f64>| match key {
+        "width" => meta.width = value,
+        "height" => meta.height = value,
+        "framerate" | "fps" => meta.framerate = meta.framerate.or(value),
+        "videocodecid" => meta.video_codec_id = value,
+        "audiocodecid" => meta.audio_codec_id = value,
+        _ => {}
+    };
+    match reader.u8()? {
+        0x08 => {
+            reader.u32()?;
+            // 截断的 onMetaData 也保留已读到的字段
+            let _ = reader.properties(1, &mut on_prop);
+        }
+        0x03 => {
+            let _ = reader.properties(1, &mut on_prop);
+        }
+        _ => return None,
+    }
+    if meta == FlvMetadata::default() {
+        None
+    } else {
+        Some(meta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amf_string(out: &mut Vec<u8>, s: &str) {
+        out.extend_from_slice(&(s.len() as u16).to_be_bytes());
+        out.extend_from_slice(s.as_bytes());
+    }
+
+    fn amf_number(out: &mut Vec<u8>, key: &str, value: f64) {
+        amf_string(out, key);
+        out.push(0x00);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+
+    // 仿照 OBS 推流首个 script tag 拼出的 ECMA array，含字符串与布尔值
+    fn obs_like_flv_header() -> Vec<u8> {
+        let mut body = vec![0x02];
+        amf_string(&mut body, "onMetaData");
+        body.push(0x08);
+        body.extend_from_slice(&12u32.to_be_bytes());
+        amf_number(&mut body, "duration", 0.0);
+        amf_number(&mut body, "width", 1920.0);
+        amf_number(&mut body, "height", 1080.0);
+        amf_number(&mut body, "videodatarate", 6000.0);
+        amf_number(&mut body, "framerate", 60.0);
+        amf_number(&mut body, "videocodecid", 7.0);
+        amf_number(&mut body, "audiodatarate", 160.0);
+        amf_number(&mut body, "audiosamplerate", 48000.0);
+        amf_string(&mut body, "stereo");
+        body.extend_from_slice(&[0x01, 0x01]);
+        amf_number(&mut body, "audiocodecid", 10.0);
+        amf_string(&mut body, "encoder");
+        body.push(0x02);
+        amf_string(&mut body, "obs-output module (libobs version 30.0.2)");
+        amf_number(&mut body, "filesize", 0.0);
+        body.extend_from_slice(&[0x00, 0x00, 0x09]);
+
+        // FLV 头（音视频）+ PreviousTagSize0
+        let mut data = b"FLV\x01\x05\x00\x00\x00\x09\x00\x00\x00\x00".to_vec();
+        data.push(FLV_TAG_SCRIPT);
+        let size = body.len() as u32;
+        data.extend_from_slice(&size.to_be_bytes()[1..]);
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(&body);
+        data.extend_from_slice(&(size + FLV_TAG_HEADER_LEN as u32).to_be_bytes());
+        // 紧随其后的视频 tag 不影响解析
+        data.extend_from_slice(&[0x09, 0x00, 0x00, 0x05]);
+        data
+    }
+
+    fn expected() -> FlvMetadata {
+        FlvMetadata {
+            width: Some(1920.0),
+            height: Some(1080.0),
+            framerate: Some(60.0),
+            video_codec_id: Some(7.0),
+            audio_codec_id: Some(10.0),
+        }
+    }
+
+    #[test]
+    fn parses_on_metadata_from_obs_like_header() {
+        let mut sniffer = FlvMetadataSniffer::new();
+        match sniffer.push(&obs_like_flv_header()) {
+            SniffResult::Found(meta) => assert_eq!(meta, expected()),
+            _ => panic!("onMetaData not found"),
+        }
+    }
+
+    #[test]
+    fn waits_for_the_whole_script_tag() {
+        let data = obs_like_flv_header();
+        let mut sniffer = FlvMetadataSniffer::new();
+        let mut found = None;
+        for (i, chunk) in data.chunks(7).enumerate() {
+            match sniffer.push(chunk) {
+                SniffResult::NeedMore => {}
+                SniffResult::Found(meta) => {
+                    found = Some((i, meta));
+                    break;
+                }
+                SniffResult::Absent => panic!("metadata reported absent"),
+            }
+        }
+        let (i, meta) = found.expect("onMetaData not found");
+        assert!(i > 0);
+        assert_eq!(meta, expected());
+    }
+
+    #[test]
+    fn streams_without_metadata_report_absent() {
+        assert!(matches!(
+            FlvMetadataSniffer::new().push(b"<html>not a stream</html>"),
+            SniffResult::Absent
+        ));
+
+        // 首个 tag 直接是视频 tag
+        let mut data = b"FLV\x01\x05\x00\x00\x00\x09\x00\x00\x00\x00".to_vec();
+        data.extend_from_slice(&[0x09, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0x17]);
+        assert!(matches!(
+            FlvMetadataSniffer::new().push(&data),
+            SniffResult::Absent
+        ));
+    }
+
+    #[test]
+    fn fps_key_is_accepted_as_framerate() {
+        let mut body = vec![0x02];
+        amf_string(&mut body, "onMetaData");
+        body.push(0x03);
+        amf_number(&mut body, "fps", 25.0);
+        body.extend_from_slice(&[0x00, 0x00, 0x09]);
+        let meta = parse_on_metadata(&body).unwrap();
+        assert_eq!(meta.framerate, Some(25.0));
+        assert_eq!(meta.width, None);
+    }
+}
@@ -0,0 +1,204 @@
+use image::{ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+
+// 缩略图边长上限：网格封面/头像用不到更大的尺寸
+pub const MAX_THUMBNAIL_EDGE: u32 = 1024;
+// 解码保护：拒绝声明尺寸过大的图片（解压炸弹），并限制解码时的内存分配
+const MAX_DECODE_EDGE: u32 = 8192;
+const MAX_DECODE_ALLOC: u64 = 128 * 1024 * 1024;
+const JPEG_QUALITY: u8 = 80;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThumbnailFormat {
+    Jpeg,
+    // image 的 WebP 编码为无损压缩，适合头像/图标等色块简单的图片
+    WebP,
+}
+
+impl ThumbnailFormat {
+    pub fn parse(value: Option<&str>) -> Option<Self> {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("") | Some("jpeg") | Some("jpg") => Some(Self::Jpeg),
+            Some("webp") => Some(Self::WebP),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::WebP => "image/webp",
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpeg",
+            Self::WebP => "webp",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ThumbnailSpec {
+    pub width: u32,
+    pub height: u32,
+    pub format: ThumbnailFormat,
+}
+
+impl ThumbnailSpec {
+    // w/h 都缺省时返回 None（保持原图）；只给一边时另一边不限，按比例缩放
+    pub fn from_query(
+        width: Option<u32>,
+        height: Option<u32>,
+        format: Option<&str>,
+    ) -> Result<Option<Self>, String> {
+        if width.is_none() && height.is_none() {
+            return Ok(None);
+        }
+        let format = ThumbnailFormat::parse(format)
+            .ok_or_else(|| format!("Unsupported image format: {}", format.unwrap_or("")))?;
+        let clamp = |v: Option<u32>| v.unwrap_or(MAX_THUMBNAIL_EDGE).clamp(1, MAX_THUMBNAIL_EDGE);
+        Ok(Some(Self {
+            width: clamp(width),
+            height: clamp(height),
+            format,
+        }))
+    }
+
+    pub fn cache_key(&self, url: &str) -> String {
+        format!(
+            "{}#thumb={}x{}.{}",
+            url,
+            self.width,
+            self.height,
+            self.format.as_str()
+        )
+    }
+}
+
+// 解码原图并缩放到 spec 限定的框内（保持比例，不放大），再按目标格式编码。
+// 编码结果不比原图小时返回 None，由调用方直接使用原图
+pub fn make_thumbnail(bytes: &[u8], spec: ThumbnailSpec) -> Result<Option<Vec<u8>>, String> {
+    let mut reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to read image: {}", e))?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DECODE_EDGE);
+    limits.max_image_height = Some(MAX_DECODE_EDGE);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    reader.limits(limits);
+    let image = reader
+        .decode()
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+
+    let image = if image.width() > spec.width || image.height() > spec.height {
+        image.thumbnail(spec.width, spec.height)
+    } else {
+        image
+    };
+
+    let mut out = Vec::new();
+    match spec.format {
+        ThumbnailFormat::Jpeg => {
+            // JPEG 不支持透明通道
+            let encoder =
+                image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY);
+            image
+                .to_rgb8()
+                .write_with_encoder(encoder)
+                .map_err(|e| format!("Failed to encode JPEG: {}", e))?;
+        }
+        ThumbnailFormat::WebP => {
+            // WebP 编码器只接受 8 位通道，统一转成 RGBA8
+            image::DynamicImage::ImageRgba8(image.to_rgba8())
+                .write_to(&mut Cursor::new(&mut out), ImageFormat::WebP)
+                .map_err(|e| format!("Failed to encode WebP: {}", e))?;
+        }
+    }
+    Ok((out.len() < bytes.len()).then_some(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, RgbImage};
+
+    fn encode(image: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut out = Vec::new();
+        image.write_to(&mut Cursor::new(&mut out), format).unwrap();
+        out
+    }
+
+    // 像素随坐标变化，避免编码器把纯色图压缩得过小
+    fn gradient(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([
+                (x * 7 % 256) as u8,
+                (y * 13 % 256) as u8,
+                ((x ^ y) % 256) as u8,
+            ])
+        }))
+    }
+
+    fn spec(width: u32, height: u32, format: ThumbnailFormat) -> ThumbnailSpec {
+        ThumbnailSpec {
+            width,
+            height,
+            format,
+        }
+    }
+
+    #[test]
+    fn downscales_into_the_box_keeping_aspect_ratio() {
+        let source = encode(&gradient(400, 200), ImageFormat::Png);
+        for format in [ThumbnailFormat::Jpeg, ThumbnailFormat::WebP] {
+            let thumb = make_thumbnail(&source, spec(100, 100, format))
+                .unwrap()
+                .expect("thumbnail is smaller than the source");
+            let decoded = image::load_from_memory(&thumb).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (100, 50));
+        }
+    }
+
+    #[test]
+    fn rejects_images_beyond_the_decode_limits() {
+        // 文件本身很小，但声明的宽度超过 MAX_DECODE_EDGE
+        let source = encode(&gradient(MAX_DECODE_EDGE + 1, 1), ImageFormat::Png);
+        let err = make_thumbnail(&source, spec(64, 64, ThumbnailFormat::Jpeg)).unwrap_err();
+        assert!(err.starts_with("Failed to decode image"), "{}", err);
+
+        assert!(make_thumbnail(b"not an image", spec(64, 64, ThumbnailFormat::Jpeg)).is_err());
+    }
+
+    #[test]
+    fn keeps_the_original_when_reencoding_is_larger() {
+        // 低质量的小 JPEG：不需要缩放，重新编码（无损 WebP / 质量 80 的 JPEG）只会更大
+        let mut source = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut source, 10)
+            .encode_image(&gradient(64, 64).to_rgb8())
+            .unwrap();
+        for format in [ThumbnailFormat::Jpeg, ThumbnailFormat::WebP] {
+            assert_eq!(make_thumbnail(&source, spec(256, 256, format)), Ok(None));
+        }
+    }
+
+    #[test]
+    fn query_without_size_keeps_the_original() {
+        assert!(ThumbnailSpec::from_query(None, None, Some("webp"))
+            .unwrap()
+            .is_none());
+        let spec = ThumbnailSpec::from_query(Some(5000), None, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (spec.width, spec.height, spec.format),
+            (
+                MAX_THUMBNAIL_EDGE,
+                MAX_THUMBNAIL_EDGE,
+                ThumbnailFormat::Jpeg
+            )
+        );
+        assert!(ThumbnailSpec::from_query(Some(10), None, Some("gif")).is_err());
+    }
+}
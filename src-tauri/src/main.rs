@@ -1,15 +1,28 @@
 // 在开发模式下允许控制台窗口
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use reqwest;
 use std::collections::HashMap;
 use std::env;
 use std::sync::{Arc, Mutex};
-use tokio::sync::oneshot;
 use tauri::Manager;
+use tokio::sync::oneshot;
+mod diagnostics;
+mod flv_metadata;
+mod image_resize;
+mod network;
 mod platforms;
 mod proxy;
-use platforms::common::{DouyinDanmakuState, FollowHttpClient, HuyaDanmakuState};
+mod recording;
+mod room;
+mod settings;
+mod window_material;
+use platforms::common::danmaku_subscribers;
+use platforms::common::request_id::{new_request_id, tag_error};
+use platforms::common::room_id::validate_room_id;
+use platforms::common::{
+    DouyinDanmakuState, FollowHttpClient, HuyaDanmakuState, SharedReqwestClient,
+    SupportedPlatformRust,
+};
 use platforms::douyin::danmu::signature::generate_douyin_ms_token;
 use platforms::douyin::fetch_douyin_partition_rooms;
 use platforms::douyin::fetch_douyin_room_info;
@@ -27,56 +40,122 @@ use platforms::huya::{fetch_huya_live_list, start_huya_danmaku_listener};
 #[derive(Default, Clone)]
 pub struct StreamUrlStore {
     pub url: Arc<Mutex<String>>,
+    // 写入当前 url 的解析请求 ID，代理日志沿用它以便串联 解析 -> 代理 -> 播放
+    pub request_id: Arc<Mutex<String>>,
 }
 
 // State for managing Douyu danmaku listener handles (stop signals)
 #[derive(Default, Clone)]
 pub struct DouyuDanmakuHandles(Arc<Mutex<HashMap<String, oneshot::Sender<()>>>>);
 
+#[derive(serde::Serialize)]
+struct DouyuStreamUrl {
+    url: String,
+    // 为 true 时只能经本地代理播放，见 proxy::upstream_needs_proxy
+    needs_proxy: bool,
+}
+
 #[tauri::command]
-async fn get_stream_url_cmd(room_id: String) -> Result<String, String> {
+async fn get_stream_url_cmd(room_id: String) -> Result<DouyuStreamUrl, String> {
+    let room_id = validate_room_id(&SupportedPlatformRust::Douyu, &room_id)?;
+    let request_id = new_request_id();
+    println!(
+        "[Douyu][req:{}] Resolving stream for room {}",
+        request_id, room_id
+    );
     // Call the actual function to fetch the stream URL from the new location
     platforms::douyu::get_stream_url(&room_id, None)
         .await
+        .map(|url| DouyuStreamUrl {
+            needs_proxy: proxy::upstream_needs_proxy(&url),
+            url,
+        })
         .map_err(|e| {
             eprintln!(
-                "[Rust Error] Failed to get stream URL for room {}: {}",
+                "[Rust Error][req:{}] Failed to get stream URL for room {}: {}",
+                request_id,
                 room_id,
                 e.to_string()
             );
-            format!("Failed to get stream URL: {}", e.to_string())
+            tag_error(
+                &request_id,
+                format!("Failed to get stream URL: {}", e.to_string()),
+            )
         })
 }
 
+// rate 为斗鱼码率编号（见 rates 的 qn），指定时优先于 quality 名称；
+// 返回实际生效的地址、线路、码率以及房间是否为竖屏
 #[tauri::command]
 async fn get_stream_url_with_quality_cmd(
     room_id: String,
     quality: String,
+    rate: Option<i32>,
     line: Option<String>,
-) -> Result<String, String> {
-    platforms::douyu::get_stream_url_with_quality(&room_id, &quality, line.as_deref())
+) -> Result<platforms::douyu::DouyuResolvedStream, String> {
+    let room_id = validate_room_id(&SupportedPlatformRust::Douyu, &room_id)?;
+    let request_id = new_request_id();
+    println!(
+        "[Douyu][req:{}] Resolving stream for room {} with quality {} rate {:?} line {:?}",
+        request_id, room_id, quality, rate, line
+    );
+    platforms::douyu::resolve_douyu_stream(&room_id, &quality, rate, line.as_deref())
         .await
         .map_err(|e| {
             eprintln!(
-                "[Rust Error] Failed to get stream URL with quality {} for room {}: {}",
+                "[Rust Error][req:{}] Failed to get stream URL with quality {} for room {}: {}",
+                request_id,
                 quality,
                 room_id,
                 e.to_string()
             );
-            format!("Failed to get stream URL with quality: {}", e.to_string())
+            tag_error(
+                &request_id,
+                format!("Failed to get stream URL with quality: {}", e.to_string()),
+            )
         })
 }
 
 // Legacy Huya stream URL command removed in favor of unified command
 
 // This is the command that should be used for setting stream URL if it interacts with StreamUrlStore
+// platform/room_id 用于同步 NowPlaying；未提供时无法确定房间，NowPlaying 被清空。
+// rate 为斗鱼实际播放的码率编号，切换线路时沿用
 #[tauri::command]
 async fn set_stream_url_cmd(
+    app_handle: tauri::AppHandle,
     url: String,
+    request_id: Option<String>,
+    platform: Option<String>,
+    room_id: Option<String>,
+    quality: Option<String>,
+    rate: Option<i32>,
     state: tauri::State<'_, StreamUrlStore>,
 ) -> Result<(), String> {
-    let mut current_url = state.url.lock().unwrap();
-    *current_url = url;
+    let room = match (platform, room_id) {
+        (Some(platform), Some(room_id)) => {
+            let platform = SupportedPlatformRust::parse(&platform)?;
+            let room_id = validate_room_id(&platform, &room_id)?;
+            Some((platform, room_id))
+        }
+        _ => None,
+    };
+    // 前端可透传解析时的请求 ID；未提供时生成新的，保证代理日志始终可关联
+    let request_id = request_id.unwrap_or_else(new_request_id);
+    proxy::bind_stream_cookies(&request_id, &url);
+    *state.url.lock().unwrap_or_else(|e| e.into_inner()) = url;
+    *state.request_id.lock().unwrap_or_else(|e| e.into_inner()) = request_id.clone();
+    match room {
+        Some((platform, room_id)) => room::set_now_playing(
+            &app_handle,
+            &platform,
+            &room_id,
+            &quality.unwrap_or_else(settings::default_quality),
+            rate,
+            &request_id,
+        ),
+        None => room::clear_now_playing(&app_handle),
+    }
     Ok(())
 }
 
@@ -87,8 +166,31 @@ async fn start_danmaku_listener(
     window: tauri::Window,
     danmaku_handles: tauri::State<'_, DouyuDanmakuHandles>,
 ) -> Result<(), String> {
-    // If a listener for this room_id already exists, stop it first.
-    if let Some(existing_sender) = danmaku_handles.0.lock().unwrap().remove(&room_id) {
+    let room_id = validate_room_id(&SupportedPlatformRust::Douyu, &room_id)?;
+    // 同一房间已有存活连接时只登记当前窗口，不抢占其他窗口的弹幕
+    let upstream_alive = danmaku_handles
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&room_id)
+        .map(|tx| !tx.is_closed())
+        .unwrap_or(false);
+    if danmaku_subscribers::subscribe("douyu", &room_id, &window, upstream_alive) {
+        println!(
+            "[Rust Main] Window {} joined existing Douyu danmaku listener for room {}",
+            window.label(),
+            room_id
+        );
+        return Ok(());
+    }
+
+    // The previous listener (if any) has exited; clean up its handle before restarting.
+    if let Some(existing_sender) = danmaku_handles
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&room_id)
+    {
         let _ = existing_sender.send(());
     }
 
@@ -96,7 +198,7 @@ async fn start_danmaku_listener(
     danmaku_handles
         .0
         .lock()
-        .unwrap()
+        .unwrap_or_else(|e| e.into_inner())
         .insert(room_id.clone(), stop_tx);
 
     let window_clone = window.clone();
@@ -122,9 +224,24 @@ async fn start_danmaku_listener(
 #[tauri::command]
 async fn stop_danmaku_listener(
     room_id: String,
+    window: tauri::Window,
     danmaku_handles: tauri::State<'_, DouyuDanmakuHandles>,
 ) -> Result<(), String> {
-    if let Some(sender) = danmaku_handles.0.lock().unwrap().remove(&room_id) {
+    // 其他窗口仍在看这个房间时保留上游连接
+    if !danmaku_subscribers::unsubscribe("douyu", Some(&room_id), Some(window.label())) {
+        println!(
+            "[Rust Main] Window {} left Douyu room {}, listener kept for remaining windows",
+            window.label(),
+            room_id
+        );
+        return Ok(());
+    }
+    if let Some(sender) = danmaku_handles
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&room_id)
+    {
         match sender.send(()) {
             Ok(_) => Ok(()),
             Err(_) => Err(format!(
@@ -137,6 +254,50 @@ async fn stop_danmaku_listener(
     }
 }
 
+// 统一的弹幕启动入口：按平台分发到各自的监听器。各平台都会先停止旧连接再启动，
+// 同一房间已有连接时只登记当前窗口；弹幕统一通过 danmaku-message 等事件发出。
+// cookie 仅 B 站使用
+#[tauri::command]
+async fn start_danmaku(
+    app_handle: tauri::AppHandle,
+    window: tauri::Window,
+    platform: String,
+    room_id: String,
+    cookie: Option<String>,
+) -> Result<(), String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let room_id = validate_room_id(&platform, &room_id)?;
+    println!(
+        "[Rust Main] start_danmaku {} room {} (window {})",
+        platform.as_str(),
+        room_id,
+        window.label()
+    );
+    room::start_room_danmaku(&app_handle, window, &platform, &room_id, cookie).await
+}
+
+// 统一的弹幕停止入口：按平台分发到对应的状态，返回是否确实停止了上游连接
+// （其他窗口仍在收看同一房间、或本就没有监听时为 false）
+#[tauri::command]
+async fn stop_danmaku(
+    app_handle: tauri::AppHandle,
+    window: tauri::Window,
+    platform: String,
+    room_id: String,
+) -> Result<bool, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let room_id = validate_room_id(&platform, &room_id)?;
+    let stopped = room::stop_room_danmaku(&app_handle, &platform, &room_id, window.label()).await;
+    println!(
+        "[Rust Main] stop_danmaku {} room {} (window {}): stopped={}",
+        platform.as_str(),
+        room_id,
+        window.label(),
+        stopped
+    );
+    Ok(stopped)
+}
+
 // search_anchor seems fine, assuming douyu::search_anchor is correct
 #[tauri::command]
 async fn search_anchor(keyword: String) -> Result<String, String> {
@@ -155,56 +316,51 @@ fn main() {
         env::set_var("GIO_USE_PROXY_RESOLVER", "dummy");
     }
 
-    // 默认启用 HTTP 代理（仅在用户未显式设置环境变量时注入），便于在受限网络环境中直接测试。
-    // 你可以通过提前设置 HTTP_PROXY / HTTPS_PROXY 覆盖此默认值。
-    const DEFAULT_HTTP_PROXY: &str = "http://192.168.1.1:8118";
-    if env::var("HTTP_PROXY").is_err() && env::var("http_proxy").is_err() {
-        env::set_var("HTTP_PROXY", DEFAULT_HTTP_PROXY);
-    }
-    if env::var("HTTPS_PROXY").is_err() && env::var("https_proxy").is_err() {
-        env::set_var("HTTPS_PROXY", DEFAULT_HTTP_PROXY);
-    }
-    if env::var("ALL_PROXY").is_err() && env::var("all_proxy").is_err() {
-        env::set_var("ALL_PROXY", DEFAULT_HTTP_PROXY);
-    }
     // 避免代理影响本地回环请求（例如本地 flv/image/hls 代理服务）。
     if env::var("NO_PROXY").is_err() && env::var("no_proxy").is_err() {
         env::set_var("NO_PROXY", "127.0.0.1,localhost");
     }
 
-    // Create a new HTTP client instance to be managed by Tauri
-    let client = reqwest::Client::builder()
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36")
-        .build()
-        .expect("Failed to create reqwest client");
-    let follow_http_client = FollowHttpClient::new().expect("Failed to create follow http client");
+    // 设置中的默认 HTTP 代理等需要写入环境变量，必须在 Tauri 启动任何线程之前应用
+    let context = tauri::generate_context!();
+    settings::load_at_startup(&context.config().identifier);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_os::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .setup(|app| {
-            // Apply macOS vibrancy to the main window when running on macOS
+            // 设置中的代理配置需先于 HTTP 客户端创建生效，客户端因此在这里创建并交给 Tauri 管理
+            settings::init();
+            let client = SharedReqwestClient::new().expect("Failed to create reqwest client");
+            let follow_http_client =
+                FollowHttpClient::new().expect("Failed to create follow http client");
+            app.manage(client); // 通用 reqwest 客户端，no_proxy 开关切换后重建
+            app.manage(follow_http_client); // 专用关注刷新客户端，避免占用默认连接池
+
+            // Apply macOS vibrancy to the main window (material is user configurable)
             #[cfg(target_os = "macos")]
             {
-                use window_vibrancy::{apply_vibrancy, NSVisualEffectMaterial};
+                let material = window_material::load_material_preference(app.handle());
                 if let Some(window) = app.get_webview_window("main") {
-                    match apply_vibrancy(&window, NSVisualEffectMaterial::HudWindow, None, None) {
-                        Ok(_) => println!("vibrancy applied successfully"),
-                        Err(e) => eprintln!("vibrancy error: {:?}", e),
+                    match window_material::apply_window_material(&window, material) {
+                        Ok(_) => println!("vibrancy applied successfully ({})", material),
+                        Err(e) => eprintln!("{}", e),
                     }
                 }
             }
             Ok(())
         })
-        .manage(client) // Manage the reqwest client
-        .manage(follow_http_client) // 专用关注刷新客户端，避免占用默认连接池
         .manage(DouyuDanmakuHandles::default()) // Manage new DouyuDanmakuHandles
         .manage(DouyinDanmakuState::default()) // Manage DouyinDanmakuState
         .manage(HuyaDanmakuState::default()) // Manage HuyaDanmakuState
         .manage(platforms::common::BilibiliDanmakuState::default()) // Manage BilibiliDanmakuState
         .manage(StreamUrlStore::default())
         .manage(proxy::ProxyServerHandle::default())
+        .manage(proxy::StaticProxyServerHandle::default())
+        .manage(room::NowPlayingState::default())
+        .manage(recording::Recordings::default())
         .manage(platforms::bilibili::state::BilibiliState::default())
         .invoke_handler(tauri::generate_handler![
             get_stream_url_cmd,
@@ -218,9 +374,14 @@ fn main() {
             stop_huya_danmaku_listener,  // Added Huya danmaku stop command
             platforms::bilibili::danmaku::start_bilibili_danmaku_listener,
             platforms::bilibili::danmaku::stop_bilibili_danmaku_listener,
+            start_danmaku,
+            stop_danmaku,
             proxy::start_proxy,
             proxy::stop_proxy,
             proxy::start_static_proxy_server,
+            proxy::set_flv_rate_limit,
+            proxy::set_upstream_allowlist,
+            proxy::add_upstream_allowed_host,
             fetch_categories,
             fetch_live_list,
             fetch_live_list_for_cate3,
@@ -232,18 +393,85 @@ fn main() {
             get_douyin_live_stream_url_with_quality,
             fetch_douyin_room_info,
             fetch_douyin_streamer_info,
+            platforms::douyin::fetch_douyin_room_by_user,
+            platforms::douyin::following::fetch_douyin_following_lives,
             fetch_huya_live_list,
             platforms::huya::danmaku::fetch_huya_join_params,
             platforms::huya::stream_url::get_huya_unified_cmd,
             platforms::bilibili::state::generate_bilibili_w_webid,
             platforms::bilibili::live_list::fetch_bilibili_live_list,
+            platforms::bilibili::areas::fetch_bilibili_areas,
             platforms::bilibili::stream_url::get_bilibili_live_stream_url_with_quality,
             platforms::bilibili::streamer_info::fetch_bilibili_streamer_info,
             platforms::bilibili::cookie::get_bilibili_cookie,
             platforms::bilibili::cookie::bootstrap_bilibili_cookie,
+            platforms::bilibili::cookie::bilibili_login_status,
             platforms::bilibili::search::search_bilibili_rooms,
             platforms::huya::search::search_huya_anchors,
+            platforms::huya::categories::fetch_huya_categories,
+            platforms::search::search_rooms,
+            platforms::live_status::is_room_live,
+            platforms::viewers::get_viewers,
+            platforms::follow_polling::start_follow_polling,
+            platforms::follow_polling::stop_follow_polling,
+            platforms::follow_polling::set_follow_notifications,
+            platforms::common::list_cache::clear_list_cache,
+            platforms::common::list_cache::set_list_cache_enabled,
+            platforms::common::danmaku_pause::pause_danmaku,
+            platforms::common::danmaku_pause::resume_danmaku,
+            platforms::common::events::set_legacy_events,
+            platforms::common::danmaku_reconnect::set_danmaku_reconnect_policy,
+            platforms::common::danmaku_history::fetch_danmaku_history,
+            platforms::common::debug_raw::set_debug_raw_responses,
+            diagnostics::diagnose_connectivity,
+            diagnostics::collect_diagnostics,
+            diagnostics::dump_state,
+            network::set_no_proxy,
+            network::set_api_proxy,
+            network::set_stream_proxy,
+            settings::get_settings,
+            settings::update_settings,
+            window_material::set_window_material,
+            room::open_room,
+            room::get_verified_stream,
+            room::resolve_auto_quality,
+            room::close_room,
+            room::export_playlist,
+            room::get_now_playing,
+            room::switch_line,
+            room::change_quality,
+            room::peek_room,
+            room::get_all_quality_urls,
+            platforms::common::user_agent::set_platform_user_agent,
+            room::set_resume_last_session,
+            room::get_last_session,
+            proxy::prewarm_stream,
+            proxy::measure_proxy_latency,
+            proxy::stop_static_proxy,
+            proxy::restart_static_proxy,
+            proxy::stream_keepalive,
+            proxy::shutdown_all,
+            proxy::stop_all_proxies,
+            proxy::prefetch_images,
+            proxy::seek_live,
+            recording::start_recording,
+            recording::stop_recording,
+            recording::list_recordings,
+            platforms::recommend::fetch_recommended,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(context)
+        .expect("error while running tauri application")
+        .run(|app_handle, event| match event {
+            tauri::RunEvent::Exit => {
+                room::persist_last_session(app_handle);
+                // 退出时释放代理端口，避免异常退出后残留占用
+                let _ = tauri::async_runtime::block_on(proxy::shutdown_all(app_handle.clone()));
+            }
+            tauri::RunEvent::WindowEvent {
+                label,
+                event: tauri::WindowEvent::Destroyed,
+                ..
+            } => danmaku_subscribers::forget_window(&label),
+            _ => {}
+        });
 }
@@ -0,0 +1,149 @@
+use once_cell::sync::Lazy;
+use reqwest::header::{REFERER, USER_AGENT};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::platforms::common::http_client::HttpClient;
+use crate::platforms::common::json_value::scalar_string;
+use crate::platforms::common::ttl_cache::TtlCache;
+use crate::platforms::common::types_rust::{
+    CommonCategoryGroupRust, CommonPlatformCategoryRust, SupportedPlatformRust,
+};
+use crate::platforms::common::user_agent::user_agent_for;
+
+const AREA_LIST_URL: &str =
+    "https://api.live.bilibili.com/room/v1/Area/getList?need_entrance=1&parent_id=0";
+// 分区列表很少变动，缓存几分钟即可
+const AREA_CACHE_TTL_SECONDS: u64 = 300;
+
+static AREA_CACHE: Lazy<TtlCache<Vec<CommonCategoryGroupRust>>> =
+    Lazy::new(|| TtlCache::new(Duration::from_secs(AREA_CACHE_TTL_SECONDS)));
+
+// B站接口中 id 有时是数字有时是字符串
+fn id_string(value: Option<&Value>) -> Option<String> {
+    scalar_string(value?).filter(|s| !s.is_empty())
+}
+
+fn str_field(value: &Value, key: &str) -> String {
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn parse_area_groups(json: &Value) -> Vec<CommonCategoryGroupRust> {
+    let Some(parents) = json.get("data").and_then(|d| d.as_array()) else {
+        return Vec::new();
+    };
+    parents
+        .iter()
+        .filter_map(|parent| {
+            let group_id = id_string(parent.get("id"))?;
+            let categories = parent
+                .get("list")
+                .and_then(|l| l.as_array())
+                .map(|children| {
+                    children
+                        .iter()
+                        .filter_map(|child| {
+                            Some(CommonPlatformCategoryRust {
+                                id: id_string(child.get("id"))?,
+                                name: str_field(child, "name"),
+                                platform: SupportedPlatformRust::Bilibili,
+                                icon_url: Some(str_field(child, "pic")).filter(|s| !s.is_empty()),
+                                parent_id: Some(group_id.clone()),
+                            })
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some(CommonCategoryGroupRust {
+                group_name: str_field(parent, "name"),
+                group_id,
+                platform: SupportedPlatformRust::Bilibili,
+                categories,
+            })
+        })
+        .collect()
+}
+
+// B站直播分区树（父分区 -> 子分区），子分区的 id/parentId 对应 fetch_bilibili_live_list 的
+// area_id/parent_area_id
+#[tauri::command]
+pub async fn fetch_bilibili_areas() -> Result<Vec<CommonCategoryGroupRust>, String> {
+    if let Some(cached) = AREA_CACHE.get("areas") {
+        return Ok(cached);
+    }
+
+    let mut http_client =
+        HttpClient::new().map_err(|e| format!("Failed to create HttpClient: {}", e))?;
+    http_client.insert_header(
+        USER_AGENT,
+        &user_agent_for(&SupportedPlatformRust::Bilibili),
+    )?;
+    http_client.insert_header(REFERER, "https://live.bilibili.com/")?;
+    let json: Value = http_client.get_json(AREA_LIST_URL).await?;
+
+    let code = json.get("code").and_then(|c| c.as_i64()).unwrap_or(-1);
+    if code != 0 {
+        return Err(format!(
+            "Bilibili area list API error. Code: {}, Msg: {}",
+            code,
+            str_field(&json, "message")
+        ));
+    }
+    let groups = parse_area_groups(&json);
+    println!("[Bilibili] Fetched {} parent areas", groups.len());
+    AREA_CACHE.insert("areas".to_string(), groups.clone());
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Area/getList 的 id 有数字与字符串两种写法
+    #[test]
+    fn parses_parent_and_child_areas() {
+        let json = serde_json::json!({
+            "code": 0,
+            "data": [
+                {
+                    "id": 2,
+                    "name": "网游",
+                    "list": [
+                        {"id": "86", "name": "英雄联盟", "pic": "https://i0.hdslb.com/lol.png"},
+                        {"id": 240, "name": "APEX英雄", "pic": ""},
+                        {"name": "缺少 id"}
+                    ]
+                },
+                {"id": "", "name": "无效分区", "list": []},
+                {"id": "9", "name": "虚拟主播"}
+            ]
+        });
+        let groups = parse_area_groups(&json);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            (groups[0].group_id.as_str(), groups[0].group_name.as_str()),
+            ("2", "网游")
+        );
+        let children = &groups[0].categories;
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].id, "86");
+        assert_eq!(
+            children[0].icon_url.as_deref(),
+            Some("https://i0.hdslb.com/lol.png")
+        );
+        assert_eq!(children[1].id, "240");
+        assert_eq!(children[1].icon_url, None);
+        assert_eq!(children[1].parent_id.as_deref(), Some("2"));
+        assert_eq!(groups[1].group_id, "9");
+        assert!(groups[1].categories.is_empty());
+    }
+
+    #[test]
+    fn missing_data_yields_no_groups() {
+        assert!(parse_area_groups(&serde_json::json!({"code": -400})).is_empty());
+    }
+}
@@ -83,11 +83,19 @@ fn _encode_wbi(
     query + &format!("&w_rid={}", web_sign)
 }
 
+// 弹幕初始化在独立线程中同步请求；no_proxy 开关打开时同样直连
+fn blocking_client() -> reqwest::blocking::Client {
+    let builder = reqwest::blocking::Client::builder().https_only(true);
+    let builder = if crate::network::is_no_proxy_enabled() {
+        builder.no_proxy()
+    } else {
+        builder
+    };
+    builder.build().unwrap()
+}
+
 fn get_wbi_keys(headers: HeaderMap) -> Result<(String, String), reqwest::Error> {
-    let client = reqwest::blocking::Client::builder()
-        .https_only(true)
-        .build()
-        .unwrap();
+    let client = blocking_client();
 
     let mut request_headers = headers;
     request_headers.insert("user-agent", USER_AGENT.parse().unwrap());
@@ -115,10 +123,7 @@ pub const USER_AGENT: &str =
 
 /// Get UID using cookie (optional). If request fails or no cookie, returns (status, body).
 pub fn init_uid(headers: HeaderMap) -> (reqwest::StatusCode, String) {
-    let client = reqwest::blocking::Client::builder()
-        .https_only(true)
-        .build()
-        .unwrap();
+    let client = blocking_client();
 
     let mut request_headers = headers;
     request_headers.insert("user-agent", USER_AGENT.parse().unwrap());
@@ -145,10 +150,7 @@ pub fn init_uid(headers: HeaderMap) -> (reqwest::StatusCode, String) {
 
 /// Query danmaku server host list and token via signed URL, with given headers
 pub fn init_host_server(headers: HeaderMap, room_id: u64) -> (reqwest::StatusCode, String) {
-    let client = reqwest::blocking::Client::builder()
-        .https_only(true)
-        .build()
-        .unwrap();
+    let client = blocking_client();
 
     let mut request_headers = headers.clone();
     request_headers.insert("user-agent", USER_AGENT.parse().unwrap());
@@ -1,10 +1,18 @@
 use ::cookie::Cookie;
 use serde::Serialize;
+use serde_json::Value;
 use std::collections::BTreeMap;
 use std::time::Duration;
-use tauri::{AppHandle, Manager, WebviewUrl};
+use tauri::{AppHandle, Manager, State, WebviewUrl};
 use url::Url;
 
+use super::state::BilibiliState;
+use crate::platforms::common::user_agent::user_agent_for;
+use crate::platforms::common::{SharedReqwestClient, SupportedPlatformRust};
+
+const NAV_URL: &str = "https://api.bilibili.com/x/web-interface/nav";
+const NAV_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Serialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct BilibiliCookieResult {
@@ -13,6 +21,37 @@ pub struct BilibiliCookieResult {
     pub has_bili_jct: bool,
 }
 
+#[derive(Debug, Serialize, Default)]
+pub struct BilibiliLoginStatus {
+    pub logged_in: bool,
+    pub uid: Option<i64>,
+    pub uname: Option<String>,
+    pub is_vip: bool,
+}
+
+// 采集到 Cookie 后记入 BilibiliState，后续命令无需前端再传
+fn remember_cookie(app_handle: &AppHandle, result: &BilibiliCookieResult) {
+    if let Some(cookie) = result.cookie.as_ref() {
+        let state = app_handle.state::<BilibiliState>();
+        *state.cookie.lock().unwrap_or_else(|e| e.into_inner()) = Some(cookie.clone());
+    }
+}
+
+// 解析 nav 接口返回；未登录（code -101）或 Cookie 失效都视为未登录
+fn parse_nav_login_status(json: &Value) -> BilibiliLoginStatus {
+    let data = &json["data"];
+    let logged_in = json["code"].as_i64() == Some(0) && data["isLogin"].as_bool() == Some(true);
+    if !logged_in {
+        return BilibiliLoginStatus::default();
+    }
+    BilibiliLoginStatus {
+        logged_in,
+        uid: data["mid"].as_i64(),
+        uname: data["uname"].as_str().map(String::from),
+        is_vip: data["vipStatus"].as_i64() == Some(1) || data["vip"]["status"].as_i64() == Some(1),
+    }
+}
+
 fn merge_bilibili_cookies(
     accumulator: &mut BTreeMap<String, String>,
     cookies: Vec<Cookie<'static>>,
@@ -130,7 +169,9 @@ pub async fn get_bilibili_cookie(
     }
 
     let labels = dedup_labels(label_list);
-    collect_cookie_from_labels(app_handle, labels, url).await
+    let result = collect_cookie_from_labels(app_handle.clone(), labels, url).await?;
+    remember_cookie(&app_handle, &result);
+    Ok(result)
 }
 
 #[tauri::command]
@@ -168,5 +209,128 @@ pub async fn bootstrap_bilibili_cookie(
         let _ = window.close();
     }
 
+    remember_cookie(&app_handle, &result);
     Ok(result)
 }
+
+// 用当前 Cookie 请求 nav，告诉前端是匿名还是已登录（用于显示登录状态与限制高画质）
+#[tauri::command]
+pub async fn bilibili_login_status(
+    state: State<'_, BilibiliState>,
+    http_client: State<'_, SharedReqwestClient>,
+    cookie: Option<String>,
+) -> Result<BilibiliLoginStatus, String> {
+    let cookie = cookie.filter(|c| !c.trim().is_empty()).or_else(|| {
+        state
+            .cookie
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    });
+    let cookie = match cookie {
+        Some(c) => c,
+        None => return Ok(BilibiliLoginStatus::default()),
+    };
+
+    let resp = http_client
+        .client()
+        .get(NAV_URL)
+        .timeout(NAV_TIMEOUT)
+        .header(
+            "User-Agent",
+            user_agent_for(&SupportedPlatformRust::Bilibili),
+        )
+        .header("Referer", "https://www.bilibili.com/")
+        .header("Cookie", cookie.trim())
+        .send()
+        .await
+        .map_err(|e| format!("nav request failed: {}", e))?;
+    let json: Value = match resp.json().await {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!(
+                "[Bilibili] nav response is not JSON, treating as logged out: {}",
+                e
+            );
+            return Ok(BilibiliLoginStatus::default());
+        }
+    };
+    let status = parse_nav_login_status(&json);
+    println!(
+        "[Bilibili] login status: logged_in={} uid={:?} vip={}",
+        status.logged_in, status.uid, status.is_vip
+    );
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 已登录账号的 nav 响应（手写，只含解析用到的字段）
+    const NAV_LOGGED_IN: &str = r#"{
+        "code": 0,
+        "message": "0",
+        "ttl": 1,
+        "data": {
+            "isLogin": true,
+            "email_verified": 0,
+            "face": "https://i0.hdslb.com/bfs/face/member/noface.jpg",
+            "level_info": {"current_level": 5},
+            "mid": 123456789,
+            "money": 12.5,
+            "uname": "测试用户",
+            "vipStatus": 1,
+            "vipType": 2,
+            "vip": {"type": 2, "status": 1, "due_date": 1767196800000}
+        }
+    }"#;
+
+    // Cookie 缺失或失效时的 nav 响应
+    const NAV_LOGGED_OUT: &str = r#"{
+        "code": -101,
+        "message": "账号未登录",
+        "ttl": 1,
+        "data": {
+            "isLogin": false,
+            "wbi_img": {
+                "img_url": "https://i0.hdslb.com/bfs/wbi/7cd084941338484aae1ad9425b84077c.png",
+                "sub_url": "https://i0.hdslb.com/bfs/wbi/4932caff0ff746eab6f01bf08b70ac45.png"
+            }
+        }
+    }"#;
+
+    #[test]
+    fn parses_logged_in_nav() {
+        let json: Value = serde_json::from_str(NAV_LOGGED_IN).unwrap();
+        let status = parse_nav_login_status(&json);
+        assert!(status.logged_in);
+        assert_eq!(status.uid, Some(123456789));
+        assert_eq!(status.uname.as_deref(), Some("测试用户"));
+        assert!(status.is_vip);
+    }
+
+    #[test]
+    fn expired_cookie_reports_logged_out() {
+        let json: Value = serde_json::from_str(NAV_LOGGED_OUT).unwrap();
+        let status = parse_nav_login_status(&json);
+        assert!(!status.logged_in);
+        assert_eq!(status.uid, None);
+        assert_eq!(status.uname, None);
+        assert!(!status.is_vip);
+    }
+
+    #[test]
+    fn vip_falls_back_to_nested_status() {
+        let json = serde_json::json!({
+            "code": 0,
+            "data": {"isLogin": true, "mid": 1, "uname": "u", "vip": {"status": 1}}
+        });
+        assert!(parse_nav_login_status(&json).is_vip);
+        let json = serde_json::json!({
+            "code": 0,
+            "data": {"isLogin": true, "mid": 1, "uname": "u", "vipStatus": 0}
+        });
+        assert!(!parse_nav_login_status(&json).is_vip);
+    }
+}
@@ -1,24 +1,51 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tauri::Emitter;
+use tauri::Manager;
 use tokio::sync::mpsc as tokio_mpsc;
 
 use crate::platforms::bilibili::models::BiliMessage;
 use crate::platforms::bilibili::websocket::BiliLiveClient;
+use crate::platforms::common::danmaku_history;
+use crate::platforms::common::danmaku_pause::admit;
+use crate::platforms::common::danmaku_reconnect;
+use crate::platforms::common::danmaku_subscribers;
+use crate::platforms::common::danmaku_subscribers::emit_to_subscribers;
+use crate::platforms::common::events::DtvEvent;
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::SupportedPlatformRust;
 
 #[tauri::command]
 pub async fn start_bilibili_danmaku_listener(
     payload: crate::platforms::common::GetStreamUrlPayload,
     cookie: Option<String>,
-    app_handle: tauri::AppHandle,
+    window: tauri::Window,
     state: tauri::State<'_, crate::platforms::common::BilibiliDanmakuState>,
 ) -> Result<(), String> {
-    let room_id = payload.args.room_id_str.clone();
+    let room_id = validate_room_id(&SupportedPlatformRust::Bilibili, &payload.args.room_id_str)?;
 
-    // stop previous listener if exists
+    // 同一房间已有存活连接时只登记当前窗口（多窗口共享一条连接）
+    let upstream_alive = state
+        .inner()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&room_id)
+        .map(|tx| !tx.is_closed())
+        .unwrap_or(false);
+    if danmaku_subscribers::subscribe("bilibili", &room_id, &window, upstream_alive) {
+        println!(
+            "[Bilibili Danmaku] window {} joined existing listener room_id={}",
+            window.label(),
+            room_id
+        );
+        danmaku_history::spawn_history(&window, SupportedPlatformRust::Bilibili, &room_id);
+        return Ok(());
+    }
+
+    // 该房间之前的连接已退出，清理残留的停止信号
     let previous_tx = {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.take()
+        let mut lock = state.inner().0.lock().unwrap_or_else(|e| e.into_inner());
+        lock.remove(&room_id)
     };
     if let Some(tx) = previous_tx {
         if tx.send(()).await.is_err() {
@@ -26,13 +53,17 @@ pub async fn start_bilibili_danmaku_listener(
         }
     }
 
+    // 历史弹幕与上游连接并行获取，不推迟实时弹幕
+    danmaku_history::spawn_history(&window, SupportedPlatformRust::Bilibili, &room_id);
+
     let (tx_shutdown, mut rx_shutdown) = tokio_mpsc::channel::<()>(1);
     {
-        let mut lock = state.inner().0.lock().unwrap();
-        *lock = Some(tx_shutdown);
+        let mut lock = state.inner().0.lock().unwrap_or_else(|e| e.into_inner());
+        lock.insert(room_id.clone(), tx_shutdown);
     }
 
-    let app_handle_clone = app_handle.clone();
+    let app_handle_clone = window.app_handle().clone();
+    let window_clone = window.clone();
     let room_id_clone = room_id.clone();
     let cookie_clone = cookie.clone();
 
@@ -47,36 +78,86 @@ pub async fn start_bilibili_danmaku_listener(
             None => BiliLiveClient::new_without_cookie(room_id_clone.as_str()),
         };
         client.send_auth();
+        // 首次连接与每次重连成功后都通知前端，重连后的 ws 地址/协议可能不同
+        let emit_connected = |client: &BiliLiveClient| {
+            emit_to_subscribers(
+                "bilibili",
+                &room_id_clone,
+                &window_clone,
+                DtvEvent::DanmakuConnected(crate::platforms::common::DanmakuConnectedPayload::new(
+                    "bilibili",
+                    &room_id_clone,
+                    client.ws_url(),
+                    client.ws_protocol(),
+                )),
+            );
+        };
+        emit_connected(&client);
 
+        danmaku_reconnect::reset("bilibili", &room_id_clone);
         loop {
             if stop_flag_for_thread.load(Ordering::Relaxed) {
                 break;
             }
+            if client.is_disconnected() {
+                let Some(delay) = danmaku_reconnect::next_retry_delay("bilibili", &room_id_clone)
+                else {
+                    danmaku_reconnect::give_up(
+                        &app_handle_clone,
+                        "bilibili",
+                        &room_id_clone,
+                        "websocket disconnected and reconnect failed".to_string(),
+                    );
+                    break;
+                };
+                // 分段等待，保证等待期间也能及时响应停止信号
+                let deadline = std::time::Instant::now() + delay;
+                while std::time::Instant::now() < deadline
+                    && !stop_flag_for_thread.load(Ordering::Relaxed)
+                {
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                }
+                if !stop_flag_for_thread.load(Ordering::Relaxed) && client.reconnect() {
+                    danmaku_reconnect::reset("bilibili", &room_id_clone);
+                    emit_connected(&client);
+                }
+                continue;
+            }
             if let Some(msg) = client.read_once() {
                 match msg {
-                    BiliMessage::Danmu { user, text } => {
-                        let _ = app_handle_clone.emit(
-                            "danmaku-message",
-                            crate::platforms::common::DanmakuFrontendPayload {
-                                room_id: room_id_clone.clone(),
-                                user,
-                                content: text,
-                                user_level: 0,
-                                fans_club_level: 0,
-                            },
-                        );
+                    BiliMessage::Danmu {
+                        user,
+                        text,
+                        sent_at,
+                    } => {
+                        let payload = crate::platforms::common::DanmakuFrontendPayload {
+                            platform: "bilibili".to_string(),
+                            room_id: room_id_clone.clone(),
+                            user,
+                            content: text,
+                            user_level: 0,
+                            fans_club_level: 0,
+                            ..crate::platforms::common::DanmakuFrontendPayload::received(sent_at)
+                        };
+                        let event = DtvEvent::DanmakuMessage(payload);
+                        if admit("bilibili", &room_id_clone, &event) {
+                            emit_to_subscribers("bilibili", &room_id_clone, &window_clone, event);
+                        }
                     }
                     BiliMessage::Gift { user, gift } => {
-                        let _ = app_handle_clone.emit(
-                            "danmaku-message",
-                            crate::platforms::common::DanmakuFrontendPayload {
-                                room_id: room_id_clone.clone(),
-                                user,
-                                content: format!("[礼物] {}", gift),
-                                user_level: 0,
-                                fans_club_level: 0,
-                            },
-                        );
+                        let payload = crate::platforms::common::DanmakuFrontendPayload {
+                            platform: "bilibili".to_string(),
+                            room_id: room_id_clone.clone(),
+                            user,
+                            content: format!("[礼物] {}", gift),
+                            user_level: 0,
+                            fans_club_level: 0,
+                            ..crate::platforms::common::DanmakuFrontendPayload::received(None)
+                        };
+                        let event = DtvEvent::DanmakuMessage(payload);
+                        if admit("bilibili", &room_id_clone, &event) {
+                            emit_to_subscribers("bilibili", &room_id_clone, &window_clone, event);
+                        }
                     }
                     BiliMessage::Unsupported { .. } => {
                         // ignore
@@ -97,20 +178,34 @@ pub async fn start_bilibili_danmaku_listener(
     Ok(())
 }
 
+// room_id 省略时当前窗口离开其订阅的所有 B 站房间
 #[tauri::command]
 pub async fn stop_bilibili_danmaku_listener(
+    room_id: Option<String>,
+    window: tauri::Window,
     state: tauri::State<'_, crate::platforms::common::BilibiliDanmakuState>,
 ) -> Result<(), String> {
-    let previous_tx = {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.take()
+    // 其他窗口仍在订阅时保留连接
+    let rooms = match room_id {
+        Some(room_id) => {
+            if !danmaku_subscribers::unsubscribe("bilibili", Some(&room_id), Some(window.label())) {
+                return Ok(());
+            }
+            vec![room_id]
+        }
+        None => danmaku_subscribers::leave_platform("bilibili", window.label()),
     };
-    if let Some(tx) = previous_tx {
-        match tx.send(()).await {
-            Ok(()) => Ok(()),
-            Err(_) => Err("停止Bilibili弹幕监听失败：接收方已关闭".to_string()),
+    let senders: Vec<_> = {
+        let mut lock = state.inner().0.lock().unwrap_or_else(|e| e.into_inner());
+        rooms
+            .iter()
+            .filter_map(|room_id| lock.remove(room_id))
+            .collect()
+    };
+    for tx in senders {
+        if tx.send(()).await.is_err() {
+            return Err("停止Bilibili弹幕监听失败：接收方已关闭".to_string());
         }
-    } else {
-        Ok(())
     }
+    Ok(())
 }
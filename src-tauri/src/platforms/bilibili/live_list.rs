@@ -2,9 +2,54 @@ use md5::{Digest, Md5};
 
 // 引入 generate_bilibili_w_webid 以便在缺失时后端自动初始化
 use crate::platforms::bilibili::state::{generate_bilibili_w_webid, BilibiliState};
+use crate::platforms::common::list_cache::{
+    is_network_error, is_unavailable_status, load_list, network_error, save_list,
+};
 
 #[tauri::command]
 pub async fn fetch_bilibili_live_list(
+    app_handle: tauri::AppHandle,
+    area_id: String,
+    parent_area_id: String,
+    page: u32,
+    state: tauri::State<'_, BilibiliState>,
+) -> Result<String, String> {
+    let cache_key = format!("bilibili_{}_{}_{}", parent_area_id, area_id, page);
+    let result = fetch_bilibili_live_list_remote(area_id, parent_area_id, page, state).await;
+    match result {
+        // 只缓存 code == 0 的成功响应
+        Ok(text)
+            if serde_json::from_str::<serde_json::Value>(&text)
+                .ok()
+                .and_then(|v| v.get("code").and_then(|c| c.as_i64()))
+                == Some(0) =>
+        {
+            if let Ok(value) = serde_json::from_str::<serde_json::Value>(&text) {
+                save_list(&app_handle, &cache_key, &value);
+            }
+            Ok(text)
+        }
+        // 只有网络不可达时才回退到缓存，接口返回的错误照常交给前端
+        Err(e) if is_network_error(&e) => {
+            match load_list::<serde_json::Value>(&app_handle, &cache_key) {
+                Some(mut cached) => {
+                    eprintln!(
+                        "[Bilibili] Live list request failed ({}), serving cached list for {}",
+                        e, cache_key
+                    );
+                    if let Some(obj) = cached.as_object_mut() {
+                        obj.insert("stale".to_string(), serde_json::Value::Bool(true));
+                    }
+                    Ok(cached.to_string())
+                }
+                None => Err(e),
+            }
+        }
+        other => other,
+    }
+}
+
+async fn fetch_bilibili_live_list_remote(
     area_id: String,
     parent_area_id: String,
     page: u32,
@@ -20,7 +65,13 @@ pub async fn fetch_bilibili_live_list(
         }
         Err(e) => {
             eprintln!("[Bilibili] Failed to refresh w_webid, will fallback to cached value if available: {}", e);
-            let fallback = { state.w_webid.lock().unwrap().clone() };
+            let fallback = {
+                state
+                    .w_webid
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .clone()
+            };
             match fallback {
                 Some(id) => {
                     println!(
@@ -29,7 +80,7 @@ pub async fn fetch_bilibili_live_list(
                     );
                     id
                 }
-                None => return Err(format!("w_webid 获取失败: {}", e)),
+                None => return Err(network_error(format!("w_webid 获取失败: {}", e))),
             }
         }
     };
@@ -83,7 +134,8 @@ pub async fn fetch_bilibili_live_list(
         ua, "https://www.bilibili.com/", "buvid3=i;"
     );
 
-    let client = reqwest::Client::builder()
+    let client = crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
         .user_agent(ua)
         .build()
         .map_err(|e| format!("Failed to build client: {}", e))?;
@@ -95,14 +147,20 @@ pub async fn fetch_bilibili_live_list(
         .query(&params)
         .send()
         .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+        .map_err(|e| network_error(format!("Request failed: {}", e)))?;
 
-    if !resp.status().is_success() {
-        return Err(format!("API status: {}", resp.status()));
+    let status = resp.status();
+    if !status.is_success() {
+        let message = format!("API status: {}", status);
+        return Err(if is_unavailable_status(status.as_u16() as i32) {
+            network_error(message)
+        } else {
+            message
+        });
     }
     let text = resp
         .text()
         .await
-        .map_err(|e| format!("Read text failed: {}", e))?;
+        .map_err(|e| network_error(format!("Read text failed: {}", e)))?;
     Ok(text)
 }
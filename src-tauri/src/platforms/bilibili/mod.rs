@@ -1,6 +1,8 @@
+pub mod areas;
 pub mod cookie;
 pub mod danmaku;
 pub mod live_list;
+pub mod qn;
 pub mod state;
 pub mod stream_url;
 pub mod streamer_info;
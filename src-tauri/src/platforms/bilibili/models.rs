@@ -56,7 +56,17 @@ impl AuthMessage {
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum BiliMessage {
-    Danmu { user: String, text: String },
-    Gift { user: String, gift: String },
-    Unsupported { cmd: String },
+    Danmu {
+        user: String,
+        text: String,
+        // info[0][4]：发送时间（毫秒）
+        sent_at: Option<i64>,
+    },
+    Gift {
+        user: String,
+        gift: String,
+    },
+    Unsupported {
+        cmd: String,
+    },
 }
@@ -0,0 +1,243 @@
+use crate::platforms::common::{Quality, SupportedPlatformRust};
+
+// B 站特有的清晰度名称（g_qn_desc 的 desc）。原画/高清/标清 是前端通用档位名，
+// 按档位处理而不是按字面映射（B 站的“高清”是 150，而通用“高清”档位优先 400）
+const QN_LABELS: &[(&str, i32)] = &[
+    ("杜比", 30000),
+    ("4K", 20000),
+    ("蓝光", 400),
+    ("超清", 250),
+    ("流畅", 80),
+];
+const TIER_LABELS: &[&str] = &["原画", "高清", "标清"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RequestedQn {
+    Exact(i32),
+    Tier(Quality),
+}
+
+// 数字按 qn 原样处理（不归并到三个档位）；房间 g_qn_desc 或上表中的名称换算为 qn；
+// 其余按通用档位解析，无法识别时退回默认档位
+fn parse_requested_qn(input: &str, qn_map: &[(i32, String)]) -> RequestedQn {
+    let trimmed = input.trim();
+    if !TIER_LABELS.contains(&trimmed) {
+        if let Some(qn) = trimmed.parse::<i32>().ok().filter(|qn| *qn > 0) {
+            return RequestedQn::Exact(qn);
+        }
+        if let Some((qn, _)) = qn_map.iter().find(|(_, desc)| desc == trimmed) {
+            return RequestedQn::Exact(*qn);
+        }
+        if let Some((_, qn)) = QN_LABELS
+            .iter()
+            .find(|(label, _)| label.eq_ignore_ascii_case(trimmed))
+        {
+            return RequestedQn::Exact(*qn);
+        }
+    }
+    RequestedQn::Tier(Quality::from_user_or_default(
+        &SupportedPlatformRust::Bilibili,
+        trimmed,
+    ))
+}
+
+// 房间未提供请求的 qn 时：取不高于它的最高 qn，都更高则取最低的一档
+fn closest_qn(requested: i32, offered: &[i32]) -> Option<i32> {
+    if offered.contains(&requested) {
+        return Some(requested);
+    }
+    offered
+        .iter()
+        .copied()
+        .filter(|qn| *qn < requested)
+        .max()
+        .or_else(|| offered.iter().copied().min())
+}
+
+// 按档位选择（更严格的匹配与优先规则）
+fn match_tier(offered: &[(i32, String)], quality: Quality) -> Option<i32> {
+    let mut qns: Vec<i32> = offered.iter().map(|(qn, _)| *qn).collect();
+    qns.sort();
+    let has = |v: i32| qns.binary_search(&v).is_ok();
+    // 优先该档位的固定 qn（原画 10000 / 高清 400 / 标清 250）
+    let preferred = quality
+        .to_platform_param(&SupportedPlatformRust::Bilibili)
+        .parse::<i32>()
+        .unwrap_or(0);
+    if has(preferred) {
+        return Some(preferred);
+    }
+
+    match quality {
+        Quality::Origin => qns.last().copied(),
+        Quality::High => {
+            // 否则按描述关键字匹配（高清/超清/HD）；再兜底选择次高值
+            for (qn, desc) in offered.iter() {
+                if desc.contains("高清") || desc.contains("超清") || desc.contains("HD") {
+                    return Some(*qn);
+                }
+            }
+            // 兜底：选择小于最大值的次高 qn（例如只有 10000 和 250 时，选 250）
+            let max = qns.last().copied();
+            if let Some(m) = max {
+                qns.into_iter().rev().find(|&x| x < m)
+            } else {
+                None
+            }
+        }
+        Quality::Standard => {
+            // 否则按描述关键字匹配（标清/流畅/SD）；再兜底选择最小值
+            for (qn, desc) in offered.iter() {
+                if desc.contains("标清") || desc.contains("流畅") || desc.contains("SD") {
+                    return Some(*qn);
+                }
+            }
+            qns.first().copied()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct QnSelection {
+    pub qn: i32,
+    // 请求的 qn 房间未提供，已改用最接近的一档
+    pub fallback: bool,
+}
+
+// 把前端传入的 quality（档位名、B 站清晰度名或 qn 数字）换算为本房间实际可用的 qn。
+// 可用范围以 accept_qn 为准（为空时退回 g_qn_desc）；两者都没有时原样使用请求的 qn
+pub fn select_qn(
+    quality: &str,
+    qn_map: &[(i32, String)],
+    accept_qn: &[i32],
+) -> Option<QnSelection> {
+    let offered: Vec<(i32, String)> = if accept_qn.is_empty() {
+        qn_map.to_vec()
+    } else {
+        accept_qn
+            .iter()
+            .map(|qn| {
+                let desc = qn_map
+                    .iter()
+                    .find(|(q, _)| q == qn)
+                    .map(|(_, d)| d.clone())
+                    .unwrap_or_default();
+                (*qn, desc)
+            })
+            .collect()
+    };
+    match parse_requested_qn(quality, qn_map) {
+        RequestedQn::Exact(qn) if offered.is_empty() => Some(QnSelection {
+            qn,
+            fallback: false,
+        }),
+        RequestedQn::Exact(qn) => {
+            let qns: Vec<i32> = offered.iter().map(|(q, _)| *q).collect();
+            closest_qn(qn, &qns).map(|closest| QnSelection {
+                qn: closest,
+                fallback: closest != qn,
+            })
+        }
+        RequestedQn::Tier(tier) => {
+            let preferred = tier
+                .to_platform_param(&SupportedPlatformRust::Bilibili)
+                .parse::<i32>()
+                .ok();
+            match_tier(&offered, tier).map(|qn| QnSelection {
+                qn,
+                fallback: preferred != Some(qn),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn qn_map() -> Vec<(i32, String)> {
+        vec![
+            (10000, "原画".to_string()),
+            (400, "蓝光".to_string()),
+            (250, "超清".to_string()),
+            (150, "高清".to_string()),
+            (80, "流畅".to_string()),
+        ]
+    }
+
+    #[test]
+    fn parse_numbers_and_labels_as_exact_qn() {
+        let map = qn_map();
+        assert_eq!(parse_requested_qn("250", &map), RequestedQn::Exact(250));
+        assert_eq!(parse_requested_qn("150", &map), RequestedQn::Exact(150));
+        assert_eq!(parse_requested_qn(" 蓝光 ", &map), RequestedQn::Exact(400));
+        // 房间自己的 g_qn_desc 优先于内置表
+        assert_eq!(parse_requested_qn("流畅", &map), RequestedQn::Exact(80));
+        assert_eq!(parse_requested_qn("4k", &[]), RequestedQn::Exact(20000));
+    }
+
+    #[test]
+    fn parse_tier_labels_as_tiers() {
+        let map = qn_map();
+        // 房间的“高清”是 150，但通用档位名按档位处理
+        assert_eq!(
+            parse_requested_qn("高清", &map),
+            RequestedQn::Tier(Quality::High)
+        );
+        assert_eq!(
+            parse_requested_qn("原画", &map),
+            RequestedQn::Tier(Quality::Origin)
+        );
+        // 无法识别的写法不报错，按默认档位处理
+        assert_eq!(
+            parse_requested_qn("不存在的清晰度", &map),
+            RequestedQn::Tier(Quality::Origin)
+        );
+    }
+
+    #[test]
+    fn closest_qn_prefers_lower_then_lowest() {
+        let offered = [10000, 400, 250];
+        assert_eq!(closest_qn(400, &offered), Some(400));
+        assert_eq!(closest_qn(20000, &offered), Some(10000));
+        assert_eq!(closest_qn(300, &offered), Some(250));
+        assert_eq!(closest_qn(80, &offered), Some(250));
+        assert_eq!(closest_qn(80, &[]), None);
+    }
+
+    #[test]
+    fn match_tier_prefers_fixed_qn_then_keywords() {
+        let map = qn_map();
+        assert_eq!(match_tier(&map, Quality::Origin), Some(10000));
+        assert_eq!(match_tier(&map, Quality::High), Some(400));
+        assert_eq!(match_tier(&map, Quality::Standard), Some(250));
+
+        let offered = vec![(10000, "原画".to_string()), (150, "高清".to_string())];
+        assert_eq!(match_tier(&offered, Quality::High), Some(150));
+        let offered = vec![(10000, String::new()), (80, String::new())];
+        assert_eq!(match_tier(&offered, Quality::High), Some(80));
+        assert_eq!(match_tier(&offered, Quality::Standard), Some(80));
+        assert_eq!(match_tier(&[], Quality::Origin), None);
+    }
+
+    #[test]
+    fn select_qn_reports_fallback() {
+        let map = qn_map();
+        let exact = select_qn("400", &map, &[10000, 400]).unwrap();
+        assert_eq!((exact.qn, exact.fallback), (400, false));
+        let lower = select_qn("250", &map, &[10000, 400, 80]).unwrap();
+        assert_eq!((lower.qn, lower.fallback), (80, true));
+
+        let tier = select_qn("高清", &map, &[10000, 400]).unwrap();
+        assert_eq!((tier.qn, tier.fallback), (400, false));
+        let tier = select_qn("高清", &map, &[10000, 150]).unwrap();
+        assert_eq!((tier.qn, tier.fallback), (150, true));
+
+        // 房间没有提供清晰度列表时原样使用请求的 qn
+        let unknown = select_qn("250", &[], &[]).unwrap();
+        assert_eq!((unknown.qn, unknown.fallback), (250, false));
+
+        let unknown = select_qn("8K", &map, &[10000, 400]).unwrap();
+        assert_eq!((unknown.qn, unknown.fallback), (10000, false));
+    }
+}
@@ -4,7 +4,11 @@ use reqwest::header::{COOKIE, REFERER, USER_AGENT};
 use serde::Serialize;
 use serde_json::Value;
 
-const DEFAULT_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36";
+use crate::platforms::common::user_agent::user_agent_for;
+use crate::platforms::common::SupportedPlatformRust;
+use crate::proxy::proxied_image_url;
+
+pub(crate) const DEFAULT_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36";
 const LIVE_REFERER: &str = "https://live.bilibili.com/";
 const SEARCH_ENDPOINT: &str = "https://api.bilibili.com/x/web-interface/search/type";
 const FINGERPRINT_ENDPOINT: &str = "https://api.bilibili.com/x/frontend/finger/spi";
@@ -101,7 +105,7 @@ async fn ensure_buvid(client: &reqwest::Client, cookie_header: &mut String) -> R
 
     let mut request = client
         .get(FINGERPRINT_ENDPOINT)
-        .header(USER_AGENT, DEFAULT_UA)
+        .header(USER_AGENT, user_agent_for(&SupportedPlatformRust::Bilibili))
         .header(REFERER, LIVE_REFERER);
 
     if !cookie_header.trim().is_empty() {
@@ -141,16 +145,30 @@ async fn ensure_buvid(client: &reqwest::Client, cookie_header: &mut String) -> R
 pub async fn search_bilibili_rooms(
     keyword: String,
     page: Option<u32>,
+    page_size: Option<u32>,
     cookie: Option<String>,
 ) -> Result<Vec<BilibiliSearchItem>, String> {
+    search_bilibili_page(&keyword, page.unwrap_or(1), page_size.unwrap_or(20), cookie)
+        .await
+        .map(|(items, _)| items)
+}
+
+// 返回 (本页结果, 是否还有下一页)；总页数取自 data.pageinfo.live_user.numPages
+pub(crate) async fn search_bilibili_page(
+    keyword: &str,
+    page: u32,
+    page_size: u32,
+    cookie: Option<String>,
+) -> Result<(Vec<BilibiliSearchItem>, bool), String> {
     let trimmed = keyword.trim();
     if trimmed.is_empty() {
-        return Ok(vec![]);
+        return Ok((vec![], false));
     }
 
     let mut cookie_header = cookie.unwrap_or_default();
 
-    let client = reqwest::Client::builder()
+    let client = crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
         .build()
         .map_err(|e| format!("Failed to build client: {}", e))?;
 
@@ -158,7 +176,7 @@ pub async fn search_bilibili_rooms(
 
     let mut req = client
         .get(SEARCH_ENDPOINT)
-        .header(USER_AGENT, DEFAULT_UA)
+        .header(USER_AGENT, user_agent_for(&SupportedPlatformRust::Bilibili))
         .header(REFERER, LIVE_REFERER)
         .query(&[
             ("context", ""),
@@ -171,7 +189,8 @@ pub async fn search_bilibili_rooms(
             ("_extra", ""),
             ("highlight", "0"),
             ("single_column", "0"),
-            ("page", &page.unwrap_or(1).to_string()),
+            ("page", &page.to_string()),
+            ("page_size", &page_size.to_string()),
         ]);
 
     if !cookie_header.trim().is_empty() {
@@ -187,7 +206,14 @@ pub async fn search_bilibili_rooms(
         .json()
         .await
         .map_err(|e| format!("Failed to parse bilibili search JSON: {}", e))?;
+    parse_bilibili_search(&payload, page, page_size)
+}
 
+fn parse_bilibili_search(
+    payload: &Value,
+    page: u32,
+    page_size: u32,
+) -> Result<(Vec<BilibiliSearchItem>, bool), String> {
     if payload.get("code").and_then(|v| v.as_i64()).unwrap_or(-1) != 0 {
         let msg = payload
             .get("message")
@@ -215,8 +241,12 @@ pub async fn search_bilibili_rooms(
                 .and_then(|v| v.as_str())
                 .map(strip_em_tags)
                 .unwrap_or_default();
-            let cover = normalize_image(entry.get("cover").and_then(|v| v.as_str()));
-            let avatar = normalize_image(entry.get("uface").and_then(|v| v.as_str()));
+            let cover = proxied_image_url(&normalize_image(
+                entry.get("cover").and_then(|v| v.as_str()),
+            ));
+            let avatar = proxied_image_url(&normalize_image(
+                entry.get("uface").and_then(|v| v.as_str()),
+            ));
             let anchor = entry
                 .get("uname")
                 .and_then(|v| v.as_str())
@@ -254,5 +284,85 @@ pub async fn search_bilibili_rooms(
         }
     }
 
-    Ok(result)
+    let num_pages = payload
+        .pointer("/data/pageinfo/live_user/numPages")
+        .or_else(|| payload.pointer("/data/numPages"))
+        .and_then(|v| v.as_u64());
+    let has_more = match num_pages {
+        Some(num_pages) => u64::from(page) < num_pages,
+        None => result.len() >= page_size as usize,
+    };
+    Ok((result, has_more))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 构造的 search/type?search_type=live 两页响应，page_size = 2，共 2 页
+    fn search_page(page: u32) -> Value {
+        let live_user = match page {
+            1 => serde_json::json!([
+                {"roomid": 7734200, "uname": "<em class=\"keyword\">测试</em>主播A", "title": "第一页 A",
+                 "uface": "//i0.hdslb.com/bfs/face/a.jpg", "online": 1200, "cate_name": "英雄联盟", "live_status": 1},
+                {"roomid": 21452505, "uname": "主播B", "title": "<em class=\"keyword\">测试</em>第一页 B",
+                 "uface": "", "online": "3.5万", "cate_name": "单机游戏", "live_status": 0}
+            ]),
+            _ => serde_json::json!([
+                {"roomid": 6, "uname": "主播C", "title": "第二页 C",
+                 "uface": "https://i0.hdslb.com/bfs/face/c.jpg", "online": 88, "cate_name": "电台", "live_status": 1}
+            ]),
+        };
+        serde_json::json!({
+            "code": 0,
+            "message": "0",
+            "data": {
+                "page": page,
+                "pageinfo": {"live_user": {"numPages": 2, "numResults": 3, "total": 3}},
+                "result": {"live_room": [], "live_user": live_user}
+            }
+        })
+    }
+
+    fn room_ids(items: &[BilibiliSearchItem]) -> Vec<&str> {
+        items.iter().map(|item| item.room_id.as_str()).collect()
+    }
+
+    #[test]
+    fn second_page_differs_from_first() {
+        let (first, first_more) = parse_bilibili_search(&search_page(1), 1, 2).unwrap();
+        let (second, second_more) = parse_bilibili_search(&search_page(2), 2, 2).unwrap();
+        assert_eq!(room_ids(&first), ["7734200", "21452505"]);
+        assert_eq!(room_ids(&second), ["6"]);
+        assert!(first_more);
+        assert!(!second_more);
+    }
+
+    #[test]
+    fn items_are_normalized() {
+        let (items, _) = parse_bilibili_search(&search_page(1), 1, 2).unwrap();
+        assert_eq!(items[0].anchor, "测试主播A");
+        assert_eq!(items[1].title, "测试第一页 B");
+        assert!(items[0].avatar.contains("i0.hdslb.com/bfs/face/a.jpg"));
+        assert_eq!(items[1].avatar, "");
+        assert_eq!(items[0].watching, "1200");
+        assert_eq!(items[1].watching, "3.5万");
+        assert!(items[0].is_live);
+        assert!(!items[1].is_live);
+    }
+
+    #[test]
+    fn has_more_without_page_info_and_errors() {
+        let mut payload = search_page(1);
+        payload["data"].as_object_mut().unwrap().remove("pageinfo");
+        // 没有总页数时按本页是否满页判断
+        assert!(parse_bilibili_search(&payload, 1, 2).unwrap().1);
+        assert!(!parse_bilibili_search(&payload, 1, 20).unwrap().1);
+
+        let rejected = serde_json::json!({"code": -412, "message": "请求被拦截"});
+        assert_eq!(
+            parse_bilibili_search(&rejected, 1, 20).unwrap_err(),
+            "Bilibili search failed: 请求被拦截"
+        );
+    }
 }
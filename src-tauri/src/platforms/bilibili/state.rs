@@ -1,8 +1,12 @@
 use std::sync::{Arc, Mutex};
 
+use crate::platforms::common::text_decode::read_text;
+
 #[derive(Default, Clone)]
 pub struct BilibiliState {
     pub w_webid: Arc<Mutex<Option<String>>>,
+    // 最近一次从 WebView 采集到的 Cookie，供登录状态检查等复用
+    pub cookie: Arc<Mutex<Option<String>>>,
 }
 
 #[tauri::command]
@@ -17,7 +21,8 @@ pub async fn generate_bilibili_w_webid(
         ua, "https://www.bilibili.com/"
     );
 
-    let client = reqwest::Client::builder()
+    let client = crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
         .user_agent(ua)
         .build()
         .map_err(|e| format!("Failed to build client: {}", e))?;
@@ -29,8 +34,7 @@ pub async fn generate_bilibili_w_webid(
         .await
         .map_err(|e| format!("Request failed: {}", e))?;
 
-    let text = resp
-        .text()
+    let text = read_text(resp)
         .await
         .map_err(|e| format!("Read text failed: {}", e))?;
 
@@ -72,7 +76,7 @@ pub async fn generate_bilibili_w_webid(
     let w_webid = access_id.ok_or_else(|| "Failed to extract w_webid (access_id)".to_string())?;
     println!("[Bilibili] w_webid extracted: {}", w_webid);
     {
-        let mut guard = state.w_webid.lock().unwrap();
+        let mut guard = state.w_webid.lock().unwrap_or_else(|e| e.into_inner());
         *guard = Some(w_webid.clone());
     }
     Ok(w_webid)
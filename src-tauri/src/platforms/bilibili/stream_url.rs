@@ -2,7 +2,12 @@ use reqwest::header::{HeaderMap, HeaderValue, COOKIE, REFERER, USER_AGENT};
 use serde_json::Value;
 use tauri::{command, AppHandle, State};
 
-use crate::platforms::common::types::StreamVariant;
+use crate::platforms::bilibili::qn::select_qn;
+use crate::platforms::common::request_id::{new_request_id, tag_error};
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::types::{AcceptedQuality, StreamVariant};
+use crate::platforms::common::user_agent::user_agent_for;
+use crate::platforms::common::{RoomRestriction, SupportedPlatformRust};
 use crate::proxy::{start_proxy, start_static_proxy_server, ProxyServerHandle};
 use crate::StreamUrlStore;
 
@@ -14,28 +19,247 @@ pub async fn get_bilibili_live_stream_url_with_quality(
     payload: crate::platforms::common::GetStreamUrlPayload,
     quality: String,
     cookie: Option<String>,
+    stream_index: Option<usize>,
+    room_password: Option<String>,
+    low_latency: Option<bool>,
 ) -> Result<crate::platforms::common::LiveStreamInfo, String> {
-    let room_id = payload.args.room_id_str.clone();
-    if room_id.trim().is_empty() {
-        return Ok(crate::platforms::common::LiveStreamInfo {
-            title: None,
-            anchor_name: None,
-            avatar: None,
-            stream_url: None,
-            status: None,
-            error_message: Some("房间ID未提供".to_string()),
-            upstream_url: None,
-            available_streams: None,
-            normalized_room_id: None,
-            web_rid: None,
-        });
+    let request_id = new_request_id();
+    eprintln!(
+        "[Bilibili][req:{}] Resolving room {} with quality '{}'",
+        request_id, payload.args.room_id_str, quality
+    );
+    let room_id = validate_room_id(&SupportedPlatformRust::Bilibili, &payload.args.room_id_str);
+    let requested_quality = quality.clone();
+    let result = match resolve_bilibili_live_stream(
+        payload,
+        quality,
+        cookie,
+        stream_index,
+        room_password,
+        low_latency.unwrap_or(false),
+        &request_id,
+    )
+    .await
+    {
+        Ok((info, Some(selected))) => {
+            let published = publish_bilibili_stream(
+                app_handle.clone(),
+                stream_url_store,
+                proxy_server_handle,
+                info,
+                selected,
+                &request_id,
+            )
+            .await;
+            // 播放存储已被改写：无论是否接入成功，NowPlaying 都要跟随这次解析
+            match (&published, room_id) {
+                (Ok(info), Ok(room_id)) if info.stream_url.is_some() => {
+                    crate::room::set_now_playing(
+                        &app_handle,
+                        &SupportedPlatformRust::Bilibili,
+                        &room_id,
+                        &requested_quality,
+                        None,
+                        &request_id,
+                    );
+                }
+                _ => crate::room::clear_now_playing(&app_handle),
+            }
+            published
+        }
+        Ok((info, None)) => Ok(info),
+        Err(e) => Err(e),
+    };
+    match &result {
+        Ok(info) => eprintln!(
+            "[Bilibili][req:{}] Resolved status={:?} upstream={:?}",
+            request_id, info.status, info.upstream_url
+        ),
+        Err(e) => eprintln!("[Bilibili][req:{}] Failed: {}", request_id, e),
+    }
+    result.map_err(|e| tag_error(&request_id, e))
+}
+
+// 只解析上游地址，不写入播放存储也不启动代理：stream_url 与 upstream_url 相同，
+// 供导出播放列表、自动清晰度测速等不应影响当前播放的调用方使用
+pub(crate) async fn resolve_bilibili_upstream(
+    payload: crate::platforms::common::GetStreamUrlPayload,
+    quality: &str,
+    cookie: Option<String>,
+    request_id: &str,
+) -> Result<crate::platforms::common::LiveStreamInfo, String> {
+    let (mut info, _) = resolve_bilibili_live_stream(
+        payload,
+        quality.to_string(),
+        cookie,
+        None,
+        None,
+        false,
+        request_id,
+    )
+    .await?;
+    info.stream_url = info.upstream_url.clone();
+    Ok(info)
+}
+
+enum SelectedStream {
+    Flv(String),
+    Hls(String),
+}
+
+// 解析出的地址接入播放：FLV 写入存储并启动代理，HLS 转成静态代理的 /hls 地址
+async fn publish_bilibili_stream(
+    app_handle: AppHandle,
+    stream_url_store: State<'_, StreamUrlStore>,
+    proxy_server_handle: State<'_, ProxyServerHandle>,
+    info: crate::platforms::common::LiveStreamInfo,
+    selected_stream: SelectedStream,
+    request_id: &str,
+) -> Result<crate::platforms::common::LiveStreamInfo, String> {
+    match selected_stream {
+        SelectedStream::Flv(real_url) => {
+            // FLV：写入到 Store 并启动代理
+            {
+                let mut current_url_in_store = stream_url_store
+                    .url
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                *current_url_in_store = real_url;
+                *stream_url_store
+                    .request_id
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner()) = request_id.to_string();
+            }
+            let proxied_url = match start_proxy(
+                app_handle,
+                proxy_server_handle,
+                stream_url_store,
+                None,
+                None,
+            )
+            .await
+            {
+                Ok(proxy) => Some(proxy),
+                Err(e) => {
+                    eprintln!(
+                        "[Bilibili][req:{}] Failed to start proxy: {}",
+                        request_id, e
+                    );
+                    None
+                }
+            };
+
+            let final_error_message = if proxied_url.is_none() {
+                Some("代理启动失败".to_string())
+            } else {
+                None
+            };
+
+            Ok(crate::platforms::common::LiveStreamInfo {
+                stream_url: proxied_url,
+                status: Some(if final_error_message.is_some() { 2 } else { 1 }),
+                error_message: final_error_message,
+                ..info
+            })
+        }
+        SelectedStream::Hls(real_url) => {
+            // HLS：无需本地代理，若存在旧的 FLV 代理则关闭并清空存储
+            {
+                let handle_to_stop = {
+                    proxy_server_handle
+                        .0
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .take()
+                };
+                if let Some(task) = handle_to_stop {
+                    task.shutdown().await;
+                    eprintln!(
+                        "[Bilibili][req:{}] Stopped existing FLV proxy before using HLS stream",
+                        request_id
+                    );
+                }
+            }
+            {
+                let mut current_url_in_store = stream_url_store
+                    .url
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                *current_url_in_store = String::new();
+            }
+
+            // 将 HLS 转成 localhost 代理地址，避免 WebView 直连外网（由 Rust 侧发起真实请求，并遵循 HTTP(S)_PROXY）。
+            let base = start_static_proxy_server(app_handle, stream_url_store)
+                .await
+                .map_err(|e| format!("Failed to start static proxy server: {}", e))?;
+            let base = base.trim_end_matches('/').to_string();
+            let proxied_hls = format!("{}/hls?url={}", base, urlencoding::encode(&real_url));
+
+            Ok(crate::platforms::common::LiveStreamInfo {
+                stream_url: Some(proxied_hls),
+                ..info
+            })
+        }
+    }
+}
+
+const LIVE_API_BASE: &str = "https://api.live.bilibili.com";
+
+// 测试中指向本地 mock 上游
+#[cfg(test)]
+pub(crate) static TEST_LIVE_API_BASE: std::sync::Mutex<Option<String>> =
+    std::sync::Mutex::new(None);
+
+fn live_api_url(path: &str) -> String {
+    #[cfg(test)]
+    if let Some(base) = TEST_LIVE_API_BASE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+    {
+        return format!("{}{}", base, path);
     }
+    format!("{}{}", LIVE_API_BASE, path)
+}
 
-    let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36";
+// 返回的 LiveStreamInfo 不含 stream_url；开播且拿到地址时同时返回选中的流，由调用方决定如何接入
+async fn resolve_bilibili_live_stream(
+    payload: crate::platforms::common::GetStreamUrlPayload,
+    quality: String,
+    cookie: Option<String>,
+    stream_index: Option<usize>,
+    room_password: Option<String>,
+    low_latency: bool,
+    request_id: &str,
+) -> Result<
+    (
+        crate::platforms::common::LiveStreamInfo,
+        Option<SelectedStream>,
+    ),
+    String,
+> {
+    let room_id =
+        match validate_room_id(&SupportedPlatformRust::Bilibili, &payload.args.room_id_str) {
+            Ok(room_id) => room_id,
+            Err(e) => {
+                return Ok((
+                    crate::platforms::common::LiveStreamInfo {
+                        error_message: Some(e.to_string()),
+                        ..Default::default()
+                    },
+                    None,
+                ));
+            }
+        };
+
+    let ua = user_agent_for(&SupportedPlatformRust::Bilibili);
 
     // Build headers
     let mut headers = HeaderMap::new();
-    headers.insert(USER_AGENT, HeaderValue::from_str(ua).unwrap());
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(&ua).map_err(|e| format!("Invalid User-Agent: {}", e))?,
+    );
     headers.insert(
         REFERER,
         HeaderValue::from_static("https://live.bilibili.com/"),
@@ -46,14 +270,23 @@ pub async fn get_bilibili_live_stream_url_with_quality(
             match HeaderValue::from_str(c_trimmed) {
                 Ok(val) => {
                     headers.insert(COOKIE, val);
-                    eprintln!("[Bilibili] Cookie header set (content hidden)");
+                    eprintln!(
+                        "[Bilibili][req:{}] Cookie header set (content hidden)",
+                        request_id
+                    );
                 }
                 Err(err) => {
-                    eprintln!("[Bilibili] Invalid cookie header, skipping. Error: {}", err);
+                    eprintln!(
+                        "[Bilibili][req:{}] Invalid cookie header, skipping. Error: {}",
+                        request_id, err
+                    );
                 }
             }
         } else {
-            eprintln!("[Bilibili] Cookie provided is empty after trimming, skipping insertion.");
+            eprintln!(
+                "[Bilibili][req:{}] Cookie provided is empty after trimming, skipping insertion.",
+                request_id
+            );
         }
     }
 
@@ -62,7 +295,8 @@ pub async fn get_bilibili_live_stream_url_with_quality(
         reqwest::header::ORIGIN,
         HeaderValue::from_static("https://live.bilibili.com"),
     );
-    let client = reqwest::Client::builder()
+    let client = crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
         .default_headers(headers)
         .build()
         .map_err(|e| format!("Failed to build client: {}", e))?;
@@ -72,12 +306,15 @@ pub async fn get_bilibili_live_stream_url_with_quality(
         client: &reqwest::Client,
         room_id: &str,
         qn: Option<i32>,
+        pwd: Option<&str>,
+        low_latency: bool,
     ) -> Result<Value, String> {
-        let url = "https://api.live.bilibili.com/xlive/web-room/v2/index/getRoomPlayInfo";
+        let url = live_api_url("/xlive/web-room/v2/index/getRoomPlayInfo");
+        let (protocol, format) = playinfo_formats(low_latency);
         let mut params = vec![
             ("room_id", room_id.to_string()),
-            ("protocol", "0,1".to_string()),
-            ("format", "0,1,2".to_string()),
+            ("protocol", protocol.to_string()),
+            ("format", format.to_string()),
             // 与参考 Python 版本保持一致：codec 使用 0，platform 使用 html5
             ("codec", "0".to_string()),
             ("platform", "html5".to_string()),
@@ -86,6 +323,13 @@ pub async fn get_bilibili_live_stream_url_with_quality(
         if let Some(q) = qn {
             params.push(("qn", q.to_string()));
         }
+        // 加密房间：随请求提交房间密码
+        if let Some(p) = pwd {
+            params.push(("pwd", p.to_string()));
+        }
+        if low_latency {
+            params.push(("ptype", LOW_LATENCY_PTYPE.to_string()));
+        }
         let resp = client
             .get(url)
             .query(&params)
@@ -104,8 +348,64 @@ pub async fn get_bilibili_live_stream_url_with_quality(
             .map_err(|e| format!("JSON parse failed: {} | body: {}", e, text))
     }
 
+    // Determine live status from room_init
+    let room_init_url = live_api_url(&format!("/room/v1/Room/room_init?id={}", room_id));
+    let init_resp = client
+        .get(&room_init_url)
+        .send()
+        .await
+        .map_err(|e| format!("room_init failed: {}", e))?;
+    let init_text = init_resp
+        .text()
+        .await
+        .map_err(|e| format!("room_init read text failed: {}", e))?;
+    let init_json: Value = serde_json::from_str(&init_text)
+        .map_err(|e| format!("room_init json failed: {} | {}", e, init_text))?;
+    // 封禁/隐藏/加密房间：返回具体原因而不是笼统的取流失败
+    if let Some(restriction) = room_restriction(&init_json["data"], room_password.is_some()) {
+        return Ok((
+            restricted_room_info(&init_json, &room_id, restriction, request_id),
+            None,
+        ));
+    }
+    let pwd = room_password
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty());
+
     // 1) First request to get qn mapping
-    let playinfo = request_playinfo(&client, &room_id, None).await?;
+    // 网页 playinfo 失败时不直接报错：后面会回退到旧版 playUrl 接口
+    // 这次请求只用于取 qn 映射，始终按常规参数请求
+    let playinfo = match request_playinfo(&client, &room_id, None, pwd, false).await {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!(
+                "[Bilibili][req:{}] web playinfo failed for room {}: {}",
+                request_id, room_id, e
+            );
+            Value::Null
+        }
+    };
+    let raw = crate::platforms::common::debug_raw::capture(
+        || serde_json::json!({ "room_init": init_json, "playinfo": playinfo }),
+    );
+    if password_rejected(&init_json["data"], &playinfo) {
+        eprintln!(
+            "[Bilibili][req:{}] Room {} rejected password: {}",
+            request_id,
+            room_id,
+            playinfo["message"].as_str().unwrap_or("")
+        );
+        let info = restricted_room_info(
+            &init_json,
+            &room_id,
+            RoomRestriction::RoomEncrypted {
+                password_rejected: true,
+            },
+            request_id,
+        );
+        return Ok((info, None));
+    }
     let playurl = playinfo["data"]["playurl_info"]["playurl"].clone();
 
     // Build qn->desc map
@@ -152,9 +452,15 @@ pub async fn get_bilibili_live_stream_url_with_quality(
             .map(|(q, d)| format!("{}:{}", q, d))
             .collect::<Vec<_>>()
             .join(", ");
-        eprintln!("[Bilibili] qn_map for room {} => [{}]", room_id, qn_str);
+        eprintln!(
+            "[Bilibili][req:{}] qn_map for room {} => [{}]",
+            request_id, room_id, qn_str
+        );
     } else {
-        eprintln!("[Bilibili] qn_map is empty for room {}", room_id);
+        eprintln!(
+            "[Bilibili][req:{}] qn_map is empty for room {}",
+            request_id, room_id
+        );
     }
     if !accept_qn.is_empty() {
         let accept_str = accept_qn
@@ -162,62 +468,15 @@ pub async fn get_bilibili_live_stream_url_with_quality(
             .map(|q| q.to_string())
             .collect::<Vec<_>>()
             .join(", ");
-        eprintln!("[Bilibili] accept_qn => [{}]", accept_str);
-    }
-
-    // Choose qn by desired quality text（更严格的匹配与优先规则）
-    fn match_qn(qn_map: &[(i32, String)], quality: &str) -> Option<i32> {
-        let q = quality.trim();
-        let mut qns: Vec<i32> = qn_map.iter().map(|(qn, _)| *qn).collect();
-        qns.sort();
-        let has = |v: i32| qns.binary_search(&v).is_ok();
-
-        match q {
-            "原画" => {
-                if has(10000) {
-                    Some(10000)
-                } else {
-                    qns.last().copied()
-                }
-            }
-            "高清" => {
-                // 优先固定值 400；否则按描述关键字匹配（高清/超清/HD）；再兜底选择次高值
-                if has(400) {
-                    return Some(400);
-                }
-                for (qn, desc) in qn_map.iter() {
-                    if desc.contains("高清") || desc.contains("超清") || desc.contains("HD") {
-                        return Some(*qn);
-                    }
-                }
-                // 兜底：选择小于最大值的次高 qn（例如只有 10000 和 250 时，选 250）
-                let max = qns.last().copied();
-                if let Some(m) = max {
-                    qns.into_iter().rev().find(|&x| x < m)
-                } else {
-                    None
-                }
-            }
-            "标清" => {
-                // 优先固定值 250；否则按描述关键字匹配（标清/流畅/SD）；再兜底选择最小值
-                if has(250) {
-                    return Some(250);
-                }
-                for (qn, desc) in qn_map.iter() {
-                    if desc.contains("标清") || desc.contains("流畅") || desc.contains("SD") {
-                        return Some(*qn);
-                    }
-                }
-                qns.first().copied()
-            }
-            _ => {
-                // 未识别文案：兜底最大值
-                qns.last().copied()
-            }
-        }
+        eprintln!(
+            "[Bilibili][req:{}] accept_qn => [{}]",
+            request_id, accept_str
+        );
     }
 
-    let selected_qn = match_qn(&qn_map, &quality);
+    let selection = select_qn(&quality, &qn_map, &accept_qn);
+    let selected_qn = selection.map(|s| s.qn);
+    let quality_fallback = selection.map(|s| s.fallback);
     let selected_desc = selected_qn.and_then(|qn| {
         qn_map
             .iter()
@@ -225,140 +484,71 @@ pub async fn get_bilibili_live_stream_url_with_quality(
             .map(|(_, d)| d.clone())
     });
     eprintln!(
-        "[Bilibili] selected quality '{}' -> qn={:?}, desc={:?}",
-        quality, selected_qn, selected_desc
+        "[Bilibili][req:{}] selected quality '{}' -> qn={:?}, desc={:?}",
+        request_id, quality, selected_qn, selected_desc
     );
+    if quality_fallback == Some(true) {
+        eprintln!(
+            "[Bilibili][req:{}] quality '{}' not offered (accept_qn {:?}), using closest qn {:?}",
+            request_id, quality, accept_qn, selected_qn
+        );
+    }
 
-    // Determine live status from room_init
-    let room_init_url = format!(
-        "https://api.live.bilibili.com/room/v1/Room/room_init?id={}",
-        room_id
-    );
-    let init_resp = client
-        .get(&room_init_url)
-        .send()
-        .await
-        .map_err(|e| format!("room_init failed: {}", e))?;
-    let init_text = init_resp
-        .text()
-        .await
-        .map_err(|e| format!("room_init read text failed: {}", e))?;
-    let init_json: Value = serde_json::from_str(&init_text)
-        .map_err(|e| format!("room_init json failed: {} | {}", e, init_text))?;
     let live_status = init_json["data"]["live_status"].as_i64().unwrap_or(0);
     if live_status != 1 {
-        return Ok(crate::platforms::common::LiveStreamInfo {
+        use crate::platforms::bilibili::streamer_info::{
+            fetch_bilibili_next_schedule, fetch_bilibili_room_news,
+        };
+        let no_extra_headers = reqwest::header::HeaderMap::new();
+        let uid = init_json["data"]["uid"].as_i64().unwrap_or(0);
+        let (notice, next_schedule) = futures_util::future::join(
+            fetch_bilibili_room_news(&client, &no_extra_headers, &room_id),
+            fetch_bilibili_next_schedule(&client, &no_extra_headers, uid),
+        )
+        .await;
+        let info = crate::platforms::common::LiveStreamInfo {
             title: init_json["data"]["title"].as_str().map(|s| s.to_string()),
             anchor_name: init_json["data"]["uname"].as_str().map(|s| s.to_string()),
             avatar: None,
             stream_url: None,
             status: Some(0),
-            error_message: None,
+            error_message: if live_status == 2 {
+                Some("直播间正在轮播，主播未开播".to_string())
+            } else {
+                None
+            },
             upstream_url: None,
             available_streams: None,
             normalized_room_id: None,
             web_rid: None,
-        });
-    }
-
-    enum SelectedStream {
-        Flv(String),
-        Hls(String),
-    }
-
-    fn parse_stream_variants(
-        playurl: &Value,
-        selected_desc: &Option<String>,
-        selected_qn: Option<i32>,
-    ) -> (Vec<StreamVariant>, Option<String>, Vec<String>) {
-        let mut variants: Vec<StreamVariant> = Vec::new();
-        let mut hls_candidates: Vec<String> = Vec::new();
-        let mut flv_candidate: Option<String> = None;
-
-        if let Some(streams) = playurl.get("stream").and_then(|v| v.as_array()) {
-            for stream_item in streams {
-                let protocol_name = stream_item
-                    .get("protocol_name")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                if let Some(formats) = stream_item.get("format").and_then(|v| v.as_array()) {
-                    for format_item in formats {
-                        let format_name = format_item
-                            .get("format_name")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("");
-                        if let Some(codecs) = format_item.get("codec").and_then(|v| v.as_array()) {
-                            for codec_item in codecs {
-                                let base_url = codec_item
-                                    .get("base_url")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("");
-                                if let Some(url_infos) =
-                                    codec_item.get("url_info").and_then(|v| v.as_array())
-                                {
-                                    for ui in url_infos {
-                                        let host =
-                                            ui.get("host").and_then(|v| v.as_str()).unwrap_or("");
-                                        let extra =
-                                            ui.get("extra").and_then(|v| v.as_str()).unwrap_or("");
-                                        let composed = format!("{}{}{}", host, base_url, extra);
-                                        if composed.is_empty() {
-                                            continue;
-                                        }
-
-                                        variants.push(StreamVariant {
-                                            url: composed.clone(),
-                                            format: Some(format_name.to_string()),
-                                            desc: selected_desc.clone(),
-                                            qn: selected_qn,
-                                            protocol: if protocol_name.is_empty() {
-                                                None
-                                            } else {
-                                                Some(protocol_name.clone())
-                                            },
-                                        });
-
-                                        let is_hls_format = matches!(
-                                            format_name,
-                                            "ts" | "fmp4" | "mp4" | "m4s" | "m3u8"
-                                        );
-                                        let is_hls_protocol = protocol_name.contains("hls");
-                                        if is_hls_format || is_hls_protocol {
-                                            hls_candidates.push(composed.clone());
-                                        }
-                                        if format_name == "flv" && flv_candidate.is_none() {
-                                            flv_candidate = Some(composed.clone());
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        (variants, flv_candidate, hls_candidates)
+            is_rotation: Some(live_status == 2),
+            notice,
+            next_schedule,
+            raw,
+            ..Default::default()
+        };
+        return Ok((info, None));
     }
 
     async fn verify_hls_candidates(
         client: &reqwest::Client,
         room_id: &str,
         candidates: &[String],
+        request_id: &str,
     ) -> Option<String> {
         for candidate in candidates.iter().take(4) {
             match client.get(candidate).send().await {
                 Ok(resp) => {
                     if resp.status().is_success() {
                         eprintln!(
-                            "[Bilibili] Verified HLS candidate for room {} -> {}",
-                            room_id, candidate
+                            "[Bilibili][req:{}] Verified HLS candidate for room {} -> {}",
+                            request_id, room_id, candidate
                         );
                         return Some(candidate.clone());
                     } else {
                         eprintln!(
-                            "[Bilibili] HLS candidate returned status {} for room {} -> {}",
+                            "[Bilibili][req:{}] HLS candidate returned status {} for room {} -> {}",
+                            request_id,
                             resp.status(),
                             room_id,
                             candidate
@@ -367,8 +557,8 @@ pub async fn get_bilibili_live_stream_url_with_quality(
                 }
                 Err(err) => {
                     eprintln!(
-                        "[Bilibili] Failed to probe HLS candidate for room {} -> {} ({})",
-                        room_id, candidate, err
+                        "[Bilibili][req:{}] Failed to probe HLS candidate for room {} -> {} ({})",
+                        request_id, room_id, candidate, err
                     );
                 }
             }
@@ -381,20 +571,64 @@ pub async fn get_bilibili_live_stream_url_with_quality(
     let mut variants_for_response: Vec<StreamVariant> = Vec::new();
     let mut fallback_hls_url: Option<String> = None;
     let mut fallback_variants: Option<Vec<StreamVariant>> = None;
+    // 低延迟请求没有拿到 FLV 时改回常规参数，最终是否生效以此为准
+    let mut use_low_latency = low_latency;
 
     for attempt in 0..=MAX_HLS_RETRY {
         let attempt_display = attempt + 1;
-        let playinfo_attempt = request_playinfo(&client, &room_id, selected_qn).await?;
+        let playinfo_attempt =
+            match request_playinfo(&client, &room_id, selected_qn, pwd, use_low_latency).await {
+                Ok(value) => value,
+                Err(e) => {
+                    eprintln!(
+                        "[Bilibili][req:{}] Attempt {} web playinfo failed for room {}: {}",
+                        request_id, attempt_display, room_id, e
+                    );
+                    break;
+                }
+            };
         let playurl_attempt = playinfo_attempt["data"]["playurl_info"]["playurl"].clone();
         let (variants, flv_candidate, hls_candidates) =
-            parse_stream_variants(&playurl_attempt, &selected_desc, selected_qn);
+            parse_stream_variants(&playurl_attempt, &qn_map, &selected_desc, selected_qn);
+
+        if use_low_latency && flv_candidate.is_none() {
+            eprintln!(
+                "[Bilibili][req:{}] Attempt {} got no low latency FLV for room {}, falling back to regular streams",
+                request_id, attempt_display, room_id
+            );
+            use_low_latency = false;
+            continue;
+        }
 
         variants_for_response = variants.clone();
 
+        // 调用方指定了 available_streams 中的下标：直接使用该路流，不再按默认规则挑选
+        if let Some(index) = stream_index {
+            if let Some(variant) = variants.get(index) {
+                eprintln!(
+                    "[Bilibili][req:{}] Using stream index {} for room {} (format={:?}, protocol={:?}, codec={:?})",
+                    request_id, index, room_id, variant.format, variant.protocol, variant.codec
+                );
+                selected_stream = Some(if variant.format.as_deref() == Some("flv") {
+                    SelectedStream::Flv(variant.url.clone())
+                } else {
+                    SelectedStream::Hls(variant.url.clone())
+                });
+                break;
+            }
+            eprintln!(
+                "[Bilibili][req:{}] Stream index {} out of range ({} variants) for room {}, falling back to default selection",
+                request_id,
+                index,
+                variants.len(),
+                room_id
+            );
+        }
+
         if let Some(flv_url) = flv_candidate {
             eprintln!(
-                "[Bilibili] Attempt {} obtained FLV stream for room {}, stop retrying",
-                attempt_display, room_id
+                "[Bilibili][req:{}] Attempt {} obtained FLV stream for room {}, stop retrying",
+                request_id, attempt_display, room_id
             );
             selected_stream = Some(SelectedStream::Flv(flv_url));
             break;
@@ -402,8 +636,8 @@ pub async fn get_bilibili_live_stream_url_with_quality(
 
         if hls_candidates.is_empty() {
             eprintln!(
-                "[Bilibili] Attempt {} returned no HLS candidates for room {}",
-                attempt_display, room_id
+                "[Bilibili][req:{}] Attempt {} returned no HLS candidates for room {}",
+                request_id, attempt_display, room_id
             );
             if attempt == MAX_HLS_RETRY {
                 break;
@@ -415,17 +649,21 @@ pub async fn get_bilibili_live_stream_url_with_quality(
             .into_iter()
             .partition(|url| url.contains("d1--cn"));
 
-        if let Some(url) = verify_hls_candidates(&client, &room_id, &preferred_candidates).await {
+        if let Some(url) =
+            verify_hls_candidates(&client, &room_id, &preferred_candidates, request_id).await
+        {
             eprintln!(
-                "[Bilibili] Selected HLS stream containing 'd1--cn' on attempt {} for room {}",
-                attempt_display, room_id
+                "[Bilibili][req:{}] Selected HLS stream containing 'd1--cn' on attempt {} for room {}",
+                request_id, attempt_display, room_id
             );
             selected_stream = Some(SelectedStream::Hls(url));
             break;
         }
 
         if fallback_hls_url.is_none() {
-            if let Some(url) = verify_hls_candidates(&client, &room_id, &other_candidates).await {
+            if let Some(url) =
+                verify_hls_candidates(&client, &room_id, &other_candidates, request_id).await
+            {
                 fallback_hls_url = Some(url.clone());
                 fallback_variants = Some(variants.clone());
             }
@@ -434,19 +672,19 @@ pub async fn get_bilibili_live_stream_url_with_quality(
         if attempt == MAX_HLS_RETRY {
             if let Some(url) = fallback_hls_url.clone() {
                 eprintln!(
-                    "[Bilibili] Using non 'd1--cn' HLS stream after {} attempts for room {}",
-                    attempt_display, room_id
+                    "[Bilibili][req:{}] Using non 'd1--cn' HLS stream after {} attempts for room {}",
+                    request_id, attempt_display, room_id
                 );
                 selected_stream = Some(SelectedStream::Hls(url));
                 if let Some(fallback) = fallback_variants.clone() {
                     variants_for_response = fallback;
                 }
             } else if let Some(url) =
-                verify_hls_candidates(&client, &room_id, &other_candidates).await
+                verify_hls_candidates(&client, &room_id, &other_candidates, request_id).await
             {
                 eprintln!(
-                    "[Bilibili] Final attempt picked non 'd1--cn' HLS stream for room {}",
-                    room_id
+                    "[Bilibili][req:{}] Final attempt picked non 'd1--cn' HLS stream for room {}",
+                    request_id, room_id
                 );
                 selected_stream = Some(SelectedStream::Hls(url));
                 variants_for_response = variants.clone();
@@ -457,8 +695,8 @@ pub async fn get_bilibili_live_stream_url_with_quality(
     if selected_stream.is_none() {
         if let Some(url) = fallback_hls_url.clone() {
             eprintln!(
-                "[Bilibili] Falling back to cached non 'd1--cn' HLS stream for room {}",
-                room_id
+                "[Bilibili][req:{}] Falling back to cached non 'd1--cn' HLS stream for room {}",
+                request_id, room_id
             );
             selected_stream = Some(SelectedStream::Hls(url));
             if let Some(fallback) = fallback_variants.clone() {
@@ -467,10 +705,30 @@ pub async fn get_bilibili_live_stream_url_with_quality(
         }
     }
 
+    // 网页 playinfo 路径没有产出地址时，回退到旧版 room/v1/Room/playUrl（只下发 FLV）
+    let mut resolver = BILIBILI_RESOLVERS[0];
+    if selected_stream.is_none() {
+        match request_legacy_play_url(&client, &room_id, selected_qn).await {
+            Ok(Some(url)) => {
+                eprintln!(
+                    "[Bilibili][req:{}] Room {} resolved via fallback '{}'",
+                    request_id, room_id, BILIBILI_RESOLVERS[1]
+                );
+                resolver = BILIBILI_RESOLVERS[1];
+                selected_stream = Some(SelectedStream::Flv(url));
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!(
+                "[Bilibili][req:{}] legacy playUrl failed for room {}: {}",
+                request_id, room_id, e
+            ),
+        }
+    }
+
     let selected_stream = match selected_stream {
         Some(stream) => stream,
         None => {
-            return Ok(crate::platforms::common::LiveStreamInfo {
+            let info = crate::platforms::common::LiveStreamInfo {
                 title: init_json["data"]["title"].as_str().map(|s| s.to_string()),
                 anchor_name: init_json["data"]["uname"].as_str().map(|s| s.to_string()),
                 avatar: None,
@@ -481,79 +739,701 @@ pub async fn get_bilibili_live_stream_url_with_quality(
                 available_streams: Some(variants_for_response),
                 normalized_room_id: None,
                 web_rid: None,
-            });
+                ..Default::default()
+            };
+            return Ok((info, None));
         }
     };
 
-    match selected_stream {
-        SelectedStream::Flv(real_url) => {
-            // FLV：写入到 Store 并启动代理
-            let proxied_url = {
-                {
-                    let mut current_url_in_store = stream_url_store.url.lock().unwrap();
-                    *current_url_in_store = real_url.clone();
-                }
-                match start_proxy(app_handle, proxy_server_handle, stream_url_store).await {
-                    Ok(proxy) => Some(proxy),
-                    Err(e) => {
-                        eprintln!("[Bilibili] Failed to start proxy: {}", e);
-                        None
+    let selected_real_url = match &selected_stream {
+        SelectedStream::Flv(url) | SelectedStream::Hls(url) => url.clone(),
+    };
+    crate::proxy::remember_resolved_referer(
+        &selected_real_url,
+        &format!("https://live.bilibili.com/{}", room_id),
+    );
+    let current_qn = variants_for_response
+        .iter()
+        .find(|v| v.url == selected_real_url)
+        .and_then(|v| v.qn);
+    let current_quality_desc = current_qn.and_then(|qn| {
+        qn_map
+            .iter()
+            .find(|(q, _)| *q == qn)
+            .map(|(_, d)| d.clone())
+    });
+    let downgrade = check_downgrade(selected_qn, current_qn, cookie.as_deref());
+    if downgrade.downgraded {
+        eprintln!(
+            "[Bilibili][req:{}] Requested qn {:?} but upstream delivered qn {:?} (login required: {})",
+            request_id, selected_qn, current_qn, downgrade.login_required
+        );
+    }
+    let login_required = Some(downgrade.login_required);
+    let accept_qualities = accepted_qualities(&accept_qn, &qn_map);
+    // 旧版 playUrl 不支持 ptype，经它回退的 FLV 不算低延迟
+    let low_latency_applied = use_low_latency
+        && resolver == BILIBILI_RESOLVERS[0]
+        && matches!(selected_stream, SelectedStream::Flv(_));
+    if low_latency && !low_latency_applied {
+        eprintln!(
+            "[Bilibili][req:{}] Low latency requested but not applied for room {}",
+            request_id, room_id
+        );
+    }
+
+    let info = crate::platforms::common::LiveStreamInfo {
+        title: init_json["data"]["title"].as_str().map(|s| s.to_string()),
+        anchor_name: init_json["data"]["uname"].as_str().map(|s| s.to_string()),
+        avatar: None,
+        stream_url: None,
+        status: Some(1),
+        error_message: None,
+        needs_proxy: Some(crate::proxy::upstream_needs_proxy(&selected_real_url)),
+        upstream_url: Some(selected_real_url),
+        available_streams: Some(variants_for_response),
+        accept_qualities: Some(accept_qualities),
+        normalized_room_id: None,
+        web_rid: None,
+        requested_qn: selected_qn,
+        quality_fallback,
+        current_qn,
+        current_quality_desc,
+        login_required,
+        is_rotation: Some(false),
+        resolver: Some(resolver.to_string()),
+        low_latency: low_latency.then_some(low_latency_applied),
+        raw,
+        ..Default::default()
+    };
+    Ok((info, Some(selected_stream)))
+}
+
+#[derive(Debug, PartialEq)]
+struct Downgrade {
+    downgraded: bool,
+    // 被降级且未携带登录 Cookie：登录后才能拿到请求的清晰度
+    login_required: bool,
+}
+
+// 对比请求的 qn 与上游实际下发的 current_qn：匿名用户请求原画时会被静默降级
+fn check_downgrade(
+    requested: Option<i32>,
+    delivered: Option<i32>,
+    cookie: Option<&str>,
+) -> Downgrade {
+    let downgraded =
+        matches!((requested, delivered), (Some(requested), Some(current)) if current < requested);
+    let has_login_cookie = cookie.map(|c| c.contains("SESSDATA=")).unwrap_or(false);
+    Downgrade {
+        downgraded,
+        login_required: downgraded && !has_login_cookie,
+    }
+}
+
+// accept_qn 单独列出，便于前端展示全部可选清晰度（其中大多数档位没有现成的播放地址）
+fn accepted_qualities(accept_qn: &[i32], qn_map: &[(i32, String)]) -> Vec<AcceptedQuality> {
+    accept_qn
+        .iter()
+        .map(|qn| AcceptedQuality {
+            qn: *qn,
+            desc: qn_map.iter().find(|(q, _)| q == qn).map(|(_, d)| d.clone()),
+        })
+        .collect()
+}
+
+// 低延迟模式：只请求 http_stream（protocol=0）下的 FLV（format=0），并与网页播放器一样带上 ptype=8；
+// 常规模式同时请求 http_stream 与 http_hls 的 flv/ts/fmp4
+const LOW_LATENCY_PTYPE: &str = "8";
+
+fn playinfo_formats(low_latency: bool) -> (&'static str, &'static str) {
+    if low_latency {
+        ("0", "0")
+    } else {
+        ("0,1", "0,1,2")
+    }
+}
+
+// 展开 playurl 中所有 stream/format/codec/url_info 组合（合流/多路房间会有多个 stream），
+// 同时返回首个 FLV 地址与全部 HLS 候选地址
+fn parse_stream_variants(
+    playurl: &Value,
+    qn_map: &[(i32, String)],
+    selected_desc: &Option<String>,
+    selected_qn: Option<i32>,
+) -> (Vec<StreamVariant>, Option<String>, Vec<String>) {
+    let mut variants: Vec<StreamVariant> = Vec::new();
+    let mut hls_candidates: Vec<String> = Vec::new();
+    let mut flv_candidate: Option<String> = None;
+
+    if let Some(streams) = playurl.get("stream").and_then(|v| v.as_array()) {
+        for stream_item in streams {
+            let protocol_name = stream_item
+                .get("protocol_name")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            if let Some(formats) = stream_item.get("format").and_then(|v| v.as_array()) {
+                for format_item in formats {
+                    let format_name = format_item
+                        .get("format_name")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    if let Some(codecs) = format_item.get("codec").and_then(|v| v.as_array()) {
+                        for codec_item in codecs {
+                            let base_url = codec_item
+                                .get("base_url")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("");
+                            let codec_name = codec_item
+                                .get("codec_name")
+                                .and_then(|v| v.as_str())
+                                .filter(|s| !s.is_empty())
+                                .map(|s| s.to_string());
+                            // 合流/多路房间中各 codec 的实际 qn 可能不同，优先使用 current_qn
+                            let codec_qn = codec_item
+                                .get("current_qn")
+                                .and_then(|v| v.as_i64())
+                                .map(|v| v as i32)
+                                .or(selected_qn);
+                            if let Some(url_infos) =
+                                codec_item.get("url_info").and_then(|v| v.as_array())
+                            {
+                                for ui in url_infos {
+                                    let host =
+                                        ui.get("host").and_then(|v| v.as_str()).unwrap_or("");
+                                    let extra =
+                                        ui.get("extra").and_then(|v| v.as_str()).unwrap_or("");
+                                    let composed = format!("{}{}{}", host, base_url, extra);
+                                    if composed.is_empty() {
+                                        continue;
+                                    }
+
+                                    variants.push(StreamVariant {
+                                        url: composed.clone(),
+                                        format: Some(format_name.to_string()),
+                                        desc: codec_qn
+                                            .and_then(|qn| {
+                                                qn_map
+                                                    .iter()
+                                                    .find(|(q, _)| *q == qn)
+                                                    .map(|(_, d)| d.clone())
+                                            })
+                                            .or_else(|| selected_desc.clone()),
+                                        qn: codec_qn,
+                                        protocol: if protocol_name.is_empty() {
+                                            None
+                                        } else {
+                                            Some(protocol_name.clone())
+                                        },
+                                        codec: codec_name.clone(),
+                                        bitrate_kbps: None,
+                                        low_latency: Some(
+                                            protocol_name == "http_stream" && format_name == "flv",
+                                        ),
+                                    });
+
+                                    let is_hls_format = matches!(
+                                        format_name,
+                                        "ts" | "fmp4" | "mp4" | "m4s" | "m3u8"
+                                    );
+                                    let is_hls_protocol = protocol_name.contains("hls");
+                                    if is_hls_format || is_hls_protocol {
+                                        hls_candidates.push(composed.clone());
+                                    }
+                                    if format_name == "flv" && flv_candidate.is_none() {
+                                        flv_candidate = Some(composed.clone());
+                                    }
+                                }
+                            }
+                        }
                     }
                 }
-            };
+            }
+        }
+    }
 
-            let final_error_message = if proxied_url.is_none() {
-                Some("代理启动失败".to_string())
-            } else {
-                None
-            };
+    (variants, flv_candidate, hls_candidates)
+}
 
-            Ok(crate::platforms::common::LiveStreamInfo {
-                title: init_json["data"]["title"].as_str().map(|s| s.to_string()),
-                anchor_name: init_json["data"]["uname"].as_str().map(|s| s.to_string()),
-                avatar: None,
-                stream_url: proxied_url,
-                status: Some(if final_error_message.is_some() { 2 } else { 1 }),
-                error_message: final_error_message,
-                upstream_url: Some(real_url),
-                available_streams: Some(variants_for_response.clone()),
-                normalized_room_id: None,
-                web_rid: None,
-            })
-        }
-        SelectedStream::Hls(real_url) => {
-            // HLS：无需本地代理，若存在旧的 FLV 代理则关闭并清空存储
+// room_init 的 data：封禁优先于隐藏，加密房间在未验证且未提供密码时才拦截
+fn room_restriction(room_data: &Value, has_password: bool) -> Option<RoomRestriction> {
+    let flag = |key: &str| room_data[key].as_bool().unwrap_or(false);
+    if flag("is_locked") {
+        Some(RoomRestriction::RoomLocked {
+            lock_till: room_data["lock_till"].as_i64().filter(|t| *t > 0),
+        })
+    } else if flag("is_hidden") {
+        Some(RoomRestriction::RoomHidden)
+    } else if flag("encrypted") && !flag("pwd_verified") && !has_password {
+        Some(RoomRestriction::RoomEncrypted {
+            password_rejected: false,
+        })
+    } else {
+        None
+    }
+}
+
+// getRoomPlayInfo 对加密房间密码校验失败返回的 code；其他非 0 code（风控、参数错误等）照常走回退解析
+const PLAYINFO_PASSWORD_ERROR_CODE: i64 = 19002005;
+
+// 加密房间提交密码后 playinfo 返回密码错误的 code 才视为密码错误
+fn password_rejected(room_data: &Value, playinfo: &Value) -> bool {
+    room_data["encrypted"].as_bool().unwrap_or(false)
+        && playinfo["code"].as_i64() == Some(PLAYINFO_PASSWORD_ERROR_CODE)
+}
+
+// 解析路径按顺序尝试：网页 getRoomPlayInfo，其次旧版 playUrl
+const BILIBILI_RESOLVERS: [&str; 2] = ["web_playinfo", "room_play_url"];
+
+async fn request_legacy_play_url(
+    client: &reqwest::Client,
+    room_id: &str,
+    qn: Option<i32>,
+) -> Result<Option<String>, String> {
+    let mut params = vec![
+        ("cid", room_id.to_string()),
+        ("platform", "web".to_string()),
+    ];
+    if let Some(q) = qn {
+        params.push(("qn", q.to_string()));
+    }
+    let json: Value = client
+        .get(live_api_url("/room/v1/Room/playUrl"))
+        .query(&params)
+        .send()
+        .await
+        .map_err(|e| format!("playUrl request failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("playUrl JSON parse failed: {}", e))?;
+    if json["code"].as_i64().unwrap_or(-1) != 0 {
+        return Err(format!(
+            "playUrl returned code {}: {}",
+            json["code"],
+            json["message"].as_str().unwrap_or("")
+        ));
+    }
+    Ok(json["data"]["durl"]
+        .as_array()
+        .and_then(|durl| durl.iter().find_map(|d| d["url"].as_str()))
+        .filter(|url| !url.is_empty())
+        .map(|url| url.to_string()))
+}
+
+fn restricted_room_info(
+    init_json: &Value,
+    room_id: &str,
+    restriction: RoomRestriction,
+    request_id: &str,
+) -> crate::platforms::common::LiveStreamInfo {
+    let message = match &restriction {
+        RoomRestriction::RoomLocked { .. } => "直播间已被封禁",
+        RoomRestriction::RoomHidden => "直播间已隐藏",
+        RoomRestriction::RoomEncrypted {
+            password_rejected: false,
+        } => "直播间已加密，请输入房间密码",
+        RoomRestriction::RoomEncrypted {
+            password_rejected: true,
+        } => "房间密码错误",
+    };
+    eprintln!(
+        "[Bilibili][req:{}] Room {} is restricted: {:?}",
+        request_id, room_id, restriction
+    );
+    crate::platforms::common::LiveStreamInfo {
+        title: init_json["data"]["title"].as_str().map(|s| s.to_string()),
+        anchor_name: init_json["data"]["uname"].as_str().map(|s| s.to_string()),
+        status: Some(0),
+        error_message: Some(message.to_string()),
+        restriction: Some(restriction),
+        ..Default::default()
+    }
+}
+
+// 轻量开播状态查询：仅请求 room_init，live_status == 1 视为直播中
+pub async fn fetch_bilibili_live_status(room_id: &str) -> Result<bool, String> {
+    let client = crate::network::api_proxy().apply(reqwest::Client::builder())
+        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36")
+        .build()
+        .map_err(|e| format!("Failed to build client: {}", e))?;
+    let room_init_url = live_api_url(&format!("/room/v1/Room/room_init?id={}", room_id));
+    let init_json: Value = client
+        .get(&room_init_url)
+        .header(REFERER, "https://live.bilibili.com/")
+        .send()
+        .await
+        .map_err(|e| format!("room_init failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("room_init json failed: {}", e))?;
+    if init_json["code"].as_i64().unwrap_or(-1) != 0 {
+        return Err(format!(
+            "room_init returned error: {}",
+            init_json["message"].as_str().unwrap_or("unknown")
+        ));
+    }
+    Ok(init_json["data"]["live_status"].as_i64().unwrap_or(0) == 1)
+}
+
+// 在线人数：get_info 直接给出 online，返回 (是否开播, 人数)
+pub async fn fetch_bilibili_viewers(room_id: &str) -> Result<(bool, Option<u64>), String> {
+    let client = crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
+        .user_agent(user_agent_for(&SupportedPlatformRust::Bilibili))
+        .build()
+        .map_err(|e| format!("Failed to build client: {}", e))?;
+    let info_url = live_api_url(&format!("/room/v1/Room/get_info?room_id={}", room_id));
+    let info_json: Value = client
+        .get(&info_url)
+        .header(REFERER, "https://live.bilibili.com/")
+        .send()
+        .await
+        .map_err(|e| format!("get_info failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("get_info json failed: {}", e))?;
+    parse_viewers(&info_json)
+}
+
+fn parse_viewers(info_json: &Value) -> Result<(bool, Option<u64>), String> {
+    if info_json["code"].as_i64().unwrap_or(-1) != 0 {
+        return Err(format!(
+            "get_info returned error: {}",
+            info_json["message"].as_str().unwrap_or("unknown")
+        ));
+    }
+    let is_live = info_json["data"]["live_status"].as_i64().unwrap_or(0) == 1;
+    Ok((is_live, info_json["data"]["online"].as_u64()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 模拟合流房间的 getRoomPlayInfo playurl，域名与签名参数均为虚构：
+    // http_stream 下 flv，http_hls 下 ts 与 fmp4 两种封装，fmp4 同时提供 avc/hevc
+    const MULTI_STREAM_PLAYURL: &str = r#"{
+        "g_qn_desc": [
+            {"qn": 10000, "desc": "原画"},
+            {"qn": 400, "desc": "蓝光"},
+            {"qn": 250, "desc": "超清"}
+        ],
+        "stream": [
             {
-                let handle_to_stop = { proxy_server_handle.0.lock().unwrap().take() };
-                if let Some(handle) = handle_to_stop {
-                    handle.stop(false).await;
-                    eprintln!("[Bilibili] Stopped existing FLV proxy before using HLS stream");
-                }
-            }
+                "protocol_name": "http_stream",
+                "format": [{
+                    "format_name": "flv",
+                    "codec": [{
+                        "codec_name": "avc",
+                        "current_qn": 10000,
+                        "accept_qn": [10000, 400, 250],
+                        "base_url": "/live-bvc/123/live_1_2.flv?",
+                        "url_info": [
+                            {"host": "https://cn-gdfs-ct-01-01.bilivideo.com", "extra": "expires=1&len=0"},
+                            {"host": "https://d1--cn-gotcha03.bilivideo.com", "extra": "expires=1&len=0"}
+                        ]
+                    }]
+                }]
+            },
             {
-                let mut current_url_in_store = stream_url_store.url.lock().unwrap();
-                *current_url_in_store = String::new();
+                "protocol_name": "http_hls",
+                "format": [
+                    {
+                        "format_name": "ts",
+                        "codec": [{
+                            "codec_name": "avc",
+                            "current_qn": 10000,
+                            "accept_qn": [10000, 400, 250],
+                            "base_url": "/live-bvc/123/live_1_2/index.m3u8?",
+                            "url_info": [
+                                {"host": "https://cn-gdfs-ct-01-01.bilivideo.com", "extra": "expires=1"}
+                            ]
+                        }]
+                    },
+                    {
+                        "format_name": "fmp4",
+                        "codec": [
+                            {
+                                "codec_name": "avc",
+                                "current_qn": 10000,
+                                "accept_qn": [10000, 400, 250],
+                                "base_url": "/live-bvc/123/live_1_2/index.m3u8?",
+                                "url_info": [
+                                    {"host": "https://d1--cn-gotcha03.bilivideo.com", "extra": "expires=1"}
+                                ]
+                            },
+                            {
+                                "codec_name": "hevc",
+                                "current_qn": 400,
+                                "accept_qn": [10000, 400, 250],
+                                "base_url": "/live-bvc/123/live_1_2_h265/index.m3u8?",
+                                "url_info": [
+                                    {"host": "https://d1--cn-gotcha03.bilivideo.com", "extra": "expires=1"}
+                                ]
+                            }
+                        ]
+                    }
+                ]
             }
+        ]
+    }"#;
 
-            // 将 HLS 转成 localhost 代理地址，避免 WebView 直连外网（由 Rust 侧发起真实请求，并遵循 HTTP(S)_PROXY）。
-            let base = start_static_proxy_server(app_handle, stream_url_store)
-                .await
-                .map_err(|e| format!("Failed to start static proxy server: {}", e))?;
-            let base = base.trim_end_matches('/').to_string();
-            let proxied_hls = format!("{}/hls?url={}", base, urlencoding::encode(&real_url));
+    fn qn_map() -> Vec<(i32, String)> {
+        vec![
+            (10000, "原画".to_string()),
+            (400, "蓝光".to_string()),
+            (250, "超清".to_string()),
+        ]
+    }
 
-            Ok(crate::platforms::common::LiveStreamInfo {
-                title: init_json["data"]["title"].as_str().map(|s| s.to_string()),
-                anchor_name: init_json["data"]["uname"].as_str().map(|s| s.to_string()),
-                avatar: None,
-                stream_url: Some(proxied_hls),
-                status: Some(1),
-                error_message: None,
-                upstream_url: Some(real_url),
-                available_streams: Some(variants_for_response),
-                normalized_room_id: None,
-                web_rid: None,
+    #[test]
+    fn enumerates_every_stream_entry() {
+        let playurl: Value = serde_json::from_str(MULTI_STREAM_PLAYURL).unwrap();
+        let (variants, flv, hls) = parse_stream_variants(&playurl, &qn_map(), &None, Some(10000));
+
+        assert_eq!(variants.len(), 5);
+        let labels: Vec<(Option<&str>, Option<&str>, Option<&str>)> = variants
+            .iter()
+            .map(|v| {
+                (
+                    v.format.as_deref(),
+                    v.protocol.as_deref(),
+                    v.codec.as_deref(),
+                )
             })
-        }
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                (Some("flv"), Some("http_stream"), Some("avc")),
+                (Some("flv"), Some("http_stream"), Some("avc")),
+                (Some("ts"), Some("http_hls"), Some("avc")),
+                (Some("fmp4"), Some("http_hls"), Some("avc")),
+                (Some("fmp4"), Some("http_hls"), Some("hevc")),
+            ]
+        );
+        assert_eq!(
+            variants[0].url,
+            "https://cn-gdfs-ct-01-01.bilivideo.com/live-bvc/123/live_1_2.flv?expires=1&len=0"
+        );
+        // 只有 http_stream 下的 FLV 标为低延迟
+        let low_latency: Vec<Option<bool>> = variants.iter().map(|v| v.low_latency).collect();
+        assert_eq!(
+            low_latency,
+            vec![
+                Some(true),
+                Some(true),
+                Some(false),
+                Some(false),
+                Some(false)
+            ]
+        );
+
+        // 首个 FLV 作为默认选择，HLS 候选按出现顺序保留
+        assert_eq!(flv.as_deref(), Some(variants[0].url.as_str()));
+        assert_eq!(
+            hls,
+            vec![
+                variants[2].url.clone(),
+                variants[3].url.clone(),
+                variants[4].url.clone()
+            ]
+        );
+    }
+
+    #[test]
+    fn low_latency_requests_only_http_stream_flv() {
+        assert_eq!(playinfo_formats(true), ("0", "0"));
+        assert_eq!(playinfo_formats(false), ("0,1", "0,1,2"));
+    }
+
+    #[test]
+    fn labels_each_variant_with_its_own_qn() {
+        let playurl: Value = serde_json::from_str(MULTI_STREAM_PLAYURL).unwrap();
+        let (variants, _, _) =
+            parse_stream_variants(&playurl, &qn_map(), &Some("原画".to_string()), Some(10000));
+
+        // hevc 路的 current_qn 与请求的不同，描述按它自己的 qn 换算
+        let hevc = variants
+            .iter()
+            .find(|v| v.codec.as_deref() == Some("hevc"))
+            .unwrap();
+        assert_eq!(hevc.qn, Some(400));
+        assert_eq!(hevc.desc.as_deref(), Some("蓝光"));
+        assert!(variants
+            .iter()
+            .filter(|v| v.codec.as_deref() == Some("avc"))
+            .all(|v| v.qn == Some(10000) && v.desc.as_deref() == Some("原画")));
+    }
+
+    // 匿名请求原画时上游只下发蓝光：codec 的 current_qn 低于请求的 qn
+    const DOWNGRADED_PLAYURL: &str = r#"{
+        "g_qn_desc": [
+            {"qn": 10000, "desc": "原画"},
+            {"qn": 400, "desc": "蓝光"},
+            {"qn": 250, "desc": "超清"}
+        ],
+        "stream": [{
+            "protocol_name": "http_stream",
+            "format": [{
+                "format_name": "flv",
+                "codec": [{
+                    "codec_name": "avc",
+                    "current_qn": 400,
+                    "accept_qn": [10000, 400, 250],
+                    "base_url": "/live-bvc/123/live_1_2_2500.flv?",
+                    "url_info": [{"host": "https://cn-gdfs-ct-01-01.bilivideo.com", "extra": "expires=1"}]
+                }]
+            }]
+        }]
+    }"#;
+
+    #[test]
+    fn anonymous_downgrade_requires_login() {
+        let playurl: Value = serde_json::from_str(DOWNGRADED_PLAYURL).unwrap();
+        let (variants, flv, _) =
+            parse_stream_variants(&playurl, &qn_map(), &Some("原画".to_string()), Some(10000));
+        let delivered = variants
+            .iter()
+            .find(|v| Some(&v.url) == flv.as_ref())
+            .and_then(|v| v.qn);
+        assert_eq!(delivered, Some(400));
+        assert_eq!(variants[0].desc.as_deref(), Some("蓝光"));
+
+        assert_eq!(
+            check_downgrade(Some(10000), delivered, None),
+            Downgrade {
+                downgraded: true,
+                login_required: true,
+            }
+        );
+        // 没有 SESSDATA 的 Cookie 不算登录
+        assert!(check_downgrade(Some(10000), delivered, Some("buvid3=abc")).login_required);
+    }
+
+    #[test]
+    fn logged_in_downgrade_does_not_ask_for_login() {
+        assert_eq!(
+            check_downgrade(Some(10000), Some(400), Some("buvid3=abc; SESSDATA=xyz")),
+            Downgrade {
+                downgraded: true,
+                login_required: false,
+            }
+        );
+    }
+
+    #[test]
+    fn delivered_requested_qn_is_not_a_downgrade() {
+        let none = Downgrade {
+            downgraded: false,
+            login_required: false,
+        };
+        assert_eq!(check_downgrade(Some(400), Some(400), None), none);
+        assert_eq!(check_downgrade(Some(250), Some(400), None), none);
+        // 任一方未知时无法判断，不提示
+        assert_eq!(check_downgrade(None, Some(400), None), none);
+        assert_eq!(check_downgrade(Some(10000), None, None), none);
+    }
+
+    #[test]
+    fn accept_qn_is_listed_with_descriptions() {
+        let qualities = accepted_qualities(&[10000, 400, 150], &qn_map());
+        let listed: Vec<(i32, Option<&str>)> = qualities
+            .iter()
+            .map(|q| (q.qn, q.desc.as_deref()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![(10000, Some("原画")), (400, Some("蓝光")), (150, None)]
+        );
+    }
+
+    #[test]
+    fn empty_playurl_yields_no_variants() {
+        let (variants, flv, hls) = parse_stream_variants(&Value::Null, &qn_map(), &None, None);
+        assert!(variants.is_empty());
+        assert!(flv.is_none());
+        assert!(hls.is_empty());
+    }
+
+    #[test]
+    fn detects_each_restricted_room_state() {
+        let locked =
+            serde_json::json!({"is_locked": true, "lock_till": 1893456000, "is_hidden": true});
+        assert_eq!(
+            room_restriction(&locked, false),
+            Some(RoomRestriction::RoomLocked {
+                lock_till: Some(1893456000)
+            })
+        );
+        let locked_forever = serde_json::json!({"is_locked": true, "lock_till": 0});
+        assert_eq!(
+            room_restriction(&locked_forever, false),
+            Some(RoomRestriction::RoomLocked { lock_till: None })
+        );
+        let hidden = serde_json::json!({"is_locked": false, "is_hidden": true});
+        assert_eq!(
+            room_restriction(&hidden, false),
+            Some(RoomRestriction::RoomHidden)
+        );
+        let encrypted = serde_json::json!({"encrypted": true, "pwd_verified": false});
+        assert_eq!(
+            room_restriction(&encrypted, false),
+            Some(RoomRestriction::RoomEncrypted {
+                password_rejected: false
+            })
+        );
+        // 提供了密码或已验证过时继续取流
+        assert_eq!(room_restriction(&encrypted, true), None);
+        let verified = serde_json::json!({"encrypted": true, "pwd_verified": true});
+        assert_eq!(room_restriction(&verified, false), None);
+        let open = serde_json::json!({"is_locked": false, "is_hidden": false, "encrypted": false});
+        assert_eq!(room_restriction(&open, false), None);
+        assert_eq!(room_restriction(&Value::Null, false), None);
+    }
+
+    #[test]
+    fn rejected_password_is_reported() {
+        let encrypted = serde_json::json!({"encrypted": true});
+        let rejected = serde_json::json!({"code": 19002005, "message": "密码错误"});
+        let accepted = serde_json::json!({"code": 0, "data": {}});
+        assert!(password_rejected(&encrypted, &rejected));
+        assert!(!password_rejected(&encrypted, &accepted));
+        assert!(!password_rejected(&serde_json::json!({}), &rejected));
+        // 其他错误（如风控 -352）不当作密码错误
+        let other_error = serde_json::json!({"code": -352, "message": "-352"});
+        assert!(!password_rejected(&encrypted, &other_error));
+        assert!(!password_rejected(&encrypted, &Value::Null));
+
+        let init = serde_json::json!({"data": {"title": "加密房", "uname": "主播"}});
+        let info = restricted_room_info(
+            &init,
+            "7734200",
+            RoomRestriction::RoomEncrypted {
+                password_rejected: true,
+            },
+            "test",
+        );
+        assert_eq!(info.error_message.as_deref(), Some("房间密码错误"));
+        assert_eq!(info.title.as_deref(), Some("加密房"));
+        assert_eq!(info.status, Some(0));
+        assert!(info.stream_url.is_none());
+    }
+
+    #[test]
+    fn parses_viewers_from_room_info() {
+        // get_info：轮播（live_status=2）不算开播，缺少 online 时为 None
+        let live = serde_json::json!({"code": 0, "data": {"live_status": 1, "online": 12345}});
+        assert_eq!(parse_viewers(&live), Ok((true, Some(12345))));
+        let rotation = serde_json::json!({"code": 0, "data": {"live_status": 2, "online": 0}});
+        assert_eq!(parse_viewers(&rotation), Ok((false, Some(0))));
+        let missing = serde_json::json!({"code": 0, "data": {"live_status": 1}});
+        assert_eq!(parse_viewers(&missing), Ok((true, None)));
+        let error = serde_json::json!({"code": 1, "message": "房间不存在"});
+        assert_eq!(
+            parse_viewers(&error),
+            Err("get_info returned error: 房间不存在".to_string())
+        );
     }
 }
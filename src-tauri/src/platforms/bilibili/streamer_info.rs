@@ -1,4 +1,5 @@
-use crate::platforms::common::FollowHttpClient;
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::{FollowHttpClient, SupportedPlatformRust};
 use md5;
 use md5::{Digest, Md5};
 use reqwest::header::{HeaderMap, HeaderValue, COOKIE, REFERER, USER_AGENT};
@@ -99,27 +100,93 @@ fn build_wbi_sign(room_id: &str, img_key: &str, sub_key: &str) -> (String, Strin
     (wts, w_rid)
 }
 
+fn non_empty_text(value: &Value) -> Option<String> {
+    value
+        .as_str()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+// 房间公告（room news），未开播时展示给用户
+pub(crate) async fn fetch_bilibili_room_news(
+    client: &reqwest::Client,
+    headers: &HeaderMap,
+    room_id: &str,
+) -> Option<String> {
+    let url = format!(
+        "https://api.live.bilibili.com/room_ex/v1/RoomNews/get?roomid={}",
+        room_id
+    );
+    let json: Value = client
+        .get(url)
+        .headers(headers.clone())
+        .send()
+        .await
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    non_empty_text(&json["data"]["content"])
+}
+
+// 预约列表中最近一个晚于 now 的开播时间（秒）；live_plan_start_time 缺失时用 stime
+fn next_schedule_timestamp(json: &Value, now: i64) -> Option<i64> {
+    json["data"]
+        .as_array()?
+        .iter()
+        .filter_map(|item| {
+            item["live_plan_start_time"]
+                .as_i64()
+                .filter(|ts| *ts > 0)
+                .or_else(|| item["stime"].as_i64())
+        })
+        .filter(|ts| *ts > now)
+        .min()
+}
+
+// 主播的直播预约：取最近一个尚未开始的预约时间，格式化为本地时间
+pub(crate) async fn fetch_bilibili_next_schedule(
+    client: &reqwest::Client,
+    headers: &HeaderMap,
+    uid: i64,
+) -> Option<String> {
+    if uid <= 0 {
+        return None;
+    }
+    let url = format!("https://api.bilibili.com/x/space/reservation?vmid={}", uid);
+    let json: Value = client
+        .get(url)
+        .headers(headers.clone())
+        .send()
+        .await
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    let next_ts = next_schedule_timestamp(&json, chrono::Utc::now().timestamp())?;
+    chrono::DateTime::from_timestamp(next_ts, 0).map(|dt| {
+        dt.with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    })
+}
+
 #[command]
 pub async fn fetch_bilibili_streamer_info(
     payload: crate::platforms::common::GetStreamUrlPayload,
     cookie: Option<String>,
     follow_http: State<'_, FollowHttpClient>,
 ) -> Result<crate::platforms::common::LiveStreamInfo, String> {
-    let room_id = payload.args.room_id_str.clone();
-    if room_id.trim().is_empty() {
-        return Ok(crate::platforms::common::LiveStreamInfo {
-            title: None,
-            anchor_name: None,
-            avatar: None,
-            stream_url: None,
-            status: None,
-            error_message: Some("房间ID未提供".to_string()),
-            upstream_url: None,
-            available_streams: None,
-            normalized_room_id: None,
-            web_rid: None,
-        });
-    }
+    let room_id =
+        match validate_room_id(&SupportedPlatformRust::Bilibili, &payload.args.room_id_str) {
+            Ok(room_id) => room_id,
+            Err(e) => {
+                return Ok(crate::platforms::common::LiveStreamInfo {
+                    error_message: Some(e.to_string()),
+                    ..Default::default()
+                });
+            }
+        };
 
     let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36";
 
@@ -139,7 +206,8 @@ pub async fn fetch_bilibili_streamer_info(
         }
     }
 
-    let client = &follow_http.0.inner;
+    let follow_client = follow_http.client();
+    let client = &follow_client.inner;
 
     // Get WBI keys and build sign
     let (img_key, sub_key) = get_wbi_keys(client, &headers).await?;
@@ -173,24 +241,45 @@ pub async fn fetch_bilibili_streamer_info(
             available_streams: None,
             normalized_room_id: None,
             web_rid: None,
+            ..Default::default()
         });
     }
     let j: Value = serde_json::from_str(&text)
         .map_err(|e| format!("Room info JSON parse failed: {} | body: {}", e, text))?;
     let data = j["data"].clone();
+    let info = parse_room_info(&data);
+    let live_status = info.status.unwrap_or(0);
+
+    // 未开播（含轮播）时补充公告与预约开播时间
+    let (notice, next_schedule) = if live_status != 1 {
+        let uid = data["room_info"]["uid"].as_i64().unwrap_or(0);
+        (
+            non_empty_text(&data["news_info"]["content"]),
+            fetch_bilibili_next_schedule(client, &headers, uid).await,
+        )
+    } else {
+        (None, None)
+    };
 
-    let base_info = data["anchor_info"]["base_info"].clone();
-    let room_info = data["room_info"].clone();
+    Ok(crate::platforms::common::LiveStreamInfo {
+        notice,
+        next_schedule,
+        ..info
+    })
+}
 
-    let title = room_info["title"].as_str().map(|s| s.to_string());
-    let anchor_name = base_info["uname"].as_str().map(|s| s.to_string());
-    let avatar = base_info["face"].as_str().map(|s| s.to_string());
+// getInfoByRoom 的 data 换算为房间信息；live_status 原样作为 status，2（轮播）单独标记 is_rotation
+fn parse_room_info(data: &Value) -> crate::platforms::common::LiveStreamInfo {
+    let base_info = &data["anchor_info"]["base_info"];
+    let room_info = &data["room_info"];
     let live_status = room_info["live_status"].as_i64().unwrap_or(0) as i32;
 
-    Ok(crate::platforms::common::LiveStreamInfo {
-        title,
-        anchor_name,
-        avatar,
+    crate::platforms::common::LiveStreamInfo {
+        title: room_info["title"].as_str().map(|s| s.to_string()),
+        anchor_name: base_info["uname"].as_str().map(|s| s.to_string()),
+        avatar: base_info["face"]
+            .as_str()
+            .map(crate::proxy::proxied_image_url),
         stream_url: None,
         status: Some(live_status),
         error_message: None,
@@ -198,5 +287,78 @@ pub async fn fetch_bilibili_streamer_info(
         available_streams: None,
         normalized_room_id: None,
         web_rid: None,
-    })
+        is_rotation: Some(live_status == 2),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 轮播中的房间：手写的 getInfoByRoom data，只保留解析用到的字段
+    const CAROUSEL_ROOM_INFO: &str = r#"{
+        "room_info": {
+            "uid": 123456,
+            "room_id": 7734200,
+            "title": "回放：昨晚的直播",
+            "live_status": 2,
+            "live_start_time": 0
+        },
+        "anchor_info": {
+            "base_info": {
+                "uname": "测试主播",
+                "face": "https://i0.hdslb.com/bfs/face/abc.jpg"
+            }
+        },
+        "news_info": {"content": "今晚八点开播"}
+    }"#;
+
+    #[test]
+    fn carousel_room_is_marked_as_rotation() {
+        let data: Value = serde_json::from_str(CAROUSEL_ROOM_INFO).unwrap();
+        let info = parse_room_info(&data);
+        assert_eq!(info.status, Some(2));
+        assert_eq!(info.is_rotation, Some(true));
+        assert_eq!(info.title.as_deref(), Some("回放：昨晚的直播"));
+        assert_eq!(info.anchor_name.as_deref(), Some("测试主播"));
+        assert_eq!(
+            info.avatar.as_deref(),
+            Some("https://i0.hdslb.com/bfs/face/abc.jpg")
+        );
+    }
+
+    #[test]
+    fn live_and_offline_rooms_are_not_rotation() {
+        let mut data: Value = serde_json::from_str(CAROUSEL_ROOM_INFO).unwrap();
+        data["room_info"]["live_status"] = 1.into();
+        let info = parse_room_info(&data);
+        assert_eq!((info.status, info.is_rotation), (Some(1), Some(false)));
+
+        data["room_info"]["live_status"] = 0.into();
+        let info = parse_room_info(&data);
+        assert_eq!((info.status, info.is_rotation), (Some(0), Some(false)));
+    }
+
+    #[test]
+    fn picks_the_nearest_upcoming_reservation() {
+        // 预约列表里混有只给 stime 的条目和已经过去的预约
+        let json = serde_json::json!({
+            "data": [
+                {"live_plan_start_time": 1_700_000_000},
+                {"live_plan_start_time": 1_700_007_200},
+                {"live_plan_start_time": 0, "stime": 1_700_003_600},
+                {"stime": 1_699_990_000}
+            ]
+        });
+        assert_eq!(
+            next_schedule_timestamp(&json, 1_700_000_000),
+            Some(1_700_003_600)
+        );
+        assert_eq!(next_schedule_timestamp(&json, 1_700_007_200), None);
+        assert_eq!(
+            next_schedule_timestamp(&serde_json::json!({"data": null}), 0),
+            None
+        );
+    }
 }
@@ -39,13 +39,18 @@ pub struct BiliLiveClient {
     heartbeat_interval: Duration,
     // Pending messages parsed from current/previous frames
     pending: VecDeque<BiliMessage>,
+    // Endpoint actually used by the current connection
+    ws_url: String,
+    ws_protocol: Option<String>,
+    // 读取出错后置位，由上层按重连策略决定何时调用 reconnect
+    disconnected: bool,
 }
 
 impl BiliLiveClient {
     pub fn new_with_cookie(cookies: &str, room_id: &str) -> Self {
         let (v, auth) = init_server_with_cookie(cookies, room_id);
         ws_debug!("[websocket] server_info host_list: {:?}", v["host_list"]);
-        let ws = connect(v["host_list"].clone());
+        let (ws, ws_url, ws_protocol) = connect(v["host_list"].clone());
         ws_debug!("[websocket] connected via cookie for room {}", room_id);
         BiliLiveClient {
             ws,
@@ -54,13 +59,16 @@ impl BiliLiveClient {
             last_heartbeat: Instant::now(),
             heartbeat_interval: Duration::from_secs(30),
             pending: VecDeque::new(),
+            ws_url,
+            ws_protocol,
+            disconnected: false,
         }
     }
 
     pub fn new_without_cookie(room_id: &str) -> Self {
         let (v, auth) = init_server_no_cookie(room_id);
         ws_debug!("[websocket] server_info host_list: {:?}", v["host_list"]);
-        let ws = connect(v["host_list"].clone());
+        let (ws, ws_url, ws_protocol) = connect(v["host_list"].clone());
         ws_debug!("[websocket] connected without cookie for room {}", room_id);
         BiliLiveClient {
             ws,
@@ -69,9 +77,24 @@ impl BiliLiveClient {
             last_heartbeat: Instant::now(),
             heartbeat_interval: Duration::from_secs(30),
             pending: VecDeque::new(),
+            ws_url,
+            ws_protocol,
+            disconnected: false,
         }
     }
 
+    pub fn ws_url(&self) -> &str {
+        &self.ws_url
+    }
+
+    pub fn ws_protocol(&self) -> Option<String> {
+        self.ws_protocol.clone()
+    }
+
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected
+    }
+
     pub fn send_auth(&mut self) {
         let pkt = make_packet(self.auth_msg.as_str(), Operation::AUTH);
         ws_debug!("[websocket] sending auth packet, len={}", pkt.len());
@@ -95,27 +118,31 @@ impl BiliLiveClient {
     }
 
     // Try to reconnect using the cached host list, and re-authenticate
-    fn reconnect(&mut self) {
+    pub fn reconnect(&mut self) -> bool {
         for attempt in 1..=2 {
             ws_debug!("[websocket] attempting reconnect (attempt {attempt}/2)...");
             match std::panic::catch_unwind({
                 let host_list = self.host_list.clone();
                 move || connect(host_list)
             }) {
-                Ok(new_ws) => {
+                Ok((new_ws, ws_url, ws_protocol)) => {
                     self.ws = new_ws;
+                    self.ws_url = ws_url;
+                    self.ws_protocol = ws_protocol;
                     ws_debug!(
                         "[websocket] reconnect successful on attempt {attempt}, resending auth"
                     );
                     self.send_auth();
-                    return;
+                    self.disconnected = false;
+                    return true;
                 }
                 Err(_) => {
                     ws_debug!("[websocket] reconnect attempt {attempt} failed");
                 }
             }
         }
-        ws_debug!("[websocket] reconnect failed after 2 attempts; will retry on next read cycle");
+        ws_debug!("[websocket] reconnect failed after 2 attempts");
+        false
     }
 
     // Parse one frame and collect all messages into pending queue
@@ -255,10 +282,13 @@ impl BiliLiveClient {
                 }
                 Err(e) => {
                     ws_debug!("[websocket] read error: {:?}", e);
-                    // try to reconnect on read error
-                    self.reconnect();
+                    // 交给上层按重连策略处理
+                    self.disconnected = true;
                 }
             }
+        } else {
+            // 连接已关闭
+            self.disconnected = true;
         }
         None
     }
@@ -315,7 +345,7 @@ fn find_server(vd: Vec<DanmuServer>) -> (String, String, String) {
     )
 }
 
-pub fn connect(v: Value) -> WebSocket<TlsStream<TcpStream>> {
+pub fn connect(v: Value) -> (WebSocket<TlsStream<TcpStream>>, String, Option<String>) {
     let danmu_server = gen_damu_list(&v);
     let (host, url, ws_url) = find_server(danmu_server);
     ws_debug!("[websocket] connecting tcp {} and ws {}", url, ws_url);
@@ -323,10 +353,15 @@ pub fn connect(v: Value) -> WebSocket<TlsStream<TcpStream>> {
     let stream: TcpStream = TcpStream::connect(url).unwrap();
     let stream: native_tls::TlsStream<TcpStream> =
         connector.connect(host.as_str(), stream).unwrap();
-    let (socket, _resp) =
+    let (socket, resp) =
         client(Url::parse(ws_url.as_str()).unwrap(), stream).expect("Can't connect");
     ws_debug!("[websocket] websocket handshake complete");
-    socket
+    let protocol = resp
+        .headers()
+        .get("Sec-WebSocket-Protocol")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    (socket, ws_url, protocol)
 }
 
 pub enum Operation {
@@ -410,6 +445,7 @@ pub fn handle(json: Value) -> Option<BiliMessage> {
                 .unwrap_or("<unknown>")
                 .to_string(),
             text: json["info"][1].as_str().unwrap_or("").to_string(),
+            sent_at: json["info"][0][4].as_i64().filter(|ts| *ts > 0),
         }),
         "SEND_GIFT" => Some(BiliMessage::Gift {
             user: json["info"][2][1]
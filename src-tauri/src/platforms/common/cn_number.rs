@@ -0,0 +1,32 @@
+// 解析/格式化各平台常见的中文数字（如 "101.8万"、"1.2亿"、"3,456"）
+
+pub fn parse_cn_number(input: &str) -> Option<u64> {
+    let trimmed = input.trim().replace(',', "");
+    if trimmed.is_empty() {
+        return None;
+    }
+    let (number_part, multiplier) = if let Some(rest) = trimmed.strip_suffix('亿') {
+        (rest, 100_000_000f64)
+    } else if let Some(rest) = trimmed.strip_suffix('万') {
+        (rest, 10_000f64)
+    } else if let Some(rest) = trimmed.strip_suffix(['w', 'W']) {
+        (rest, 10_000f64)
+    } else {
+        (trimmed.as_str(), 1f64)
+    };
+    let value: f64 = number_part.trim().parse().ok()?;
+    if !value.is_finite() || value < 0.0 {
+        return None;
+    }
+    Some((value * multiplier).round() as u64)
+}
+
+pub fn format_cn_number(value: u64) -> String {
+    if value >= 100_000_000 {
+        format!("{:.1}亿", value as f64 / 100_000_000f64)
+    } else if value >= 10_000 {
+        format!("{:.1}万", value as f64 / 10_000f64)
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use serde_json::Value;
+use tauri::Window;
+
+use super::danmaku_pause;
+use super::events::{emit_event_to, DtvEvent};
+use super::user_agent::user_agent_for;
+use super::{DanmakuFrontendPayload, SupportedPlatformRust};
+
+const HISTORY_TIMEOUT: Duration = Duration::from_secs(3);
+const DEFAULT_HISTORY_LIMIT: usize = 20;
+const MAX_HISTORY_LIMIT: usize = 50;
+
+// 加入房间时补发最近的弹幕，避免聊天区在新消息到达前一片空白。
+// 目前只实现了 B 站（dM/gethistory）。斗鱼的弹幕只通过 WebSocket 实时推送，
+// 找不到可用的公开历史接口，斗鱼历史弹幕不在本功能范围内；虎牙/抖音同样没有，直接返回空列表
+pub async fn fetch_history(
+    platform: &SupportedPlatformRust,
+    room_id: &str,
+    limit: usize,
+) -> Result<Vec<DanmakuFrontendPayload>, String> {
+    match platform {
+        SupportedPlatformRust::Bilibili => fetch_bilibili_history(room_id, limit).await,
+        SupportedPlatformRust::Douyu
+        | SupportedPlatformRust::Huya
+        | SupportedPlatformRust::Douyin => Ok(Vec::new()),
+    }
+}
+
+async fn fetch_bilibili_history(
+    room_id: &str,
+    limit: usize,
+) -> Result<Vec<DanmakuFrontendPayload>, String> {
+    let client = crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
+        .user_agent(user_agent_for(&SupportedPlatformRust::Bilibili))
+        .timeout(HISTORY_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to build client: {}", e))?;
+    let json: Value = client
+        .get("https://api.live.bilibili.com/xlive/web-room/v1/dM/gethistory")
+        .query(&[("roomid", room_id)])
+        .header("Referer", format!("https://live.bilibili.com/{}", room_id))
+        .send()
+        .await
+        .map_err(|e| format!("Request danmaku history failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse danmaku history: {}", e))?;
+    parse_bilibili_history(&json, room_id, limit)
+}
+
+fn parse_bilibili_history(
+    json: &Value,
+    room_id: &str,
+    limit: usize,
+) -> Result<Vec<DanmakuFrontendPayload>, String> {
+    if json.get("code").and_then(|c| c.as_i64()) != Some(0) {
+        return Err(format!(
+            "Danmaku history API error: {}",
+            json.get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("unknown")
+        ));
+    }
+
+    let items = json
+        .pointer("/data/room")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    // 接口按时间正序返回，只保留最新的 limit 条
+    let skip = items.len().saturating_sub(limit);
+    Ok(items
+        .iter()
+        .skip(skip)
+        .filter_map(|item| {
+            let content = item.get("text").and_then(|v| v.as_str())?.to_string();
+            let sent_at = item
+                .pointer("/check_info/ts")
+                .and_then(|v| v.as_i64())
+                .map(|ts| ts * 1000);
+            Some(DanmakuFrontendPayload {
+                platform: "bilibili".to_string(),
+                room_id: room_id.to_string(),
+                user: item
+                    .get("nickname")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                content,
+                user_level: item
+                    .pointer("/user_level/0")
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(0),
+                fans_club_level: item
+                    .pointer("/medal/0")
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(0) as i32,
+                history: true,
+                ..DanmakuFrontendPayload::received(sent_at)
+            })
+        })
+        .collect())
+}
+
+// 窗口订阅房间时调用，只发给该窗口（已在看的窗口不会重复收到）。
+// 在后台获取，不阻塞上游连接的建立；历史弹幕带 history 标记和原始发送时间，
+// 偶尔晚于首批实时弹幕到达时前端可据此区分。与实时弹幕一样遵循暂停状态；获取失败只记录日志
+pub fn spawn_history(window: &Window, platform: SupportedPlatformRust, room_id: &str) {
+    let window = window.clone();
+    let room_id = room_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        emit_history(&window, &platform, &room_id).await;
+    });
+}
+
+async fn emit_history(window: &Window, platform: &SupportedPlatformRust, room_id: &str) {
+    match fetch_history(platform, room_id, DEFAULT_HISTORY_LIMIT).await {
+        Ok(items) => {
+            if !items.is_empty() {
+                println!(
+                    "[Danmaku] replaying {} history messages for {} room {}",
+                    items.len(),
+                    platform.as_str(),
+                    room_id
+                );
+            }
+            for item in items {
+                let event = DtvEvent::DanmakuMessage(item);
+                if danmaku_pause::admit(platform.as_str(), room_id, &event) {
+                    emit_event_to(window, window.label(), event);
+                }
+            }
+        }
+        Err(e) => eprintln!(
+            "[Danmaku] history for {} room {} unavailable: {}",
+            platform.as_str(),
+            room_id,
+            e
+        ),
+    }
+}
+
+#[tauri::command]
+pub async fn fetch_danmaku_history(
+    platform: String,
+    room_id: String,
+    limit: Option<usize>,
+) -> Result<Vec<DanmakuFrontendPayload>, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let limit = limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .clamp(1, MAX_HISTORY_LIMIT);
+    fetch_history(&platform, room_id.trim(), limit).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // 按 dM/gethistory 的字段结构手写的合成数据
+    fn history_body() -> Value {
+        json!({
+            "code": 0,
+            "message": "0",
+            "data": {
+                "room": [
+                    { "text": "第一条", "nickname": "a", "user_level": [3, 0], "medal": [5, "x"], "check_info": { "ts": 1700000000 } },
+                    { "text": "第二条", "nickname": "b", "user_level": [], "medal": [], "check_info": { "ts": 1700000001 } },
+                    { "nickname": "no-text" },
+                    { "text": "第三条", "nickname": "c" }
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn parses_bilibili_history_items() {
+        let items = parse_bilibili_history(&history_body(), "6", 20).unwrap();
+        let contents: Vec<_> = items.iter().map(|i| i.content.as_str()).collect();
+        assert_eq!(contents, ["第一条", "第二条", "第三条"]);
+        assert!(items.iter().all(|i| i.history && i.room_id == "6"));
+        assert_eq!(items[0].user, "a");
+        assert_eq!(items[0].user_level, 3);
+        assert_eq!(items[0].fans_club_level, 5);
+        assert_eq!(items[0].sent_at, Some(1_700_000_000_000));
+        assert_eq!(items[1].user_level, 0);
+        assert_eq!(items[2].sent_at, None);
+    }
+
+    #[test]
+    fn keeps_only_the_latest_items() {
+        let items = parse_bilibili_history(&history_body(), "6", 2).unwrap();
+        let contents: Vec<_> = items.iter().map(|i| i.content.as_str()).collect();
+        // 截取发生在过滤之前，缺少 text 的条目也占一个位置
+        assert_eq!(contents, ["第三条"]);
+    }
+
+    #[test]
+    fn reports_api_errors() {
+        let body = json!({ "code": -400, "message": "bad roomid" });
+        let err = parse_bilibili_history(&body, "6", 20).unwrap_err();
+        assert!(err.contains("bad roomid"));
+    }
+}
@@ -0,0 +1,99 @@
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tauri::Window;
+
+use super::danmaku_subscribers::emit_to_subscribers;
+use super::events::DtvEvent;
+use super::SupportedPlatformRust;
+
+// 暂停期间最多缓存的事件数量，超出后丢弃最旧的
+const MAX_BUFFERED_EVENTS: usize = 500;
+
+#[derive(Default)]
+struct PauseEntry {
+    paused: bool,
+    buffer: bool,
+    pending: VecDeque<DtvEvent>,
+}
+
+// 按 (platform, room_id) 记录的弹幕暂停状态；WS 与心跳保持不变，只拦截发往前端的事件
+static PAUSE_REGISTRY: Lazy<Mutex<HashMap<String, PauseEntry>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn registry_key(platform: &str, room_id: &str) -> String {
+    format!("{}:{}", platform, room_id)
+}
+
+// 监听器在 emit 之前调用：未暂停返回 true；暂停时按 buffer 标志缓存或丢弃并返回 false
+pub fn admit(platform: &str, room_id: &str, event: &DtvEvent) -> bool {
+    let mut registry = PAUSE_REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let entry = match registry.get_mut(&registry_key(platform, room_id)) {
+        Some(entry) if entry.paused => entry,
+        _ => return true,
+    };
+    if entry.buffer {
+        if entry.pending.len() >= MAX_BUFFERED_EVENTS {
+            entry.pending.pop_front();
+        }
+        entry.pending.push_back(event.clone());
+    }
+    false
+}
+
+// 房间关闭时丢弃暂停状态与缓存
+pub fn forget(platform: &str, room_id: &str) {
+    let mut registry = PAUSE_REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    registry.remove(&registry_key(platform, room_id));
+}
+
+#[tauri::command]
+pub async fn pause_danmaku(
+    platform: String,
+    room_id: String,
+    buffer: Option<bool>,
+) -> Result<(), String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let mut registry = PAUSE_REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let entry = registry
+        .entry(registry_key(platform.as_str(), &room_id))
+        .or_default();
+    entry.paused = true;
+    entry.buffer = buffer.unwrap_or(false);
+    println!(
+        "[Danmaku] paused {} room {} (buffer={})",
+        platform.as_str(),
+        room_id,
+        entry.buffer
+    );
+    Ok(())
+}
+
+// 恢复推送，并把暂停期间缓存的事件按原顺序补发；返回补发数量。
+// 补发与实时弹幕一样只投递给订阅该房间的窗口（没有订阅者时投递给调用的窗口），不广播到所有窗口
+#[tauri::command]
+pub async fn resume_danmaku(
+    window: Window,
+    platform: String,
+    room_id: String,
+) -> Result<usize, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let pending = {
+        let mut registry = PAUSE_REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+        match registry.remove(&registry_key(platform.as_str(), &room_id)) {
+            Some(entry) => entry.pending,
+            None => VecDeque::new(),
+        }
+    };
+    let flushed = pending.len();
+    for event in pending {
+        emit_to_subscribers(platform.as_str(), &room_id, &window, event);
+    }
+    println!(
+        "[Danmaku] resumed {} room {} (flushed {} buffered events)",
+        platform.as_str(),
+        room_id,
+        flushed
+    );
+    Ok(flushed)
+}
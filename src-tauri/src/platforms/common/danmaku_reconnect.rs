@@ -0,0 +1,151 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use super::events::{emit_event, DtvEvent};
+
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 8;
+pub const DEFAULT_MAX_BACKOFF_MS: u64 = 30_000;
+pub const BASE_BACKOFF_MS: u64 = 1_000;
+
+// 弹幕断线重连策略：指数退避，连续失败超过上限后放弃并通知前端，
+// 避免 IP 被封等持续性故障时无限重试、聊天区却一直静默
+static MAX_ATTEMPTS: AtomicU32 = AtomicU32::new(DEFAULT_MAX_ATTEMPTS);
+static MAX_BACKOFF_MS: AtomicU64 = AtomicU64::new(DEFAULT_MAX_BACKOFF_MS);
+
+// 按 (platform, room_id) 记录连续失败次数；每次成功建立连接时清零
+static ATTEMPTS: Lazy<Mutex<HashMap<String, u32>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn registry_key(platform: &str, room_id: &str) -> String {
+    format!("{}:{}", platform, room_id)
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DanmakuGaveUpPayload {
+    pub platform: String,
+    pub room_id: String,
+    pub attempts: u32,
+    pub error: String,
+}
+
+// 每次成功建立连接（含重连成功），或监听器重新启动时调用
+pub fn reset(platform: &str, room_id: &str) {
+    ATTEMPTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&registry_key(platform, room_id));
+}
+
+// 记一次失败并返回下次重连前的等待时间；已达上限时返回 None，调用方应放弃
+pub fn next_retry_delay(platform: &str, room_id: &str) -> Option<Duration> {
+    let mut attempts = ATTEMPTS.lock().unwrap_or_else(|e| e.into_inner());
+    let count = attempts.entry(registry_key(platform, room_id)).or_insert(0);
+    *count += 1;
+    retry_delay(
+        *count,
+        MAX_ATTEMPTS.load(Ordering::Relaxed),
+        MAX_BACKOFF_MS.load(Ordering::Relaxed),
+    )
+}
+
+// 第 attempt 次（从 1 开始）失败后的等待时间：从 BASE_BACKOFF_MS 起逐次翻倍，不超过 max_backoff_ms
+fn retry_delay(attempt: u32, max_attempts: u32, max_backoff_ms: u64) -> Option<Duration> {
+    if attempt == 0 || attempt > max_attempts {
+        return None;
+    }
+    let backoff = BASE_BACKOFF_MS.saturating_mul(1u64 << (attempt - 1).min(16));
+    Some(Duration::from_millis(backoff.min(max_backoff_ms)))
+}
+
+// 放弃重连：清理计数并发出 danmaku-gave-up，前端据此提示弹幕不可用
+pub fn give_up(app_handle: &AppHandle, platform: &str, room_id: &str, error: String) {
+    let attempts = ATTEMPTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&registry_key(platform, room_id))
+        .unwrap_or(0)
+        .saturating_sub(1);
+    eprintln!(
+        "[Danmaku] {} room {} gave up after {} reconnect attempts: {}",
+        platform, room_id, attempts, error
+    );
+    emit_event(
+        app_handle,
+        DtvEvent::DanmakuGaveUp(DanmakuGaveUpPayload {
+            platform: platform.to_string(),
+            room_id: room_id.to_string(),
+            attempts,
+            error,
+        }),
+    );
+}
+
+pub fn apply_reconnect_policy(max_attempts: u32, max_backoff_ms: u64) -> Result<(), String> {
+    if max_backoff_ms < BASE_BACKOFF_MS {
+        return Err(format!(
+            "max_backoff_ms must be at least {} ms",
+            BASE_BACKOFF_MS
+        ));
+    }
+    MAX_ATTEMPTS.store(max_attempts, Ordering::Relaxed);
+    MAX_BACKOFF_MS.store(max_backoff_ms, Ordering::Relaxed);
+    println!(
+        "[Danmaku] reconnect policy: max_attempts={} max_backoff_ms={}",
+        max_attempts, max_backoff_ms
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_danmaku_reconnect_policy(
+    max_attempts: Option<u32>,
+    max_backoff_ms: Option<u64>,
+) -> Result<(), String> {
+    apply_reconnect_policy(
+        max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS),
+        max_backoff_ms.unwrap_or(DEFAULT_MAX_BACKOFF_MS),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        let delays: Vec<u64> = (1..=6)
+            .map(|attempt| retry_delay(attempt, 8, 10_000).unwrap().as_millis() as u64)
+            .collect();
+        assert_eq!(delays, [1_000, 2_000, 4_000, 8_000, 10_000, 10_000]);
+        // 次数很大时不会溢出
+        assert_eq!(
+            retry_delay(60, 100, u64::MAX),
+            Some(Duration::from_millis(BASE_BACKOFF_MS << 16))
+        );
+    }
+
+    #[test]
+    fn retry_delay_gives_up_after_max_attempts() {
+        assert!(retry_delay(3, 3, DEFAULT_MAX_BACKOFF_MS).is_some());
+        assert_eq!(retry_delay(4, 3, DEFAULT_MAX_BACKOFF_MS), None);
+        assert_eq!(retry_delay(1, 0, DEFAULT_MAX_BACKOFF_MS), None);
+    }
+
+    #[test]
+    fn reset_restarts_the_backoff() {
+        let first = next_retry_delay("test", "reset-room");
+        next_retry_delay("test", "reset-room");
+        reset("test", "reset-room");
+        assert_eq!(next_retry_delay("test", "reset-room"), first);
+        reset("test", "reset-room");
+    }
+
+    #[test]
+    fn apply_reconnect_policy_rejects_tiny_backoff() {
+        assert!(apply_reconnect_policy(DEFAULT_MAX_ATTEMPTS, BASE_BACKOFF_MS - 1).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::Window;
+
+use super::events::{emit_event_to, DtvEvent};
+
+// 按 (platform, room_id) 记录订阅弹幕的窗口：同一房间只保持一条上游连接，
+// 多个窗口（多窗口/画中画）共享；最后一个窗口离开时才真正停止监听
+static SUBSCRIBERS: Lazy<Mutex<HashMap<String, Vec<Window>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn registry_key(platform: &str, room_id: &str) -> String {
+    format!("{}:{}", platform, room_id)
+}
+
+// 登记订阅窗口；返回 true 表示该房间已有存活的上游连接，调用方无需重新建立
+pub fn subscribe(platform: &str, room_id: &str, window: &Window, upstream_alive: bool) -> bool {
+    let mut registry = SUBSCRIBERS.lock().unwrap_or_else(|e| e.into_inner());
+    let key = registry_key(platform, room_id);
+    let joined = upstream_alive && registry.contains_key(&key);
+    let windows = registry.entry(key).or_default();
+    if !joined {
+        // 上游连接已退出，旧订阅者由新连接接管
+        windows.retain(|w| w.label() != window.label());
+    }
+    if !windows.iter().any(|w| w.label() == window.label()) {
+        windows.push(window.clone());
+    }
+    joined
+}
+
+// 各房间当前订阅的窗口数（"platform:room_id" -> 窗口数），用于状态导出
+pub fn snapshot() -> HashMap<String, usize> {
+    let registry = SUBSCRIBERS.lock().unwrap_or_else(|e| e.into_inner());
+    registry
+        .iter()
+        .map(|(key, windows)| (key.clone(), windows.len()))
+        .collect()
+}
+// 窗口离开房间；room_id 为 None 时匹配该平台所有房间，label 为 None 时移除全部订阅者。
+// 返回 true 表示已无订阅者，调用方应停止上游连接
+pub fn unsubscribe(platform: &str, room_id: Option<&str>, label: Option<&str>) -> bool {
+    let mut registry = SUBSCRIBERS.lock().unwrap_or_else(|e| e.into_inner());
+    let prefix = format!("{}:", platform);
+    let keys: Vec<String> = registry
+        .keys()
+        .filter(|key| match room_id {
+            Some(room_id) => **key == registry_key(platform, room_id),
+            None => key.starts_with(&prefix),
+        })
+        .cloned()
+        .collect();
+    let mut remaining = 0;
+    for key in keys {
+        let now_empty = match registry.get_mut(&key) {
+            Some(windows) => {
+                match label {
+                    Some(label) => windows.retain(|w| w.label() != label),
+                    None => windows.clear(),
+                }
+                remaining += windows.len();
+                windows.is_empty()
+            }
+            None => false,
+        };
+        if now_empty {
+            registry.remove(&key);
+        }
+    }
+    remaining == 0
+}
+
+// 窗口离开该平台的所有房间（停止命令未指明房间时使用）；
+// 返回因此不再有订阅者的房间，调用方应停止这些房间的上游连接
+pub fn leave_platform(platform: &str, label: &str) -> Vec<String> {
+    let mut registry = SUBSCRIBERS.lock().unwrap_or_else(|e| e.into_inner());
+    let prefix = format!("{}:", platform);
+    let mut emptied = Vec::new();
+    registry.retain(|key, windows| {
+        let Some(room_id) = key.strip_prefix(&prefix) else {
+            return true;
+        };
+        let before = windows.len();
+        windows.retain(|w| w.label() != label);
+        if windows.is_empty() && before > 0 {
+            emptied.push(room_id.to_string());
+            return false;
+        }
+        !windows.is_empty()
+    });
+    emptied
+}
+
+// 窗口销毁时移除其所有订阅，避免已关闭的窗口让连接一直保持
+pub fn forget_window(label: &str) {
+    let mut registry = SUBSCRIBERS.lock().unwrap_or_else(|e| e.into_inner());
+    for windows in registry.values_mut() {
+        windows.retain(|w| w.label() != label);
+    }
+    registry.retain(|_, windows| !windows.is_empty());
+}
+
+// 向该房间的每个订阅窗口分别投递事件；尚未登记订阅者时退回 fallback 窗口
+pub fn emit_to_subscribers(platform: &str, room_id: &str, fallback: &Window, event: DtvEvent) {
+    let windows = {
+        let registry = SUBSCRIBERS.lock().unwrap_or_else(|e| e.into_inner());
+        registry
+            .get(&registry_key(platform, room_id))
+            .cloned()
+            .unwrap_or_default()
+    };
+    if windows.is_empty() {
+        emit_event_to(fallback, fallback.label(), event);
+        return;
+    }
+    for window in windows {
+        emit_event_to(&window, window.label(), event.clone());
+    }
+}
@@ -0,0 +1,25 @@
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// 调试开关：开启后房间信息与流解析结果会在 raw 字段附带平台原始响应，便于排查平台改版导致的解析问题。
+// 默认关闭，避免返回体过大以及把 Cookie 等敏感内容带到前端
+static RAW_RESPONSES_ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enabled() -> bool {
+    RAW_RESPONSES_ENABLED.load(Ordering::Relaxed)
+}
+
+// 只有开启时才克隆/组装原始响应
+pub fn capture(build: impl FnOnce() -> Value) -> Option<Value> {
+    if enabled() {
+        Some(build())
+    } else {
+        None
+    }
+}
+
+#[tauri::command]
+pub fn set_debug_raw_responses(enabled: bool) {
+    RAW_RESPONSES_ENABLED.store(enabled, Ordering::Relaxed);
+    println!("[Debug] raw platform responses enabled: {}", enabled);
+}
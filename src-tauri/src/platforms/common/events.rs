@@ -0,0 +1,89 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{Emitter, Runtime};
+
+use super::danmaku_reconnect::DanmakuGaveUpPayload;
+use super::{DanmakuConnectedPayload, DanmakuFrontendPayload};
+
+// 汇总事件通道：所有后端事件都会经 "dtv-event" 发出，前端注册一个监听按 type 分派，
+// 同一通道内的事件顺序与发出顺序一致
+pub const DTV_EVENT: &str = "dtv-event";
+
+// 兼容旧前端：默认仍同时发出各自独立的事件，可通过 set_legacy_events 关闭
+static LEGACY_EVENTS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum DtvEvent {
+    DanmakuMessage(DanmakuFrontendPayload),
+    // 斗鱼按房间的原始事件（旧事件名 danmaku-{room_id}），含 chatmsg/uenter
+    DouyuRoomMessage { room_id: String, message: Value },
+    DanmakuConnected(DanmakuConnectedPayload),
+    DanmakuGaveUp(DanmakuGaveUpPayload),
+    Bitrate(Value),
+    StreamMetadata(Value),
+    StreamReconnect(Value),
+    ProxyRestarted(Value),
+    RecordingProgress(Value),
+    FollowStatusChanged(Value),
+}
+
+impl DtvEvent {
+    // 序列化失败时退回 null，事件本身仍然发出
+    pub fn json<T: Serialize>(payload: T) -> Value {
+        serde_json::to_value(payload).unwrap_or(Value::Null)
+    }
+
+    pub fn legacy_name(&self) -> String {
+        match self {
+            Self::DanmakuMessage(_) => "danmaku-message".to_string(),
+            Self::DouyuRoomMessage { room_id, .. } => format!("danmaku-{}", room_id),
+            Self::DanmakuConnected(_) => "danmaku-connected".to_string(),
+            Self::DanmakuGaveUp(_) => "danmaku-gave-up".to_string(),
+            Self::Bitrate(_) => "bitrate".to_string(),
+            Self::StreamMetadata(_) => "stream-metadata".to_string(),
+            Self::StreamReconnect(_) => "stream-reconnect".to_string(),
+            Self::ProxyRestarted(_) => "proxy-restarted".to_string(),
+            Self::RecordingProgress(_) => "recording-progress".to_string(),
+            Self::FollowStatusChanged(_) => "follow-status-changed".to_string(),
+        }
+    }
+
+    fn legacy_payload(&self) -> Value {
+        match self {
+            Self::DanmakuMessage(payload) => Self::json(payload),
+            Self::DouyuRoomMessage { message, .. } => message.clone(),
+            Self::DanmakuConnected(payload) => Self::json(payload),
+            Self::DanmakuGaveUp(payload) => Self::json(payload),
+            Self::Bitrate(value)
+            | Self::StreamMetadata(value)
+            | Self::StreamReconnect(value)
+            | Self::ProxyRestarted(value)
+            | Self::RecordingProgress(value)
+            | Self::FollowStatusChanged(value) => value.clone(),
+        }
+    }
+}
+
+// 所有后端事件的统一出口：发出 dtv-event，并在兼容模式下同时发出旧事件
+pub fn emit_event<R: Runtime, E: Emitter<R>>(emitter: &E, event: DtvEvent) {
+    if LEGACY_EVENTS_ENABLED.load(Ordering::Relaxed) {
+        let _ = emitter.emit(&event.legacy_name(), event.legacy_payload());
+    }
+    let _ = emitter.emit(DTV_EVENT, event);
+}
+
+// 只投递给指定窗口（多窗口订阅同一房间弹幕时使用）
+pub fn emit_event_to<R: Runtime, E: Emitter<R>>(emitter: &E, target: &str, event: DtvEvent) {
+    if LEGACY_EVENTS_ENABLED.load(Ordering::Relaxed) {
+        let _ = emitter.emit_to(target, &event.legacy_name(), event.legacy_payload());
+    }
+    let _ = emitter.emit_to(target, DTV_EVENT, event);
+}
+
+#[tauri::command]
+pub fn set_legacy_events(enabled: bool) {
+    LEGACY_EVENTS_ENABLED.store(enabled, Ordering::Relaxed);
+    println!("[Events] legacy per-kind events enabled: {}", enabled);
+}
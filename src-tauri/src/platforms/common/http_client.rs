@@ -3,6 +3,8 @@ use reqwest::{cookie::Jar, Client, RequestBuilder, Response};
 use std::sync::Arc;
 use std::time::Duration;
 
+use super::text_decode::read_text;
+
 pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/109.0.0.0 Safari/537.36";
 const DEFAULT_TIMEOUT_SECONDS: u64 = 20;
 const FOLLOW_POOL_MAX_IDLE_PER_HOST: usize = 2;
@@ -29,6 +31,7 @@ impl HttpClient {
         let client_builder = Client::builder()
             .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECONDS))
             .cookie_provider(cookie_jar);
+        let client_builder = crate::network::api_proxy().apply(client_builder);
 
         let inner_client = client_builder
             .build()
@@ -40,7 +43,7 @@ impl HttpClient {
         })
     }
 
-    /// 限制连接池规模（仍然遵循环境变量代理设置），用于关注刷新等低并发任务
+    /// 限制连接池规模（遵循 API 链路的代理设置），用于关注刷新等低并发任务
     pub fn new_limited(max_idle_per_host: usize) -> Result<Self, String> {
         let mut default_headers = ReqwestHeaderMap::new();
         default_headers.insert(
@@ -55,6 +58,7 @@ impl HttpClient {
             .cookie_provider(cookie_jar)
             .pool_max_idle_per_host(max_idle_per_host)
             .pool_idle_timeout(Duration::from_secs(FOLLOW_POOL_IDLE_TIMEOUT_SECONDS));
+        let client_builder = crate::network::api_proxy().apply(client_builder);
 
         let inner_client = client_builder
             .build()
@@ -148,8 +152,7 @@ impl HttpClient {
     pub async fn get_text(&self, url: &str) -> Result<String, String> {
         let response = self.get(url).await?;
         let status = response.status();
-        let response_text = response
-            .text()
+        let response_text = read_text(response)
             .await
             .map_err(|e| format!("Failed to read response body from {}: {}", url, e))?;
         if !status.is_success() {
@@ -275,8 +278,7 @@ impl HttpClient {
                 url, status, err_text
             ));
         }
-        let text_response = response
-            .text()
+        let text_response = read_text(response)
             .await
             .map_err(|e| format!("Failed to read text response from {}: {}", url, e))?;
         Ok(text_response)
@@ -304,11 +306,57 @@ impl HttpClient {
 }
 
 /// 专用于关注刷新等低并发任务的 HTTP 客户端包装
-#[derive(Debug, Clone)]
-pub struct FollowHttpClient(pub HttpClient);
+/// 内部可替换，以便运行时切换代理配置后立即生效
+#[derive(Debug)]
+pub struct FollowHttpClient(std::sync::RwLock<HttpClient>);
 
 impl FollowHttpClient {
     pub fn new() -> Result<Self, String> {
-        Ok(Self(HttpClient::new_limited(FOLLOW_POOL_MAX_IDLE_PER_HOST)?))
+        Ok(Self(std::sync::RwLock::new(HttpClient::new_limited(
+            FOLLOW_POOL_MAX_IDLE_PER_HOST,
+        )?)))
+    }
+
+    /// 获取当前客户端（reqwest::Client 内部为 Arc，克隆开销很小）
+    pub fn client(&self) -> HttpClient {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 按新的代理配置重建客户端：API 链路为 env 时遵循 no_proxy 开关，direct/custom 按配置生效
+    pub fn rebuild(&self) -> Result<(), String> {
+        let client = HttpClient::new_limited(FOLLOW_POOL_MAX_IDLE_PER_HOST)?;
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = client;
+        Ok(())
+    }
+}
+
+// 通用客户端沿用的桌面 Chrome UA
+const SHARED_CLIENT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
+
+/// 交给 Tauri 管理的通用 reqwest 客户端，遵循环境变量代理
+/// 内部可替换，no_proxy 开关切换后重建
+#[derive(Debug)]
+pub struct SharedReqwestClient(std::sync::RwLock<Client>);
+
+impl SharedReqwestClient {
+    fn build() -> Result<Client, String> {
+        crate::network::apply_env_proxy(Client::builder())
+            .user_agent(SHARED_CLIENT_USER_AGENT)
+            .build()
+            .map_err(|e| format!("Failed to build reqwest client: {}", e))
+    }
+
+    pub fn new() -> Result<Self, String> {
+        Ok(Self(std::sync::RwLock::new(Self::build()?)))
+    }
+
+    pub fn client(&self) -> Client {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn rebuild(&self) -> Result<(), String> {
+        let client = Self::build()?;
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = client;
+        Ok(())
     }
 }
@@ -0,0 +1,25 @@
+use serde_json::Value;
+
+// 各平台接口里的 id、人数等字段时而是数字时而是字符串，统一取成字符串；其他类型返回 None
+pub fn scalar_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn numbers_and_strings_become_strings() {
+        assert_eq!(scalar_string(&json!("288016")), Some("288016".to_string()));
+        assert_eq!(scalar_string(&json!(288016)), Some("288016".to_string()));
+        assert_eq!(scalar_string(&json!(1.5)), Some("1.5".to_string()));
+        assert_eq!(scalar_string(&json!(null)), None);
+        assert_eq!(scalar_string(&json!({"value": 1})), None);
+    }
+}
@@ -0,0 +1,215 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+// 直播列表离线缓存：每个分类保存最近一次成功的结果，网络不可达时回退并标记 stale。
+// 默认关闭，由设置 offline_list_cache 开启
+const LIST_CACHE_DIR: &str = "live_list_cache";
+// 超过这个时间的缓存视为过期，不再返回并在读取时删除
+const LIST_CACHE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+// 缓存文件数上限，写入时按修改时间淘汰最旧的
+const LIST_CACHE_MAX_FILES: usize = 200;
+
+static LIST_CACHE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+const NETWORK_ERROR_PREFIX: &str = "Network error: ";
+
+#[tauri::command]
+pub fn set_list_cache_enabled(enabled: bool) {
+    LIST_CACHE_ENABLED.store(enabled, Ordering::Relaxed);
+    println!("[List Cache] offline list cache enabled: {}", enabled);
+}
+
+fn enabled() -> bool {
+    LIST_CACHE_ENABLED.load(Ordering::Relaxed)
+}
+
+// 请求没能到达平台（连接失败、超时、读取响应失败）或平台临时故障（5xx/429）时的错误信息。
+// 只有这类错误才回退到缓存；业务错误与解析失败照常返回，避免把接口变化掩盖成旧数据
+pub fn network_error(detail: impl std::fmt::Display) -> String {
+    format!("{}{}", NETWORK_ERROR_PREFIX, detail)
+}
+
+pub fn is_network_error(error: &str) -> bool {
+    error.starts_with(NETWORK_ERROR_PREFIX)
+}
+
+// 返回数值错误码的列表接口（斗鱼/虎牙）：网络失败记为 500，HTTP 失败记为响应状态码
+pub fn is_unavailable_status(code: i32) -> bool {
+    code == 429 || (500..600).contains(&code)
+}
+
+fn cache_dir(app_handle: &AppHandle) -> Option<PathBuf> {
+    app_handle
+        .path()
+        .app_cache_dir()
+        .ok()
+        .map(|dir| dir.join(LIST_CACHE_DIR))
+}
+
+fn cache_file(app_handle: &AppHandle, key: &str) -> Option<PathBuf> {
+    let file_name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    cache_dir(app_handle).map(|dir| dir.join(format!("{}.json", file_name)))
+}
+
+// 本地图片代理地址依赖代理是否在运行，缓存里只保存原始图片地址，读取后由调用方重新包装
+fn strip_local_image_urls(value: &mut Value) {
+    match value {
+        Value::String(s) => {
+            if crate::proxy::is_local_image_url(s) {
+                *s = crate::proxy::unwrap_local_image_url(s);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_local_image_urls),
+        Value::Object(map) => map.values_mut().for_each(strip_local_image_urls),
+        _ => {}
+    }
+}
+
+fn modified_age(path: &Path) -> Option<Duration> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(
+        SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default(),
+    )
+}
+
+// 删除过期的缓存，并把文件数压到上限以内（先删最旧的）
+fn prune(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(Duration, PathBuf)> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| modified_age(&path).map(|age| (age, path)))
+        .collect();
+    files.sort_by(|a, b| b.0.cmp(&a.0));
+    let excess = files.len().saturating_sub(LIST_CACHE_MAX_FILES);
+    for (index, (age, path)) in files.iter().enumerate() {
+        if index < excess || *age > LIST_CACHE_MAX_AGE {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+pub fn save_list<T: Serialize>(app_handle: &AppHandle, key: &str, value: &T) {
+    if !enabled() {
+        return;
+    }
+    let Some(path) = cache_file(app_handle, key) else {
+        return;
+    };
+    let result = serde_json::to_value(value)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+        .and_then(|mut value| {
+            strip_local_image_urls(&mut value);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&path, value.to_string())
+        });
+    match result {
+        Ok(_) => {
+            if let Some(dir) = path.parent() {
+                prune(dir);
+            }
+        }
+        Err(e) => eprintln!("[List Cache] Failed to write cache for {}: {}", key, e),
+    }
+}
+
+pub fn load_list<T: DeserializeOwned>(app_handle: &AppHandle, key: &str) -> Option<T> {
+    if !enabled() {
+        return None;
+    }
+    let path = cache_file(app_handle, key)?;
+    if modified_age(&path)? > LIST_CACHE_MAX_AGE {
+        let _ = fs::remove_file(&path);
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    match serde_json::from_slice(&bytes) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("[List Cache] Ignoring unreadable cache for {}: {}", key, e);
+            None
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn clear_list_cache(app_handle: AppHandle) -> Result<(), String> {
+    let Some(dir) = cache_dir(&app_handle) else {
+        return Ok(());
+    };
+    match fs::remove_dir_all(&dir) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to clear list cache: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_network_failures_fall_back() {
+        assert!(is_network_error(&network_error("connection refused")));
+        assert!(!is_network_error(
+            "Douyin API returned non-zero status code: 1"
+        ));
+        assert!(is_unavailable_status(500));
+        assert!(is_unavailable_status(503));
+        assert!(is_unavailable_status(429));
+        assert!(!is_unavailable_status(404));
+        assert!(!is_unavailable_status(-1));
+        assert!(!is_unavailable_status(-2));
+    }
+
+    #[test]
+    fn prune_drops_oldest_files_over_the_limit() {
+        let dir = std::env::temp_dir().join(format!("dtv-list-cache-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for i in 0..LIST_CACHE_MAX_FILES + 3 {
+            let path = dir.join(format!("{:04}.json", i));
+            fs::write(&path, "{}").unwrap();
+            let modified =
+                SystemTime::now() - Duration::from_secs((LIST_CACHE_MAX_FILES + 3 - i) as u64);
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        fs::write(dir.join("note.txt"), "keep").unwrap();
+        prune(&dir);
+        let mut left: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(left.len(), LIST_CACHE_MAX_FILES + 1);
+        assert_eq!(left[0], "0003.json");
+        assert!(left.contains(&"note.txt".to_string()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,14 +1,35 @@
 #![allow(unused_imports)]
+pub mod cn_number;
+pub mod danmaku_history;
+pub mod danmaku_pause;
+pub mod danmaku_reconnect;
+pub mod danmaku_subscribers;
+pub mod debug_raw;
+pub mod events;
 pub mod http_client;
+pub mod json_value;
+pub mod list_cache;
+pub mod quality;
+pub mod request_id;
+pub mod resolver_chain;
+pub mod room_id;
+pub mod text_decode;
+pub mod ttl_cache;
 pub mod types;
 pub mod types_rust;
+pub mod user_agent;
 
 // Re-export necessary types to make them available directly under platforms::common::TypeName
-pub use http_client::FollowHttpClient;
+pub use http_client::{FollowHttpClient, SharedReqwestClient};
+pub use quality::Quality;
 pub use types::BilibiliDanmakuState;
+pub use types::DanmakuConnectedPayload;
 pub use types::DanmakuFrontendPayload;
 pub use types::DouyinDanmakuState;
 pub use types::DouyuDanmakuState;
 pub use types::GetStreamUrlPayload;
 pub use types::HuyaDanmakuState;
 pub use types::LiveStreamInfo;
+pub use types::RoomRestriction;
+pub use types::UnifiedRoom;
+pub use types_rust::SupportedPlatformRust;
@@ -0,0 +1,212 @@
+use serde::Serialize;
+
+use super::SupportedPlatformRust;
+
+// 跨平台统一的清晰度档位。前端可能传 "原画"/"OD"/B 站 qn/虎牙码率 等任意写法，
+// 统一在这里换算，各解析器只消费 to_platform_param 的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quality {
+    Origin,
+    High,
+    Standard,
+}
+
+impl Quality {
+    pub fn from_user(platform: &SupportedPlatformRust, input: &str) -> Result<Self, String> {
+        let trimmed = input.trim();
+        let upper = trimmed.to_ascii_uppercase();
+
+        // 平台专有写法优先：抖音的 UHD 是“标清”，虎牙的 uhd 却表示原画
+        let platform_specific = match platform {
+            SupportedPlatformRust::Douyin => match upper.as_str() {
+                "OD" => Some(Self::Origin),
+                "BD" => Some(Self::High),
+                "UHD" => Some(Self::Standard),
+                _ => None,
+            },
+            SupportedPlatformRust::Huya => match upper.as_str() {
+                "UHD" | "0" => Some(Self::Origin),
+                "4000" => Some(Self::High),
+                "2000" => Some(Self::Standard),
+                _ => None,
+            },
+            // B 站的“蓝光”是 qn=400，属于高清档；斗鱼的“蓝光”档位才是原画
+            SupportedPlatformRust::Bilibili if trimmed.contains("蓝光") => Some(Self::High),
+            SupportedPlatformRust::Bilibili => trimmed.parse::<i32>().ok().map(|qn| match qn {
+                qn if qn >= 10000 => Self::Origin,
+                qn if qn >= 400 => Self::High,
+                _ => Self::Standard,
+            }),
+            SupportedPlatformRust::Douyu => match trimmed {
+                "0" => Some(Self::Origin),
+                "4" => Some(Self::High),
+                "3" => Some(Self::Standard),
+                _ => None,
+            },
+        };
+        if let Some(quality) = platform_specific {
+            return Ok(quality);
+        }
+
+        if trimmed.contains("原画") || trimmed.contains("蓝光") {
+            return Ok(Self::Origin);
+        }
+        if trimmed.contains("高清") || trimmed.contains("超清") {
+            return Ok(Self::High);
+        }
+        if trimmed.contains("标清") || trimmed.contains("流畅") {
+            return Ok(Self::Standard);
+        }
+        match upper.as_str() {
+            "ORIGIN" | "SOURCE" | "OD" => Ok(Self::Origin),
+            "HIGH" | "HD" | "BD" => Ok(Self::High),
+            "STANDARD" | "SD" | "LD" => Ok(Self::Standard),
+            _ => Err(format!(
+                "Unsupported quality for {}: {}",
+                platform.as_str(),
+                input
+            )),
+        }
+    }
+
+    // 解析器使用：无法识别的写法不让整次解析失败，记录日志后按默认档位（原画）处理。
+    // 设置校验等需要拒绝非法输入的地方仍使用 from_user
+    pub fn from_user_or_default(platform: &SupportedPlatformRust, input: &str) -> Self {
+        Self::from_user(platform, input).unwrap_or_else(|e| {
+            eprintln!("[Quality] {}, falling back to {}", e, Self::Origin.label());
+            Self::Origin
+        })
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Origin => "原画",
+            Self::High => "高清",
+            Self::Standard => "标清",
+        }
+    }
+
+    // 各平台解析器期望的参数：斗鱼 rate、B 站 qn、抖音清晰度标记、虎牙码率（原画为 0，表示不限）
+    pub fn to_platform_param(&self, platform: &SupportedPlatformRust) -> &'static str {
+        match (platform, self) {
+            (SupportedPlatformRust::Douyu, Self::Origin) => "0",
+            (SupportedPlatformRust::Douyu, Self::High) => "4",
+            (SupportedPlatformRust::Douyu, Self::Standard) => "3",
+            (SupportedPlatformRust::Bilibili, Self::Origin) => "10000",
+            (SupportedPlatformRust::Bilibili, Self::High) => "400",
+            (SupportedPlatformRust::Bilibili, Self::Standard) => "250",
+            (SupportedPlatformRust::Douyin, Self::Origin) => "OD",
+            (SupportedPlatformRust::Douyin, Self::High) => "BD",
+            (SupportedPlatformRust::Douyin, Self::Standard) => "UHD",
+            (SupportedPlatformRust::Huya, Self::Origin) => "0",
+            (SupportedPlatformRust::Huya, Self::High) => "4000",
+            (SupportedPlatformRust::Huya, Self::Standard) => "2000",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLATFORMS: [SupportedPlatformRust; 4] = [
+        SupportedPlatformRust::Douyu,
+        SupportedPlatformRust::Bilibili,
+        SupportedPlatformRust::Douyin,
+        SupportedPlatformRust::Huya,
+    ];
+    const QUALITIES: [Quality; 3] = [Quality::Origin, Quality::High, Quality::Standard];
+
+    #[test]
+    fn platform_params_round_trip() {
+        for platform in &PLATFORMS {
+            for quality in QUALITIES {
+                let param = quality.to_platform_param(platform);
+                assert_eq!(
+                    Quality::from_user(platform, param),
+                    Ok(quality),
+                    "{:?} {}",
+                    platform,
+                    param
+                );
+                // 通用档位名在所有平台上都能识别
+                assert_eq!(Quality::from_user(platform, quality.label()), Ok(quality));
+            }
+        }
+    }
+
+    #[test]
+    fn douyu_rates() {
+        let p = SupportedPlatformRust::Douyu;
+        assert_eq!(Quality::from_user(&p, " 0 "), Ok(Quality::Origin));
+        assert_eq!(Quality::from_user(&p, "4"), Ok(Quality::High));
+        assert_eq!(Quality::from_user(&p, "3"), Ok(Quality::Standard));
+        assert_eq!(Quality::from_user(&p, "蓝光4M"), Ok(Quality::Origin));
+        assert!(Quality::from_user(&p, "2").is_err());
+    }
+
+    #[test]
+    fn bilibili_qn_thresholds() {
+        let p = SupportedPlatformRust::Bilibili;
+        assert_eq!(Quality::from_user(&p, "20000"), Ok(Quality::Origin));
+        assert_eq!(Quality::from_user(&p, "10000"), Ok(Quality::Origin));
+        assert_eq!(Quality::from_user(&p, "400"), Ok(Quality::High));
+        assert_eq!(Quality::from_user(&p, "250"), Ok(Quality::Standard));
+        assert_eq!(Quality::from_user(&p, "80"), Ok(Quality::Standard));
+        assert_eq!(Quality::from_user(&p, "超清"), Ok(Quality::High));
+    }
+
+    #[test]
+    fn bilibili_blu_ray_is_high() {
+        let p = SupportedPlatformRust::Bilibili;
+        assert_eq!(Quality::from_user(&p, "蓝光"), Ok(Quality::High));
+        assert_eq!(Quality::from_user(&p, "蓝光 4M"), Ok(Quality::High));
+        assert_eq!(
+            Quality::from_user(&SupportedPlatformRust::Douyu, "蓝光"),
+            Ok(Quality::Origin)
+        );
+    }
+
+    #[test]
+    fn unknown_input_falls_back_to_default_tier() {
+        for platform in &PLATFORMS {
+            assert_eq!(
+                Quality::from_user_or_default(platform, "8K"),
+                Quality::Origin
+            );
+            assert_eq!(Quality::from_user_or_default(platform, ""), Quality::Origin);
+            assert_eq!(
+                Quality::from_user_or_default(platform, "标清"),
+                Quality::Standard
+            );
+        }
+    }
+
+    #[test]
+    fn douyin_and_huya_uhd_differ() {
+        // 抖音的 UHD 是“标清”，虎牙的 uhd 表示原画
+        let douyin = SupportedPlatformRust::Douyin;
+        let huya = SupportedPlatformRust::Huya;
+        assert_eq!(Quality::from_user(&douyin, "uhd"), Ok(Quality::Standard));
+        assert_eq!(Quality::from_user(&huya, "uhd"), Ok(Quality::Origin));
+        assert_eq!(Quality::from_user(&douyin, "od"), Ok(Quality::Origin));
+        assert_eq!(Quality::from_user(&douyin, "BD"), Ok(Quality::High));
+        assert_eq!(Quality::from_user(&huya, "4000"), Ok(Quality::High));
+        assert_eq!(Quality::from_user(&huya, "2000"), Ok(Quality::Standard));
+        assert_eq!(Quality::from_user(&huya, "OD"), Ok(Quality::Origin));
+    }
+
+    #[test]
+    fn generic_aliases_and_invalid_input() {
+        for platform in &PLATFORMS {
+            assert_eq!(Quality::from_user(platform, "source"), Ok(Quality::Origin));
+            assert_eq!(Quality::from_user(platform, "HD"), Ok(Quality::High));
+            assert_eq!(Quality::from_user(platform, "流畅"), Ok(Quality::Standard));
+            assert_eq!(Quality::from_user(platform, "ld"), Ok(Quality::Standard));
+            let err = Quality::from_user(platform, "8K").unwrap_err();
+            assert!(err.contains(platform.as_str()));
+            assert!(Quality::from_user(platform, "").is_err());
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::fmt::Display;
+use std::sync::Mutex;
+
+// 最近一次返回给前端的错误，附在诊断信息中
+#[derive(Serialize, Clone, Debug)]
+pub struct LastError {
+    pub request_id: String,
+    pub message: String,
+    pub at: i64,
+}
+
+static LAST_ERROR: Lazy<Mutex<Option<LastError>>> = Lazy::new(|| Mutex::new(None));
+
+// 每次命令调用生成一个短 ID，用于在并发解析/代理时关联日志与返回给前端的错误
+pub fn new_request_id() -> String {
+    format!("{:08x}", rand::random::<u32>())
+}
+
+pub fn tag_error(request_id: &str, err: impl Display) -> String {
+    let message = err.to_string();
+    *LAST_ERROR.lock().unwrap_or_else(|e| e.into_inner()) = Some(LastError {
+        request_id: request_id.to_string(),
+        message: message.clone(),
+        at: chrono::Utc::now().timestamp_millis(),
+    });
+    format!("[req:{}] {}", request_id, message)
+}
+
+pub fn last_error() -> Option<LastError> {
+    LAST_ERROR.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
@@ -0,0 +1,124 @@
+use futures_util::future::BoxFuture;
+
+// 一个解析策略：Ok(Some) 表示得到可播放结果，Ok(None) 表示该路径正常但没有可用流（如未开播），
+// Err 表示该路径本身失败（接口改版、风控等）
+pub type Resolver<'a, T> = (&'static str, BoxFuture<'a, Result<Option<T>, String>>);
+
+// 按顺序尝试各平台的解析策略，返回第一个产出结果的策略及其名称。
+// 全部没有结果时：只要有一条路径正常返回（Ok(None)）就视为确实无流，返回 Ok(None)；
+// 全部出错才返回汇总的错误
+pub async fn first_playable<T>(
+    context: &str,
+    resolvers: Vec<Resolver<'_, T>>,
+) -> Result<Option<(T, &'static str)>, String> {
+    let mut errors = Vec::new();
+    let mut any_answered = false;
+    for (name, resolver) in resolvers {
+        match resolver.await {
+            Ok(Some(value)) => {
+                if !errors.is_empty() || any_answered {
+                    println!("[Resolver] {} resolved via fallback '{}'", context, name);
+                }
+                return Ok(Some((value, name)));
+            }
+            Ok(None) => any_answered = true,
+            Err(e) => {
+                eprintln!("[Resolver] {} strategy '{}' failed: {}", context, name, e);
+                errors.push(format!("{}: {}", name, e));
+            }
+        }
+    }
+    if any_answered || errors.is_empty() {
+        Ok(None)
+    } else {
+        Err(format!(
+            "{} all resolvers failed ({})",
+            context,
+            errors.join("; ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn resolver<'a>(
+        name: &'static str,
+        result: Result<Option<&'static str>, String>,
+    ) -> Resolver<'a, &'static str> {
+        (name, async move { result }.boxed())
+    }
+
+    #[tokio::test]
+    async fn falls_through_failed_and_empty_strategies() {
+        let resolved = first_playable(
+            "test",
+            vec![
+                resolver("web", Err("接口改版".to_string())),
+                resolver("flat", Ok(None)),
+                resolver("mobile", Ok(Some("https://example.com/live.flv"))),
+            ],
+        )
+        .await;
+        assert_eq!(
+            resolved,
+            Ok(Some(("https://example.com/live.flv", "mobile")))
+        );
+    }
+
+    #[tokio::test]
+    async fn stops_at_the_first_playable_strategy() {
+        let polled = AtomicBool::new(false);
+        let resolved = first_playable(
+            "test",
+            vec![
+                resolver("web", Ok(Some("web-url"))),
+                (
+                    "mobile",
+                    async {
+                        polled.store(true, Ordering::SeqCst);
+                        Ok(Some("mobile-url"))
+                    }
+                    .boxed(),
+                ),
+            ],
+        )
+        .await;
+        assert_eq!(resolved, Ok(Some(("web-url", "web"))));
+        assert!(!polled.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn offline_when_any_strategy_answered() {
+        let resolved = first_playable(
+            "test",
+            vec![
+                resolver("web", Err("风控".to_string())),
+                resolver("mobile", Ok(None)),
+            ],
+        )
+        .await;
+        assert_eq!(resolved, Ok(None));
+        assert_eq!(first_playable::<&str>("test", vec![]).await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn reports_every_error_when_all_fail() {
+        let err = first_playable(
+            "huya 1199",
+            vec![
+                resolver("page", Err("403".to_string())),
+                resolver("api", Err("timeout".to_string())),
+            ],
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "huya 1199 all resolvers failed (page: 403; api: timeout)"
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+
+use super::SupportedPlatformRust;
+
+// 房间号/链接的长度上限：超过的基本是误粘贴的大段文本
+const MAX_ROOM_ID_LEN: usize = 512;
+
+// 房间号输入不合法：在发起任何网络请求前返回，避免上游给出难以理解的错误
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidInput {
+    pub platform: &'static str,
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[InvalidInput] {} 房间号无效: {}",
+            self.platform, self.reason
+        )
+    }
+}
+
+impl std::error::Error for InvalidInput {}
+
+impl From<InvalidInput> for String {
+    fn from(error: InvalidInput) -> Self {
+        error.to_string()
+    }
+}
+
+// 链接交给各平台自己的解析逻辑（normalize_*），这里只做粗略判断
+fn looks_like_url(input: &str) -> bool {
+    input.contains("://") || input.contains('/') || input.contains('.')
+}
+
+// 去掉首尾空白并拒绝明显无效的房间号；返回修剪后的值。
+// 纯数字平台（B站）只接受数字，其余平台允许字母数字靓号（如虎牙 "lpl"）
+pub fn validate_room_id(
+    platform: &SupportedPlatformRust,
+    input: &str,
+) -> Result<String, InvalidInput> {
+    let invalid = |reason: String| InvalidInput {
+        platform: platform.as_str(),
+        reason,
+    };
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err(invalid("房间ID未提供".to_string()));
+    }
+    if trimmed.len() > MAX_ROOM_ID_LEN {
+        return Err(invalid(format!("输入过长（{} 字节）", trimmed.len())));
+    }
+    if trimmed.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(invalid(format!("包含空白或控制字符: {:?}", trimmed)));
+    }
+    if looks_like_url(trimmed) {
+        return Ok(trimmed.to_string());
+    }
+
+    let numeric = trimmed.chars().all(|c| c.is_ascii_digit());
+    if numeric && trimmed.chars().all(|c| c == '0') {
+        return Err(invalid(format!("房间号不能为 {}", trimmed)));
+    }
+    match platform {
+        SupportedPlatformRust::Bilibili if !numeric => {
+            Err(invalid(format!("B站房间号应为数字: {}", trimmed)))
+        }
+        _ if !trimmed
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+        {
+            Err(invalid(format!("包含非法字符: {}", trimmed)))
+        }
+        _ => Ok(trimmed.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLATFORMS: [SupportedPlatformRust; 4] = [
+        SupportedPlatformRust::Douyu,
+        SupportedPlatformRust::Bilibili,
+        SupportedPlatformRust::Douyin,
+        SupportedPlatformRust::Huya,
+    ];
+
+    #[test]
+    fn empty_and_whitespace_are_rejected_on_every_platform() {
+        for platform in &PLATFORMS {
+            for input in ["", "   ", "\t\n"] {
+                let err = validate_room_id(platform, input).unwrap_err();
+                assert_eq!(err.platform, platform.as_str());
+                assert_eq!(err.reason, "房间ID未提供");
+            }
+        }
+    }
+
+    #[test]
+    fn ids_are_trimmed_on_every_platform() {
+        for platform in &PLATFORMS {
+            assert_eq!(
+                validate_room_id(platform, "  7734200 \n"),
+                Ok("7734200".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_ids_are_rejected() {
+        for platform in &PLATFORMS {
+            for input in ["0", "000", "12 34", "12\u{0}34", "12;34", "<script>"] {
+                assert!(
+                    validate_room_id(platform, input).is_err(),
+                    "{:?} {:?}",
+                    platform,
+                    input
+                );
+            }
+            let too_long = "9".repeat(MAX_ROOM_ID_LEN + 1);
+            assert!(validate_room_id(platform, &too_long).is_err());
+        }
+    }
+
+    #[test]
+    fn aliases_are_allowed_except_on_bilibili() {
+        assert_eq!(
+            validate_room_id(&SupportedPlatformRust::Huya, "lpl"),
+            Ok("lpl".to_string())
+        );
+        assert_eq!(
+            validate_room_id(&SupportedPlatformRust::Douyu, "yyf_1"),
+            Ok("yyf_1".to_string())
+        );
+        assert_eq!(
+            validate_room_id(&SupportedPlatformRust::Douyin, "abc-123"),
+            Ok("abc-123".to_string())
+        );
+        let err = validate_room_id(&SupportedPlatformRust::Bilibili, "lpl").unwrap_err();
+        assert!(err.to_string().starts_with("[InvalidInput] bilibili"));
+    }
+
+    #[test]
+    fn urls_are_passed_through_to_platform_normalizers() {
+        for platform in &PLATFORMS {
+            for input in [
+                "https://www.douyu.com/topic/lpl?rid=9999",
+                "live.bilibili.com/7734200",
+                "www.huya.com",
+            ] {
+                assert_eq!(validate_room_id(platform, input), Ok(input.to_string()));
+            }
+            // 链接中间的空白仍然拒绝
+            assert!(validate_room_id(platform, "https://live.douyin.com/ 1").is_err());
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use encoding_rs::{Encoding, UTF_8};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Response;
+
+// 只在页面开头查找 <meta charset>，与浏览器的预扫描范围一致
+const META_SNIFF_LIMIT: usize = 1024;
+
+fn charset_from_content_type(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        Encoding::for_label(
+            value
+                .trim()
+                .trim_matches(|c| c == '"' || c == '\'')
+                .as_bytes(),
+        )
+    })
+}
+
+// 匹配 <meta charset="gbk"> 以及 <meta http-equiv="Content-Type" content="text/html; charset=gbk">
+fn charset_from_meta(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = &bytes[..bytes.len().min(META_SNIFF_LIMIT)];
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+    let mut rest = head.as_str();
+    while let Some(pos) = rest.find("charset=") {
+        rest = &rest[pos + "charset=".len()..];
+        let label: String = rest
+            .trim_start_matches(|c| c == '"' || c == '\'')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        if let Some(encoding) = Encoding::for_label(label.as_bytes()) {
+            return Some(encoding);
+        }
+    }
+    None
+}
+
+// 按 BOM -> Content-Type 的 charset -> <meta charset> -> UTF-8 的顺序确定编码并解码；
+// 非法字节替换为 U+FFFD 而不是报错，抓取页面时宁可丢几个字也不要整页失败
+pub fn decode_body(bytes: &[u8], content_type: Option<&str>) -> String {
+    let encoding = Encoding::for_bom(bytes)
+        .map(|(encoding, _)| encoding)
+        .or_else(|| content_type.and_then(charset_from_content_type))
+        .or_else(|| charset_from_meta(bytes))
+        .unwrap_or(UTF_8);
+    let (text, used, had_errors) = encoding.decode(bytes);
+    if had_errors {
+        eprintln!(
+            "[Rust/text_decode.rs] body contained invalid {} sequences; replaced",
+            used.name()
+        );
+    }
+    text.into_owned()
+}
+
+// 替代 Response::text()：reqwest 只看响应头的 charset，部分页面（GBK 等）只在 <meta> 里声明编码
+pub async fn read_text(response: Response) -> Result<String, reqwest::Error> {
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let bytes = response.bytes().await?;
+    Ok(decode_body(&bytes, content_type.as_deref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // GBK 编码的页面片段，只在 <meta> 中声明编码："虎牙直播" / "主播"
+    const GBK_PAGE: &[u8] = b"<html><head><meta charset=\"gbk\"><title>\xbb\xa2\xd1\xc0\xd6\xb1\xb2\xa5</title></head><body>{\"nick\":\"\xd6\xf7\xb2\xa5\"}</body></html>";
+    const GBK_JSON: &[u8] = b"{\"nick\":\"\xd6\xf7\xb2\xa5\"}";
+
+    #[test]
+    fn decodes_gbk_page_declared_in_meta() {
+        let text = decode_body(GBK_PAGE, Some("text/html"));
+        assert!(text.contains("<title>虎牙直播</title>"));
+        let json = &text[text.find('{').unwrap()..text.rfind('}').unwrap() + 1];
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(value["nick"], "主播");
+    }
+
+    #[test]
+    fn decodes_gbk_declared_in_content_type() {
+        assert_eq!(
+            decode_body(GBK_JSON, Some("application/json; charset=GBK")),
+            "{\"nick\":\"主播\"}"
+        );
+        assert_eq!(
+            decode_body(GBK_JSON, Some("text/html; charset=\"gb2312\"")),
+            "{\"nick\":\"主播\"}"
+        );
+    }
+
+    #[test]
+    fn defaults_to_utf8_and_bom_wins() {
+        assert_eq!(decode_body("主播".as_bytes(), None), "主播");
+        // 未声明编码的 GBK 字节按 UTF-8 解码，非法序列被替换而不是报错
+        assert!(decode_body(GBK_JSON, None).contains('\u{FFFD}'));
+        let with_bom = [b"\xef\xbb\xbf".as_slice(), "主播".as_bytes()].concat();
+        assert_eq!(
+            decode_body(&with_bom, Some("text/html; charset=gbk")),
+            "主播"
+        );
+    }
+
+    #[test]
+    fn meta_charset_forms() {
+        let http_equiv = b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=gbk\">";
+        assert_eq!(charset_from_meta(http_equiv), Some(encoding_rs::GBK));
+        assert_eq!(charset_from_meta(b"<meta charset='utf-8'>"), Some(UTF_8));
+        assert_eq!(charset_from_meta(b"<meta charset=\"bogus\">"), None);
+        // 超出预扫描范围的声明不生效
+        let late = [vec![b' '; META_SNIFF_LIMIT], b"<meta charset=gbk>".to_vec()].concat();
+        assert_eq!(charset_from_meta(&late), None);
+    }
+}
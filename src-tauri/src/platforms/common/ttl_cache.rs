@@ -0,0 +1,175 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// 简单的内存 TTL 缓存：用于房间状态等短时间内重复查询的结果。
+// 可选的容量上限（条目数 / 按 weigh 计算的总大小）超出时按写入顺序淘汰最旧的条目
+pub struct TtlCache<V: Clone> {
+    ttl: Duration,
+    max_entries: usize,
+    max_weight: usize,
+    weigh: fn(&V) -> usize,
+    entries: Mutex<Entries<V>>,
+}
+
+struct Entries<V> {
+    map: HashMap<String, (u64, Instant, V)>,
+    // 按写入顺序排列；同一 key 重新写入后旧的记录留在队列中，弹出时与 map 中的序号比对后跳过
+    order: VecDeque<(u64, Instant, String)>,
+    weight: usize,
+    seq: u64,
+}
+
+impl<V> Entries<V> {
+    // 弹出最旧的一条记录，返回是否真的删除了条目
+    fn pop_oldest(&mut self, weigh: fn(&V) -> usize) -> bool {
+        let Some((seq, _, key)) = self.order.pop_front() else {
+            return false;
+        };
+        if self.map.get(&key).map(|(s, _, _)| *s) == Some(seq) {
+            if let Some((_, _, value)) = self.map.remove(&key) {
+                self.weight -= weigh(&value);
+            }
+        }
+        true
+    }
+
+    fn remove(&mut self, key: &str, weigh: fn(&V) -> usize) {
+        if let Some((_, _, value)) = self.map.remove(key) {
+            self.weight -= weigh(&value);
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl<V: Clone> TtlCache<V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self::bounded(ttl, usize::MAX, usize::MAX, |_| 0)
+    }
+
+    pub fn bounded(
+        ttl: Duration,
+        max_entries: usize,
+        max_weight: usize,
+        weigh: fn(&V) -> usize,
+    ) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            max_weight,
+            weigh,
+            entries: Mutex::new(Entries {
+                map: HashMap::new(),
+                order: VecDeque::new(),
+                weight: 0,
+                seq: 0,
+            }),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.map.get(key) {
+            Some((_, inserted_at, value)) if inserted_at.elapsed() < self.ttl => {
+                Some(value.clone())
+            }
+            Some(_) => {
+                entries.remove(key, self.weigh);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: String, value: V) {
+        let weight = (self.weigh)(&value);
+        if weight > self.max_weight {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.remove(&key, self.weigh);
+        // 顺便清理队首的过期条目，避免长时间运行后无限增长
+        while entries
+            .order
+            .front()
+            .is_some_and(|(_, inserted_at, _)| inserted_at.elapsed() >= self.ttl)
+        {
+            entries.pop_oldest(self.weigh);
+        }
+        while entries.map.len() >= self.max_entries || entries.weight + weight > self.max_weight {
+            if !entries.pop_oldest(self.weigh) {
+                break;
+            }
+        }
+        let now = Instant::now();
+        entries.seq += 1;
+        let seq = entries.seq;
+        entries.order.push_back((seq, now, key.clone()));
+        entries.weight += weight;
+        entries.map.insert(key, (seq, now, value));
+    }
+
+    pub fn remove(&self, key: &str) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(key, self.weigh);
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.map.clear();
+        entries.order.clear();
+        entries.weight = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn evicts_oldest_entries_beyond_the_entry_limit() {
+        let cache = TtlCache::bounded(TTL, 2, usize::MAX, |_: &u32| 0);
+        cache.insert("a".to_string(), 1);
+        cache.insert("b".to_string(), 2);
+        // 重新写入 a 后 b 成为最旧的条目
+        cache.insert("a".to_string(), 3);
+        cache.insert("c".to_string(), 4);
+        assert_eq!(cache.get("a"), Some(3));
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("c"), Some(4));
+    }
+
+    #[test]
+    fn evicts_by_total_weight_and_skips_oversized_values() {
+        let cache = TtlCache::bounded(TTL, usize::MAX, 10, |v: &Vec<u8>| v.len());
+        cache.insert("a".to_string(), vec![0; 4]);
+        cache.insert("b".to_string(), vec![0; 4]);
+        cache.insert("c".to_string(), vec![0; 4]);
+        assert_eq!(cache.get("a"), None);
+        assert!(cache.get("b").is_some());
+        assert!(cache.get("c").is_some());
+
+        // 单个值超过上限时不缓存，也不挤掉已有条目
+        cache.insert("big".to_string(), vec![0; 11]);
+        assert_eq!(cache.get("big"), None);
+        assert!(cache.get("b").is_some());
+
+        cache.remove("b");
+        cache.insert("d".to_string(), vec![0; 6]);
+        assert!(cache.get("c").is_some());
+        assert!(cache.get("d").is_some());
+    }
+
+    #[test]
+    fn expired_entries_are_dropped() {
+        let cache = TtlCache::new(Duration::ZERO);
+        cache.insert("a".to_string(), 1);
+        assert_eq!(cache.get("a"), None);
+        cache.insert("b".to_string(), 2);
+        assert_eq!(cache.entries.lock().unwrap().map.len(), 1);
+    }
+}
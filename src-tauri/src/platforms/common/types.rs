@@ -1,4 +1,6 @@
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 // Wrapper for payload like { args: { room_id_str: "..." } }
 // Used by get_douyin_live_stream_url and start_douyin_danmaku_listener
@@ -29,18 +31,42 @@ pub struct GetStreamUrlPayload {
 }
 
 // 描述一个可用的播放流变体（用于调试/导出所有地址）
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, Default)]
 pub struct StreamVariant {
     pub url: String,
     pub format: Option<String>,   // e.g. flv, ts, mp4
     pub desc: Option<String>,     // e.g. 原画/高清
     pub qn: Option<i32>,          // B 站的清晰度编号
     pub protocol: Option<String>, // e.g. http, https, ws/hls
+    pub codec: Option<String>,    // e.g. avc, hevc
+    // 上游给出的码率（目前只有斗鱼下发），供自动清晰度按带宽选档
+    pub bitrate_kbps: Option<u32>,
+    // 是否为低延迟流（目前只有 B 站标注，http_stream 下的 FLV 为 true），前端据此展示低延迟开关
+    pub low_latency: Option<bool>,
+}
+
+// 房间提供的一档清晰度（B 站 accept_qn），不一定有对应的播放地址
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct AcceptedQuality {
+    pub qn: i32,
+    pub desc: Option<String>,
+}
+
+// 房间访问受限的具体原因（目前仅 B 站下发）：封禁、隐藏或需要密码，前端据此展示对应提示
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind")]
+pub enum RoomRestriction {
+    // lock_till 为解封时间戳（秒），上游未给出时为 None
+    RoomLocked { lock_till: Option<i64> },
+    RoomHidden,
+    // password_rejected 为 true 表示已提交密码但校验失败
+    RoomEncrypted { password_rejected: bool },
 }
 
 // For the return type of get_douyin_live_stream_url
 // Matches LiveStreamInfo interface in DouyinLive.vue
-#[derive(Serialize, Clone, Debug)]
+// 新增字段请给出合理默认值，构造处统一使用 ..Default::default()
+#[derive(Serialize, Clone, Debug, Default)]
 pub struct LiveStreamInfo {
     pub title: Option<String>,
     pub anchor_name: Option<String>,
@@ -52,10 +78,42 @@ pub struct LiveStreamInfo {
     pub upstream_url: Option<String>,
     // 新增：所有可用的播放地址列表（调试/导出用）
     pub available_streams: Option<Vec<StreamVariant>>,
+    // 新增：房间可选的全部清晰度（目前仅 B 站），包括 available_streams 中还没有地址的档位
+    pub accept_qualities: Option<Vec<AcceptedQuality>>,
     // 新增：规范化后的房间ID（例如从 web_id 提取出的 room.id_str）
     pub normalized_room_id: Option<String>,
     // 新增：直播间的 web_rid（用于关注列表以 web_id 为主键）
     pub web_rid: Option<String>,
+    // 新增：请求的清晰度编号与上游实际下发的清晰度（B 站匿名用户可能被静默降级）
+    pub requested_qn: Option<i32>,
+    pub current_qn: Option<i32>,
+    pub current_quality_desc: Option<String>,
+    // 新增：请求的清晰度不在房间 accept_qn 中，已改用最接近的 qn（即 requested_qn）
+    pub quality_fallback: Option<bool>,
+    // 新增：实际清晰度低于请求且未登录时为 true，提示需要登录才能获取更高清晰度
+    pub login_required: Option<bool>,
+    // 新增：B 站 live_status == 2 表示轮播（录像循环），并非真正开播
+    pub is_rotation: Option<bool>,
+    // 新增：斗鱼竖屏（手机开播）房间
+    pub is_vertical: Option<bool>,
+    // 新增：未开播时的房间公告，以及主播预约的下次开播时间（平台不提供时为 None）
+    pub notice: Option<String>,
+    pub next_schedule: Option<String>,
+    // 新增：房间被封禁/隐藏/加密时的具体原因，正常房间为 None
+    pub restriction: Option<RoomRestriction>,
+    // 新增：上游需要 webview 无法设置的请求头（Referer/Cookie）时为 true，只能经本地代理播放；
+    // 为 false 时前端可直接播放 upstream_url，省去代理这一跳。没有播放地址时为 None
+    pub needs_proxy: Option<bool>,
+    // 新增：播放地址的过期时间（Unix 秒，目前仅虎牙防盗链 wsTime 可解析），用于失效前主动刷新
+    pub expires_at: Option<i64>,
+    // 新增：实际产出播放地址的解析策略名（各平台按顺序回退），便于定位哪条路径失效
+    pub resolver: Option<String>,
+    // 新增：请求低延迟模式时是否生效；支持的平台为 B 站（ptype=8 的 FLV）与虎牙（hs 线路），
+    // 其他平台或房间没有低延迟流时按常规流播放并返回 false，未请求时为 None
+    pub low_latency: Option<bool>,
+    // 新增：平台原始响应，仅在开启 set_debug_raw_responses 时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<serde_json::Value>,
 }
 
 #[derive(Default, Clone)]
@@ -64,16 +122,20 @@ pub struct StreamUrlStore {
     pub url: std::sync::Arc<std::sync::Mutex<String>>,
 }
 
+// 各房间弹幕监听任务的停止信号，按房间 ID 保存：不同窗口可以同时收看同一平台的不同房间
+pub type DanmakuStopSenders =
+    std::sync::Mutex<std::collections::HashMap<String, tokio::sync::mpsc::Sender<()>>>;
+
 // Moved from main.rs
 // State for the Douyin Danmaku listener
 #[derive(Default)]
 #[allow(dead_code)]
-pub struct DouyinDanmakuState(pub std::sync::Mutex<Option<tokio::sync::mpsc::Sender<()>>>);
+pub struct DouyinDanmakuState(pub DanmakuStopSenders);
 
 // State for the Bilibili Danmaku listener
 #[derive(Default)]
 #[allow(dead_code)]
-pub struct BilibiliDanmakuState(pub std::sync::Mutex<Option<tokio::sync::mpsc::Sender<()>>>);
+pub struct BilibiliDanmakuState(pub DanmakuStopSenders);
 
 // State for the Douyu Danmaku listener
 #[derive(Default)]
@@ -83,13 +145,129 @@ pub struct DouyuDanmakuState(pub std::sync::Mutex<Option<tokio::sync::mpsc::Send
 // State for the Huya Danmaku listener
 #[derive(Default)]
 #[allow(dead_code)]
-pub struct HuyaDanmakuState(pub std::sync::Mutex<Option<tokio::sync::mpsc::Sender<()>>>);
+pub struct HuyaDanmakuState(pub DanmakuStopSenders);
+
+// 跨平台统一的房间卡片结构（推荐首页等混排场景使用）
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct UnifiedRoom {
+    pub platform: String,
+    pub room_id: String,
+    pub title: String,
+    pub nickname: String,
+    pub avatar: String,
+    pub cover: String,
+    // 数值化热度，用于跨平台排序；viewer_count_str 保留平台原始展示文本
+    pub viewer_count: Option<u64>,
+    pub viewer_count_str: String,
+}
+
+// 弹幕 WebSocket 连接成功后通过 "danmaku-connected" 事件告知前端实际使用的服务器
+#[derive(Serialize, Clone, Debug, specta::Type)]
+pub struct DanmakuConnectedPayload {
+    pub platform: String,
+    pub room_id: String,
+    // 只保留 scheme://host/path：抖音等平台的查询串里带有签名与用户标识，不对外暴露
+    pub url: String,
+    pub host: String,
+    // 握手响应中的 Sec-WebSocket-Protocol（如有）
+    pub protocol: Option<String>,
+}
+
+impl DanmakuConnectedPayload {
+    pub fn new(platform: &str, room_id: &str, url: &str, protocol: Option<String>) -> Self {
+        let parsed = url::Url::parse(url).ok();
+        let host = parsed
+            .as_ref()
+            .and_then(|u| u.host_str().map(|h| h.to_string()))
+            .unwrap_or_default();
+        let endpoint = match parsed {
+            Some(mut u) => {
+                u.set_query(None);
+                u.set_fragment(None);
+                let _ = u.set_username("");
+                let _ = u.set_password(None);
+                u.to_string()
+            }
+            None => String::new(),
+        };
+        Self {
+            platform: platform.to_string(),
+            room_id: room_id.to_string(),
+            url: endpoint,
+            host,
+            protocol,
+        }
+    }
+}
 
+// 弹幕时间戳（毫秒）：
+// - received_at：本地接收时间，单调递增，所有平台都有
+// - sent_at：平台提供的发送时间，斗鱼(cst)/B站(info[0][4])/抖音(event_time) 提供，虎牙没有
+// - timestamp：排序/回放用，优先 sent_at，缺失时用 received_at 合成
 #[derive(Serialize, Clone, Debug, specta::Type)]
 pub struct DanmakuFrontendPayload {
+    // 多房间/多窗口时前端据此路由，每条弹幕都必须带上所属平台与房间
+    pub platform: String,
     pub room_id: String,
     pub user: String,
     pub content: String,
     pub user_level: i64,
     pub fans_club_level: i32,
+    pub received_at: i64,
+    pub sent_at: Option<i64>,
+    pub timestamp: i64,
+    // 加入房间时补发的历史弹幕，前端可据此区分展示
+    pub history: bool,
+}
+
+// 进程启动时的墙钟时间 + Instant 偏移，保证接收时间不会因系统校时而倒退
+static RECEIVE_CLOCK_BASE: Lazy<(Instant, i64)> = Lazy::new(|| {
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+    (Instant::now(), now_ms)
+});
+
+impl DanmakuFrontendPayload {
+    // 结构体更新语法使用：DanmakuFrontendPayload { platform, room_id, user, ..DanmakuFrontendPayload::received(sent_at) }
+    pub fn received(sent_at: Option<i64>) -> Self {
+        let (base_instant, base_ms) = *RECEIVE_CLOCK_BASE;
+        let received_at = base_ms + base_instant.elapsed().as_millis() as i64;
+        Self {
+            platform: String::new(),
+            room_id: String::new(),
+            user: String::new(),
+            content: String::new(),
+            user_level: 0,
+            fans_club_level: 0,
+            received_at,
+            sent_at,
+            timestamp: sent_at.unwrap_or(received_at),
+            history: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connected_payload_drops_signed_query() {
+        let payload = DanmakuConnectedPayload::new(
+            "douyin",
+            "7376",
+            "wss://webcast5-ws-web-lf.douyin.com/webcast/im/push/v2/?room_id=7376&user_unique_id=123&signature=abc",
+            None,
+        );
+        assert_eq!(
+            payload.url,
+            "wss://webcast5-ws-web-lf.douyin.com/webcast/im/push/v2/"
+        );
+        assert_eq!(payload.host, "webcast5-ws-web-lf.douyin.com");
+
+        let payload = DanmakuConnectedPayload::new("huya", "1", "not a url", None);
+        assert_eq!((payload.url.as_str(), payload.host.as_str()), ("", ""));
+    }
 }
@@ -7,9 +7,35 @@ pub enum SupportedPlatformRust {
     Douyu,
     #[serde(rename = "bilibili")]
     Bilibili,
+    #[serde(rename = "douyin")]
+    Douyin,
+    #[serde(rename = "huya")]
+    Huya,
     // Add other platforms as they are supported
 }
 
+impl SupportedPlatformRust {
+    // 前端同时存在 'douyu' 与 Platform.DOUYU 两种写法，这里统一忽略大小写解析
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "douyu" => Ok(Self::Douyu),
+            "bilibili" => Ok(Self::Bilibili),
+            "douyin" => Ok(Self::Douyin),
+            "huya" => Ok(Self::Huya),
+            other => Err(format!("Unsupported platform: {}", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Douyu => "douyu",
+            Self::Bilibili => "bilibili",
+            Self::Douyin => "douyin",
+            Self::Huya => "huya",
+        }
+    }
+}
+
 // Struct mirroring TypeScript CommonPlatformCategory
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CommonPlatformCategoryRust {
@@ -24,9 +50,11 @@ pub struct CommonPlatformCategoryRust {
 }
 
 // Struct mirroring TypeScript CommonCategoryGroup
-#[allow(dead_code)]
+// 两级分类树：分组（父分区）下挂子分类，子分类的 parentId 指向 groupId
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CommonCategoryGroupRust {
+    #[serde(rename = "groupId")]
+    pub group_id: String,
     #[serde(rename = "groupName")]
     pub group_name: String,
     pub platform: SupportedPlatformRust,
@@ -0,0 +1,67 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use super::SupportedPlatformRust;
+
+// 各平台默认 UA（均为桌面浏览器）：
+// - 斗鱼：Chrome 114 / Windows（betard、getH5Play 接口）
+// - B 站：Chrome 138 / Windows（playUrl、搜索接口，需与 Cookie 来源一致）
+// - 抖音：QQBrowser / Windows（a_bogus 签名以 UA 为输入，覆盖时签名会一并改用新 UA）
+// - 虎牙：Firefox 123 / Windows（移动端页面仍固定使用 iOS UA）
+fn default_user_agent(platform: &SupportedPlatformRust) -> &'static str {
+    match platform {
+        SupportedPlatformRust::Douyu => crate::platforms::douyu::stream_url::DEFAULT_DOUYU_UA,
+        SupportedPlatformRust::Bilibili => crate::platforms::bilibili::search::DEFAULT_UA,
+        SupportedPlatformRust::Douyin => crate::platforms::douyin::web_api::DEFAULT_USER_AGENT,
+        SupportedPlatformRust::Huya => crate::platforms::huya::stream_url::DESKTOP_UA,
+    }
+}
+
+// 运行时覆盖（例如抖音部分接口只对 Android UA 返回数据）
+static UA_OVERRIDES: Lazy<RwLock<HashMap<&'static str, String>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+// 平台请求使用的 UA：有覆盖用覆盖，否则用默认值
+pub fn user_agent_for(platform: &SupportedPlatformRust) -> String {
+    UA_OVERRIDES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(platform.as_str())
+        .cloned()
+        .unwrap_or_else(|| default_user_agent(platform).to_string())
+}
+
+// 覆盖某个平台的 UA；user_agent 为空或不传时恢复默认，返回生效的 UA
+#[tauri::command]
+pub async fn set_platform_user_agent(
+    platform: String,
+    user_agent: Option<String>,
+) -> Result<String, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let user_agent = user_agent
+        .map(|ua| ua.trim().to_string())
+        .filter(|ua| !ua.is_empty());
+    if let Some(ua) = user_agent.as_ref() {
+        reqwest::header::HeaderValue::from_str(ua)
+            .map_err(|e| format!("Invalid User-Agent: {}", e))?;
+    }
+    {
+        let mut overrides = UA_OVERRIDES.write().unwrap_or_else(|e| e.into_inner());
+        match user_agent {
+            Some(ua) => {
+                overrides.insert(platform.as_str(), ua);
+            }
+            None => {
+                overrides.remove(platform.as_str());
+            }
+        }
+    }
+    let effective = user_agent_for(&platform);
+    println!(
+        "[Rust/user_agent] {} User-Agent set to: {}",
+        platform.as_str(),
+        effective
+    );
+    Ok(effective)
+}
@@ -2,20 +2,90 @@ use flate2::read::GzDecoder;
 use futures_util::{stream::SplitStream, StreamExt};
 use prost::Message as ProstMessage; // For decode/encode
 use std::io::Read;
-use tauri::Emitter;
 use tokio::sync::mpsc::Sender;
-use tokio_tungstenite::tungstenite::protocol::Message as WsMessage; // Import the Emitter trait for app_handle.emit()
+use tokio_tungstenite::tungstenite::protocol::Message as WsMessage;
 
-use crate::platforms::douyin::danmu::gen::{PushFrame, Response}; // Removed ::douyin
+use crate::platforms::common::danmaku_subscribers::emit_to_subscribers;
+use crate::platforms::common::events::DtvEvent;
+use crate::platforms::common::DanmakuFrontendPayload;
+use crate::platforms::douyin::danmu::gen::{Message, PushFrame, Response};
 use crate::platforms::douyin::danmu::message_parsers;
 use crate::platforms::douyin::danmu::websocket_connection::WsStream; // Corrected path // Corrected path
 
+// PushFrame -> (gzip) -> Response 的结构化解码结果
+pub enum DecodedFrame {
+    Messages {
+        push_frame: PushFrame,
+        response: Response,
+    },
+    Ack,
+    Heartbeat,
+    Other,
+}
+
+// payloadEncoding 为 gzip 或负载带 gzip 魔数时解压，否则按原始 protobuf 解码
+fn decompress_payload(push_frame: &PushFrame) -> Result<Vec<u8>, String> {
+    let payload = push_frame.payload.as_slice();
+    let is_gzip = push_frame.payload_encoding.eq_ignore_ascii_case("gzip")
+        || payload.starts_with(&[0x1f, 0x8b]);
+    if !is_gzip {
+        return Ok(payload.to_vec());
+    }
+    let mut gz = GzDecoder::new(payload);
+    let mut decompressed = Vec::new();
+    gz.read_to_end(&mut decompressed)
+        .map_err(|e| format!("Gzip decompression error: {}", e))?;
+    Ok(decompressed)
+}
+
+pub fn decode_frame(bin_data: &[u8]) -> Result<DecodedFrame, String> {
+    let push_frame =
+        PushFrame::decode(bin_data).map_err(|e| format!("Failed to parse PushFrame: {}", e))?;
+    match push_frame.payload_type.as_str() {
+        "msg" if !push_frame.payload.is_empty() => {
+            let payload = decompress_payload(&push_frame)?;
+            let response = Response::decode(payload.as_slice())
+                .map_err(|e| format!("Failed to parse Response: {}", e))?;
+            Ok(DecodedFrame::Messages {
+                push_frame,
+                response,
+            })
+        }
+        "ack" => Ok(DecodedFrame::Ack),
+        "hb" => Ok(DecodedFrame::Heartbeat),
+        _ => Ok(DecodedFrame::Other),
+    }
+}
+
+// 按 method 分发到对应的 prost 类型解析；新增礼物/点赞/进场等事件只需在这里加分支
+fn dispatch_message(msg: &Message, room_id: &str) -> Option<DanmakuFrontendPayload> {
+    match msg.method.as_str() {
+        "WebcastChatMessage" => message_parsers::parse_chat_message(&msg.payload, room_id)
+            .ok()
+            .flatten(),
+        _ => None,
+    }
+}
+
+fn build_ack(push_frame: &PushFrame, response: &Response) -> Option<Vec<u8>> {
+    let ack_push_frame = PushFrame {
+        log_id: push_frame.log_id,
+        payload_type: "ack".to_string(),
+        payload: response.internal_ext.encode_to_vec(),
+        ..Default::default()
+    };
+    let mut ack_buf = Vec::new();
+    ack_push_frame.encode(&mut ack_buf).ok()?;
+    Some(ack_buf)
+}
+
 // This function will handle the message receiving loop and parsing
 pub async fn handle_received_messages(
     mut read_stream: SplitStream<WsStream>,
     ack_tx: Sender<WsMessage>,
-    app_handle: tauri::AppHandle, // Added AppHandle
-    room_id: String,              // Added room_id parameter
+    window: tauri::Window, // 用于投递给订阅该房间的窗口
+    room_id: String,       // Added room_id parameter
+    web_rid: String,       // 前端使用的房间号，用于暂停/恢复查询
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     println!(
         "[Douyin Danmaku] Message handler started for room_id: {}",
@@ -25,84 +95,47 @@ pub async fn handle_received_messages(
         match message_result {
             Ok(ws_msg) => {
                 if let WsMessage::Binary(bin_data) = ws_msg {
-                    match PushFrame::decode(bin_data.as_slice()) {
-                        Ok(push_frame) => {
-                            if push_frame.payload_type == "msg" && !push_frame.payload.is_empty() {
-                                let mut gz = GzDecoder::new(push_frame.payload.as_slice());
-                                let mut decompressed_payload = Vec::new();
-                                if let Err(e) = gz.read_to_end(&mut decompressed_payload) {
-                                    eprintln!("[Douyin Danmaku] Gzip decompression error: {}", e);
-                                    continue;
-                                }
-                                match Response::decode(decompressed_payload.as_slice()) {
-                                    Ok(response) => {
-                                        if response.need_ack {
-                                            let ack_payload_bytes =
-                                                response.internal_ext.encode_to_vec();
-                                            let ack_push_frame = PushFrame {
-                                                log_id: push_frame.log_id,
-                                                payload_type: "ack".to_string(),
-                                                payload: ack_payload_bytes,
-                                                ..Default::default()
-                                            };
-                                            let mut ack_buf = Vec::new();
-                                            if ack_push_frame.encode(&mut ack_buf).is_ok() {
-                                                if ack_tx
-                                                    .send(WsMessage::Binary(ack_buf))
-                                                    .await
-                                                    .is_err()
-                                                {
-                                                    eprintln!("[Douyin Danmaku] Failed to send ACK message via channel");
-                                                }
-                                            } else {
-                                                eprintln!("[Douyin Danmaku] Failed to encode ACK PushFrame for channel");
-                                            }
-                                        }
-                                        for msg in response.messages_list {
-                                            // println!("  -> Method: {}, Payload Length: {}", msg.method, msg.payload.len());
-                                            let mut danmaku_to_send = None;
-                                            if msg.method == "WebcastChatMessage" {
-                                                match message_parsers::parse_chat_message(
-                                                    &msg.payload,
-                                                    &room_id,
-                                                ) {
-                                                    Ok(Some(chat_payload)) => {
-                                                        danmaku_to_send = Some(chat_payload);
-                                                    }
-                                                    Ok(None) => { /* Not a message to display or ignored */
-                                                    }
-                                                    Err(_e) => { /* Error already logged in parser */
-                                                    }
-                                                }
-                                            }
-                                            // Add other message types here if needed, similar to ChatMessage
-                                            // else if msg.method == "WebcastMemberMessage" { ... }
-
-                                            if let Some(payload) = danmaku_to_send {
-                                                // Use app_handle.emit for Tauri v2 style global event emitting
-                                                if let Err(e) = app_handle
-                                                    .emit("danmaku-message", payload.clone())
-                                                {
-                                                    // payload needs to be Clone for emit
-                                                    eprintln!("[Douyin Danmaku] Failed to emit danmaku event: {}", e);
-                                                }
-                                            }
-                                        }
-                                    }
-                                    Err(e) => eprintln!(
-                                        "[Douyin Danmaku] Failed to parse Response: {}",
-                                        e
-                                    ),
+                    let (push_frame, response) = match decode_frame(bin_data.as_slice()) {
+                        Ok(DecodedFrame::Messages {
+                            push_frame,
+                            response,
+                        }) => (push_frame, response),
+                        // 服务端的 ACK / 心跳无需处理
+                        Ok(DecodedFrame::Ack | DecodedFrame::Heartbeat | DecodedFrame::Other) => {
+                            continue
+                        }
+                        Err(e) => {
+                            eprintln!("[Douyin Danmaku] {}", e);
+                            continue;
+                        }
+                    };
+                    if response.need_ack {
+                        match build_ack(&push_frame, &response) {
+                            Some(ack_buf) => {
+                                if ack_tx.send(WsMessage::Binary(ack_buf)).await.is_err() {
+                                    eprintln!(
+                                        "[Douyin Danmaku] Failed to send ACK message via channel"
+                                    );
                                 }
-                            } else if push_frame.payload_type == "ack" {
-                                // Optional: log received ACKs from server
-                                // println!("[Douyin Danmaku] Received ACK from server for log_id: {}", push_frame.log_id);
-                            } else if push_frame.payload_type == "hb" {
-                                // Optional: log received server heartbeats
-                                // println!("[Douyin Danmaku] Received Heartbeat from server.");
+                            }
+                            None => {
+                                eprintln!(
+                                    "[Douyin Danmaku] Failed to encode ACK PushFrame for channel"
+                                )
                             }
                         }
-                        Err(e) => eprintln!("[Douyin Danmaku] Failed to parse PushFrame: {}", e),
+                    }
+                    for msg in &response.messages_list {
+                        let Some(payload) = dispatch_message(msg, &room_id) else {
+                            continue;
+                        };
+                        let event = DtvEvent::DanmakuMessage(payload);
+                        if !crate::platforms::common::danmaku_pause::admit(
+                            "douyin", &web_rid, &event,
+                        ) {
+                            continue;
+                        }
+                        emit_to_subscribers("douyin", &web_rid, &window, event);
                     }
                 } else if let WsMessage::Ping(ping_data) = ws_msg {
                     if ack_tx.send(WsMessage::Pong(ping_data)).await.is_err() {
@@ -128,3 +161,136 @@ pub async fn handle_received_messages(
     println!("[Douyin Danmaku] Message handler finished.");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platforms::douyin::danmu::gen::{ChatMessage, User};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    // 按 PushFrame 协议逐层编码：ChatMessage -> Message -> Response -> gzip -> PushFrame
+    fn chat_frame(content: &str, need_ack: bool) -> Vec<u8> {
+        let chat = ChatMessage {
+            user: Some(User {
+                nick_name: "测试用户".to_string(),
+                ..Default::default()
+            }),
+            content: content.to_string(),
+            event_time: 1_700_000_000,
+            ..Default::default()
+        };
+        let response = Response {
+            messages_list: vec![
+                Message {
+                    method: "WebcastChatMessage".to_string(),
+                    payload: chat.encode_to_vec(),
+                    msg_id: 1,
+                    ..Default::default()
+                },
+                Message {
+                    method: "WebcastRoomUserSeqMessage".to_string(),
+                    payload: vec![0x08, 0x01],
+                    msg_id: 2,
+                    ..Default::default()
+                },
+            ],
+            internal_ext: "internal_src:dim|wss_push_room_id:7".to_string(),
+            need_ack,
+            ..Default::default()
+        };
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&response.encode_to_vec()).unwrap();
+        PushFrame {
+            log_id: 42,
+            payload_encoding: "gzip".to_string(),
+            payload_type: "msg".to_string(),
+            payload: gz.finish().unwrap(),
+            ..Default::default()
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn decodes_gzip_push_frame_into_messages() {
+        let frame = chat_frame("你好", true);
+        let Ok(DecodedFrame::Messages {
+            push_frame,
+            response,
+        }) = decode_frame(&frame)
+        else {
+            panic!("expected a message frame");
+        };
+        assert_eq!(push_frame.log_id, 42);
+        let methods: Vec<&str> = response
+            .messages_list
+            .iter()
+            .map(|m| m.method.as_str())
+            .collect();
+        assert_eq!(methods, ["WebcastChatMessage", "WebcastRoomUserSeqMessage"]);
+
+        let payload = dispatch_message(&response.messages_list[0], "7").unwrap();
+        assert_eq!(payload.platform, "douyin");
+        assert_eq!(payload.room_id, "7");
+        assert_eq!(payload.user, "测试用户");
+        assert_eq!(payload.content, "你好");
+        // 未支持的 method 忽略
+        assert!(dispatch_message(&response.messages_list[1], "7").is_none());
+    }
+
+    #[test]
+    fn ack_echoes_log_id_and_internal_ext() {
+        let frame = chat_frame("hi", true);
+        let Ok(DecodedFrame::Messages {
+            push_frame,
+            response,
+        }) = decode_frame(&frame)
+        else {
+            panic!("expected a message frame");
+        };
+        assert!(response.need_ack);
+        let ack = PushFrame::decode(build_ack(&push_frame, &response).unwrap().as_slice()).unwrap();
+        assert_eq!(ack.log_id, 42);
+        assert_eq!(ack.payload_type, "ack");
+        assert_eq!(ack.payload, response.internal_ext.as_bytes());
+    }
+
+    #[test]
+    fn uncompressed_payload_and_control_frames() {
+        // 未标注 gzip 且无魔数时按原始 protobuf 解码
+        let response = Response {
+            messages_list: vec![Message {
+                method: "WebcastChatMessage".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let frame = PushFrame {
+            payload_type: "msg".to_string(),
+            payload: response.encode_to_vec(),
+            ..Default::default()
+        }
+        .encode_to_vec();
+        assert!(matches!(
+            decode_frame(&frame),
+            Ok(DecodedFrame::Messages { response, .. }) if response.messages_list.len() == 1
+        ));
+
+        for (payload_type, expected) in [("ack", "ack"), ("hb", "hb"), ("close", "other")] {
+            let frame = PushFrame {
+                payload_type: payload_type.to_string(),
+                ..Default::default()
+            }
+            .encode_to_vec();
+            let kind = match decode_frame(&frame) {
+                Ok(DecodedFrame::Ack) => "ack",
+                Ok(DecodedFrame::Heartbeat) => "hb",
+                Ok(DecodedFrame::Other) => "other",
+                _ => "messages",
+            };
+            assert_eq!(kind, expected);
+        }
+        assert!(decode_frame(&[0xff, 0xff, 0xff]).is_err());
+    }
+}
@@ -9,6 +9,8 @@ pub fn parse_chat_message(
 ) -> Result<Option<DanmakuFrontendPayload>, Box<dyn std::error::Error + Send + Sync>> {
     match ChatMessage::decode(payload) {
         Ok(chat_msg) => {
+            // event_time 为秒级发送时间，0 表示未提供
+            let sent_at = (chat_msg.event_time > 0).then_some(chat_msg.event_time as i64 * 1000);
             if let Some(user) = chat_msg.user {
                 // 获取用户等级 (来自 demo)
                 let user_level = user.pay_grade.as_ref().map(|pg| pg.level).unwrap_or(0);
@@ -24,12 +26,14 @@ pub fn parse_chat_message(
                     .unwrap_or(0);
 
                 Ok(Some(DanmakuFrontendPayload {
+                    platform: "douyin".to_string(),
                     room_id: current_room_id.to_string(), // Populate room_id
                     user: user.nick_name.clone(),
                     content: chat_msg.content.clone(),
                     user_level,
                     fans_club_level,
                     // r#type: "chat".to_string(),
+                    ..DanmakuFrontendPayload::received(sent_at)
                 }))
             } else {
                 // 对于没有用户信息的聊天消息 (例如系统消息)，也可能需要发送，但等级为0
@@ -38,11 +42,13 @@ pub fn parse_chat_message(
                     chat_msg.content
                 );
                 Ok(Some(DanmakuFrontendPayload {
+                    platform: "douyin".to_string(),
                     room_id: current_room_id.to_string(), // Populate room_id
                     user: "系统".to_string(),             // Or some other placeholder
                     content: chat_msg.content.clone(),
                     user_level: 0,
                     fans_club_level: 0,
+                    ..DanmakuFrontendPayload::received(sent_at)
                 }))
             }
         }
@@ -1,4 +1,6 @@
 use crate::platforms::common::http_client::HttpClient;
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::SupportedPlatformRust;
 use crate::platforms::douyin::web_api::{
     fetch_room_data, normalize_douyin_live_id, DouyinRoomData, DEFAULT_USER_AGENT,
 };
@@ -318,7 +320,10 @@ impl DouyinLiveWebFetcher {
         }
 
         let mut headers = HeaderMap::new();
-        headers.insert(ACCEPT, HeaderValue::from_static("application/json, text/plain, */*"));
+        headers.insert(
+            ACCEPT,
+            HeaderValue::from_static("application/json, text/plain, */*"),
+        );
         headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("zh-CN,zh;q=0.9"));
         headers.insert(
             REFERER,
@@ -397,9 +402,11 @@ pub async fn fetch_douyin_room_info(live_id: String) -> Result<DouyinFollowListR
         "[fetch_douyin_room_info] Fetching details for web_id: {}",
         live_id
     );
+    let live_id = validate_room_id(&SupportedPlatformRust::Douyin, &live_id)?;
     let normalized_id = normalize_douyin_live_id(&live_id);
 
-    let http_client = HttpClient::new().map_err(|e| format!("Failed to create HttpClient: {}", e))?;
+    let http_client =
+        HttpClient::new().map_err(|e| format!("Failed to create HttpClient: {}", e))?;
 
     let DouyinRoomData { room } = fetch_room_data(&http_client, &normalized_id, None)
         .await
@@ -20,13 +20,17 @@ use prost::Message as ProstMessage; // For encoding heartbeat
 pub type WsStream = tokio_tungstenite::WebSocketStream<MaybeTlsStream<TcpStream>>;
 
 // This function will establish the connection and spawn send/heartbeat tasks.
-// It returns the read half of the stream and the sender for the outgoing message channel.
+// It returns the read half of the stream, the sender for the outgoing message channel
+// and the final ws url that was connected.
 pub async fn connect_and_manage_websocket(
     _fetcher: &DouyinLiveWebFetcher, // Changed to immutable reference as we only read from it now
     room_id: &str,
     cookie_header: &str,
     user_unique_id: &str,
-) -> Result<(SplitStream<WsStream>, Sender<WsMessage>), Box<dyn std::error::Error + Send + Sync>> {
+) -> Result<
+    (SplitStream<WsStream>, Sender<WsMessage>, String),
+    Box<dyn std::error::Error + Send + Sync>,
+> {
     let ws_cookie_header = cookie_header.to_string();
     let current_timestamp_ms = Utc::now().timestamp_millis();
     let first_req_ms = current_timestamp_ms - 100;
@@ -60,6 +64,7 @@ pub async fn connect_and_manage_websocket(
         signature
     );
 
+    let connected_url = final_wss_url_str.clone();
     let mut client_request = final_wss_url_str.into_client_request()?;
     let headers = client_request.headers_mut();
     headers.insert("accept", "application/json, text/plain, */*".parse()?);
@@ -122,5 +127,5 @@ pub async fn connect_and_manage_websocket(
         println!("WebSocket send/heartbeat task ended.");
     });
 
-    Ok((read, tx)) // Return the read stream and the sender for other tasks to send messages
+    Ok((read, tx, connected_url)) // Return the read stream and the sender for other tasks to send messages
 }
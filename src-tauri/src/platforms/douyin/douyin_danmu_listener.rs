@@ -1,11 +1,37 @@
+use crate::platforms::common::danmaku_reconnect;
+use crate::platforms::common::danmaku_subscribers;
+use crate::platforms::common::danmaku_subscribers::emit_to_subscribers;
+use crate::platforms::common::events::DtvEvent;
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::SupportedPlatformRust;
 use crate::platforms::douyin::web_api::normalize_douyin_live_id;
-use tauri::Emitter;
+use tauri::Manager;
 use tokio::sync::mpsc as tokio_mpsc;
 
+async fn stop_previous_listener(
+    state: &tauri::State<'_, crate::platforms::common::DouyinDanmakuState>,
+    room_id: &str,
+) {
+    let previous_tx = {
+        let mut lock = state.inner().0.lock().unwrap_or_else(|e| e.into_inner());
+        lock.remove(room_id)
+    };
+
+    if let Some(tx) = previous_tx {
+        println!(
+            "[Douyin Danmaku] Sending shutdown to previous Douyin listener task for room {}.",
+            room_id
+        );
+        if tx.send(()).await.is_err() {
+            eprintln!("[Douyin Danmaku] Failed to send shutdown. Task might have already completed or panicked.");
+        }
+    }
+}
+
 #[tauri::command]
 pub async fn start_douyin_danmu_listener(
     payload: crate::platforms::common::GetStreamUrlPayload,
-    app_handle: tauri::AppHandle,
+    window: tauri::Window,
     state: tauri::State<'_, crate::platforms::common::DouyinDanmakuState>,
 ) -> Result<(), String> {
     let room_id_or_url = payload.args.room_id_str;
@@ -14,34 +40,56 @@ pub async fn start_douyin_danmu_listener(
         room_id_or_url
     );
 
-    let previous_tx = {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.take()
-    };
-
-    if let Some(tx) = previous_tx {
-        println!("[Douyin Danmaku] Sending shutdown to previous Douyin listener task.");
-        if tx.send(()).await.is_err() {
-            eprintln!("[Douyin Danmaku] Failed to send shutdown. Task might have already completed or panicked.");
-        }
-    }
-
     if room_id_or_url == "stop_listening" {
+        // 只停止当前窗口离开后无人订阅的房间，其他窗口仍在订阅的连接保留
+        let rooms = danmaku_subscribers::leave_platform("douyin", window.label());
+        if rooms.is_empty() {
+            println!(
+                "[Douyin Danmaku] Window {} unsubscribed; listeners kept for remaining windows.",
+                window.label()
+            );
+            return Ok(());
+        }
+        for room_id in &rooms {
+            stop_previous_listener(&state, room_id).await;
+        }
         println!(
             "[Douyin Danmaku] Received stop_listening signal. Listener will not be restarted."
         );
         return Ok(());
     }
 
+    let room_id_or_url = validate_room_id(&SupportedPlatformRust::Douyin, &room_id_or_url)?;
     let normalized_room_id = normalize_douyin_live_id(&room_id_or_url);
 
+    // 同一房间已有存活连接时只登记当前窗口（多窗口共享一条连接）
+    let upstream_alive = state
+        .inner()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&normalized_room_id)
+        .map(|tx| !tx.is_closed())
+        .unwrap_or(false);
+    if danmaku_subscribers::subscribe("douyin", &normalized_room_id, &window, upstream_alive) {
+        println!(
+            "[Douyin Danmaku] Window {} joined existing listener for room: {}",
+            window.label(),
+            normalized_room_id
+        );
+        return Ok(());
+    }
+
+    stop_previous_listener(&state, &normalized_room_id).await;
+
     let (tx_shutdown, mut rx_shutdown) = tokio_mpsc::channel::<()>(1);
     {
-        let mut lock = state.inner().0.lock().unwrap();
-        *lock = Some(tx_shutdown);
+        let mut lock = state.inner().0.lock().unwrap_or_else(|e| e.into_inner());
+        lock.insert(normalized_room_id.clone(), tx_shutdown);
     }
 
-    let app_handle_clone = app_handle.clone();
+    let app_handle_clone = window.app_handle().clone();
+    let window_clone = window.clone();
     let room_id_str_clone = normalized_room_id.clone();
 
     tokio::spawn(async move {
@@ -50,8 +98,8 @@ pub async fn start_douyin_danmu_listener(
             room_id_str_clone
         );
 
+        danmaku_reconnect::reset("douyin", &room_id_str_clone);
         let task_result = {
-            let mut attempt: u32 = 1;
             loop {
                 let attempt_result = async {
                     let mut fetcher = crate::platforms::douyin::danmu::web_fetcher::DouyinLiveWebFetcher::new(&room_id_str_clone)?;
@@ -68,7 +116,7 @@ pub async fn start_douyin_danmu_listener(
                         actual_room_id, user_unique_id
                     );
 
-                    let (read_stream, ack_tx) = crate::platforms::douyin::danmu::websocket_connection::connect_and_manage_websocket(
+                    let (read_stream, ack_tx, ws_url) = crate::platforms::douyin::danmu::websocket_connection::connect_and_manage_websocket(
                         &fetcher,
                         &actual_room_id,
                         &cookie_header,
@@ -80,17 +128,30 @@ pub async fn start_douyin_danmu_listener(
                         "[Douyin Danmaku] WebSocket connected for room: {}",
                         actual_room_id
                     );
+                    danmaku_reconnect::reset("douyin", &room_id_str_clone);
+                    emit_to_subscribers(
+                        "douyin",
+                        &room_id_str_clone,
+                        &window_clone,
+                        DtvEvent::DanmakuConnected(crate::platforms::common::DanmakuConnectedPayload::new(
+                            "douyin",
+                            &actual_room_id,
+                            &ws_url,
+                            None,
+                        )),
+                    );
 
                     tokio::select! {
                         res = crate::platforms::douyin::danmu::message_handler::handle_received_messages(
                             read_stream,
                             ack_tx,
-                            app_handle_clone.clone(),
-                            actual_room_id.clone()
+                            window_clone.clone(),
+                            actual_room_id.clone(),
+                            room_id_str_clone.clone()
                         ) => {
-                            if let Err(e) = res {
-                                return Err(e);
-                            }
+                            res?;
+                            // 未收到停止信号时连接被关闭，同样按重连策略处理
+                            return Err("WebSocket closed by server".into());
                         }
                         _ = rx_shutdown.recv() => {
                             println!(
@@ -107,16 +168,24 @@ pub async fn start_douyin_danmu_listener(
                 match attempt_result {
                     Ok(_) => break Ok(()),
                     Err(e) => {
-                        if attempt >= 2 {
-                            eprintln!("[Douyin Danmaku] Listener connect/fetch failed after {} attempts: {}", attempt, e);
-                            break Err(e);
-                        } else {
-                            eprintln!(
-                                "[Douyin Danmaku WARN] Attempt {} failed: {}. Retrying...",
-                                attempt, e
+                        let Some(delay) =
+                            danmaku_reconnect::next_retry_delay("douyin", &room_id_str_clone)
+                        else {
+                            danmaku_reconnect::give_up(
+                                &app_handle_clone,
+                                "douyin",
+                                &room_id_str_clone,
+                                e.to_string(),
                             );
-                            attempt += 1;
-                            continue;
+                            break Err(e);
+                        };
+                        eprintln!(
+                            "[Douyin Danmaku WARN] Connection failed: {}. Retrying in {:?}...",
+                            e, delay
+                        );
+                        tokio::select! {
+                            _ = tokio::time::sleep(delay) => continue,
+                            _ = rx_shutdown.recv() => break Ok(()),
                         }
                     }
                 }
@@ -129,18 +198,20 @@ pub async fn start_douyin_danmu_listener(
                 room_id_str_clone, e
             );
             let error_payload = crate::platforms::common::DanmakuFrontendPayload {
+                platform: "douyin".to_string(),
                 room_id: room_id_str_clone.clone(),
                 user: "系统消息".to_string(),
                 content: format!("弹幕连接发生错误: {}", e),
                 user_level: 0,
                 fans_club_level: 0,
+                ..crate::platforms::common::DanmakuFrontendPayload::received(None)
             };
-            if let Err(emit_err) = app_handle.emit("danmaku-message", error_payload) {
-                eprintln!(
-                    "[Douyin Danmaku] Failed to emit error event to frontend: {}",
-                    emit_err
-                );
-            }
+            emit_to_subscribers(
+                "douyin",
+                &room_id_str_clone,
+                &window_clone,
+                DtvEvent::DanmakuMessage(error_payload),
+            );
         } else {
             println!(
                 "[Douyin Danmaku] Listener task for room {} completed.",
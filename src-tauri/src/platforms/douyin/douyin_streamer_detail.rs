@@ -1,18 +1,21 @@
+use crate::platforms::common::cn_number::parse_cn_number;
 use crate::platforms::common::http_client::HttpClient;
+use crate::platforms::common::request_id::{new_request_id, tag_error};
+use crate::platforms::common::resolver_chain::{first_playable, Resolver};
+use crate::platforms::common::room_id::validate_room_id;
 use crate::platforms::common::types::StreamVariant;
-use crate::platforms::common::GetStreamUrlPayload;
 use crate::platforms::common::LiveStreamInfo as CommonLiveStreamInfo;
+use crate::platforms::common::{GetStreamUrlPayload, Quality, SupportedPlatformRust};
 use crate::platforms::douyin::web_api::{
-    choose_flv_stream, fetch_room_data, normalize_douyin_live_id, DouyinRoomData,
+    choose_flv_stream, choose_stream_data_flv, fetch_room_data, normalize_douyin_live_id,
+    DouyinRoomData,
 };
 use crate::proxy::ProxyServerHandle;
 use crate::StreamUrlStore;
+use futures_util::FutureExt;
 use serde_json::Value;
 use tauri::{command, AppHandle, State};
 
-const QUALITY_OD: &str = "OD";
-const QUALITY_BD: &str = "BD";
-const QUALITY_UHD: &str = "UHD";
 #[command]
 pub async fn get_douyin_live_stream_url(
     app_handle: AppHandle,
@@ -25,7 +28,9 @@ pub async fn get_douyin_live_stream_url(
         stream_url_store,
         proxy_server_handle,
         payload,
-        QUALITY_OD.to_string(),
+        Quality::Origin
+            .to_platform_param(&SupportedPlatformRust::Douyin)
+            .to_string(),
     )
     .await
 }
@@ -38,33 +43,31 @@ pub async fn get_douyin_live_stream_url_with_quality(
     payload: GetStreamUrlPayload,
     quality: String,
 ) -> Result<CommonLiveStreamInfo, String> {
-    let requested_id = payload.args.room_id_str.trim().to_string();
-    if requested_id.is_empty() {
-        return Ok(CommonLiveStreamInfo {
-            title: None,
-            anchor_name: None,
-            avatar: None,
-            stream_url: None,
-            status: None,
-            error_message: Some("Douyin web_id cannot be empty.".to_string()),
-            upstream_url: None,
-            available_streams: None,
-            normalized_room_id: None,
-            web_rid: None,
-        });
-    }
+    let request_id = new_request_id();
+    let requested_id =
+        match validate_room_id(&SupportedPlatformRust::Douyin, &payload.args.room_id_str) {
+            Ok(room_id) => room_id,
+            Err(e) => {
+                return Ok(CommonLiveStreamInfo {
+                    error_message: Some(e.to_string()),
+                    ..Default::default()
+                });
+            }
+        };
 
     println!(
-        "[Douyin Stream Detail] Fetching stream for '{}' with requested quality '{}'",
-        requested_id, quality
+        "[Douyin Stream Detail][req:{}] Fetching stream for '{}' with requested quality '{}'",
+        request_id, requested_id, quality
     );
 
     // 使用默认 HTTP 客户端（遵循 HTTP(S)_PROXY 环境变量）
-    let http_client =
-        HttpClient::new().map_err(|e| format!("Failed to create HttpClient: {}", e))?;
+    let http_client = HttpClient::new()
+        .map_err(|e| tag_error(&request_id, format!("Failed to create HttpClient: {}", e)))?;
 
     let normalized_id = normalize_douyin_live_id(&requested_id);
-    let DouyinRoomData { room } = fetch_room_data(&http_client, &normalized_id, None).await?;
+    let DouyinRoomData { room } = fetch_room_data(&http_client, &normalized_id, None)
+        .await
+        .map_err(|e| tag_error(&request_id, e))?;
     let web_rid = extract_web_rid(&room).unwrap_or_else(|| normalized_id.clone());
     let status = room
         .get("status")
@@ -75,13 +78,14 @@ pub async fn get_douyin_live_stream_url_with_quality(
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
     let anchor_name = extract_anchor_name(&room);
-    let avatar = extract_avatar(&room);
+    let avatar = extract_avatar(&room).map(|u| crate::proxy::proxied_image_url(&u));
     let available_streams = collect_available_streams(&room);
+    let raw = crate::platforms::common::debug_raw::capture(|| room.clone());
 
     if status != 2 {
         println!(
-            "[Douyin Stream Detail] Room '{}' is not live (status={}). Returning metadata only.",
-            web_rid, status
+            "[Douyin Stream Detail][req:{}] Room '{}' is not live (status={}). Returning metadata only.",
+            request_id, web_rid, status
         );
         return Ok(CommonLiveStreamInfo {
             title,
@@ -94,19 +98,45 @@ pub async fn get_douyin_live_stream_url_with_quality(
             available_streams: available_streams.clone(),
             normalized_room_id: None,
             web_rid: Some(web_rid),
+            raw,
+            ..Default::default()
         });
     }
 
-    let target_quality = normalize_quality_tag(&quality);
-    let selected = choose_flv_stream(&room, target_quality)
-        .or_else(|| first_flv_stream(&room))
+    let target_quality = Quality::from_user_or_default(&SupportedPlatformRust::Douyin, &quality)
+        .to_platform_param(&SupportedPlatformRust::Douyin);
+    // 先用扁平的 flv_pull_url，缺失时回退到嵌套的 stream_data
+    let resolvers: Vec<Resolver<'_, (String, String)>> = vec![
+        (
+            "flv_pull_url",
+            async {
+                Ok::<_, String>(
+                    choose_flv_stream(&room, target_quality).or_else(|| first_flv_stream(&room)),
+                )
+            }
+            .boxed(),
+        ),
+        (
+            "stream_data",
+            async { Ok::<_, String>(choose_stream_data_flv(&room, target_quality)) }.boxed(),
+        ),
+    ];
+    let context = format!(
+        "[Douyin Stream Detail][req:{}] room {}",
+        request_id, web_rid
+    );
+    let ((selected_key, real_url), resolver) = first_playable(&context, resolvers)
+        .await
+        .map_err(|e| tag_error(&request_id, e))?
         .ok_or_else(|| {
-            "[Douyin Stream Detail] No FLV streams available in stream_url.flv_pull_url".to_string()
+            tag_error(
+                &request_id,
+                "[Douyin Stream Detail] No FLV streams available in flv_pull_url or stream_data",
+            )
         })?;
-    let (selected_key, real_url) = selected;
     println!(
-        "[Douyin Stream Detail] Selected FLV stream key='{}' url='{}'",
-        selected_key, real_url
+        "[Douyin Stream Detail][req:{}] Selected FLV stream key='{}' via {} url='{}'",
+        request_id, selected_key, resolver, real_url
     );
 
     let sanitized_url = enforce_https(&real_url);
@@ -118,22 +148,17 @@ pub async fn get_douyin_live_stream_url_with_quality(
         stream_url: Some(sanitized_url.clone()),
         status: Some(status),
         error_message: None,
+        needs_proxy: Some(crate::proxy::upstream_needs_proxy(&sanitized_url)),
         upstream_url: Some(sanitized_url),
         available_streams,
         normalized_room_id: None,
         web_rid: Some(web_rid),
+        resolver: Some(resolver.to_string()),
+        raw,
+        ..Default::default()
     })
 }
 
-fn normalize_quality_tag(input: &str) -> &str {
-    match input.trim().to_uppercase().as_str() {
-        "OD" | "原画" => QUALITY_OD,
-        "BD" | "高清" => QUALITY_BD,
-        "UHD" | "标清" => QUALITY_UHD,
-        _ => QUALITY_OD,
-    }
-}
-
 pub(crate) fn extract_web_rid(room: &Value) -> Option<String> {
     room.get("owner")
         .and_then(|o| o.get("web_rid"))
@@ -211,6 +236,9 @@ pub(crate) fn collect_available_streams(room: &Value) -> Option<Vec<StreamVarian
                 desc: Some(k.to_string()),
                 qn: None,
                 protocol: url.split(':').next().map(|s| s.to_string()),
+                codec: None,
+                bitrate_kbps: None,
+                low_latency: None,
             })
         })
         .collect::<Vec<_>>();
@@ -230,3 +258,56 @@ fn first_flv_stream(room: &Value) -> Option<(String, String)> {
         .iter()
         .find_map(|(k, v)| v.as_str().map(|url| (k.to_string(), url.to_string())))
 }
+
+// 预览卡片用：一次房间接口拿到元数据与已下发的可用流，不挑选清晰度、不启动代理
+pub(crate) async fn peek_douyin_room(room_id: &str) -> Result<CommonLiveStreamInfo, String> {
+    let http_client =
+        HttpClient::new().map_err(|e| format!("Failed to create HttpClient: {}", e))?;
+    let normalized_id = normalize_douyin_live_id(room_id);
+    let DouyinRoomData { room } = fetch_room_data(&http_client, &normalized_id, None).await?;
+    let is_live = room.get("status").and_then(|v| v.as_i64()) == Some(2);
+    Ok(CommonLiveStreamInfo {
+        title: room
+            .get("title")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        anchor_name: extract_anchor_name(&room),
+        avatar: extract_avatar(&room).map(|u| crate::proxy::proxied_image_url(&u)),
+        status: Some(if is_live { 1 } else { 0 }),
+        available_streams: if is_live {
+            collect_available_streams(&room)
+        } else {
+            None
+        },
+        web_rid: extract_web_rid(&room),
+        ..Default::default()
+    })
+}
+
+// 轻量开播状态查询：只取房间数据中的 status 字段（2 表示直播中）
+pub async fn fetch_douyin_live_status(room_id: &str) -> Result<bool, String> {
+    let http_client =
+        HttpClient::new().map_err(|e| format!("Failed to create HttpClient: {}", e))?;
+    let normalized_id = normalize_douyin_live_id(room_id);
+    let DouyinRoomData { room } = fetch_room_data(&http_client, &normalized_id, None).await?;
+    let status = room
+        .get("status")
+        .and_then(|v| v.as_i64())
+        .unwrap_or_default();
+    Ok(status == 2)
+}
+
+// 在线人数（stats.user_count_str，形如 "1.2万"），返回 (是否开播, 人数)
+pub async fn fetch_douyin_viewers(room_id: &str) -> Result<(bool, Option<u64>), String> {
+    let http_client =
+        HttpClient::new().map_err(|e| format!("Failed to create HttpClient: {}", e))?;
+    let normalized_id = normalize_douyin_live_id(room_id);
+    let DouyinRoomData { room } = fetch_room_data(&http_client, &normalized_id, None).await?;
+    let is_live = room.get("status").and_then(|v| v.as_i64()) == Some(2);
+    let viewers = room
+        .pointer("/stats/user_count_str")
+        .and_then(|v| v.as_str())
+        .and_then(parse_cn_number)
+        .or_else(|| room.get("user_count").and_then(|v| v.as_u64()));
+    Ok((is_live, viewers))
+}
@@ -1,6 +1,11 @@
 use crate::platforms::common::http_client::HttpClient;
-use crate::platforms::common::{FollowHttpClient, GetStreamUrlPayload, LiveStreamInfo};
-use crate::platforms::douyin::web_api::{fetch_room_data, normalize_douyin_live_id, DouyinRoomData};
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::{
+    FollowHttpClient, GetStreamUrlPayload, LiveStreamInfo, SupportedPlatformRust,
+};
+use crate::platforms::douyin::web_api::{
+    fetch_live_room_by_user, fetch_room_data, normalize_douyin_live_id, DouyinRoomData,
+};
 use tauri::command;
 use tauri::State;
 
@@ -9,23 +14,19 @@ pub async fn fetch_douyin_streamer_info(
     payload: GetStreamUrlPayload,
     follow_http: State<'_, FollowHttpClient>,
 ) -> Result<LiveStreamInfo, String> {
-    let requested_id = payload.args.room_id_str.trim().to_string();
-    if requested_id.is_empty() {
-        return Ok(LiveStreamInfo {
-            title: None,
-            anchor_name: None,
-            avatar: None,
-            stream_url: None,
-            status: None,
-            error_message: Some("Douyin web_id cannot be empty.".to_string()),
-            upstream_url: None,
-            available_streams: None,
-            normalized_room_id: None,
-            web_rid: None,
-        });
-    }
+    let requested_id =
+        match validate_room_id(&SupportedPlatformRust::Douyin, &payload.args.room_id_str) {
+            Ok(room_id) => room_id,
+            Err(e) => {
+                return Ok(LiveStreamInfo {
+                    error_message: Some(e.to_string()),
+                    ..Default::default()
+                });
+            }
+        };
 
-    let http_client: &HttpClient = &follow_http.0;
+    let follow_client = follow_http.client();
+    let http_client: &HttpClient = &follow_client;
 
     let normalized_id = normalize_douyin_live_id(&requested_id);
 
@@ -42,8 +43,10 @@ pub async fn fetch_douyin_streamer_info(
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string());
             let anchor_name = super::douyin_streamer_detail::extract_anchor_name(&room);
-            let avatar = super::douyin_streamer_detail::extract_avatar(&room);
+            let avatar = super::douyin_streamer_detail::extract_avatar(&room)
+                .map(|u| crate::proxy::proxied_image_url(&u));
             let available_streams = super::douyin_streamer_detail::collect_available_streams(&room);
+            let raw = crate::platforms::common::debug_raw::capture(|| room.clone());
 
             Ok(LiveStreamInfo {
                 title,
@@ -56,6 +59,8 @@ pub async fn fetch_douyin_streamer_info(
                 available_streams,
                 normalized_room_id: None,
                 web_rid: Some(web_rid),
+                raw,
+                ..Default::default()
             })
         }
         Err(e) => Ok(LiveStreamInfo {
@@ -64,11 +69,71 @@ pub async fn fetch_douyin_streamer_info(
             avatar: None,
             stream_url: None,
             status: None,
-                error_message: Some(format!("获取抖音房间信息失败: {}", e)),
-                upstream_url: None,
-                available_streams: None,
-                normalized_room_id: None,
-                web_rid: Some(normalized_id),
+            error_message: Some(format!("获取抖音房间信息失败: {}", e)),
+            upstream_url: None,
+            available_streams: None,
+            normalized_room_id: None,
+            web_rid: Some(normalized_id),
+            ..Default::default()
         }),
     }
 }
+
+// 抖音 room_id 每次开播都会变化，关注可改为以用户(sec_uid/uid)为主键，通过此接口找到当前直播间
+#[command]
+pub async fn fetch_douyin_room_by_user(
+    uid: String,
+    follow_http: State<'_, FollowHttpClient>,
+) -> Result<LiveStreamInfo, String> {
+    let uid = uid.trim().to_string();
+    if uid.is_empty() {
+        return Err("Douyin uid cannot be empty.".to_string());
+    }
+
+    let follow_client = follow_http.client();
+    let http_client: &HttpClient = &follow_client;
+
+    let (room_id, web_rid) = match fetch_live_room_by_user(http_client, &uid, None).await? {
+        Some(found) => found,
+        None => {
+            return Ok(LiveStreamInfo {
+                status: Some(0),
+                error_message: Some("该用户当前未开播".to_string()),
+                ..Default::default()
+            });
+        }
+    };
+    println!(
+        "[Douyin] user {} is live: room_id={} web_rid={:?}",
+        uid, room_id, web_rid
+    );
+
+    // 网页版 enter 接口以 web_rid 为参数；取不到 web_rid 时只能返回 room_id
+    let Some(web_rid) = web_rid else {
+        return Ok(LiveStreamInfo {
+            status: Some(2),
+            normalized_room_id: Some(room_id),
+            ..Default::default()
+        });
+    };
+
+    let DouyinRoomData { room } = fetch_room_data(http_client, &web_rid, None).await?;
+    let status = room
+        .get("status")
+        .and_then(|v| v.as_i64())
+        .unwrap_or_default() as i32;
+    Ok(LiveStreamInfo {
+        title: room
+            .get("title")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        anchor_name: super::douyin_streamer_detail::extract_anchor_name(&room),
+        avatar: super::douyin_streamer_detail::extract_avatar(&room)
+            .map(|u| crate::proxy::proxied_image_url(&u)),
+        status: Some(status),
+        available_streams: super::douyin_streamer_detail::collect_available_streams(&room),
+        normalized_room_id: Some(room_id),
+        web_rid: Some(web_rid),
+        ..Default::default()
+    })
+}
@@ -1,9 +1,14 @@
+use crate::platforms::common::cn_number::parse_cn_number;
 use crate::platforms::common::http_client::HttpClient;
+use crate::platforms::common::list_cache::{is_network_error, load_list, network_error, save_list};
+use crate::platforms::common::user_agent::user_agent_for;
+use crate::platforms::common::SupportedPlatformRust;
 use crate::platforms::douyin::a_bogus::generate_a_bogus;
-use crate::platforms::douyin::web_api::DEFAULT_USER_AGENT;
+use crate::platforms::douyin::token::{parse_signed_body, with_fresh_token, DouyinFetchError};
+use crate::proxy::proxied_image_url;
 use reqwest::header::{HeaderMap, HeaderValue, COOKIE, USER_AGENT};
 use serde::{Deserialize, Serialize};
-use tauri::State; // Removed SET_COOKIE
+use tauri::{AppHandle, State}; // Removed SET_COOKIE
 use urlencoding::encode;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -75,16 +80,101 @@ pub struct DouyinLiveListResponse {
     pub rooms: Vec<LiveRoomFrontend>,
     pub has_more: bool,
     pub next_offset: i32, // The offset to use for the next request
+    // 网络失败时返回的离线缓存数据会标记为 stale
+    #[serde(default)]
+    pub stale: bool,
+    // 实际生效的排序方式；请求的排序不被支持时会回退并在这里体现。
+    // 抖音只有推荐流，请求 hot 时返回 hot_per_page：仅对当前这一页按在线人数重排，跨页并不全局有序
+    #[serde(default)]
+    pub effective_sort: Option<String>,
+}
+
+const SORT_RECOMMEND: &str = "recommend";
+const SORT_HOT: &str = "hot";
+const SORT_HOT_PER_PAGE: &str = "hot_per_page";
+
+// 分区接口本身只按推荐流返回：hot 在每页内按在线人数重排（上报为 hot_per_page），
+// new 接口不支持，回退为 recommend
+fn resolve_douyin_sort(sort: Option<&str>) -> &'static str {
+    match sort.map(|s| s.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("") | Some(SORT_RECOMMEND) => SORT_RECOMMEND,
+        Some(SORT_HOT) => SORT_HOT_PER_PAGE,
+        Some(other) => {
+            eprintln!(
+                "[Douyin List] Sort '{}' is not supported by the partition API, falling back to {}",
+                other, SORT_RECOMMEND
+            );
+            SORT_RECOMMEND
+        }
+    }
 }
 
 #[tauri::command]
 pub async fn fetch_douyin_partition_rooms(
-    _http_client: State<'_, reqwest::Client>,
+    app_handle: AppHandle,
+    _http_client: State<'_, crate::platforms::common::SharedReqwestClient>,
     partition: String,
     partition_type: String,
     offset: i32, // This is the offset for the current request (0, 15, 30...)
     ms_token: String,
+    sort: Option<String>,
 ) -> Result<DouyinLiveListResponse, String> {
+    let effective_sort = resolve_douyin_sort(sort.as_deref());
+    let cache_key = format!(
+        "douyin_{}_{}_{}_{}",
+        partition_type, partition, effective_sort, offset
+    );
+    match fetch_douyin_partition_rooms_remote(partition, partition_type, offset, ms_token).await {
+        Ok(mut response) => {
+            if effective_sort == SORT_HOT_PER_PAGE {
+                response.rooms.sort_by_key(|room| {
+                    std::cmp::Reverse(parse_cn_number(&room.user_count_str).unwrap_or(0))
+                });
+            }
+            response.effective_sort = Some(effective_sort.to_string());
+            save_list(&app_handle, &cache_key, &response);
+            Ok(response)
+        }
+        // 只有网络不可达时才回退到缓存，接口返回的错误照常交给前端
+        Err(e) if is_network_error(&e) => {
+            match load_list::<DouyinLiveListResponse>(&app_handle, &cache_key) {
+                Some(mut cached) => {
+                    eprintln!(
+                        "[Douyin List] Live request failed ({}), serving cached list for {}",
+                        e, cache_key
+                    );
+                    for room in cached.rooms.iter_mut() {
+                        room.cover_url = proxied_image_url(&room.cover_url);
+                        room.avatar_url = proxied_image_url(&room.avatar_url);
+                    }
+                    cached.stale = true;
+                    Ok(cached)
+                }
+                None => Err(e),
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub(crate) async fn fetch_douyin_partition_rooms_remote(
+    partition: String,
+    partition_type: String,
+    offset: i32,
+    ms_token: String,
+) -> Result<DouyinLiveListResponse, String> {
+    with_fresh_token(Some(ms_token), |ms_token| {
+        fetch_partition_page(&partition, &partition_type, offset, ms_token)
+    })
+    .await
+}
+
+async fn fetch_partition_page(
+    partition: &str,
+    partition_type: &str,
+    offset: i32,
+    ms_token: String,
+) -> Result<DouyinLiveListResponse, DouyinFetchError> {
     let count: i32 = 15; // Number of items requested per page, explicitly typed as i32
 
     // 使用默认 HTTP 客户端（遵循 HTTP(S)_PROXY 环境变量）
@@ -103,9 +193,10 @@ pub async fn fetch_douyin_partition_rooms(
         HeaderValue::from_str(&cookie_string)
             .map_err(|e| format!("Failed to create cookie header value: {}", e))?,
     );
+    let ua = user_agent_for(&SupportedPlatformRust::Douyin);
     headers.insert(
         USER_AGENT,
-        HeaderValue::from_static(DEFAULT_USER_AGENT),
+        HeaderValue::from_str(&ua).map_err(|e| format!("Invalid User-Agent: {}", e))?,
     );
 
     let params: Vec<(String, String)> = vec![
@@ -124,25 +215,33 @@ pub async fn fetch_douyin_partition_rooms(
         ("browser_version".to_string(), "120.0.0.0".to_string()),
         ("count".to_string(), count.to_string()),
         ("offset".to_string(), offset.to_string()),
-        ("partition".to_string(), partition.clone()),
-        ("partition_type".to_string(), partition_type.clone()),
+        ("partition".to_string(), partition.to_string()),
+        ("partition_type".to_string(), partition_type.to_string()),
         ("req_from".to_string(), "2".to_string()),
         ("msToken".to_string(), ms_token.clone()),
     ];
 
     let query = serde_urlencoded::to_string(&params)
         .map_err(|e| format!("Failed to encode Douyin partition params: {}", e))?;
-    let sign = generate_a_bogus(&query, DEFAULT_USER_AGENT);
+    let sign = generate_a_bogus(&query, &ua);
     let url = format!(
         "https://live.douyin.com/webcast/web/partition/detail/room/v2/?{}&a_bogus={}",
         query,
         encode(&sign)
     );
 
-    match local_client
-        .get_json_with_headers::<DouyinPartitionApiResponse>(&url, Some(headers))
+    let body = local_client
+        .inner
+        .get(&url)
+        .headers(headers)
+        .send()
         .await
-    {
+        .map_err(|e| network_error(format!("fetching Douyin room list: {}", e)))?
+        .text()
+        .await
+        .map_err(|e| network_error(format!("reading Douyin room list response: {}", e)))?;
+
+    match parse_signed_body::<DouyinPartitionApiResponse>(&body, "Douyin partition API") {
         Ok(api_response) => {
             if api_response.status_code == 0 {
                 let mut frontend_rooms = Vec::new();
@@ -168,15 +267,17 @@ pub async fn fetch_douyin_partition_rooms(
                     frontend_rooms.push(LiveRoomFrontend {
                         web_rid: room_data.actual_web_rid_for_frontend.clone(),
                         title: room_details.title,
-                        cover_url: room_details
-                            .cover
-                            .url_list
-                            .get(0)
-                            .cloned()
-                            .unwrap_or_default(),
+                        cover_url: proxied_image_url(
+                            &room_details
+                                .cover
+                                .url_list
+                                .get(0)
+                                .cloned()
+                                .unwrap_or_default(),
+                        ),
                         owner_nickname: room_details.owner.nickname,
                         user_count_str: user_count_display,
-                        avatar_url,
+                        avatar_url: proxied_image_url(&avatar_url),
                     });
                 }
 
@@ -195,14 +296,16 @@ pub async fn fetch_douyin_partition_rooms(
                     rooms: frontend_rooms,
                     has_more,
                     next_offset: next_offset_for_frontend,
+                    stale: false,
+                    effective_sort: None,
                 })
             } else {
-                Err(format!(
+                Err(DouyinFetchError::Failed(format!(
                     "Douyin API returned non-zero status code: {}",
                     api_response.status_code
-                ))
+                )))
             }
         }
-        Err(e) => Err(format!("Network error fetching Douyin room list: {}", e)),
+        Err(e) => Err(e),
     }
 }
@@ -0,0 +1,242 @@
+use once_cell::sync::Lazy;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING, COOKIE, REFERER, USER_AGENT};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::platforms::common::cn_number::parse_cn_number;
+use crate::platforms::common::http_client::HttpClient;
+use crate::platforms::common::ttl_cache::TtlCache;
+use crate::platforms::common::user_agent::user_agent_for;
+use crate::platforms::common::{SupportedPlatformRust, UnifiedRoom};
+use crate::platforms::douyin::a_bogus::generate_a_bogus;
+use crate::platforms::douyin::token::{parse_signed_body, with_fresh_token, DouyinFetchError};
+use crate::proxy::proxied_image_url;
+
+// 关注列表短时缓存，避免前端频繁刷新触发风控
+static FOLLOWING_CACHE: Lazy<TtlCache<Vec<UnifiedRoom>>> =
+    Lazy::new(|| TtlCache::new(Duration::from_secs(60)));
+
+// 登录态 Cookie 中的会话字段；匿名 Cookie（只有 ttwid 等）不包含
+fn session_id(cookie: &str) -> Option<String> {
+    cookie.split(';').find_map(|kv| {
+        let (key, value) = kv.trim().split_once('=')?;
+        matches!(key, "sessionid" | "sessionid_ss")
+            .then(|| value.trim().to_string())
+            .filter(|v| !v.is_empty())
+    })
+}
+
+fn first_url(value: Option<&Value>) -> String {
+    value
+        .and_then(|v| v.get("url_list"))
+        .and_then(|list| list.get(0))
+        .and_then(|u| u.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn to_unified_room(item: &Value) -> Option<UnifiedRoom> {
+    let room = item.get("room")?;
+    // status == 2 表示正在直播
+    if room.get("status").and_then(|v| v.as_i64()) != Some(2) {
+        return None;
+    }
+    let room_id = item
+        .get("web_rid")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())?
+        .to_string();
+    let owner = room.get("owner");
+    let viewers_str = room
+        .get("stats")
+        .and_then(|s| s.get("user_count_str"))
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+    Some(UnifiedRoom {
+        platform: SupportedPlatformRust::Douyin.as_str().to_string(),
+        room_id,
+        title: room
+            .get("title")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        nickname: owner
+            .and_then(|o| o.get("nickname"))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        avatar: proxied_image_url(&first_url(owner.and_then(|o| o.get("avatar_thumb")))),
+        cover: proxied_image_url(&first_url(room.get("cover"))),
+        viewer_count: parse_cn_number(&viewers_str),
+        viewer_count_str: viewers_str,
+    })
+}
+
+async fn fetch_following_page(
+    http_client: &HttpClient,
+    cookie: &str,
+    ms_token: String,
+) -> Result<Vec<UnifiedRoom>, DouyinFetchError> {
+    let ua = user_agent_for(&SupportedPlatformRust::Douyin);
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(&ua).map_err(|e| format!("Invalid User-Agent: {}", e))?,
+    );
+    headers.insert(
+        REFERER,
+        HeaderValue::from_static("https://live.douyin.com/"),
+    );
+    headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+    headers.insert(
+        COOKIE,
+        HeaderValue::from_str(cookie).map_err(|e| format!("Invalid cookie header value: {}", e))?,
+    );
+
+    let params = vec![
+        ("aid", "6383"),
+        ("app_name", "douyin_web"),
+        ("live_id", "1"),
+        ("device_platform", "web"),
+        ("language", "zh-CN"),
+        ("enter_from", "web_homepage_follow"),
+        ("cookie_enabled", "true"),
+        ("browser_language", "zh-CN"),
+        ("browser_platform", "Win32"),
+        ("browser_name", "Chrome"),
+        ("browser_version", "116.0.0.0"),
+        ("scene", "aweme_pc_follow_top"),
+        ("msToken", ms_token.as_str()),
+    ];
+    let query = serde_urlencoded::to_string(&params)
+        .map_err(|e| format!("Failed to encode Douyin follow params: {}", e))?;
+    let sign = generate_a_bogus(&query, &ua);
+    let url = format!(
+        "https://live.douyin.com/webcast/web/feed/follow/?{}&a_bogus={}",
+        query,
+        urlencoding::encode(&sign)
+    );
+    let body = http_client
+        .inner
+        .get(&url)
+        .headers(headers)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to request Douyin follow feed: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read Douyin follow feed: {}", e))?;
+    let json: Value = parse_signed_body(&body, "Douyin follow feed")?;
+    parse_following_feed(&json)
+}
+
+// 关注直播流响应：status_code 非 0 视为失败（多为 Cookie 失效），否则取 data.data 中正在直播的房间
+fn parse_following_feed(json: &Value) -> Result<Vec<UnifiedRoom>, DouyinFetchError> {
+    let status_code = json
+        .get("status_code")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+    if status_code != 0 {
+        let message = json
+            .get("data")
+            .and_then(|d| d.get("message"))
+            .or_else(|| json.get("status_msg"))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        return Err(DouyinFetchError::Failed(format!(
+            "Douyin follow feed returned status {}: {}（Cookie 可能已失效，请重新登录）",
+            status_code, message
+        )));
+    }
+
+    Ok(json
+        .get("data")
+        .and_then(|d| d.get("data"))
+        .and_then(|v| v.as_array())
+        .map(|items| items.iter().filter_map(to_unified_room).collect())
+        .unwrap_or_default())
+}
+
+// 登录用户关注的主播中正在直播的房间（统一房间结构）；未提供登录 Cookie 时直接报错
+#[tauri::command]
+pub async fn fetch_douyin_following_lives(
+    cookie: Option<String>,
+) -> Result<Vec<UnifiedRoom>, String> {
+    let cookie = cookie.unwrap_or_default().trim().to_string();
+    let Some(session) = session_id(&cookie) else {
+        return Err("未登录抖音：需要包含 sessionid 的登录 Cookie".to_string());
+    };
+    if let Some(cached) = FOLLOWING_CACHE.get(&session) {
+        return Ok(cached);
+    }
+
+    let http_client =
+        HttpClient::new().map_err(|e| format!("Failed to create HttpClient: {}", e))?;
+    let rooms = with_fresh_token(None, |ms_token| {
+        fetch_following_page(&http_client, &cookie, ms_token)
+    })
+    .await?;
+    println!("[Douyin] following lives: {} rooms", rooms.len());
+    FOLLOWING_CACHE.insert(session, rooms.clone());
+    Ok(rooms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_id_requires_a_login_cookie() {
+        assert_eq!(
+            session_id("ttwid=abc; sessionid_ss=s1; passport_csrf_token=x").as_deref(),
+            Some("s1")
+        );
+        assert_eq!(session_id("sessionid=; ttwid=abc"), None);
+        assert_eq!(session_id("ttwid=abc; odin_tt=def"), None);
+    }
+
+    // 关注流响应只保留解析用到的字段：一个直播中、一个已下播、一个缺 web_rid
+    #[test]
+    fn keeps_only_live_rooms_from_the_follow_feed() {
+        let json = serde_json::json!({
+            "status_code": 0,
+            "data": {"data": [
+                {
+                    "web_rid": "123456",
+                    "room": {
+                        "status": 2,
+                        "title": "测试直播",
+                        "owner": {
+                            "nickname": "测试主播",
+                            "avatar_thumb": {"url_list": ["https://p3.example.com/avatar.jpg"]}
+                        },
+                        "cover": {"url_list": ["https://p3.example.com/cover.jpg"]},
+                        "stats": {"user_count_str": "1.2万"}
+                    }
+                },
+                {"web_rid": "654321", "room": {"status": 4, "title": "已下播"}},
+                {"web_rid": "", "room": {"status": 2}}
+            ]}
+        });
+        let Ok(rooms) = parse_following_feed(&json) else {
+            panic!("follow feed should parse");
+        };
+        assert_eq!(rooms.len(), 1);
+        let room = &rooms[0];
+        assert_eq!(room.room_id, "123456");
+        assert_eq!(room.nickname, "测试主播");
+        assert_eq!(room.cover, "https://p3.example.com/cover.jpg");
+        assert_eq!(room.viewer_count, Some(12000));
+        assert_eq!(room.viewer_count_str, "1.2万");
+    }
+
+    #[test]
+    fn non_zero_status_is_a_failure() {
+        let json = serde_json::json!({"status_code": 20003, "data": {"message": "用户未登录"}});
+        match parse_following_feed(&json) {
+            Err(DouyinFetchError::Failed(e)) => assert!(e.contains("用户未登录")),
+            _ => panic!("expected a failure"),
+        }
+    }
+}
@@ -1,16 +1,18 @@
+pub mod a_bogus;
 pub mod danmu;
 pub mod douyin_danmu_listener;
 pub mod douyin_streamer_detail;
 pub mod douyin_streamer_info;
 pub mod douyin_streamer_list;
+pub mod following;
 pub mod models;
+pub mod token;
 pub mod web_api;
-pub mod a_bogus;
 
 pub use self::danmu::web_fetcher::fetch_douyin_room_info;
 pub use self::douyin_danmu_listener::start_douyin_danmu_listener;
 pub use self::douyin_streamer_detail::{
     get_douyin_live_stream_url, get_douyin_live_stream_url_with_quality,
 };
-pub use self::douyin_streamer_info::fetch_douyin_streamer_info;
+pub use self::douyin_streamer_info::{fetch_douyin_room_by_user, fetch_douyin_streamer_info};
 pub use self::douyin_streamer_list::fetch_douyin_partition_rooms;
@@ -0,0 +1,123 @@
+use once_cell::sync::Lazy;
+use std::future::Future;
+use std::sync::Mutex;
+
+use crate::platforms::douyin::danmu::signature::generate_douyin_ms_token;
+
+// 抖音网页接口共用的 msToken；为空时由 Cookie 兜底，被上游拒绝后才生成新的
+static MS_TOKEN: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
+
+pub enum DouyinFetchError {
+    // msToken 过期或 a_bogus 签名未通过：上游通常返回空 body 或非 JSON 内容
+    TokenRejected(String),
+    Failed(String),
+}
+
+impl From<String> for DouyinFetchError {
+    fn from(error: String) -> Self {
+        Self::Failed(error)
+    }
+}
+
+// 响应体为空或不是 JSON 时视为令牌/签名被拒
+pub fn parse_signed_body<T: serde::de::DeserializeOwned>(
+    body: &str,
+    context: &str,
+) -> Result<T, DouyinFetchError> {
+    if body.trim().is_empty() {
+        return Err(DouyinFetchError::TokenRejected(format!(
+            "{} returned an empty body",
+            context
+        )));
+    }
+    serde_json::from_str(body).map_err(|e| {
+        DouyinFetchError::TokenRejected(format!("{} returned non-JSON body: {}", context, e))
+    })
+}
+
+// 用当前 msToken 执行一次请求；若被判定为令牌/签名失效，重新生成 msToken（签名随之重算）后重试一次。
+// initial 为调用方自带的 msToken（如前端传入），为空时使用共享的 msToken
+pub async fn with_fresh_token<T, F, Fut>(initial: Option<String>, mut fetch: F) -> Result<T, String>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<T, DouyinFetchError>>,
+{
+    let token = initial
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| MS_TOKEN.lock().unwrap_or_else(|e| e.into_inner()).clone());
+    match fetch(token).await {
+        Ok(value) => Ok(value),
+        Err(DouyinFetchError::Failed(e)) => Err(e),
+        Err(DouyinFetchError::TokenRejected(reason)) => {
+            let fresh = generate_douyin_ms_token();
+            println!(
+                "[Douyin] Request rejected ({}), retrying once with a fresh msToken",
+                reason
+            );
+            *MS_TOKEN.lock().unwrap_or_else(|e| e.into_inner()) = fresh.clone();
+            match fetch(fresh).await {
+                Ok(value) => Ok(value),
+                Err(DouyinFetchError::Failed(e)) => Err(e),
+                Err(DouyinFetchError::TokenRejected(e)) => {
+                    Err(format!("Douyin rejected the refreshed token: {}", e))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_or_non_json_bodies_count_as_rejected_tokens() {
+        let parsed: Result<serde_json::Value, _> = parse_signed_body("  ", "webcast");
+        assert!(matches!(parsed, Err(DouyinFetchError::TokenRejected(_))));
+        let parsed: Result<serde_json::Value, _> = parse_signed_body("<html>", "webcast");
+        assert!(matches!(parsed, Err(DouyinFetchError::TokenRejected(_))));
+        let parsed: Result<serde_json::Value, _> = parse_signed_body(r#"{"data":1}"#, "webcast");
+        assert!(matches!(parsed, Ok(v) if v["data"] == 1));
+    }
+
+    #[tokio::test]
+    async fn retries_once_with_a_fresh_token_after_rejection() {
+        let mut tokens = Vec::new();
+        let result = with_fresh_token(Some("caller-token".to_string()), |token| {
+            let rejected = tokens.is_empty();
+            tokens.push(token.clone());
+            async move {
+                if rejected {
+                    Err(DouyinFetchError::TokenRejected("empty body".to_string()))
+                } else {
+                    Ok(token)
+                }
+            }
+        })
+        .await;
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0], "caller-token");
+        assert_ne!(tokens[1], "caller-token");
+        assert_eq!(result, Ok(tokens[1].clone()));
+    }
+
+    #[tokio::test]
+    async fn failures_are_not_retried_and_a_second_rejection_gives_up() {
+        let mut calls = 0;
+        let result: Result<(), String> = with_fresh_token(Some("t".to_string()), |_| {
+            calls += 1;
+            async { Err(DouyinFetchError::Failed("status 500".to_string())) }
+        })
+        .await;
+        assert_eq!((calls, result), (1, Err("status 500".to_string())));
+
+        let mut calls = 0;
+        let result: Result<(), String> = with_fresh_token(Some("t".to_string()), |_| {
+            calls += 1;
+            async { Err(DouyinFetchError::TokenRejected("empty body".to_string())) }
+        })
+        .await;
+        assert_eq!(calls, 2);
+        assert!(result.unwrap_err().contains("refreshed token"));
+    }
+}
@@ -1,5 +1,8 @@
 use crate::platforms::common::http_client::HttpClient;
+use crate::platforms::common::user_agent::user_agent_for;
+use crate::platforms::common::SupportedPlatformRust;
 use crate::platforms::douyin::a_bogus::generate_a_bogus;
+use crate::platforms::douyin::token::{parse_signed_body, with_fresh_token, DouyinFetchError};
 use reqwest::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING, COOKIE, REFERER, USER_AGENT};
 use serde_json::Value;
 
@@ -15,9 +18,11 @@ pub struct DouyinRoomData {
     pub room: Value,
 }
 
-// 直接从返回的 stream_data 中补全 ORIGIN，不依赖 HTML 解析，贴近 douyin_rust 实现。
+// 直接从返回的 stream_data 中补全 ORIGIN 及缺失的清晰度，不依赖 HTML 解析，贴近 douyin_rust 实现。
 fn merge_origin_stream(room: &mut Value) {
-    let Some(stream_url) = room.get_mut("stream_url") else { return };
+    let Some(stream_url) = room.get_mut("stream_url") else {
+        return;
+    };
     let live_core_sdk_data = stream_url.get("live_core_sdk_data");
     if live_core_sdk_data.is_none() {
         return;
@@ -43,11 +48,15 @@ fn merge_origin_stream(room: &mut Value) {
     let Some(json_str) = json_str else { return };
 
     let parsed: Value = serde_json::from_str(&json_str).unwrap_or(Value::Null);
+    merge_nested_qualities(stream_url, &parsed);
+
     let origin_main = parsed
         .get("data")
         .and_then(|d| d.get("origin"))
         .and_then(|o| o.get("main"));
-    let Some(origin_main) = origin_main else { return };
+    let Some(origin_main) = origin_main else {
+        return;
+    };
 
     let origin_codec = origin_main
         .get("sdk_params")
@@ -78,9 +87,9 @@ fn merge_origin_stream(room: &mut Value) {
             _ => {
                 let mut new_map = serde_json::Map::new();
                 new_map.insert("ORIGIN".to_string(), Value::String(hls_origin));
-                stream_url.as_object_mut().map(|obj| {
-                    obj.insert("hls_pull_url_map".to_string(), Value::Object(new_map))
-                });
+                stream_url
+                    .as_object_mut()
+                    .map(|obj| obj.insert("hls_pull_url_map".to_string(), Value::Object(new_map)));
             }
         }
     }
@@ -98,9 +107,57 @@ fn merge_origin_stream(room: &mut Value) {
             _ => {
                 let mut new_map = serde_json::Map::new();
                 new_map.insert("ORIGIN".to_string(), Value::String(flv_origin));
-                stream_url.as_object_mut().map(|obj| {
-                    obj.insert("flv_pull_url".to_string(), Value::Object(new_map))
-                });
+                stream_url
+                    .as_object_mut()
+                    .map(|obj| obj.insert("flv_pull_url".to_string(), Value::Object(new_map)));
+            }
+        }
+    }
+}
+
+// stream_data 中各清晰度的 key 与扁平 flv_pull_url / hls_pull_url_map 中 key 的对应关系
+const NESTED_QUALITY_KEYS: [(&str, &str); 5] = [
+    ("uhd", "FULL_HD1"),
+    ("hd", "HD1"),
+    ("sd", "SD1"),
+    ("ld", "SD2"),
+    ("md", "MD1"),
+];
+
+// 部分房间只在 stream_data(JSON 字符串) 中下发各清晰度，扁平 map 为空或缺项；
+// 这里把缺失的清晰度补进 flv_pull_url / hls_pull_url_map，已有的扁平地址优先
+fn merge_nested_qualities(stream_url: &mut Value, parsed: &Value) {
+    let Some(data) = parsed.get("data").and_then(|d| d.as_object()) else {
+        return;
+    };
+    for (format_key, map_key) in [("flv", "flv_pull_url"), ("hls", "hls_pull_url_map")] {
+        let mut additions = Vec::new();
+        for (nested_key, flat_key) in NESTED_QUALITY_KEYS.iter() {
+            let url = data
+                .get(*nested_key)
+                .and_then(|q| q.get("main"))
+                .and_then(|m| m.get(format_key))
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty());
+            if let Some(url) = url {
+                additions.push((flat_key.to_string(), url.to_string()));
+            }
+        }
+        if additions.is_empty() {
+            continue;
+        }
+        let Some(obj) = stream_url.as_object_mut() else {
+            return;
+        };
+        let entry = obj
+            .entry(map_key.to_string())
+            .or_insert_with(|| Value::Object(serde_json::Map::new()));
+        if !entry.is_object() {
+            *entry = Value::Object(serde_json::Map::new());
+        }
+        if let Some(map) = entry.as_object_mut() {
+            for (key, url) in additions {
+                map.entry(key).or_insert(Value::String(url));
             }
         }
     }
@@ -110,12 +167,25 @@ async fn fetch_room_from_api(
     http_client: &HttpClient,
     web_id: &str,
     cookies: Option<&str>,
-) -> Result<DouyinRoomData, String> {
+    ms_token: &str,
+) -> Result<DouyinRoomData, DouyinFetchError> {
     let mut headers = HeaderMap::new();
-    headers.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_USER_AGENT));
-    headers.insert(REFERER, HeaderValue::from_str(&format!("https://live.douyin.com/{web_id}")).map_err(|e| format!("Invalid Referer: {e}"))?);
+    let ua = user_agent_for(&SupportedPlatformRust::Douyin);
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(&ua).map_err(|e| format!("Invalid User-Agent: {e}"))?,
+    );
+    headers.insert(
+        REFERER,
+        HeaderValue::from_str(&format!("https://live.douyin.com/{web_id}"))
+            .map_err(|e| format!("Invalid Referer: {e}"))?,
+    );
     headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
-    headers.insert(COOKIE, HeaderValue::from_str(cookies.unwrap_or(DEFAULT_COOKIE)).map_err(|e| format!("Invalid cookie header value: {}", e))?);
+    headers.insert(
+        COOKIE,
+        HeaderValue::from_str(cookies.unwrap_or(DEFAULT_COOKIE))
+            .map_err(|e| format!("Invalid cookie header value: {}", e))?,
+    );
 
     let params = vec![
         ("aid", "6383"),
@@ -128,26 +198,26 @@ async fn fetch_room_from_api(
         ("browser_name", "Chrome"),
         ("browser_version", "116.0.0.0"),
         ("web_rid", web_id),
-        ("msToken", ""),
+        ("msToken", ms_token),
     ];
     let query = serde_urlencoded::to_string(&params)
         .map_err(|e| format!("Failed to encode Douyin enter params: {}", e))?;
-    let sign = generate_a_bogus(&query, DEFAULT_USER_AGENT);
+    let sign = generate_a_bogus(&query, &ua);
     let api = format!(
         "https://live.douyin.com/webcast/room/web/enter/?{}&a_bogus={}",
-        query,
-        sign
+        query, sign
     );
-    let json: Value = http_client
+    let body = http_client
         .inner
         .get(&api)
         .headers(headers)
         .send()
         .await
         .map_err(|e| format!("Failed to request Douyin web enter API: {}", e))?
-        .json()
+        .text()
         .await
-        .map_err(|e| format!("Failed to parse Douyin web enter response: {}", e))?;
+        .map_err(|e| format!("Failed to read Douyin web enter response: {}", e))?;
+    let json: Value = parse_signed_body(&body, "Douyin web enter API")?;
 
     let room = json
         .get("data")
@@ -191,10 +261,7 @@ pub fn normalize_douyin_live_id(id_or_url: &str) -> String {
                 .or_else(|| kv.strip_prefix("web_rid="))
                 .or_else(|| kv.strip_prefix("webId="))
             {
-                let cleaned = val
-                    .split(['&', '#'])
-                    .find(|s| !s.is_empty())
-                    .unwrap_or(val);
+                let cleaned = val.split(['&', '#']).find(|s| !s.is_empty()).unwrap_or(val);
                 if !cleaned.is_empty() {
                     return cleaned.to_string();
                 }
@@ -207,10 +274,7 @@ pub fn normalize_douyin_live_id(id_or_url: &str) -> String {
         let start = pos + "douyin.com/".len();
         let remainder = &trimmed[start..];
         let path_only = remainder.split(['?', '#']).next().unwrap_or(remainder);
-        if let Some(segment) = path_only
-            .rsplit('/')
-            .find(|segment| !segment.is_empty())
-        {
+        if let Some(segment) = path_only.rsplit('/').find(|segment| !segment.is_empty()) {
             return segment
                 .split(['?', '&', '#'])
                 .find(|s| !s.is_empty())
@@ -234,7 +298,98 @@ pub async fn fetch_room_data(
 ) -> Result<DouyinRoomData, String> {
     let web_id = normalize_douyin_live_id(raw_id);
     // 简化逻辑：直接走网页版接口 + a_bogus，避免 HTML 解析失败。
-    fetch_room_from_api(http_client, &web_id, cookies).await
+    let web_id = web_id.as_str();
+    with_fresh_token(None, |ms_token| async move {
+        fetch_room_from_api(http_client, web_id, cookies, &ms_token).await
+    })
+    .await
+}
+
+/// 通过用户的 sec_uid（MS4w 开头）或数字 uid 查询其当前直播间。
+/// 返回 Ok(None) 表示用户存在但未开播；Some((room_id, web_rid))，web_rid 可能缺失。
+pub async fn fetch_live_room_by_user(
+    http_client: &HttpClient,
+    uid: &str,
+    cookies: Option<&str>,
+) -> Result<Option<(String, Option<String>)>, String> {
+    let uid = uid.trim();
+    let mut headers = HeaderMap::new();
+    let ua = user_agent_for(&SupportedPlatformRust::Douyin);
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(&ua).map_err(|e| format!("Invalid User-Agent: {e}"))?,
+    );
+    headers.insert(REFERER, HeaderValue::from_static("https://www.douyin.com/"));
+    headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+    headers.insert(
+        COOKIE,
+        HeaderValue::from_str(cookies.unwrap_or(DEFAULT_COOKIE))
+            .map_err(|e| format!("Invalid cookie header value: {}", e))?,
+    );
+
+    let id_param = if uid.chars().all(|c| c.is_ascii_digit()) {
+        ("user_id", uid)
+    } else {
+        ("sec_user_id", uid)
+    };
+    let params = vec![
+        ("device_platform", "webapp"),
+        ("aid", "6383"),
+        ("channel", "channel_pc_web"),
+        ("publish_video_strategy_type", "2"),
+        ("source", "channel_pc_web"),
+        id_param,
+        ("pc_client_type", "1"),
+        ("version_code", "170400"),
+        ("version_name", "17.4.0"),
+        ("cookie_enabled", "true"),
+        ("platform", "PC"),
+        ("msToken", ""),
+    ];
+    let query = serde_urlencoded::to_string(&params)
+        .map_err(|e| format!("Failed to encode Douyin profile params: {}", e))?;
+    let sign = generate_a_bogus(&query, &ua);
+    let api = format!(
+        "https://www.douyin.com/aweme/v1/web/user/profile/other/?{}&a_bogus={}",
+        query, sign
+    );
+    let json: Value = http_client
+        .inner
+        .get(&api)
+        .headers(headers)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to request Douyin user profile API: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Douyin user profile response: {}", e))?;
+
+    let user = json
+        .get("user")
+        .filter(|u| u.is_object())
+        .ok_or_else(|| format!("Douyin user {} not found", uid))?;
+
+    // room_id 为 0 表示未开播；room_data 是嵌套的 JSON 字符串，其中 owner.web_rid 为直播间号
+    let room_id = match user.get("room_id") {
+        Some(Value::Number(n)) => n.to_string(),
+        Some(Value::String(s)) => s.clone(),
+        _ => String::new(),
+    };
+    if room_id.is_empty() || room_id == "0" {
+        return Ok(None);
+    }
+    let web_rid = user
+        .get("room_data")
+        .and_then(|v| v.as_str())
+        .and_then(|s| serde_json::from_str::<Value>(s).ok())
+        .and_then(|data| {
+            data.get("owner")
+                .and_then(|o| o.get("web_rid"))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+        })
+        .filter(|s| !s.is_empty());
+    Ok(Some((room_id, web_rid)))
 }
 
 pub fn choose_flv_stream(room: &Value, desired_quality: &str) -> Option<(String, String)> {
@@ -273,3 +428,160 @@ pub fn choose_flv_stream(room: &Value, desired_quality: &str) -> Option<(String,
         .cloned()
         .or_else(|| entries.last().cloned())
 }
+
+// 兜底路径：stream_url.live_core_sdk_data.pull_data.stream_data 是嵌套的 JSON 字符串，
+// 结构为 {"data": {"origin": {"main": {"flv": ...}}, "uhd": {...}, "hd": {...}, ...}}
+pub fn choose_stream_data_flv(room: &Value, desired_quality: &str) -> Option<(String, String)> {
+    const LEVELS: [(&str, &str); 6] = [
+        ("OD", "origin"),
+        ("BD", "uhd"),
+        ("UHD", "hd"),
+        ("HD", "sd"),
+        ("SD", "ld"),
+        ("LD", "md"),
+    ];
+    let raw = room
+        .pointer("/stream_url/live_core_sdk_data/pull_data/stream_data")
+        .and_then(|v| v.as_str())?;
+    let parsed: Value = serde_json::from_str(raw).ok()?;
+    let data = parsed.get("data")?;
+
+    let desired = desired_quality.trim().to_uppercase();
+    let start = LEVELS.iter().position(|(q, _)| *q == desired).unwrap_or(0);
+    // 先从目标清晰度往低找，找不到再往高找
+    LEVELS[start..]
+        .iter()
+        .chain(LEVELS[..start].iter().rev())
+        .find_map(|(_, key)| {
+            let url = data.get(*key)?.pointer("/main/flv")?.as_str()?;
+            (!url.is_empty()).then(|| (key.to_string(), url.to_string()))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // web/enter 返回的 room：扁平 map 为空，各清晰度只在 stream_data（JSON 字符串）中下发
+    fn stream_data_room() -> Value {
+        let stream_data = serde_json::json!({
+            "common": {"session_id": "0123-abcd"},
+            "data": {
+                "origin": {"main": {
+                    "flv": "https://pull-flv-l1.douyincdn.com/stage/stream-123_or4.flv?expire=1",
+                    "hls": "https://pull-hls-l1.douyincdn.com/stage/stream-123_or4.m3u8?expire=1",
+                    "sdk_params": "{\"VCodec\":\"h265\",\"vbitrate\":8000000}"
+                }},
+                "hd": {"main": {
+                    "flv": "https://pull-flv-l1.douyincdn.com/stage/stream-123_hd.flv?expire=1",
+                    "hls": "https://pull-hls-l1.douyincdn.com/stage/stream-123_hd.m3u8?expire=1",
+                    "sdk_params": "{\"VCodec\":\"h264\"}"
+                }},
+                "ld": {"main": {
+                    "flv": "https://pull-flv-l1.douyincdn.com/stage/stream-123_ld.flv?expire=1",
+                    "hls": "",
+                    "sdk_params": "{\"VCodec\":\"h264\"}"
+                }}
+            }
+        });
+        serde_json::json!({
+            "id_str": "7412345678901234567",
+            "status": 2,
+            "stream_url": {
+                "flv_pull_url": {},
+                "hls_pull_url_map": {},
+                "live_core_sdk_data": {
+                    "pull_data": {"stream_data": stream_data.to_string()}
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn picks_flv_from_nested_stream_data() {
+        let room = stream_data_room();
+        assert!(choose_flv_stream(&room, "OD").is_none());
+        assert_eq!(
+            choose_stream_data_flv(&room, "OD"),
+            Some((
+                "origin".to_string(),
+                "https://pull-flv-l1.douyincdn.com/stage/stream-123_or4.flv?expire=1".to_string()
+            ))
+        );
+        // UHD 对应 hd；BD 对应的 uhd 缺失时往低找到 hd
+        assert_eq!(
+            choose_stream_data_flv(&room, "uhd").map(|(key, _)| key),
+            Some("hd".to_string())
+        );
+        assert_eq!(
+            choose_stream_data_flv(&room, "BD").map(|(key, _)| key),
+            Some("hd".to_string())
+        );
+        // LD 对应的 md 缺失，低处没有时往高找
+        assert_eq!(
+            choose_stream_data_flv(&room, "LD").map(|(key, _)| key),
+            Some("ld".to_string())
+        );
+    }
+
+    #[test]
+    fn merges_nested_qualities_into_flat_maps() {
+        let mut room = stream_data_room();
+        merge_origin_stream(&mut room);
+        let flv = room["stream_url"]["flv_pull_url"].as_object().unwrap();
+        assert_eq!(
+            flv["ORIGIN"],
+            "https://pull-flv-l1.douyincdn.com/stage/stream-123_or4.flv?expire=1&codec=h265"
+        );
+        assert_eq!(
+            flv["HD1"],
+            "https://pull-flv-l1.douyincdn.com/stage/stream-123_hd.flv?expire=1"
+        );
+        assert_eq!(
+            flv["SD2"],
+            "https://pull-flv-l1.douyincdn.com/stage/stream-123_ld.flv?expire=1"
+        );
+        let hls = room["stream_url"]["hls_pull_url_map"].as_object().unwrap();
+        assert!(hls.contains_key("ORIGIN"));
+        assert!(hls.contains_key("HD1"));
+        // 空地址不补
+        assert!(!hls.contains_key("SD2"));
+
+        // 合并后扁平路径即可选出地址，不再报 No FLV streams available
+        let (key, url) = choose_flv_stream(&room, "OD").unwrap();
+        assert_eq!(flv[&key], url.as_str());
+    }
+
+    #[test]
+    fn existing_flat_urls_take_precedence() {
+        let mut room = stream_data_room();
+        room["stream_url"]["flv_pull_url"] = serde_json::json!({
+            "HD1": "https://pull-flv-f1.douyincdn.com/stage/stream-123_hd.flv?flat=1"
+        });
+        merge_origin_stream(&mut room);
+        let flv = &room["stream_url"]["flv_pull_url"];
+        assert_eq!(
+            flv["HD1"],
+            "https://pull-flv-f1.douyincdn.com/stage/stream-123_hd.flv?flat=1"
+        );
+        assert_eq!(
+            flv["SD2"],
+            "https://pull-flv-l1.douyincdn.com/stage/stream-123_ld.flv?expire=1"
+        );
+    }
+
+    #[test]
+    fn rooms_without_stream_data_are_left_alone() {
+        let mut room = serde_json::json!({
+            "stream_url": {"flv_pull_url": {"FULL_HD1": "https://a/b.flv"}}
+        });
+        let before = room.clone();
+        merge_origin_stream(&mut room);
+        assert_eq!(room, before);
+        assert!(choose_stream_data_flv(&room, "OD").is_none());
+        assert_eq!(
+            choose_flv_stream(&room, "OD"),
+            Some(("FULL_HD1".to_string(), "https://a/b.flv".to_string()))
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use futures_util::{SinkExt, StreamExt};
 use std::collections::HashMap;
-use tauri::{Emitter, Window};
+use tauri::{Manager, Window};
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use tokio::time::Duration;
@@ -8,6 +8,11 @@ use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use tokio_tungstenite::{connect_async_tls_with_config, tungstenite::Message};
 use url::Url;
 
+use crate::platforms::common::danmaku_pause::admit;
+use crate::platforms::common::danmaku_reconnect;
+use crate::platforms::common::danmaku_subscribers::emit_to_subscribers;
+use crate::platforms::common::events::DtvEvent;
+
 pub struct DanmakuClient {
     room_id: String,
     window: Window,
@@ -39,14 +44,55 @@ impl DanmakuClient {
         result
     }
 
+    // 连接断开或失败时按统一的重连策略重试，直到收到停止信号或放弃
     pub async fn start(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut stop_rx = std::mem::replace(&mut self.stop_signal_rx, oneshot::channel().1);
+        danmaku_reconnect::reset("douyu", &self.room_id);
+        loop {
+            let error = match self.run_connection(&mut stop_rx).await {
+                Ok(true) => return Ok(()),
+                Ok(false) => "websocket closed".to_string(),
+                Err(e) => e.to_string(),
+            };
+            let Some(delay) = danmaku_reconnect::next_retry_delay("douyu", &self.room_id) else {
+                danmaku_reconnect::give_up(
+                    self.window.app_handle(),
+                    "douyu",
+                    &self.room_id,
+                    error.clone(),
+                );
+                return Err(error.into());
+            };
+            eprintln!(
+                "[Douyu Danmaku {}] {}, reconnecting in {:?}",
+                self.room_id, error, delay
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = &mut stop_rx => return Ok(()),
+            }
+        }
+    }
+
+    // 建立一次连接并处理消息；收到停止信号返回 true，连接被关闭返回 false
+    async fn run_connection(
+        &self,
+        stop_rx: &mut oneshot::Receiver<()>,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
         let url = Url::parse("wss://danmuproxy.douyu.com:8506/")?;
+        let ws_url = url.to_string();
         let mut request = url.into_client_request()?;
         request
             .headers_mut()
             .insert("Sec-WebSocket-Protocol", "binary".parse()?);
 
-        let (ws_stream, _) = connect_async_tls_with_config(request, None, false, None).await?;
+        let (ws_stream, ws_response) =
+            connect_async_tls_with_config(request, None, false, None).await?;
+        let ws_protocol = ws_response
+            .headers()
+            .get("Sec-WebSocket-Protocol")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
 
         let (mut write, mut read) = ws_stream.split();
 
@@ -60,6 +106,19 @@ impl DanmakuClient {
         let join_data = self.encode_msg(&join_msg);
         write.send(Message::Binary(join_data)).await?;
 
+        emit_to_subscribers(
+            "douyu",
+            &self.room_id,
+            &self.window,
+            DtvEvent::DanmakuConnected(crate::platforms::common::DanmakuConnectedPayload::new(
+                "douyu",
+                &self.room_id,
+                &ws_url,
+                ws_protocol,
+            )),
+        );
+        danmaku_reconnect::reset("douyu", &self.room_id);
+
         // 创建消息通道
         let (tx, mut rx) = mpsc::channel(32);
 
@@ -77,10 +136,6 @@ impl DanmakuClient {
             }
         });
 
-        // Keep a reference to self.stop_signal_rx to move into tasks
-        // We need to select between receiving a message from the websocket and the stop signal
-        let mut stop_rx = std::mem::replace(&mut self.stop_signal_rx, oneshot::channel().1);
-
         // Message sending task
         let send_task = tokio::spawn(async move {
             while let Some(msg_to_send) = rx.recv().await {
@@ -94,11 +149,11 @@ impl DanmakuClient {
         let room_id_clone = self.room_id.clone();
 
         // Processing incoming messages
-        loop {
+        let stopped = loop {
             tokio::select! {
-                _ = &mut stop_rx => {
+                _ = &mut *stop_rx => {
                     eprintln!("[Douyu Danmaku {}] Stop signal received, terminating listener.", room_id_clone);
-                    break;
+                    break true;
                 }
                 msg_option = read.next() => {
                     match msg_option {
@@ -121,8 +176,6 @@ impl DanmakuClient {
                                 }
                             }
 
-                            let event_name = format!("danmaku-{}", room_id_clone);
-
                             if result.get("type").map_or(false, |t| t == "chatmsg") {
                                 let unknown = "unknown".to_string();
                                 let empty = "".to_string();
@@ -139,27 +192,39 @@ impl DanmakuClient {
                                     "room_id": room_id_clone.clone()
                                 });
 
-                                let _ = window.emit(&event_name, danmaku);
+                                let event = DtvEvent::DouyuRoomMessage {
+                                    room_id: room_id_clone.clone(),
+                                    message: danmaku,
+                                };
+                                if admit("douyu", &room_id_clone, &event) {
+                                    emit_to_subscribers("douyu", &room_id_clone, &window, event);
+                                }
 
                                 // 统一向前端发送通用弹幕事件，便于跨平台 DanmuList 使用
-                                let _ = window.emit(
-                                    "danmaku-message",
-                                    crate::platforms::common::DanmakuFrontendPayload {
-                                        room_id: room_id_clone.clone(),
-                                        user: result.get("nn").unwrap_or(&unknown).to_string(),
-                                        content: result.get("txt").unwrap_or(&empty).to_string(),
-                                        user_level: result
-                                            .get("level")
-                                            .unwrap_or(&zero)
-                                            .parse::<i64>()
-                                            .unwrap_or(0),
-                                        fans_club_level: result
-                                            .get("bl")
-                                            .unwrap_or(&zero)
-                                            .parse::<i32>()
-                                            .unwrap_or(0),
-                                    },
-                                );
+                                let payload = crate::platforms::common::DanmakuFrontendPayload {
+                                    platform: "douyu".to_string(),
+                                    room_id: room_id_clone.clone(),
+                                    user: result.get("nn").unwrap_or(&unknown).to_string(),
+                                    content: result.get("txt").unwrap_or(&empty).to_string(),
+                                    user_level: result
+                                        .get("level")
+                                        .unwrap_or(&zero)
+                                        .parse::<i64>()
+                                        .unwrap_or(0),
+                                    fans_club_level: result
+                                        .get("bl")
+                                        .unwrap_or(&zero)
+                                        .parse::<i32>()
+                                        .unwrap_or(0),
+                                    // cst：客户端发送时间（毫秒）
+                                    ..crate::platforms::common::DanmakuFrontendPayload::received(
+                                        result.get("cst").and_then(|v| v.parse::<i64>().ok()),
+                                    )
+                                };
+                                let event = DtvEvent::DanmakuMessage(payload);
+                                if admit("douyu", &room_id_clone, &event) {
+                                    emit_to_subscribers("douyu", &room_id_clone, &window, event);
+                                }
                             } else if result.get("type").map_or(false, |t| t == "uenter") {
                                 let unknown = "unknown".to_string();
                                 let empty = "".to_string();
@@ -174,20 +239,28 @@ impl DanmakuClient {
                                     "badgeLevel": result.get("bl").unwrap_or(&zero),
                                     "room_id": room_id_clone.clone()
                                 });
-                                let _ = window.emit(&event_name, uenter_msg);
+                                let event = DtvEvent::DouyuRoomMessage {
+                                    room_id: room_id_clone.clone(),
+                                    message: uenter_msg,
+                                };
+                                if admit("douyu", &room_id_clone, &event) {
+                                    emit_to_subscribers("douyu", &room_id_clone, &window, event);
+                                }
                             }
                         }
                         Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
-                            eprintln!("[Douyu Danmaku {}] Websocket closed or error, terminating listener.", room_id_clone);
-                            break;
+                            eprintln!("[Douyu Danmaku {}] Websocket closed or error.", room_id_clone);
+                            break false;
                         }
                         _ => {}
                     }
                 }
             }
-        }
+        };
         send_task.abort();
-        eprintln!("[Douyu Danmaku {}] Listener stopped.", room_id_clone);
-        Ok(())
+        if stopped {
+            eprintln!("[Douyu Danmaku {}] Listener stopped.", room_id_clone);
+        }
+        Ok(stopped)
     }
 }
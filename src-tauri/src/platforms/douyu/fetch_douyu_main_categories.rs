@@ -152,7 +152,8 @@ pub async fn fetch_categories() -> Result<CategoriesApiResponse, String> {
 
 // Internal function to fetch and parse to the old frontend-specific structure
 async fn fetch_categories_douyu_raw() -> Result<Vec<RawFrontendCate1Item>, String> {
-    let client = reqwest::Client::builder()
+    let client = crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
         .build()
         .map_err(|e| e.to_string())?;
     let url = "https://m.douyu.com/api/cate/list";
@@ -6,7 +6,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tauri::State;
 
-use crate::platforms::common::FollowHttpClient;
+use crate::platforms::common::cn_number::parse_cn_number;
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::{FollowHttpClient, SupportedPlatformRust};
 
 // Define the structure to be returned to TypeScript
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -17,6 +19,52 @@ pub struct DouyuFollowInfo {
     avatar_url: Option<String>,
     video_loop: Option<i64>,
     show_status: Option<i64>,
+    // 竖屏（手机开播）房间，前端据此调整播放器比例
+    is_vertical: Option<bool>,
+    // 未开播时的房间公告（show_details）；斗鱼不提供预约开播时间，next_schedule 恒为 None
+    notice: Option<String>,
+    next_schedule: Option<String>,
+    // 热度（hn）、分区排名与关注数；接口未返回时为 None
+    popularity: Option<u64>,
+    category_rank: Option<u64>,
+    follower_count: Option<u64>,
+    // 平台原始响应，仅在开启 set_debug_raw_responses 时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw: Option<Value>,
+}
+
+impl DouyuFollowInfo {
+    // 预览卡片用：status 统一为 1 开播 / 0 未开播，轮播（videoLoop）不算开播
+    pub(crate) fn to_live_stream_info(&self) -> crate::platforms::common::LiveStreamInfo {
+        let is_live = self.show_status == Some(1) && self.video_loop != Some(1);
+        crate::platforms::common::LiveStreamInfo {
+            title: self.room_name.clone(),
+            anchor_name: self.nickname.clone(),
+            avatar: self.avatar_url.clone(),
+            status: Some(if is_live { 1 } else { 0 }),
+            normalized_room_id: Some(self.room_id.clone()),
+            is_rotation: Some(self.video_loop == Some(1)),
+            is_vertical: self.is_vertical,
+            notice: self.notice.clone(),
+            ..Default::default()
+        }
+    }
+
+    // (是否开播, 热度)；斗鱼不公开真实在线人数，以热度值代替
+    pub(crate) fn viewers(&self) -> (bool, Option<u64>) {
+        let is_live = self.show_status == Some(1) && self.video_loop != Some(1);
+        (is_live, self.popularity)
+    }
+}
+
+// 斗鱼这几个字段有时是数字、有时是 "101.8万" 这样的字符串，且不同接口版本所在位置不同，
+// 按候选路径依次查找
+fn cn_number_at(data: &Value, paths: &[&str]) -> Option<u64> {
+    paths.iter().find_map(|path| match data.pointer(path)? {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => parse_cn_number(s),
+        _ => None,
+    })
 }
 
 #[tauri::command]
@@ -24,6 +72,8 @@ pub async fn fetch_douyu_room_info(
     room_id: String,
     follow_http: State<'_, FollowHttpClient>,
 ) -> Result<DouyuFollowInfo, String> {
+    let room_id = validate_room_id(&SupportedPlatformRust::Douyu, &room_id)?;
+    let room_id = crate::platforms::douyu::normalize_douyu_room_id(&room_id)?;
     let mut headers = HeaderMap::new();
     headers.insert(
         "Accept",
@@ -42,7 +92,7 @@ pub async fn fetch_douyu_room_info(
     headers.insert("User-Agent", HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36"));
 
     let response_result = follow_http
-        .0
+        .client()
         .inner
         .get(format!("https://www.douyu.com/betard/{}", room_id))
         .headers(headers)
@@ -78,12 +128,17 @@ pub async fn fetch_douyu_room_info(
             ))
         }
     };
+    parse_betard_room(&full_json_value, &room_id)
+}
+
+// betard 响应 -> DouyuFollowInfo；room_id 为接口未返回房间号时的兜底
+fn parse_betard_room(full_json_value: &Value, room_id: &str) -> Result<DouyuFollowInfo, String> {
     let room_data_ref = full_json_value
         .get("data")
         .and_then(|d| d.get("room")) // Path 1: { data: { room: { ... } } }
         .or_else(|| full_json_value.get("data")) // Path 2: { data: { ...room_info... } }
         .or_else(|| full_json_value.get("room")) // Path 3: { room: { ... } }
-        .or_else(|| Some(&full_json_value)); // Path 4: { ...room_info... } (root is room object)
+        .or_else(|| Some(full_json_value)); // Path 4: { ...room_info... } (root is room object)
 
     let room_data = match room_data_ref {
         Some(data) => data,
@@ -110,16 +165,99 @@ pub async fn fetch_douyu_room_info(
         .or_else(|| get_nested_str(room_data, &["avatar", "middle"]));
 
     // If API provides its own room_id, prefer that. Otherwise, use the input room_id.
-    let final_room_id = get_str(room_data, "room_id").unwrap_or_else(|| room_id.clone());
+    let final_room_id = get_str(room_data, "room_id").unwrap_or_else(|| room_id.to_string());
+
+    let show_status = get_i64(room_data, "show_status");
+    let notice = if show_status != Some(1) {
+        get_str(room_data, "show_details")
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    } else {
+        None
+    };
 
     let info = DouyuFollowInfo {
         room_id: final_room_id,
         room_name: get_str(room_data, "room_name"),
         nickname: get_str(room_data, "nickname"),
-        avatar_url: avatar_final_url,
+        avatar_url: avatar_final_url.map(|u| crate::proxy::proxied_image_url(&u)),
         video_loop: get_i64(room_data, "videoLoop"),
-        show_status: get_i64(room_data, "show_status"),
+        show_status,
+        is_vertical: get_i64(room_data, "isVertical").map(|v| v == 1),
+        notice,
+        next_schedule: None,
+        popularity: cn_number_at(room_data, &["/hn", "/room_biz_all/hot", "/hot"])
+            .or_else(|| cn_number_at(full_json_value, &["/hn", "/data/hn"])),
+        category_rank: cn_number_at(room_data, &["/cate_rank", "/rankInfo/cate_rank", "/rank"]),
+        follower_count: cn_number_at(room_data, &["/fans_num", "/follower", "/fans"]),
+        raw: crate::platforms::common::debug_raw::capture(|| full_json_value.clone()),
     };
 
     Ok(info)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 手写的 betard 响应：hn 为中文数字字符串，排名与关注数为数字
+    const BETARD_ROOM: &str = r#"{
+        "room": {
+            "room_id": 9999,
+            "room_name": "今晚双排",
+            "nickname": "测试主播",
+            "avatar_mid": "https://apic.douyucdn.cn/upload/avatar_v3/demo_middle.jpg",
+            "show_status": 1,
+            "videoLoop": 0,
+            "isVertical": 0,
+            "show_details": "每晚八点",
+            "room_biz_all": {"hot": "101.8万"},
+            "rankInfo": {"cate_rank": 3},
+            "fans_num": "52,301"
+        },
+        "game": {"tag_name": "英雄联盟"}
+    }"#;
+
+    #[test]
+    fn parses_popularity_rank_and_followers() {
+        let json: Value = serde_json::from_str(BETARD_ROOM).unwrap();
+        let info = parse_betard_room(&json, "9999").unwrap();
+        assert_eq!(info.room_id, "9999");
+        assert_eq!(info.room_name.as_deref(), Some("今晚双排"));
+        assert_eq!(info.nickname.as_deref(), Some("测试主播"));
+        assert!(info.avatar_url.unwrap().contains("demo_middle.jpg"));
+        assert_eq!(info.show_status, Some(1));
+        assert_eq!(info.is_vertical, Some(false));
+        // 开播中不展示公告
+        assert_eq!(info.notice, None);
+        assert_eq!(info.popularity, Some(1_018_000));
+        assert_eq!(info.category_rank, Some(3));
+        assert_eq!(info.follower_count, Some(52_301));
+        assert_eq!(info.viewers(), (true, Some(1_018_000)));
+    }
+
+    #[test]
+    fn hn_is_read_from_the_room_or_the_top_level() {
+        let json = serde_json::json!({"room": {"hn": 12345, "show_status": 2}});
+        let info = parse_betard_room(&json, "9999").unwrap();
+        assert_eq!(info.popularity, Some(12345));
+
+        let json = serde_json::json!({"hn": "3.2万", "room": {"show_status": 2}});
+        let info = parse_betard_room(&json, "9999").unwrap();
+        assert_eq!(info.popularity, Some(32_000));
+    }
+
+    #[test]
+    fn missing_fields_are_none() {
+        let json = serde_json::json!({
+            "room": {"room_name": "下播了", "show_status": 2, "show_details": " 明天见 ", "fans_num": "未知"}
+        });
+        let info = parse_betard_room(&json, "9999").unwrap();
+        assert_eq!(info.room_id, "9999");
+        assert_eq!(info.popularity, None);
+        assert_eq!(info.category_rank, None);
+        assert_eq!(info.follower_count, None);
+        assert_eq!(info.notice.as_deref(), Some("明天见"));
+        assert_eq!(info.viewers(), (false, None));
+    }
+}
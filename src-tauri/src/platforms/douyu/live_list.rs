@@ -1,5 +1,24 @@
 use serde::{Deserialize, Serialize};
-use tauri::command;
+use tauri::{command, AppHandle};
+
+use crate::platforms::common::cn_number::{format_cn_number, parse_cn_number};
+use crate::platforms::common::list_cache::{is_unavailable_status, load_list, save_list};
+use crate::proxy::proxied_image_url;
+
+const DOUYU_AVATAR_PREFIX: &str = "https://apic.douyucdn.cn/upload/";
+
+// V1 目录接口返回的 av 是相对路径，补全为与主列表一致的完整头像地址
+fn normalize_douyu_avatar(av: &str) -> String {
+    if av.is_empty() || av.starts_with("http://") || av.starts_with("https://") {
+        av.to_string()
+    } else {
+        format!(
+            "{}{}_middle.jpg",
+            DOUYU_AVATAR_PREFIX,
+            av.trim_start_matches('/')
+        )
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
@@ -24,6 +43,11 @@ pub struct FrontendStreamer {
     pub hn: String, // Will store 'ol' (online count) as string
     #[serde(rename = "isLive", skip_serializing_if = "Option::is_none")]
     pub is_live: Option<bool>,
+    // 数值化的热度，便于排序（hn 仍保留展示用字符串）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub viewers: Option<u64>,
+    #[serde(rename = "isVertical", skip_serializing_if = "Option::is_none")]
+    pub is_vertical: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -37,6 +61,9 @@ pub struct FrontendLiveListResponse {
     pub error: i32,
     pub msg: Option<String>,
     pub data: Option<LiveListDataWrapper>,
+    // 网络失败时返回的离线缓存数据会标记为 stale
+    #[serde(default)]
+    pub stale: bool,
 }
 
 // Structs for parsing Douyu's mobile API (hgapi/live/cate/newRecList) response
@@ -51,7 +78,9 @@ struct NewRecStreamerRaw {
     room_src: String, // Main cover image
     avatar: String,
     hn: String, // Viewers count string (e.g., "101.8万")
-                // rs_ext: Option<Vec<ImageRsExtRaw>>, // Removed as unused
+    // rs_ext: Option<Vec<ImageRsExtRaw>>, // Removed as unused
+    #[serde(rename = "isVertical", default)]
+    is_vertical: Option<i32>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -78,12 +107,17 @@ struct DouyuV1Streamer {
     rs16: String, // Cover image
     #[serde(rename = "type")]
     stream_type: Option<u32>, // Example: type:1 might mean live
-                  // Add any other fields you might need, e.g. cid3 for verification
+    // Add any other fields you might need, e.g. cid3 for verification
+    #[serde(rename = "isVertical", default)]
+    is_vertical: Option<i32>,
 }
 
 #[derive(Deserialize, Debug)]
 struct DouyuV1Data {
     rl: Vec<DouyuV1Streamer>,
+    // 部分版本会返回总页数，存在时用于计算 total，与主列表分页保持一致
+    #[serde(default)]
+    pgcnt: Option<u32>,
     // Douyu's V1 directory API typically doesn't provide a total count.
     // It might have `pgcnt` (page count) in some versions, but not in the example.
     // We will estimate `total` based on the number of items returned vs. page size.
@@ -97,13 +131,58 @@ struct DouyuV1ApiResponse {
 }
 
 #[command]
-pub async fn fetch_live_list(offset: u32, cate2: String, limit: u32) -> FrontendLiveListResponse {
+pub async fn fetch_live_list(
+    app_handle: AppHandle,
+    offset: u32,
+    cate2: String,
+    limit: u32,
+) -> FrontendLiveListResponse {
+    let cache_key = format!("douyu_cate2_{}_{}_{}", cate2, offset, limit);
+    with_list_cache(
+        &app_handle,
+        &cache_key,
+        fetch_live_list_remote(offset, cate2, limit).await,
+    )
+}
+
+fn with_list_cache(
+    app_handle: &AppHandle,
+    cache_key: &str,
+    response: FrontendLiveListResponse,
+) -> FrontendLiveListResponse {
+    if response.error == 0 && response.data.is_some() {
+        save_list(app_handle, cache_key, &response);
+        return response;
+    }
+    // 只有网络不可达时才回退到缓存，接口返回的错误照常交给前端
+    if !is_unavailable_status(response.error) {
+        return response;
+    }
+    match load_list::<FrontendLiveListResponse>(app_handle, cache_key) {
+        Some(mut cached) => {
+            eprintln!(
+                "[Backend Douyu list] Live request failed ({:?}), serving cached list for {}",
+                response.msg, cache_key
+            );
+            cached.stale = true;
+            cached
+        }
+        None => response,
+    }
+}
+
+async fn fetch_live_list_remote(
+    offset: u32,
+    cate2: String,
+    limit: u32,
+) -> FrontendLiveListResponse {
     let url = format!(
         "https://m.douyu.com/hgapi/live/cate/newRecList?offset={}&cate2={}&limit={}",
         offset, cate2, limit
     );
 
-    let client = reqwest::Client::builder()
+    let client = crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
         .build()
         .map_err(|e| e.to_string())
         .unwrap();
@@ -121,6 +200,7 @@ pub async fn fetch_live_list(offset: u32, cate2: String, limit: u32) -> Frontend
                 error: 500,
                 msg: Some(format!("Network request failed: {}", e)),
                 data: None,
+                stale: false,
             };
         }
     };
@@ -136,6 +216,7 @@ pub async fn fetch_live_list(offset: u32, cate2: String, limit: u32) -> Frontend
             error: status_code,
             msg: Some(format!("Douyu API request failed: {}", response.status())),
             data: None,
+            stale: false,
         };
     }
 
@@ -150,6 +231,7 @@ pub async fn fetch_live_list(offset: u32, cate2: String, limit: u32) -> Frontend
                 error: 500,
                 msg: Some(format!("Failed to read response text: {}", e)),
                 data: None,
+                stale: false,
             };
         }
     };
@@ -167,10 +249,12 @@ pub async fn fetch_live_list(offset: u32, cate2: String, limit: u32) -> Frontend
                                 rid: s_raw.rid.to_string(),
                                 room_name: s_raw.room_name,
                                 nickname: s_raw.nickname,
-                                avatar: s_raw.avatar,
-                                room_src: s_raw.room_src, // Using main room_src for now
+                                avatar: proxied_image_url(&s_raw.avatar),
+                                room_src: proxied_image_url(&s_raw.room_src), // Using main room_src for now
+                                viewers: parse_cn_number(&s_raw.hn),
                                 hn: s_raw.hn,
                                 is_live: Some(true), // Assuming all returned by this API are live
+                                is_vertical: s_raw.is_vertical.map(|v| v == 1),
                             }
                         })
                         .collect();
@@ -183,6 +267,7 @@ pub async fn fetch_live_list(offset: u32, cate2: String, limit: u32) -> Frontend
                         error: 0,
                         msg: douyu_response.msg.or_else(|| Some("Success".to_string())),
                         data: Some(frontend_data),
+                        stale: false,
                     }
                 } else {
                     eprintln!(
@@ -193,6 +278,7 @@ pub async fn fetch_live_list(offset: u32, cate2: String, limit: u32) -> Frontend
                         error: -1,
                         msg: Some("Douyu API success code but no data field.".to_string()),
                         data: None,
+                        stale: false,
                     }
                 }
             } else {
@@ -206,6 +292,7 @@ pub async fn fetch_live_list(offset: u32, cate2: String, limit: u32) -> Frontend
                         .msg
                         .or_else(|| Some("Error from Douyu API".to_string())),
                     data: None,
+                    stale: false,
                 }
             }
         }
@@ -218,6 +305,7 @@ pub async fn fetch_live_list(offset: u32, cate2: String, limit: u32) -> Frontend
                 error: -2,
                 msg: Some(format!("Failed to parse Douyu API response: {}", e)),
                 data: None,
+                stale: false,
             }
         }
     }
@@ -226,6 +314,20 @@ pub async fn fetch_live_list(offset: u32, cate2: String, limit: u32) -> Frontend
 // New command for third-level categories
 #[command]
 pub async fn fetch_live_list_for_cate3(
+    app_handle: AppHandle,
+    cate3_id: String,
+    page: u32,
+    limit: u32,
+) -> FrontendLiveListResponse {
+    let cache_key = format!("douyu_cate3_{}_{}_{}", cate3_id, page, limit);
+    with_list_cache(
+        &app_handle,
+        &cache_key,
+        fetch_live_list_for_cate3_remote(cate3_id, page, limit).await,
+    )
+}
+
+async fn fetch_live_list_for_cate3_remote(
     cate3_id: String,
     page: u32,
     limit: u32,
@@ -238,13 +340,17 @@ pub async fn fetch_live_list_for_cate3(
     );
     println!("[Backend fetch_live_list_for_cate3] Fetching URL: {}", url);
 
-    let client = match reqwest::Client::builder().build() {
+    let client = match crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
+        .build()
+    {
         Ok(c) => c,
         Err(e) => {
             return FrontendLiveListResponse {
                 error: 500,
                 msg: Some(format!("Failed to build HTTP client: {}", e)),
                 data: None,
+                stale: false,
             };
         }
     };
@@ -262,6 +368,7 @@ pub async fn fetch_live_list_for_cate3(
                 error: 500, // Simulate HTTP 500 for client error
                 msg: Some(format!("Network request failed: {}", e)),
                 data: None,
+                stale: false,
             };
         }
     };
@@ -277,6 +384,7 @@ pub async fn fetch_live_list_for_cate3(
             error: status_code,
             msg: Some(format!("Douyu API request failed: {}", response.status())),
             data: None,
+            stale: false,
         };
     }
 
@@ -291,11 +399,17 @@ pub async fn fetch_live_list_for_cate3(
                 error: 500,
                 msg: Some(format!("Failed to read response text: {}", e)),
                 data: None,
+                stale: false,
             };
         }
     };
 
-    match serde_json::from_str::<DouyuV1ApiResponse>(&text) {
+    parse_cate3_response(&text, current_page, limit)
+}
+
+// 三级分类接口的响应换算为与主列表相同的结构；current_page 从 1 开始
+fn parse_cate3_response(text: &str, current_page: u32, limit: u32) -> FrontendLiveListResponse {
+    match serde_json::from_str::<DouyuV1ApiResponse>(text) {
         Ok(douyu_response) => {
             if douyu_response.code == 0 {
                 if let Some(douyu_data) = douyu_response.data {
@@ -306,19 +420,24 @@ pub async fn fetch_live_list_for_cate3(
                             rid: s.rid.to_string(),
                             room_name: s.rn,
                             nickname: s.nn,
-                            avatar: s.av, // This is usually a path, might need full URL prefix if not already there
-                            room_src: s.rs16,
-                            hn: s.ol.to_string(), // Convert online count to string
+                            avatar: proxied_image_url(&normalize_douyu_avatar(&s.av)),
+                            room_src: proxied_image_url(&s.rs16),
+                            hn: format_cn_number(s.ol as u64), // 与主列表一致的 "xx.x万" 展示
                             is_live: Some(s.stream_type.map_or(true, |st| st == 1)), // Assume live if no type or type is 1
+                            viewers: Some(s.ol as u64),
+                            is_vertical: s.is_vertical.map(|v| v == 1),
                         })
                         .collect();
 
                     let total_returned = streamers_transformed.len() as u32;
-                    let estimated_total = if total_returned < limit {
-                        (current_page - 1) * limit + total_returned // If less than limit, means it's the last page
-                    } else {
-                        current_page * limit + 1 // Otherwise, assume there's at least one more page
-                    };
+                    let estimated_total =
+                        if let Some(page_count) = douyu_data.pgcnt.filter(|c| *c > 0) {
+                            page_count * limit
+                        } else if total_returned < limit {
+                            (current_page - 1) * limit + total_returned // If less than limit, means it's the last page
+                        } else {
+                            current_page * limit + 1 // Otherwise, assume there's at least one more page
+                        };
 
                     let frontend_data = LiveListDataWrapper {
                         list: streamers_transformed,
@@ -328,6 +447,7 @@ pub async fn fetch_live_list_for_cate3(
                         error: 0,
                         msg: douyu_response.msg.or_else(|| Some("Success".to_string())),
                         data: Some(frontend_data),
+                        stale: false,
                     }
                 } else {
                     eprintln!("[Backend fetch_live_list_for_cate3] API success but no data field. Raw: {}", text);
@@ -335,6 +455,7 @@ pub async fn fetch_live_list_for_cate3(
                         error: -1,
                         msg: Some("Douyu API success code but no data field.".to_string()),
                         data: None,
+                        stale: false,
                     }
                 }
             } else {
@@ -348,6 +469,7 @@ pub async fn fetch_live_list_for_cate3(
                         .msg
                         .or_else(|| Some("Error from Douyu API".to_string())),
                     data: None,
+                    stale: false,
                 }
             }
         }
@@ -360,7 +482,123 @@ pub async fn fetch_live_list_for_cate3(
                 error: -2,
                 msg: Some(format!("Failed to parse Douyu API response: {}", e)),
                 data: None,
+                stale: false,
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 模拟 gapi/rkc/directory/mixListV1/3_{cate3} 的响应，两条记录的昵称与地址均为虚构
+    const CATE3_RESPONSE: &str = r#"{
+        "code": 0,
+        "msg": "success",
+        "data": {
+            "ct": {"iv": 0, "ivcv": 0, "tag": 3},
+            "pgcnt": 12,
+            "rl": [
+                {
+                    "type": 1,
+                    "rid": 9999,
+                    "rn": "横屏房间",
+                    "uid": 1,
+                    "nn": "主播A",
+                    "cid1": 1,
+                    "cid2": 270,
+                    "cid3": 1189,
+                    "iv": 0,
+                    "av": "avatar_v3/202301/abc",
+                    "ol": 1234567,
+                    "c2url": "/g_jdqs",
+                    "c2name": "绝地求生",
+                    "rs16": "https://rpic.douyucdn.cn/asrpic/230101/9999_1200.png/dy1",
+                    "isVertical": 0
+                },
+                {
+                    "type": 1,
+                    "rid": 8888,
+                    "rn": "竖屏房间",
+                    "uid": 2,
+                    "nn": "主播B",
+                    "av": "https://apic.douyucdn.cn/upload/avatar_v3/b_middle.jpg",
+                    "ol": 980,
+                    "rs16": "https://rpic.douyucdn.cn/asrpic/230101/8888_1200.png/dy1",
+                    "isVertical": 1
+                }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn cate3_rooms_match_main_list_fields() {
+        let response = parse_cate3_response(CATE3_RESPONSE, 1, 20);
+        assert_eq!(response.error, 0);
+        let data = response.data.unwrap();
+        assert_eq!(data.list.len(), 2);
+
+        let first = &data.list[0];
+        assert_eq!(first.rid, "9999");
+        assert_eq!(first.room_name, "横屏房间");
+        assert_eq!(first.nickname, "主播A");
+        assert_eq!(
+            first.avatar,
+            "https://apic.douyucdn.cn/upload/avatar_v3/202301/abc_middle.jpg"
+        );
+        assert_eq!(
+            first.room_src,
+            "https://rpic.douyucdn.cn/asrpic/230101/9999_1200.png/dy1"
+        );
+        assert_eq!(first.hn, format_cn_number(1234567));
+        assert_eq!(first.viewers, Some(1234567));
+        assert_eq!(first.is_live, Some(true));
+        assert_eq!(first.is_vertical, Some(false));
+
+        let second = &data.list[1];
+        assert_eq!(
+            second.avatar,
+            "https://apic.douyucdn.cn/upload/avatar_v3/b_middle.jpg"
+        );
+        assert_eq!(second.viewers, Some(980));
+        assert_eq!(second.is_vertical, Some(true));
+    }
+
+    #[test]
+    fn cate3_total_uses_page_count_when_present() {
+        let response = parse_cate3_response(CATE3_RESPONSE, 1, 20);
+        assert_eq!(response.data.unwrap().total, 12 * 20);
+    }
+
+    #[test]
+    fn cate3_total_is_estimated_without_page_count() {
+        let page = |rooms: usize| {
+            let rl: Vec<serde_json::Value> = (0..rooms)
+                .map(|i| {
+                    serde_json::json!({
+                        "rid": i, "rn": "", "nn": "", "av": "", "ol": 0, "rs16": ""
+                    })
+                })
+                .collect();
+            serde_json::json!({"code": 0, "data": {"rl": rl}}).to_string()
+        };
+        // 满页：至少还有下一页
+        let full = parse_cate3_response(&page(2), 3, 2);
+        assert_eq!(full.data.unwrap().total, 3 * 2 + 1);
+        // 不足一页：当前页是最后一页
+        let last = parse_cate3_response(&page(1), 3, 2);
+        assert_eq!(last.data.unwrap().total, 2 * 2 + 1);
+    }
+
+    #[test]
+    fn cate3_api_errors_are_reported() {
+        let response = parse_cate3_response(r#"{"code": 110, "msg": "参数错误"}"#, 1, 20);
+        assert_eq!(response.error, 110);
+        assert_eq!(response.msg.as_deref(), Some("参数错误"));
+        assert!(response.data.is_none());
+
+        let response = parse_cate3_response("<html>", 1, 20);
+        assert_eq!(response.error, -2);
+    }
+}
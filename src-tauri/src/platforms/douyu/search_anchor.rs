@@ -9,13 +9,23 @@ use std::time::{SystemTime, UNIX_EPOCH}; // For timestamp for did // For URL enc
 
 // Renamed from search_anchor to avoid ambiguity with Tauri command
 pub async fn perform_anchor_search(keyword: &str) -> Result<String, Box<dyn std::error::Error>> {
+    perform_anchor_search_page(keyword, 1, 20).await
+}
+
+// 返回斗鱼搜索接口的原始 JSON 文本，page 从 1 开始
+pub async fn perform_anchor_search_page(
+    keyword: &str,
+    page: u32,
+    page_size: u32,
+) -> Result<String, Box<dyn std::error::Error>> {
     let mut default_headers = HeaderMap::new();
     default_headers.insert(
         "User-Agent",
         HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36"),
     );
 
-    let client = Client::builder()
+    let client = crate::network::api_proxy()
+        .apply(Client::builder())
         .redirect(Policy::limited(10))
         .default_headers(default_headers)
         .build()?;
@@ -30,8 +40,10 @@ pub async fn perform_anchor_search(keyword: &str) -> Result<String, Box<dyn std:
     let did = format!("{:x}", hasher.finalize());
 
     let url = format!(
-        "https://www.douyu.com/japi/search/api/searchUser?kw={}&page=1&pageSize=20&filterType=0",
-        percent_encode(keyword.as_bytes(), NON_ALPHANUMERIC)
+        "https://www.douyu.com/japi/search/api/searchUser?kw={}&page={}&pageSize={}&filterType=0",
+        percent_encode(keyword.as_bytes(), NON_ALPHANUMERIC),
+        page,
+        page_size
     );
 
     let text = client
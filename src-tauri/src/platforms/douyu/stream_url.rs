@@ -5,16 +5,44 @@ use reqwest::{
     redirect::Policy,
     Client,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 #[cfg(target_os = "linux")]
 use std::sync::Once;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::platforms::common::json_value::scalar_string;
+use crate::platforms::common::types::StreamVariant;
+use crate::platforms::common::user_agent::user_agent_for;
+use crate::platforms::common::{Quality, SupportedPlatformRust};
+
 #[derive(Deserialize, Debug)]
 struct BetardRoomInfo {
     room_id: Option<Value>,
     show_status: Option<Value>,
+    // 竖屏（手机开播）房间为 1
+    #[serde(rename = "isVertical", default)]
+    is_vertical: Option<Value>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DouyuResolvedStream {
+    pub url: String,
+    pub is_vertical: bool,
+    // 实际使用的线路，以及房间当前可用的全部线路
+    pub cdn: String,
+    pub cdns: Vec<String>,
+    // 实际使用的码率编号（斗鱼 multirates 中的 rate），以及房间提供的全部码率档位
+    pub rate: i32,
+    pub rates: Vec<StreamVariant>,
+    // 为 true 时只能经本地代理播放，见 proxy::upstream_needs_proxy
+    pub needs_proxy: bool,
+}
+
+struct DouyuRoomDetail {
+    room_id: String,
+    is_live: bool,
+    is_vertical: bool,
 }
 
 #[derive(Deserialize, Debug)]
@@ -22,6 +50,30 @@ struct BetardResponse {
     room: Option<BetardRoomInfo>,
 }
 
+impl DouyuRoomDetail {
+    fn from_betard(json: BetardResponse) -> Result<Self, Box<dyn std::error::Error>> {
+        let room = json.room.ok_or("Missing room data")?;
+        let room_id_value = room.room_id.ok_or("Missing room_id")?;
+        let room_id = scalar_string(&room_id_value).ok_or("Invalid room_id")?;
+        let show_status = room
+            .show_status
+            .as_ref()
+            .and_then(value_to_i32)
+            .unwrap_or(0);
+        let is_vertical = room
+            .is_vertical
+            .as_ref()
+            .and_then(value_to_i32)
+            .unwrap_or(0)
+            == 1;
+        Ok(DouyuRoomDetail {
+            room_id,
+            is_live: show_status == 1,
+            is_vertical,
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 struct DouyuPlayInfo {
     variants: Vec<DouyuRateVariant>,
@@ -35,6 +87,69 @@ struct DouyuRateVariant {
     bit: Option<i32>,
 }
 
+impl DouyuPlayInfo {
+    // getH5Play（rate=-1）响应：multirates 为可选码率，cdnsWithName 为可用线路（scdn 排在最后）
+    fn from_h5_play(json: &Value) -> Result<Self, String> {
+        let error_code = json.get("error").and_then(value_to_i32).unwrap_or(-1);
+        if error_code != 0 {
+            let msg = json
+                .get("msg")
+                .and_then(|v| v.as_str())
+                .unwrap_or("getH5Play failed");
+            return Err(format!("getH5Play error {}: {}", error_code, msg));
+        }
+
+        let data = json.get("data").ok_or("No data field in response")?;
+        let cdns = data
+            .get("cdnsWithName")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|item| {
+                        item.get("cdn")
+                            .and_then(|v| v.as_str())
+                            .map(|s| s.to_string())
+                    })
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default();
+
+        let mut cdns_sorted = cdns;
+        cdns_sorted.sort_by(|a, b| {
+            let a_is_scdn = a.starts_with("scdn");
+            let b_is_scdn = b.starts_with("scdn");
+            (a_is_scdn, a).cmp(&(b_is_scdn, b))
+        });
+
+        let variants = data
+            .get("multirates")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|item| {
+                        let name = item
+                            .get("name")
+                            .and_then(|v| v.as_str())
+                            .map(|s| s.to_string())?;
+                        let rate_value = item.get("rate").and_then(value_to_i32)?;
+                        let bit_value = item.get("bit").and_then(value_to_i32);
+                        Some(DouyuRateVariant {
+                            name,
+                            rate: rate_value,
+                            bit: bit_value,
+                        })
+                    })
+                    .collect::<Vec<DouyuRateVariant>>()
+            })
+            .unwrap_or_default();
+
+        Ok(DouyuPlayInfo {
+            variants,
+            cdns: cdns_sorted,
+        })
+    }
+}
+
 fn value_to_i32(value: &Value) -> Option<i32> {
     match value {
         Value::Number(num) => num.as_i64().map(|n| n as i32),
@@ -43,12 +158,31 @@ fn value_to_i32(value: &Value) -> Option<i32> {
     }
 }
 
-fn value_to_string(value: &Value) -> Option<String> {
-    match value {
-        Value::Number(num) => Some(num.to_string()),
-        Value::String(s) => Some(s.to_string()),
-        _ => None,
+fn h5_play_payload(sign_data: &str, cdn: &str, rate: i32, is_vertical: bool) -> String {
+    let mut payload = format!("{}&cdn={}&rate={}", sign_data, cdn, rate);
+    if is_vertical {
+        // 竖屏房间需带上与 Web 播放器一致的 iar/ive 参数，否则可能拿到无画面的流
+        payload.push_str("&ver=Douyu_223061205&iar=1&ive=1&hevc=0&fa=0");
     }
+    payload
+}
+
+fn parse_mobile_play_url(json: &Value) -> Result<String, Box<dyn std::error::Error>> {
+    let code = json.get("code").and_then(value_to_i32).unwrap_or(-1);
+    if code != 0 {
+        let msg = json
+            .get("msg")
+            .and_then(|v| v.as_str())
+            .unwrap_or("getStreamUrl failed");
+        return Err(format!("mobile getStreamUrl error {}: {}", code, msg).into());
+    }
+    let url = json
+        .get("data")
+        .and_then(|d| d.get("url"))
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .ok_or("No url field in mobile getStreamUrl response")?;
+    Ok(decode_html_entities(url).to_string())
 }
 
 struct DouYu {
@@ -58,7 +192,8 @@ struct DouYu {
 }
 
 const DEFAULT_DOUYU_CDN: &str = "ws-h5";
-const DEFAULT_DOUYU_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36";
+pub(crate) const DEFAULT_DOUYU_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36";
+const DOUYU_MOBILE_UA: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 16_6 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.6 Mobile/15E148 Safari/604.1";
 const DEFAULT_DOUYU_DID: &str = "10000000000000000000000000001501";
 const CRYPTO_JS: &str = include_str!("cryptojs.min.js");
 
@@ -86,26 +221,82 @@ fn normalize_douyu_cdn(input: Option<&str>) -> &'static str {
     }
 }
 
+/// Normalize user input into a Douyu room id. Supports raw ids and URLs such as
+/// `douyu.com/123456`, `douyu.com/room/123456`, `m.douyu.com/123456` and
+/// topic pages like `douyu.com/topic/xxx?rid=123456`.
+pub fn normalize_douyu_room_id(input: &str) -> Result<String, String> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err("房间ID未提供".to_string());
+    }
+    if trimmed.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(trimmed.to_string());
+    }
+
+    let with_scheme = if trimmed.contains("://") {
+        trimmed.to_string()
+    } else {
+        format!("https://{}", trimmed)
+    };
+    let url = url::Url::parse(&with_scheme)
+        .map_err(|_| format!("无法识别的斗鱼房间号或链接: {}", trimmed))?;
+    let host = url.host_str().unwrap_or("").to_ascii_lowercase();
+    if host != "douyu.com" && !host.ends_with(".douyu.com") {
+        return Err(format!("不是斗鱼链接: {}", trimmed));
+    }
+
+    // 专题页与分享链接通过 rid 参数携带真实房间号，优先使用
+    if let Some((_, rid)) = url
+        .query_pairs()
+        .find(|(k, _)| k == "rid" || k == "room_id")
+    {
+        let rid = rid.trim();
+        if !rid.is_empty() && rid.chars().all(|c| c.is_ascii_digit()) {
+            return Ok(rid.to_string());
+        }
+    }
+
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|s| s.filter(|seg| !seg.is_empty()).collect())
+        .unwrap_or_default();
+    let candidate = match segments.as_slice() {
+        ["topic", ..] => {
+            return Err(format!("斗鱼专题页链接缺少 rid 参数: {}", trimmed));
+        }
+        ["room", id, ..] | ["beta", id, ..] | [id, ..] => *id,
+        [] => return Err(format!("斗鱼链接中没有房间号: {}", trimmed)),
+    };
+    // 除数字房间号外，斗鱼也支持靓号别名（如 douyu.com/xxx），交给 betard 解析真实房间号
+    if candidate.chars().all(|c| c.is_ascii_alphanumeric()) {
+        Ok(candidate.to_string())
+    } else {
+        Err(format!("斗鱼链接中没有房间号: {}", trimmed))
+    }
+}
+
 impl DouYu {
     async fn new(rid: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let rid = normalize_douyu_room_id(rid)?;
         // 迁移到 reqwest：禁用系统代理、限制重定向、设置默认 UA/语言等头部
         let mut default_headers = HeaderMap::new();
         default_headers.insert(
             "User-Agent",
-            HeaderValue::from_static(DEFAULT_DOUYU_UA),
+            HeaderValue::from_str(&user_agent_for(&SupportedPlatformRust::Douyu))?,
         );
         default_headers.insert(
             "Accept-Language",
             HeaderValue::from_static("zh-CN,zh;q=0.9"),
         );
-        let client = Client::builder()
+        let client = crate::network::api_proxy()
+            .apply(Client::builder())
             .redirect(Policy::limited(10))
             .default_headers(default_headers)
             .build()?;
 
         Ok(Self {
             did: DEFAULT_DOUYU_DID.to_string(),
-            rid: rid.to_string(),
+            rid,
             client,
         })
     }
@@ -141,7 +332,7 @@ impl DouYu {
         Ok(params)
     }
 
-    async fn fetch_room_detail(&self) -> Result<(String, bool), Box<dyn std::error::Error>> {
+    async fn fetch_room_detail(&self) -> Result<DouyuRoomDetail, Box<dyn std::error::Error>> {
         let url = format!("https://www.douyu.com/betard/{}", self.rid);
         let json = self
             .client
@@ -151,16 +342,7 @@ impl DouYu {
             .await?
             .json::<BetardResponse>()
             .await?;
-
-        let room = json.room.ok_or("Missing room data")?;
-        let room_id_value = room.room_id.ok_or("Missing room_id")?;
-        let room_id = value_to_string(&room_id_value).ok_or("Invalid room_id")?;
-        let show_status = room
-            .show_status
-            .as_ref()
-            .and_then(value_to_i32)
-            .unwrap_or(0);
-        Ok((room_id, show_status == 1))
+        DouyuRoomDetail::from_betard(json)
     }
 
     async fn get_h5_enc(&self, room_id: &str) -> Result<String, Box<dyn std::error::Error>> {
@@ -188,10 +370,7 @@ impl DouYu {
         Ok(crptext.to_string())
     }
 
-    async fn build_sign_params(
-        &self,
-        room_id: &str,
-    ) -> Result<String, Box<dyn std::error::Error>> {
+    async fn build_sign_params(&self, room_id: &str) -> Result<String, Box<dyn std::error::Error>> {
         let crptext = self.get_h5_enc(room_id).await?;
         let ts = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
         let params = self
@@ -220,63 +399,7 @@ impl DouYu {
             .json::<Value>()
             .await?;
 
-        let error_code = json.get("error").and_then(value_to_i32).unwrap_or(-1);
-        if error_code != 0 {
-            let msg = json
-                .get("msg")
-                .and_then(|v| v.as_str())
-                .unwrap_or("getH5Play failed");
-            return Err(format!("getH5Play error {}: {}", error_code, msg).into());
-        }
-
-        let data = json.get("data").ok_or("No data field in response")?;
-        let cdns = data
-            .get("cdnsWithName")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|item| {
-                        item.get("cdn")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string())
-                    })
-                    .collect::<Vec<String>>()
-            })
-            .unwrap_or_default();
-
-        let mut cdns_sorted = cdns;
-        cdns_sorted.sort_by(|a, b| {
-            let a_is_scdn = a.starts_with("scdn");
-            let b_is_scdn = b.starts_with("scdn");
-            (a_is_scdn, a).cmp(&(b_is_scdn, b))
-        });
-
-        let variants = data
-            .get("multirates")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|item| {
-                        let name = item
-                            .get("name")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string())?;
-                        let rate_value = item.get("rate").and_then(value_to_i32)?;
-                        let bit_value = item.get("bit").and_then(value_to_i32);
-                        Some(DouyuRateVariant {
-                            name,
-                            rate: rate_value,
-                            bit: bit_value,
-                        })
-                    })
-                    .collect::<Vec<DouyuRateVariant>>()
-            })
-            .unwrap_or_default();
-
-        Ok(DouyuPlayInfo {
-            variants,
-            cdns: cdns_sorted,
-        })
+        Ok(DouyuPlayInfo::from_h5_play(&json)?)
     }
 
     async fn get_play_url(
@@ -285,8 +408,9 @@ impl DouYu {
         sign_data: &str,
         rate: i32,
         cdn: &str,
+        is_vertical: bool,
     ) -> Result<String, Box<dyn std::error::Error>> {
-        let payload = format!("{}&cdn={}&rate={}", sign_data, cdn, rate);
+        let payload = h5_play_payload(sign_data, cdn, rate, is_vertical);
         let url = format!("https://www.douyu.com/lapi/live/getH5Play/{}", room_id);
         let json = self
             .client
@@ -321,6 +445,52 @@ impl DouYu {
         Ok(format!("{}/{}", rtmp_url, rtmp_live))
     }
 
+    // 竖屏房间在 H5 接口失败时的兜底：移动端取流接口
+    async fn get_mobile_play_url(
+        &self,
+        room_id: &str,
+        sign_data: &str,
+        rate: i32,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let payload = format!("{}&rid={}&rate={}", sign_data, room_id, rate);
+        let json = self
+            .client
+            .post("https://m.douyu.com/hgapi/livenc/room/getStreamUrl")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Referer", format!("https://m.douyu.com/{}", room_id))
+            .header("User-Agent", DOUYU_MOBILE_UA)
+            .body(payload)
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        parse_mobile_play_url(&json)
+    }
+
+    async fn get_play_url_for_room(
+        &self,
+        detail: &DouyuRoomDetail,
+        sign_data: &str,
+        rate: i32,
+        cdn: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let result = self
+            .get_play_url(&detail.room_id, sign_data, rate, cdn, detail.is_vertical)
+            .await;
+        match result {
+            Err(e) if detail.is_vertical => {
+                println!(
+                    "[Douyu Stream URL] Vertical room {} H5 play failed ({}), trying mobile API",
+                    detail.room_id, e
+                );
+                self.get_mobile_play_url(&detail.room_id, sign_data, rate)
+                    .await
+            }
+            other => other,
+        }
+    }
+
     fn select_cdn(requested: Option<&str>, available: &[String]) -> String {
         if let Some(cdn) = requested {
             let trimmed = cdn.trim();
@@ -340,9 +510,13 @@ impl DouYu {
             .unwrap_or_else(|| normalize_douyu_cdn(requested).to_string())
     }
 
-    pub async fn get_real_url(&self, cdn: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
-        let (real_room_id, is_live) = self.fetch_room_detail().await?;
-        if !is_live {
+    pub async fn get_real_url(
+        &self,
+        cdn: Option<&str>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let detail = self.fetch_room_detail().await?;
+        let real_room_id = detail.room_id.clone();
+        if !detail.is_live {
             return Err(Box::new(std::io::Error::new(
                 std::io::ErrorKind::Other,
                 "主播未开播",
@@ -358,17 +532,21 @@ impl DouYu {
             .max()
             .unwrap_or(0);
         let selected_cdn = Self::select_cdn(cdn, &play_info.cdns);
-        self.get_play_url(&real_room_id, &sign_data, best_rate, &selected_cdn)
+        self.get_play_url_for_room(&detail, &sign_data, best_rate, &selected_cdn)
             .await
     }
 
-    pub async fn get_real_url_with_quality(
+    // 同时返回实际使用的线路与房间可用线路，供切换线路使用
+    // rate 为斗鱼的码率编号：指定时精确使用该档位，不存在则报错；否则按 quality 名称匹配
+    async fn resolve_with_quality(
         &self,
         quality: &str,
+        rate: Option<i32>,
         cdn: Option<&str>,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let (real_room_id, is_live) = self.fetch_room_detail().await?;
-        if !is_live {
+    ) -> Result<DouyuResolvedStream, Box<dyn std::error::Error>> {
+        let detail = self.fetch_room_detail().await?;
+        let real_room_id = detail.room_id.clone();
+        if !detail.is_live {
             return Err(Box::new(std::io::Error::new(
                 std::io::ErrorKind::Other,
                 "主播未开播",
@@ -377,16 +555,76 @@ impl DouYu {
 
         let sign_data = self.build_sign_params(&real_room_id).await?;
         let play_info = self.get_play_qualities(&real_room_id, &sign_data).await?;
-        let selected_rate = Self::resolve_rate_for_quality(quality, &play_info.variants)
-            .or_else(|| play_info.variants.iter().map(|v| v.rate).max())
-            .unwrap_or(0);
+        let selected_rate = Self::select_rate(quality, rate, &play_info.variants)?;
         println!(
-            "[Douyu Stream URL] Requested quality '{}', resolved rate {} (variants: {:?})",
-            quality, selected_rate, play_info.variants
+            "[Douyu Stream URL] Quality '{}' rate {:?} resolved to rate {} (variants: {:?})",
+            quality, rate, selected_rate, play_info.variants
         );
         let selected_cdn = Self::select_cdn(cdn, &play_info.cdns);
-        self.get_play_url(&real_room_id, &sign_data, selected_rate, &selected_cdn)
-            .await
+        let url = self
+            .get_play_url_for_room(&detail, &sign_data, selected_rate, &selected_cdn)
+            .await?;
+        let rates = Self::rate_variants(&play_info.variants, selected_rate, &url);
+        Ok(DouyuResolvedStream {
+            needs_proxy: crate::proxy::upstream_needs_proxy(&url),
+            url,
+            is_vertical: detail.is_vertical,
+            cdn: selected_cdn,
+            cdns: play_info.cdns,
+            rate: selected_rate,
+            rates,
+        })
+    }
+
+    fn select_rate(
+        quality: &str,
+        rate: Option<i32>,
+        variants: &[DouyuRateVariant],
+    ) -> Result<i32, String> {
+        let Some(rate) = rate else {
+            return Ok(Self::resolve_rate_for_quality(quality, variants)
+                .or_else(|| variants.iter().map(|v| v.rate).max())
+                .unwrap_or(0));
+        };
+        if !variants.iter().any(|v| v.rate == rate) {
+            let available: Vec<String> = variants
+                .iter()
+                .map(|v| format!("{}={}", v.rate, v.name))
+                .collect();
+            return Err(format!(
+                "房间不提供码率 {}（可用: {}）",
+                rate,
+                available.join(", ")
+            ));
+        }
+        Ok(rate)
+    }
+
+    // 码率档位列表：qn 为斗鱼 rate，desc 带上码率标签；每个档位的地址需单独签名获取，
+    // 因此只有当前选中的档位带 url，其余为空，前端按 qn 重新请求
+    fn rate_variants(
+        variants: &[DouyuRateVariant],
+        selected_rate: i32,
+        selected_url: &str,
+    ) -> Vec<StreamVariant> {
+        variants
+            .iter()
+            .map(|v| StreamVariant {
+                url: if v.rate == selected_rate {
+                    selected_url.to_string()
+                } else {
+                    String::new()
+                },
+                format: Some("flv".to_string()),
+                desc: Some(match v.bit {
+                    Some(bit) if bit > 0 => format!("{} ({}kbps)", v.name, bit),
+                    _ => v.name.clone(),
+                }),
+                qn: Some(v.rate),
+                bitrate_kbps: v.bit.filter(|bit| *bit > 0).map(|bit| bit as u32),
+                ..Default::default()
+            })
+            .collect()
     }
 
     fn resolve_rate_for_quality(quality: &str, variants: &[DouyuRateVariant]) -> Option<i32> {
@@ -395,16 +633,20 @@ impl DouYu {
         }
 
         let trimmed = quality.trim();
-        let ascii_lower = trimmed.to_ascii_lowercase();
-        let canonical = if trimmed.contains('原') || ascii_lower == "origin" {
-            "原画"
-        } else if trimmed.contains('高') || ascii_lower == "high" {
-            "高清"
-        } else if trimmed.contains('标') || ascii_lower == "standard" {
-            "标清"
-        } else {
-            trimmed
-        };
+        // 通用档位名（原画/高清/标清）按档位换算，不与房间里同名的码率档位精确匹配：
+        // 斗鱼的“高清”是 rate=2 的低码率档，而通用“高清”对应 rate=4
+        let is_generic_tier = [Quality::Origin, Quality::High, Quality::Standard]
+            .iter()
+            .any(|q| q.label() == trimmed);
+        // 直接传档位名（如“蓝光4M”）时按名称精确匹配，其余写法统一由 Quality 换算
+        if !is_generic_tier {
+            if let Some(item) = variants.iter().find(|v| v.name == trimmed) {
+                return Some(item.rate);
+            }
+        }
+        let canonical = Quality::from_user(&SupportedPlatformRust::Douyu, trimmed)
+            .map(|q| q.label())
+            .unwrap_or(trimmed);
 
         let find_by_keywords = |keywords: &[&str], exclude_zero: bool| -> Option<i32> {
             for keyword in keywords {
@@ -505,9 +747,244 @@ pub async fn get_stream_url(
 pub async fn get_stream_url_with_quality(
     room_id: &str,
     quality: &str,
+    rate: Option<i32>,
     cdn: Option<&str>,
 ) -> Result<String, Box<dyn std::error::Error>> {
+    let resolved = resolve_douyu_stream(room_id, quality, rate, cdn).await?;
+    Ok(resolved.url)
+}
+
+// 按指定线路解析，返回实际生效的线路（请求的线路不可用时会回退）
+pub async fn resolve_douyu_stream(
+    room_id: &str,
+    quality: &str,
+    rate: Option<i32>,
+    cdn: Option<&str>,
+) -> Result<DouyuResolvedStream, Box<dyn std::error::Error>> {
     let douyu = DouYu::new(room_id).await?;
-    let url = douyu.get_real_url_with_quality(quality, cdn).await?;
-    Ok(url)
+    douyu.resolve_with_quality(quality, rate, cdn).await
+}
+
+// 轻量开播状态查询：只请求 betard，不执行 JS 签名和取流
+pub async fn fetch_douyu_live_status(room_id: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let douyu = DouYu::new(room_id).await?;
+    let detail = douyu.fetch_room_detail().await?;
+    Ok(detail.is_live)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 竖屏（手机开播）房间的 betard 响应（示例数据）；isVertical 为字符串
+    const VERTICAL_BETARD: &str = r#"{
+        "room": {
+            "room_id": 5720533,
+            "room_name": "手机直播",
+            "owner_name": "测试主播",
+            "show_status": 1,
+            "videoLoop": 0,
+            "isVertical": "1",
+            "room_pic": "https://rpic.douyucdn.cn/asrpic/230101/5720533_1200.png"
+        }
+    }"#;
+
+    #[test]
+    fn detects_vertical_room_from_betard() {
+        let json: BetardResponse = serde_json::from_str(VERTICAL_BETARD).unwrap();
+        let detail = DouyuRoomDetail::from_betard(json).unwrap();
+        assert_eq!(detail.room_id, "5720533");
+        assert!(detail.is_live);
+        assert!(detail.is_vertical);
+    }
+
+    #[test]
+    fn rooms_without_the_flag_are_horizontal() {
+        let json: BetardResponse = serde_json::from_str(
+            r#"{"room": {"room_id": "9999", "show_status": 2, "isVertical": 0}}"#,
+        )
+        .unwrap();
+        let detail = DouyuRoomDetail::from_betard(json).unwrap();
+        assert_eq!(detail.room_id, "9999");
+        assert!(!detail.is_live);
+        assert!(!detail.is_vertical);
+
+        let json: BetardResponse = serde_json::from_str(r#"{"room": {"room_id": 1}}"#).unwrap();
+        assert!(!DouyuRoomDetail::from_betard(json).unwrap().is_vertical);
+        let json: BetardResponse = serde_json::from_str(r#"{}"#).unwrap();
+        assert!(DouyuRoomDetail::from_betard(json).is_err());
+    }
+
+    #[test]
+    fn vertical_rooms_send_web_player_params() {
+        let horizontal = h5_play_payload("v=1&sign=abc", "hw-h5", 0, false);
+        assert_eq!(horizontal, "v=1&sign=abc&cdn=hw-h5&rate=0");
+        let vertical = h5_play_payload("v=1&sign=abc", "hw-h5", 0, true);
+        assert!(vertical.starts_with("v=1&sign=abc&cdn=hw-h5&rate=0&"));
+        assert!(vertical.contains("&iar=1&ive=1"));
+    }
+
+    #[test]
+    fn parses_mobile_stream_url() {
+        let json = serde_json::json!({
+            "code": 0,
+            "msg": "ok",
+            "data": {
+                "url": "https://hls3a-akm.douyucdn.cn/live/5720533rkpl_2000.m3u8?txSecret=abc&amp;txTime=65a0"
+            }
+        });
+        assert_eq!(
+            parse_mobile_play_url(&json).unwrap(),
+            "https://hls3a-akm.douyucdn.cn/live/5720533rkpl_2000.m3u8?txSecret=abc&txTime=65a0"
+        );
+
+        let json = serde_json::json!({"code": 2, "msg": "房间未开播"});
+        let err = parse_mobile_play_url(&json).unwrap_err().to_string();
+        assert!(err.contains("房间未开播"));
+        let json = serde_json::json!({"code": 0, "data": {"url": ""}});
+        assert!(parse_mobile_play_url(&json).is_err());
+    }
+
+    #[test]
+    fn normalize_room_id_from_each_url_shape() {
+        let cases = [
+            ("  9999 ", "9999"),
+            ("douyu.com/9999", "9999"),
+            ("https://www.douyu.com/9999", "9999"),
+            ("https://www.douyu.com/9999?dyshid=abc", "9999"),
+            ("www.douyu.com/room/9999", "9999"),
+            ("https://m.douyu.com/9999", "9999"),
+            ("https://www.douyu.com/beta/9999", "9999"),
+            ("https://www.douyu.com/topic/lpl?rid=9999", "9999"),
+            (
+                "https://www.douyu.com/topic/lpl?rid=9999&dyshid=abc",
+                "9999",
+            ),
+            ("https://m.douyu.com/share?room_id=9999", "9999"),
+            // 靓号别名交给 betard 换算
+            ("https://www.douyu.com/yyfyyf", "yyfyyf"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                normalize_douyu_room_id(input).as_deref(),
+                Ok(expected),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn normalize_room_id_rejects_other_inputs() {
+        for input in [
+            "",
+            "   ",
+            "https://www.huya.com/9999",
+            "https://douyu.com.evil.com/9999",
+            "https://www.douyu.com/",
+            "https://www.douyu.com/topic/lpl",
+            "https://www.douyu.com/topic/lpl?rid=abc",
+            "https://www.douyu.com/room/99-99",
+        ] {
+            assert!(normalize_douyu_room_id(input).is_err(), "{}", input);
+        }
+    }
+
+    // 虚构的 getH5Play（rate=-1）响应：房间提供四档码率
+    const MULTI_RATE_H5_PLAY: &str = r#"{
+        "error": 0,
+        "msg": "ok",
+        "data": {
+            "room_id": 9999,
+            "rate": 0,
+            "cdnsWithName": [
+                {"name": "备用线路5", "cdn": "scdnctshh"},
+                {"name": "主线路", "cdn": "tct-h5"},
+                {"name": "备用线路2", "cdn": "hw-h5"}
+            ],
+            "multirates": [
+                {"name": "原画", "rate": 0, "highBit": 1, "bit": 10000},
+                {"name": "蓝光4M", "rate": 4, "highBit": 0, "bit": 4000},
+                {"name": "超清", "rate": 3, "highBit": 0, "bit": 2000},
+                {"name": "高清", "rate": 2, "highBit": 0, "bit": "900"}
+            ]
+        }
+    }"#;
+
+    fn multi_rate_play_info() -> DouyuPlayInfo {
+        DouyuPlayInfo::from_h5_play(&serde_json::from_str(MULTI_RATE_H5_PLAY).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parses_rates_and_cdns_from_h5_play() {
+        let info = multi_rate_play_info();
+        let rates: Vec<(&str, i32, Option<i32>)> = info
+            .variants
+            .iter()
+            .map(|v| (v.name.as_str(), v.rate, v.bit))
+            .collect();
+        assert_eq!(
+            rates,
+            vec![
+                ("原画", 0, Some(10000)),
+                ("蓝光4M", 4, Some(4000)),
+                ("超清", 3, Some(2000)),
+                ("高清", 2, Some(900)),
+            ]
+        );
+        // scdn 线路排在最后
+        assert_eq!(info.cdns, ["hw-h5", "tct-h5", "scdnctshh"]);
+
+        let err = DouyuPlayInfo::from_h5_play(&serde_json::json!({"error": -5, "msg": "签名失败"}))
+            .unwrap_err();
+        assert_eq!(err, "getH5Play error -5: 签名失败");
+    }
+
+    #[test]
+    fn numeric_rate_selects_exactly_that_rate() {
+        let info = multi_rate_play_info();
+        assert_eq!(DouYu::select_rate("原画", Some(2), &info.variants), Ok(2));
+        assert_eq!(DouYu::select_rate("", Some(4), &info.variants), Ok(4));
+        let err = DouYu::select_rate("原画", Some(8), &info.variants).unwrap_err();
+        assert_eq!(
+            err,
+            "房间不提供码率 8（可用: 0=原画, 4=蓝光4M, 3=超清, 2=高清）"
+        );
+    }
+
+    #[test]
+    fn named_quality_maps_onto_the_offered_rates() {
+        let info = multi_rate_play_info();
+        let select = |quality: &str| DouYu::select_rate(quality, None, &info.variants);
+        assert_eq!(select("原画"), Ok(0));
+        assert_eq!(select("高清"), Ok(4));
+        assert_eq!(select("标清"), Ok(3));
+        // 档位名精确匹配；通用档位名不匹配同名的斗鱼档位
+        assert_eq!(select("蓝光4M"), Ok(4));
+        assert_eq!(select("超清"), Ok(3));
+        assert_ne!(select("高清"), Ok(2));
+        // 无法识别时退回最高 rate
+        assert_eq!(select("杜比"), Ok(4));
+        assert_eq!(DouYu::select_rate("原画", None, &[]), Ok(0));
+    }
+
+    #[test]
+    fn available_streams_list_each_rate_with_its_bitrate() {
+        let info = multi_rate_play_info();
+        let url = "https://hw-h5.douyucdn.cn/live/9999_4000.flv";
+        let variants = DouYu::rate_variants(&info.variants, 4, url);
+        let listed: Vec<(Option<i32>, Option<&str>, Option<u32>, &str)> = variants
+            .iter()
+            .map(|v| (v.qn, v.desc.as_deref(), v.bitrate_kbps, v.url.as_str()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                (Some(0), Some("原画 (10000kbps)"), Some(10000), ""),
+                (Some(4), Some("蓝光4M (4000kbps)"), Some(4000), url),
+                (Some(3), Some("超清 (2000kbps)"), Some(2000), ""),
+                (Some(2), Some("高清 (900kbps)"), Some(900), ""),
+            ]
+        );
+    }
 }
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::{NotificationExt, PermissionState};
+
+use crate::platforms::common::events::{emit_event, DtvEvent};
+use crate::platforms::common::{FollowHttpClient, SupportedPlatformRust};
+use crate::platforms::live_status::fetch_live_status;
+
+// 关注列表保存在前端，启动轮询时由前端传入；列表变化后再次调用 start_follow_polling 即可
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FollowedRoom {
+    pub platform: String,
+    pub room_id: String,
+    // 主播昵称，用于开播通知的标题
+    #[serde(default)]
+    pub name: Option<String>,
+    // 单个关注的开播通知开关，未传时默认开启
+    #[serde(default)]
+    pub notify: Option<bool>,
+}
+
+// notified 表示已弹出系统通知；桌面端系统通知不支持按钮回调，
+// 前端收到 is_live 且 notified 的事件后可在应用内提示“打开”并调用 open_room
+#[derive(Serialize, Clone, Debug)]
+struct FollowStatusChangedPayload {
+    platform: String,
+    room_id: String,
+    name: Option<String>,
+    is_live: bool,
+    notified: bool,
+}
+
+// 开播通知总开关（默认关闭），由 set_follow_notifications 设置
+static NOTIFICATIONS_ENABLED: AtomicBool = AtomicBool::new(false);
+// 同一房间两次通知的最小间隔：状态抖动（开播/下播反复切换）时不重复提醒
+const NOTIFY_DEBOUNCE: Duration = Duration::from_secs(600);
+static LAST_NOTIFIED: Lazy<Mutex<HashMap<String, Instant>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+// 系统拒绝通知权限时只提示一次
+static PERMISSION_DENIED_LOGGED: AtomicBool = AtomicBool::new(false);
+
+#[tauri::command]
+pub fn set_follow_notifications(enabled: bool) {
+    NOTIFICATIONS_ENABLED.store(enabled, Ordering::Relaxed);
+    println!("[Follow Polling] live notifications enabled: {}", enabled);
+}
+
+fn notification_permitted(app_handle: &AppHandle) -> bool {
+    let notification = app_handle.notification();
+    let state = match notification.permission_state() {
+        Ok(PermissionState::Prompt) | Ok(PermissionState::PromptWithRationale) => {
+            notification.request_permission()
+        }
+        other => other,
+    };
+    match state {
+        Ok(PermissionState::Granted) => true,
+        Ok(state) => {
+            if !PERMISSION_DENIED_LOGGED.swap(true, Ordering::Relaxed) {
+                eprintln!(
+                    "[Follow Polling] notification permission not granted: {:?}",
+                    state
+                );
+            }
+            false
+        }
+        Err(e) => {
+            eprintln!(
+                "[Follow Polling] failed to query notification permission: {}",
+                e
+            );
+            false
+        }
+    }
+}
+
+// 开播时弹出系统通知；返回是否实际发出。总开关/单个关注关闭、防抖期内或无权限时跳过
+fn notify_went_live(
+    app_handle: &AppHandle,
+    platform: &SupportedPlatformRust,
+    room: &FollowedRoom,
+) -> bool {
+    if !NOTIFICATIONS_ENABLED.load(Ordering::Relaxed) || room.notify == Some(false) {
+        return false;
+    }
+    let key = format!("{}:{}", platform.as_str(), room.room_id);
+    {
+        let mut last_notified = LAST_NOTIFIED.lock().unwrap_or_else(|e| e.into_inner());
+        if last_notified
+            .get(&key)
+            .is_some_and(|at| at.elapsed() < NOTIFY_DEBOUNCE)
+        {
+            return false;
+        }
+        last_notified.insert(key, Instant::now());
+    }
+    if !notification_permitted(app_handle) {
+        return false;
+    }
+    let name = room.name.as_deref().unwrap_or(&room.room_id);
+    let result = app_handle
+        .notification()
+        .builder()
+        .title(format!("{} 开播了", name))
+        .body(format!(
+            "{} 房间 {}，打开 DTV 观看",
+            platform.as_str(),
+            room.room_id
+        ))
+        .show();
+    if let Err(e) = result {
+        eprintln!(
+            "[Follow Polling] failed to show notification for {}: {}",
+            name, e
+        );
+        return false;
+    }
+    true
+}
+
+// 每次启动/停止轮询递增，旧的轮询任务据此退出
+static FOLLOW_POLLING_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// 间隔下限，避免过于频繁的请求触发平台风控
+const MIN_POLL_INTERVAL_SECS: u64 = 30;
+// 同时进行的开播查询数
+const MAX_CONCURRENT_CHECKS: usize = 4;
+
+async fn poll_once(
+    app_handle: &AppHandle,
+    rooms: &[(SupportedPlatformRust, FollowedRoom)],
+) -> Vec<(usize, bool)> {
+    let follow_http = app_handle.state::<FollowHttpClient>();
+    let follow_http = follow_http.inner();
+    futures_util::stream::iter(rooms.iter().enumerate())
+        .map(|(index, (platform, room))| async move {
+            let room_id = &room.room_id;
+            match fetch_live_status(platform, room_id, follow_http).await {
+                Ok(is_live) => Some((index, is_live)),
+                Err(e) => {
+                    eprintln!(
+                        "[Follow Polling] Failed to check {} room {}: {}",
+                        platform.as_str(),
+                        room_id,
+                        e
+                    );
+                    None
+                }
+            }
+        })
+        .buffer_unordered(MAX_CONCURRENT_CHECKS)
+        .filter_map(|result| async move { result })
+        .collect()
+        .await
+}
+
+// 后台定时检查关注房间的开播状态，只在状态发生变化时发出 follow-status-changed 事件；
+// 首轮只记录状态不发事件。查询失败的房间保持上一次的状态
+#[tauri::command]
+pub fn start_follow_polling(
+    app_handle: AppHandle,
+    interval_secs: u64,
+    rooms: Vec<FollowedRoom>,
+) -> Result<(), String> {
+    let rooms: Vec<(SupportedPlatformRust, FollowedRoom)> = rooms
+        .into_iter()
+        .map(|room| Ok((SupportedPlatformRust::parse(&room.platform)?, room)))
+        .collect::<Result<_, String>>()?;
+    let interval = Duration::from_secs(interval_secs.max(MIN_POLL_INTERVAL_SECS));
+    let generation = FOLLOW_POLLING_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    println!(
+        "[Follow Polling] Started for {} rooms every {}s",
+        rooms.len(),
+        interval.as_secs()
+    );
+
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        let mut last_status: HashMap<usize, bool> = HashMap::new();
+        loop {
+            ticker.tick().await;
+            if FOLLOW_POLLING_GENERATION.load(Ordering::SeqCst) != generation {
+                break;
+            }
+            let results = poll_once(&app_handle, &rooms).await;
+            // 查询期间被停止或重启：丢弃这一轮结果
+            if FOLLOW_POLLING_GENERATION.load(Ordering::SeqCst) != generation {
+                break;
+            }
+            for (index, is_live) in results {
+                let previous = last_status.insert(index, is_live);
+                if previous.is_none() || previous == Some(is_live) {
+                    continue;
+                }
+                let (platform, room) = &rooms[index];
+                println!(
+                    "[Follow Polling] {} room {} is now {}",
+                    platform.as_str(),
+                    room.room_id,
+                    if is_live { "live" } else { "offline" }
+                );
+                let notified = is_live && notify_went_live(&app_handle, platform, room);
+                emit_event(
+                    &app_handle,
+                    DtvEvent::FollowStatusChanged(DtvEvent::json(FollowStatusChangedPayload {
+                        platform: platform.as_str().to_string(),
+                        room_id: room.room_id.clone(),
+                        name: room.name.clone(),
+                        is_live,
+                        notified,
+                    })),
+                );
+            }
+        }
+        println!("[Follow Polling] Poller generation {} stopped", generation);
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_follow_polling() {
+    FOLLOW_POLLING_GENERATION.fetch_add(1, Ordering::SeqCst);
+    println!("[Follow Polling] Stopped");
+}
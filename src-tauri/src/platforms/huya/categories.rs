@@ -0,0 +1,152 @@
+use once_cell::sync::Lazy;
+use reqwest::header::{REFERER, USER_AGENT};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::platforms::common::http_client::HttpClient;
+use crate::platforms::common::json_value::scalar_string;
+use crate::platforms::common::ttl_cache::TtlCache;
+use crate::platforms::common::types_rust::{
+    CommonCategoryGroupRust, CommonPlatformCategoryRust, SupportedPlatformRust,
+};
+use crate::platforms::common::user_agent::user_agent_for;
+
+const GAME_LIST_URL: &str = "https://live.cdn.huya.com/liveconfig/game/bussLive";
+// 虎牙的四个一级分类（bussType）
+const BUSS_TYPES: [(&str, &str); 4] = [
+    ("1", "网游竞技"),
+    ("2", "单机热游"),
+    ("3", "手游休闲"),
+    ("8", "娱乐天地"),
+];
+// 分类列表很少变动，缓存几分钟即可
+const CATEGORY_CACHE_TTL_SECONDS: u64 = 300;
+
+static CATEGORY_CACHE: Lazy<TtlCache<Vec<CommonCategoryGroupRust>>> =
+    Lazy::new(|| TtlCache::new(Duration::from_secs(CATEGORY_CACHE_TTL_SECONDS)));
+
+// gid 有时是数字，有时是 {"value": "1,xxx"} 形式，取第一个值
+fn game_id(value: Option<&Value>) -> Option<String> {
+    let id = match value? {
+        Value::Object(obj) => scalar_string(obj.get("value")?)?
+            .split(',')
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        other => scalar_string(other)?,
+    };
+    Some(id.trim().to_string()).filter(|id| !id.is_empty() && id != "0")
+}
+
+fn parse_games(json: &Value, group_id: &str) -> Vec<CommonPlatformCategoryRust> {
+    let Some(games) = json.get("data").and_then(|d| d.as_array()) else {
+        return Vec::new();
+    };
+    games
+        .iter()
+        .filter_map(|game| {
+            let id = game_id(game.get("gid"))?;
+            Some(CommonPlatformCategoryRust {
+                icon_url: Some(format!(
+                    "https://huyaimg.msstatic.com/cdnimage/game/{}-MS.jpg",
+                    id
+                )),
+                name: game
+                    .get("gameFullName")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                id,
+                platform: SupportedPlatformRust::Huya,
+                parent_id: Some(group_id.to_string()),
+            })
+        })
+        .collect()
+}
+
+// 虎牙分类树（一级分类 -> 游戏/品类），子分类的 id 即 fetch_huya_live_list 的 i_gid
+#[tauri::command]
+pub async fn fetch_huya_categories() -> Result<Vec<CommonCategoryGroupRust>, String> {
+    if let Some(cached) = CATEGORY_CACHE.get("categories") {
+        return Ok(cached);
+    }
+
+    let mut http_client =
+        HttpClient::new().map_err(|e| format!("Failed to create HttpClient: {}", e))?;
+    http_client.insert_header(USER_AGENT, &user_agent_for(&SupportedPlatformRust::Huya))?;
+    http_client.insert_header(REFERER, "https://www.huya.com/")?;
+
+    let mut groups = Vec::with_capacity(BUSS_TYPES.len());
+    for (buss_type, group_name) in BUSS_TYPES {
+        let url = format!("{}?bussType={}", GAME_LIST_URL, buss_type);
+        let json: Value = http_client.get_json(&url).await?;
+        let status = json.get("status").and_then(|s| s.as_i64()).unwrap_or(-1);
+        if status != 200 {
+            return Err(format!(
+                "Huya category API error for bussType {}. Status: {}",
+                buss_type, status
+            ));
+        }
+        groups.push(CommonCategoryGroupRust {
+            group_id: buss_type.to_string(),
+            group_name: group_name.to_string(),
+            platform: SupportedPlatformRust::Huya,
+            categories: parse_games(&json, buss_type),
+        });
+    }
+    println!(
+        "[Huya] Fetched {} categories in {} groups",
+        groups.iter().map(|g| g.categories.len()).sum::<usize>(),
+        groups.len()
+    );
+    CATEGORY_CACHE.insert("categories".to_string(), groups.clone());
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_id_accepts_numbers_strings_and_value_objects() {
+        let json = serde_json::json!([1, "2168", {"value": "1663,0"}, {"value": 393}, 0, ""]);
+        let ids: Vec<Option<String>> = (0..6).map(|i| game_id(json.get(i))).collect();
+        assert_eq!(
+            ids,
+            vec![
+                Some("1".to_string()),
+                Some("2168".to_string()),
+                Some("1663".to_string()),
+                Some("393".to_string()),
+                None,
+                None
+            ]
+        );
+        assert_eq!(game_id(None), None);
+    }
+
+    // bussLive 响应：gid 的对象写法与 0 值各有一条
+    #[test]
+    fn parses_games_under_their_group() {
+        let json = serde_json::json!({
+            "status": 200,
+            "data": [
+                {"gid": {"value": "1,0"}, "gameFullName": "英雄联盟"},
+                {"gid": 0, "gameFullName": "无效品类"},
+                {"gid": "2336", "gameFullName": "王者荣耀"}
+            ]
+        });
+        let games = parse_games(&json, "1");
+        assert_eq!(games.len(), 2);
+        assert_eq!(
+            (games[0].id.as_str(), games[0].name.as_str()),
+            ("1", "英雄联盟")
+        );
+        assert_eq!(
+            games[1].icon_url.as_deref(),
+            Some("https://huyaimg.msstatic.com/cdnimage/game/2336-MS.jpg")
+        );
+        assert_eq!(games[1].parent_id.as_deref(), Some("1"));
+        assert!(parse_games(&serde_json::json!({"status": 500}), "1").is_empty());
+    }
+}
@@ -1,11 +1,19 @@
 use futures_util::{SinkExt, StreamExt};
 use log::info;
-use tars_stream::prelude::*;
-use tauri::Emitter;
+use tauri::Manager;
 use tokio::sync::mpsc as tokio_mpsc;
 use tokio::time::{sleep, Duration};
 use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
 
+use crate::platforms::common::danmaku_reconnect;
+use crate::platforms::common::danmaku_subscribers;
+use crate::platforms::common::danmaku_subscribers::emit_to_subscribers;
+use crate::platforms::common::events::DtvEvent;
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::text_decode::read_text;
+use crate::platforms::common::SupportedPlatformRust;
+use crate::platforms::huya::tars::{self, HuyaMessage};
+
 const WS_URL: &str = "wss://cdnws.api.huya.com";
 // 恢复 HEARTBEAT 常量（被误删），供心跳发送使用
 const HEARTBEAT: &'static [u8] = b"\x00\x03\x1d\x00\x00\x69\x00\x00\x00\x69\x10\x03\x2c\x3c\x4c\x56\x08\x6f\x6e\x6c\x69\x6e\x65\x75\x69\x66\x0f\x4f\x6e\x55\x73\x65\x72\x48\x65\x61\x72\x74\x42\x65\x61\x74\x7d\x00\x00\x3c\x08\x00\x01\x06\x04\x74\x52\x65\x71\x1d\x00\x00\x2f\x0a\x0a\x0c\x16\x00\x26\x00\x36\x07\x61\x64\x72\x5f\x77\x61\x70\x46\x00\x0b\x12\x03\xae\xf0\x0f\x22\x03\xae\xf0\x0f\x3c\x42\x6d\x52\x02\x60\x5c\x60\x01\x7c\x82\x00\x0b\xb0\x1f\x9c\xac\x0b\x8c\x98\x0c\xa8\x0c";
@@ -13,14 +21,14 @@ const HEARTBEAT: &'static [u8] = b"\x00\x03\x1d\x00\x00\x69\x00\x00\x00\x69\x10\
 #[allow(dead_code)]
 const HEARTBEAT_BASE64: &str = "ABQdAAwsNgBM"; // same as Python
 
-// Minimal JCE/TARS codec for required Huya structures
-
-async fn fetch_huya_ids(room_id: &str) -> Result<(i64, i64), String> {
+// 返回 (yyid, topSid, subSid)
+async fn fetch_huya_ids(room_id: &str) -> Result<(i64, i64, i64), String> {
     let url = format!(
         "https://mp.huya.com/cache.php?m=Live&do=profileRoom&roomid={}&showSecret=1",
         room_id
     );
-    let client = reqwest::Client::builder()
+    let client = crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
         .build()
         .map_err(|e| e.to_string())?;
     let resp = client
@@ -30,9 +38,19 @@ async fn fetch_huya_ids(room_id: &str) -> Result<(i64, i64), String> {
         .header("Origin", "https://www.huya.com")
         .header("Referer", "https://www.huya.com/")
         .send().await.map_err(|e| e.to_string())?;
-    let text = resp.text().await.map_err(|e| e.to_string())?;
+    let text = read_text(resp).await.map_err(|e| e.to_string())?;
     let v: serde_json::Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    let (ayyuid, top_sid, sub_sid) = parse_profile_room_ids(&v)?;
+
+    println!(
+        "[Huya Danmaku] fetch_huya_ids: room_id={} yyid={} topSid={}",
+        room_id, ayyuid, top_sid
+    );
+    Ok((ayyuid, top_sid, sub_sid))
+}
 
+// profileRoom 响应中的 (yyid, topSid, subSid)；未开播时没有频道信息
+fn parse_profile_room_ids(v: &serde_json::Value) -> Result<(i64, i64, i64), String> {
     let status = v.get("status").and_then(|x| x.as_i64()).unwrap_or(0);
     if status != 200 {
         return Err("房间未开播或无流信息，无法获取弹幕参数".to_string());
@@ -60,42 +78,64 @@ async fn fetch_huya_ids(room_id: &str) -> Result<(i64, i64), String> {
     } else {
         0
     };
+    let sub_sid = base_list
+        .get(0)
+        .and_then(|first| first.get("lSubChannelId"))
+        .and_then(|x| x.as_i64())
+        .unwrap_or(0);
 
     if top_sid == 0 {
         return Err("未找到频道ID，房间可能未开播".to_string());
     }
-
-    println!(
-        "[Huya Danmaku] fetch_huya_ids: room_id={} yyid={} topSid={}",
-        room_id, ayyuid, top_sid
-    );
-    Ok((ayyuid, top_sid))
+    Ok((ayyuid, top_sid, sub_sid))
 }
 
+// 加入弹幕所需的全部参数均在 Rust 侧解析完成，前端无需再拼装；
+// register_payload 与监听器实际发送的注册包由同一函数生成
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct HuyaJoinParams {
+    pub ws_url: String,
+    pub ayyuid: String,
+    // tid/sid 即频道 topSid/subSid；匿名观看时 uid 为 0
+    pub tid: i64,
+    pub sid: i64,
+    pub uid: i64,
+    pub topics: Vec<String>,
+    // base64 编码的 WS 注册包
+    pub register_payload: String,
+    // 兼容旧字段
     pub yyid: i64,
     pub top_sid: i64,
 }
 
 #[tauri::command]
 pub async fn fetch_huya_join_params(room_id: String) -> Result<HuyaJoinParams, String> {
-    match fetch_huya_ids(&room_id).await {
-        Ok((ayyuid, top_sid)) => Ok(HuyaJoinParams {
-            yyid: ayyuid,
-            top_sid,
-        }),
-        Err(e) => Err(e),
-    }
+    use base64::{engine::general_purpose, Engine as _};
+
+    let room_id = validate_room_id(&SupportedPlatformRust::Huya, &room_id)?;
+    let (yyid, top_sid, sub_sid) = fetch_huya_ids(&room_id).await?;
+    let (_, ayyuid) = resolve_huya_ayyuid(&room_id).await?;
+    let register_payload = build_register_payload(&ayyuid)?;
+    Ok(HuyaJoinParams {
+        ws_url: WS_URL.to_string(),
+        topics: join_topics(&ayyuid),
+        ayyuid,
+        tid: top_sid,
+        sid: sub_sid,
+        uid: 0,
+        register_payload: general_purpose::STANDARD.encode(register_payload),
+        yyid,
+        top_sid,
+    })
 }
 
 #[tauri::command]
 pub async fn start_huya_danmaku_listener(
     payload: crate::platforms::common::GetStreamUrlPayload,
-    app_handle: tauri::AppHandle,
+    window: tauri::Window,
     state: tauri::State<'_, crate::platforms::common::HuyaDanmakuState>,
 ) -> Result<(), String> {
-    let room_id_or_url = payload.args.room_id_str.clone();
+    let room_id_or_url = validate_room_id(&SupportedPlatformRust::Huya, &payload.args.room_id_str)?;
     println!(
         "[Huya Danmaku] start listener room_id_or_url={}",
         room_id_or_url
@@ -105,10 +145,28 @@ pub async fn start_huya_danmaku_listener(
         room_id_or_url
     );
 
-    // 停止已有监听
+    // 同一房间已有存活连接时只登记当前窗口（多窗口共享一条连接）
+    let upstream_alive = state
+        .inner()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&room_id_or_url)
+        .map(|tx| !tx.is_closed())
+        .unwrap_or(false);
+    if danmaku_subscribers::subscribe("huya", &room_id_or_url, &window, upstream_alive) {
+        println!(
+            "[Huya Danmaku] window {} joined existing listener room_id={}",
+            window.label(),
+            room_id_or_url
+        );
+        return Ok(());
+    }
+
+    // 该房间之前的连接已退出，清理残留的停止信号
     let previous_tx = {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.take()
+        let mut lock = state.inner().0.lock().unwrap_or_else(|e| e.into_inner());
+        lock.remove(&room_id_or_url)
     };
     if let Some(tx) = previous_tx {
         if tx.send(()).await.is_err() {
@@ -119,11 +177,11 @@ pub async fn start_huya_danmaku_listener(
     // 创建新的关闭通道并保存到 State
     let (tx_shutdown, mut rx_shutdown) = tokio_mpsc::channel::<()>(1);
     {
-        let mut lock = state.inner().0.lock().unwrap();
-        *lock = Some(tx_shutdown);
+        let mut lock = state.inner().0.lock().unwrap_or_else(|e| e.into_inner());
+        lock.insert(room_id_or_url.clone(), tx_shutdown);
     }
 
-    let app_handle_clone = app_handle.clone();
+    let window_clone = window.clone();
     let room_id_clone = room_id_or_url.clone();
 
     tokio::spawn(async move {
@@ -135,171 +193,218 @@ pub async fn start_huya_danmaku_listener(
             "[Huya Danmaku] spawned worker for room_id={}",
             room_id_clone
         );
-        // 1) 获取 ws 与注册数据（与根目录 huya.rs 同步）
-        let (ws_url, reg_data) = match get_ws_info_tars(&room_id_clone).await {
-            Ok(v) => v,
-            Err(e) => {
-                let _ = app_handle_clone.emit(
-                    "danmaku-message",
-                    crate::platforms::common::DanmakuFrontendPayload {
+        danmaku_reconnect::reset("huya", &room_id_clone);
+        loop {
+            let error =
+                match run_huya_connection(&room_id_clone, &window_clone, &mut rx_shutdown).await {
+                    Ok(true) => break,
+                    Ok(false) => "websocket closed".to_string(),
+                    Err(e) => e,
+                };
+            let Some(delay) = danmaku_reconnect::next_retry_delay("huya", &room_id_clone) else {
+                emit_to_subscribers(
+                    "huya",
+                    &room_id_clone,
+                    &window_clone,
+                    DtvEvent::DanmakuMessage(crate::platforms::common::DanmakuFrontendPayload {
+                        platform: "huya".to_string(),
                         room_id: room_id_clone.clone(),
                         user: "系统".to_string(),
-                        content: format!("Huya房间信息获取失败: {}", e),
+                        content: error.clone(),
                         user_level: 0,
                         fans_club_level: 0,
-                    },
+                        ..crate::platforms::common::DanmakuFrontendPayload::received(None)
+                    }),
                 );
-                return;
+                danmaku_reconnect::give_up(
+                    window_clone.app_handle(),
+                    "huya",
+                    &room_id_clone,
+                    error,
+                );
+                break;
+            };
+            eprintln!("[Huya Danmaku] {}, reconnecting in {:?}", error, delay);
+            tokio::select! {
+                _ = sleep(delay) => {}
+                _ = rx_shutdown.recv() => break,
             }
-        };
+        }
+    });
 
-        println!(
-            "[Huya Danmaku] ws_url={} reg_len={}",
-            ws_url,
-            reg_data.len()
-        );
-        info!(
-            "[Huya Danmaku] ws_url={} reg_len={}",
-            ws_url,
-            reg_data.len()
-        );
+    Ok(())
+}
 
-        // 2) 连接 WebSocket
-        println!("[Huya Danmaku] connecting to {}", ws_url);
-        info!("[Huya Danmaku] connecting to {}", ws_url);
-        let (ws_stream, _) = match connect_async(&ws_url).await {
-            Ok(v) => v,
-            Err(e) => {
-                let _ = app_handle_clone.emit(
-                    "danmaku-message",
-                    crate::platforms::common::DanmakuFrontendPayload {
-                        room_id: room_id_clone.clone(),
-                        user: "系统".to_string(),
-                        content: format!("Huya弹幕连接失败: {}", e),
-                        user_level: 0,
-                        fans_club_level: 0,
-                    },
-                );
-                return;
-            }
-        };
-
-        let (mut ws_write, mut ws_read) = ws_stream.split();
-        if let Err(e) = ws_write.send(WsMessage::Binary(reg_data)).await {
-            let _ = app_handle_clone.emit(
-                "danmaku-message",
-                crate::platforms::common::DanmakuFrontendPayload {
-                    room_id: room_id_clone.clone(),
-                    user: "系统".to_string(),
-                    content: format!("Huya注册数据发送失败: {}", e),
-                    user_level: 0,
-                    fans_club_level: 0,
-                },
-            );
-            return;
+// 建立一次弹幕连接并处理消息；收到停止信号返回 true，连接被关闭返回 false
+async fn run_huya_connection(
+    room_id: &str,
+    window: &tauri::Window,
+    rx_shutdown: &mut tokio_mpsc::Receiver<()>,
+) -> Result<bool, String> {
+    let room_id_clone = room_id.to_string();
+    let window_clone = window.clone();
+
+    // 1) 获取 ws 与注册数据（与根目录 huya.rs 同步）
+    let (ws_url, reg_data) = get_ws_info_tars(&room_id_clone)
+        .await
+        .map_err(|e| format!("Huya房间信息获取失败: {}", e))?;
+
+    println!(
+        "[Huya Danmaku] ws_url={} reg_len={}",
+        ws_url,
+        reg_data.len()
+    );
+    info!(
+        "[Huya Danmaku] ws_url={} reg_len={}",
+        ws_url,
+        reg_data.len()
+    );
+
+    // 2) 连接 WebSocket
+    println!("[Huya Danmaku] connecting to {}", ws_url);
+    info!("[Huya Danmaku] connecting to {}", ws_url);
+    let (ws_stream, ws_response) = connect_async(&ws_url)
+        .await
+        .map_err(|e| format!("Huya弹幕连接失败: {}", e))?;
+
+    let (mut ws_write, mut ws_read) = ws_stream.split();
+    ws_write
+        .send(WsMessage::Binary(reg_data))
+        .await
+        .map_err(|e| format!("Huya注册数据发送失败: {}", e))?;
+    emit_to_subscribers(
+        "huya",
+        &room_id_clone,
+        &window_clone,
+        DtvEvent::DanmakuConnected(crate::platforms::common::DanmakuConnectedPayload::new(
+            "huya",
+            &room_id_clone,
+            &ws_url,
+            ws_response
+                .headers()
+                .get("Sec-WebSocket-Protocol")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string()),
+        )),
+    );
+    danmaku_reconnect::reset("huya", &room_id_clone);
+
+    // 3) 心跳与接收
+    let hb_task = async {
+        let mut hb_seq = 0usize;
+        while let Ok(_) = ws_write.send(WsMessage::Binary(HEARTBEAT.into())).await {
+            hb_seq += 1;
+            println!("[Huya Danmaku] heartbeat sent #{}", hb_seq);
+            info!("[Huya Danmaku] heartbeat sent #{}", hb_seq);
+            sleep(Duration::from_secs(20)).await;
         }
+        Err::<(), anyhow::Error>(anyhow::anyhow!("Huya心跳发送失败"))
+    };
 
-        // 3) 心跳与接收
-        let hb_task = async {
-            let mut hb_seq = 0usize;
-            while let Ok(_) = ws_write.send(WsMessage::Binary(HEARTBEAT.into())).await {
-                hb_seq += 1;
-                println!("[Huya Danmaku] heartbeat sent #{}", hb_seq);
-                info!("[Huya Danmaku] heartbeat sent #{}", hb_seq);
-                sleep(Duration::from_secs(20)).await;
-            }
-            Err::<(), anyhow::Error>(anyhow::anyhow!("Huya心跳发送失败"))
-        };
-
-        let recv_task = async {
-            while let Some(m) = ws_read.next().await {
-                let m = match m {
-                    Ok(x) => x,
-                    Err(e) => return Err(anyhow::anyhow!(e)),
-                };
-                match m {
-                    WsMessage::Binary(bin) => {
-                        let (top_cmd, nested_cmd) = peek_cmds(&bin);
-                        println!(
-                            "[Huya Danmaku] WS msg: len={} top_cmd={:?} nested_cmd={:?}",
-                            bin.len(),
-                            top_cmd,
-                            nested_cmd
-                        );
-                        info!(
-                            "[Huya Danmaku] WS msg: len={} top_cmd={:?} nested_cmd={:?}",
-                            bin.len(),
-                            top_cmd,
-                            nested_cmd
-                        );
-                        match decode_msg_tars(&bin)? {
-                            Some((nick, text)) => {
-                                println!("[Huya Danmaku] decoded chat: {} -> {}", nick, text);
-                                info!("[Huya Danmaku] decoded chat: {} -> {}", nick, text);
-                                let _ = app_handle_clone.emit(
-                                    "danmaku-message",
-                                    crate::platforms::common::DanmakuFrontendPayload {
-                                        room_id: room_id_clone.clone(),
-                                        user: nick,
-                                        content: text,
-                                        user_level: 0,
-                                        fans_club_level: 0,
-                                    },
-                                );
+    let recv_task = async {
+        while let Some(m) = ws_read.next().await {
+            let m = match m {
+                Ok(x) => x,
+                Err(e) => return Err(anyhow::anyhow!(e)),
+            };
+            match m {
+                WsMessage::Binary(bin) => {
+                    let (top_cmd, nested_cmd) = tars::peek_cmds(&bin);
+                    println!(
+                        "[Huya Danmaku] WS msg: len={} top_cmd={:?} nested_cmd={:?}",
+                        bin.len(),
+                        top_cmd,
+                        nested_cmd
+                    );
+                    info!(
+                        "[Huya Danmaku] WS msg: len={} top_cmd={:?} nested_cmd={:?}",
+                        bin.len(),
+                        top_cmd,
+                        nested_cmd
+                    );
+                    match decode_msg_tars(&bin)? {
+                        Some((nick, text)) => {
+                            println!("[Huya Danmaku] decoded chat: {} -> {}", nick, text);
+                            info!("[Huya Danmaku] decoded chat: {} -> {}", nick, text);
+                            let payload = crate::platforms::common::DanmakuFrontendPayload {
+                                platform: "huya".to_string(),
+                                room_id: room_id_clone.clone(),
+                                user: nick,
+                                content: text,
+                                user_level: 0,
+                                fans_club_level: 0,
+                                ..crate::platforms::common::DanmakuFrontendPayload::received(None)
+                            };
+                            let event = DtvEvent::DanmakuMessage(payload);
+                            if crate::platforms::common::danmaku_pause::admit(
+                                "huya",
+                                &room_id_clone,
+                                &event,
+                            ) {
+                                emit_to_subscribers("huya", &room_id_clone, &window_clone, event);
                             }
-                            None => {
-                                if top_cmd == Some(7) {
-                                    println!(
-                                        "[Huya Danmaku] non-chat or empty msg, nested={:?}",
-                                        nested_cmd
-                                    );
-                                    info!(
-                                        "[Huya Danmaku] non-chat or empty msg, nested={:?}",
-                                        nested_cmd
-                                    );
-                                }
+                        }
+                        None => {
+                            if top_cmd == Some(7) {
+                                println!(
+                                    "[Huya Danmaku] non-chat or empty msg, nested={:?}",
+                                    nested_cmd
+                                );
+                                info!(
+                                    "[Huya Danmaku] non-chat or empty msg, nested={:?}",
+                                    nested_cmd
+                                );
                             }
                         }
                     }
-                    other => {
-                        println!("[Huya Danmaku] non-binary ws message: {:?}", other);
-                        info!("[Huya Danmaku] non-binary ws message: {:?}", other);
-                    }
                 }
-            }
-            anyhow::Ok(())
-        };
-
-        tokio::select! {
-            _ = rx_shutdown.recv() => {
-                // 主动关闭
-            }
-            it = hb_task => {
-                if let Err(e) = it { eprintln!("[Huya Danmaku] {}", e); }
-            }
-            it = recv_task => {
-                if let Err(e) = it { eprintln!("[Huya Danmaku] 接收失败: {}", e); }
+                other => {
+                    println!("[Huya Danmaku] non-binary ws message: {:?}", other);
+                    info!("[Huya Danmaku] non-binary ws message: {:?}", other);
+                }
             }
         }
-    });
+        anyhow::Ok(())
+    };
 
-    Ok(())
+    tokio::select! {
+        _ = rx_shutdown.recv() => Ok(true),
+        it = hb_task => {
+            it.map_err(|e| e.to_string())?;
+            Ok(false)
+        }
+        it = recv_task => {
+            it.map_err(|e| format!("接收失败: {}", e))?;
+            Ok(false)
+        }
+    }
 }
 
 #[tauri::command]
 pub async fn stop_huya_danmaku_listener(
     room_id: String,
+    window: tauri::Window,
     state: tauri::State<'_, crate::platforms::common::HuyaDanmakuState>,
 ) -> Result<(), String> {
     println!(
         "[Huya Danmaku] stop_huya_danmaku_listener called for room_id={}",
         room_id
     );
+    let room_id = validate_room_id(&SupportedPlatformRust::Huya, &room_id)?;
+
+    // 还有其他窗口订阅时保留连接
+    if !danmaku_subscribers::unsubscribe("huya", Some(&room_id), Some(window.label())) {
+        println!(
+            "[Huya Danmaku] 其他窗口仍在订阅 room_id={}，保留监听",
+            room_id
+        );
+        return Ok(());
+    }
 
     // 取出当前监听的停止信号发送器
     let tx = {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.take()
+        let mut lock = state.inner().0.lock().unwrap_or_else(|e| e.into_inner());
+        lock.remove(&room_id)
     };
 
     if let Some(tx) = tx {
@@ -315,54 +420,6 @@ pub async fn stop_huya_danmaku_listener(
     Ok(())
 }
 
-// 采用 tars_stream 的实现（参考 all_in_one.rs），保留 Tauri 命令，对旧 jce 逻辑停用
-
-struct HuyaUser {
-    _uid: i64,
-    _imid: i64,
-    name: String,
-    _gender: i32,
-}
-
-struct HuyaDanmakuFmt {
-    color: i32,
-}
-
-impl StructFromTars for HuyaUser {
-    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
-        let uid = decoder.read_int64(0, false, -1)?;
-        let imid = decoder.read_int64(1, false, -1)?;
-        let name = decoder.read_string(2, false, "".to_string())?;
-        let gender = decoder.read_int32(3, false, -1)?;
-        Ok(HuyaUser {
-            _uid: uid,
-            _imid: imid,
-            name,
-            _gender: gender,
-        })
-    }
-}
-
-impl StructFromTars for HuyaDanmakuFmt {
-    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
-        let color = decoder.read_int32(0, false, 16777215)?;
-        Ok(HuyaDanmakuFmt { color })
-    }
-}
-
-fn peek_cmds(data: &[u8]) -> (Option<i32>, Option<i64>) {
-    let mut ios = TarsDecoder::from(data);
-    let top_cmd = ios.read_int32(0, false, -1).ok();
-    let nested_cmd = ios
-        .read_bytes(1, false, Default::default())
-        .ok()
-        .and_then(|b1| {
-            let mut inner = TarsDecoder::from(b1.as_ref());
-            inner.read_int32(1, false, -1).ok().map(|v| v as i64)
-        });
-    (top_cmd, nested_cmd)
-}
-
 fn find_uid_in_json(v: &serde_json::Value) -> Option<String> {
     match v {
         serde_json::Value::Object(map) => {
@@ -402,40 +459,35 @@ fn gen_ua() -> String {
 }
 
 async fn get_ws_info_tars(room_id_or_url: &str) -> Result<(String, Vec<u8>), String> {
-    let url = if room_id_or_url.starts_with("http") {
-        reqwest::Url::parse(room_id_or_url).map_err(|e| e.to_string())?
-    } else {
-        reqwest::Url::parse(&format!("https://www.huya.com/{}", room_id_or_url))
-            .map_err(|e| e.to_string())?
-    };
-    let rid = url
-        .path_segments()
-        .and_then(|s| s.last())
-        .ok_or_else(|| "房间ID解析失败".to_string())?;
-    println!("[Huya Danmaku] get_ws_info_tars rid={}", rid);
-    info!("[Huya Danmaku] get_ws_info_tars rid={}", rid);
+    let (_, ayyuid) = resolve_huya_ayyuid(room_id_or_url).await?;
+    let payload = build_register_payload(&ayyuid)?;
+    Ok((WS_URL.to_owned(), payload))
+}
 
-    let client = reqwest::Client::builder()
-        .build()
-        .map_err(|e| e.to_string())?;
-    let resp_text = client
-        .get(format!("https://www.huya.com/{}", rid))
-        .header("User-Agent", gen_ua())
-        .header("Referer", "https://www.huya.com/")
-        .send()
-        .await
-        .map_err(|e| e.to_string())?
-        .text()
-        .await
-        .map_err(|e| e.to_string())?;
-    println!("[Huya Danmaku] fetched room page len={}", resp_text.len());
-    info!("[Huya Danmaku] fetched room page len={}", resp_text.len());
+// 订阅的弹幕主题
+fn join_topics(ayyuid: &str) -> Vec<String> {
+    vec![format!("live:{}", ayyuid), format!("chat:{}", ayyuid)]
+}
 
+// WS 注册包，编码见 tars::encode_register_group
+fn build_register_payload(ayyuid: &str) -> Result<Vec<u8>, String> {
+    let topics = join_topics(ayyuid);
+    println!("[Huya Danmaku] topics={:?}", topics);
+    info!("[Huya Danmaku] topics={:?}", topics);
+
+    let b = tars::encode_register_group(&topics)?;
+    println!("[Huya Danmaku] reg payload built, len={}", b.len());
+    info!("[Huya Danmaku] reg payload built, len={}", b.len());
+    Ok(b)
+}
+
+// 房间页中的主播 ayyuid：TT_PROFILE_INFO.lp，其次页面内嵌 JSON 的 lp / ayyuid / yyuid
+fn ayyuid_from_page(page: &str) -> Result<Option<String>, String> {
     // 先尝试 TT_PROFILE_INFO 提取 lp
     let mut ayyuid = {
         let re_prof = regex::Regex::new(r#"var\s+TT_PROFILE_INFO\s*=\s*(\{[\s\S]*?\});"#)
             .map_err(|e| e.to_string())?;
-        if let Some(cap) = re_prof.captures(&resp_text) {
+        if let Some(cap) = re_prof.captures(page) {
             if let Ok(j) = serde_json::from_str::<serde_json::Value>(&cap[1]) {
                 j.pointer("/lp")
                     .map(|v| v.to_string().replace('"', ""))
@@ -451,7 +503,7 @@ async fn get_ws_info_tars(room_id_or_url: &str) -> Result<(String, Vec<u8>), Str
         // 直接匹配 lp
         let re_lp =
             regex::Regex::new(r#"\\\"lp\\\"\s*:\s*\\\"?(\d+)\\\"?"#).map_err(|e| e.to_string())?;
-        if let Some(cap) = re_lp.captures(&resp_text) {
+        if let Some(cap) = re_lp.captures(page) {
             ayyuid = cap.get(1).unwrap().as_str().to_string();
         }
     }
@@ -461,27 +513,59 @@ async fn get_ws_info_tars(room_id_or_url: &str) -> Result<(String, Vec<u8>), Str
             .map_err(|e| e.to_string())?;
         let re_yyuid = regex::Regex::new(r#"\\\"yyuid\\\"\s*:\s*\\\"?(\d+)\\\"?"#)
             .map_err(|e| e.to_string())?;
-        if let Some(cap) = re_ayyuid.captures(&resp_text) {
+        if let Some(cap) = re_ayyuid.captures(page) {
             ayyuid = cap.get(1).unwrap().as_str().to_string();
-        } else if let Some(cap) = re_yyuid.captures(&resp_text) {
+        } else if let Some(cap) = re_yyuid.captures(page) {
             ayyuid = cap.get(1).unwrap().as_str().to_string();
         }
     }
+    Ok(Some(ayyuid).filter(|id| !id.is_empty()))
+}
+
+// 从房间页（TT_PROFILE_INFO / lp / ayyuid）或 mp.huya.com 解析主播 ayyuid，返回 (rid, ayyuid)
+async fn resolve_huya_ayyuid(room_id_or_url: &str) -> Result<(String, String), String> {
+    let url = if room_id_or_url.starts_with("http") {
+        reqwest::Url::parse(room_id_or_url).map_err(|e| e.to_string())?
+    } else {
+        reqwest::Url::parse(&format!("https://www.huya.com/{}", room_id_or_url))
+            .map_err(|e| e.to_string())?
+    };
+    let rid = url
+        .path_segments()
+        .and_then(|s| s.last())
+        .ok_or_else(|| "房间ID解析失败".to_string())?;
+    println!("[Huya Danmaku] get_ws_info_tars rid={}", rid);
+    info!("[Huya Danmaku] get_ws_info_tars rid={}", rid);
+
+    let client = crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
+        .build()
+        .map_err(|e| e.to_string())?;
+    let resp = client
+        .get(format!("https://www.huya.com/{}", rid))
+        .header("User-Agent", gen_ua())
+        .header("Referer", "https://www.huya.com/")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let resp_text = read_text(resp).await.map_err(|e| e.to_string())?;
+    println!("[Huya Danmaku] fetched room page len={}", resp_text.len());
+    info!("[Huya Danmaku] fetched room page len={}", resp_text.len());
+
+    let mut ayyuid = ayyuid_from_page(&resp_text)?.unwrap_or_default();
     if ayyuid.is_empty() {
         // 回退：调用 mp.huya.com
         let url_api = format!(
             "https://mp.huya.com/cache.php?m=Live&do=profileRoom&roomid={}",
             rid
         );
-        let text = client
+        let resp = client
             .get(&url_api)
             .header("User-Agent", gen_ua())
             .send()
             .await
-            .map_err(|e| e.to_string())?
-            .text()
-            .await
             .map_err(|e| e.to_string())?;
+        let text = read_text(resp).await.map_err(|e| e.to_string())?;
         if let Ok(j) = serde_json::from_str::<serde_json::Value>(&text) {
             if let Some(found) = find_uid_in_json(&j) {
                 ayyuid = found;
@@ -494,93 +578,114 @@ async fn get_ws_info_tars(room_id_or_url: &str) -> Result<(String, Vec<u8>), Str
     println!("[Huya Danmaku] final ayyuid={}", ayyuid);
     info!("[Huya Danmaku] final ayyuid={}", ayyuid);
 
-    let mut topics = Vec::new();
-    topics.push(format!("live:{}", ayyuid));
-    topics.push(format!("chat:{}", ayyuid));
-    println!("[Huya Danmaku] topics={:?}", topics);
-    info!("[Huya Danmaku] topics={:?}", topics);
+    Ok((rid.to_string(), ayyuid))
+}
 
-    let mut oos = TarsEncoder::new();
-    oos.write_list(0, &topics).map_err(|e| e.to_string())?;
-    oos.write_string(1, &"".to_owned())
-        .map_err(|e| e.to_string())?;
+// 返回 (昵称, 内容)；礼物消息按 B 站的格式以 “[礼物] ...” 作为内容
+fn decode_msg_tars(data: &[u8]) -> anyhow::Result<Option<(String, String)>> {
+    let (nick, text) = match tars::decode_message(data)? {
+        Some(HuyaMessage::Chat(chat)) => (chat.user.name, chat.content),
+        Some(HuyaMessage::Gift(gift)) => (
+            gift.sender_nick,
+            format!("[礼物] #{} x{}", gift.item_type, gift.count),
+        ),
+        None => return Ok(None),
+    };
+    let nick = if nick.is_empty() {
+        "匿名".to_string()
+    } else {
+        nick
+    };
+    println!("[Huya Danmaku] decoded nick={} text={}", nick, text);
+    info!("[Huya Danmaku] decoded nick={} text={}", nick, text);
+    Ok(Some((nick, text)))
+}
 
-    let mut wscmd = TarsEncoder::new();
-    wscmd.write_int32(0, 16).map_err(|e| e.to_string())?;
-    wscmd
-        .write_bytes(1, &oos.to_bytes())
-        .map_err(|e| e.to_string())?;
-    let b = wscmd.to_bytes();
-    println!("[Huya Danmaku] reg payload built, len={}", b.len());
-    info!("[Huya Danmaku] reg payload built, len={}", b.len());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tars_stream::prelude::*;
+
+    // 仿照房间页写的 TT_PROFILE_INFO 片段，主播信息均为测试值
+    const ROOM_PAGE: &str = r#"<script>
+        var TT_META_DATA = {"time": 1700000000};
+        var TT_PROFILE_INFO = {"sex":1,"lp":"1199512345678","aid":0,"yyid":1234567,"nick":"测试主播","avatar":"https://huyaimg.msstatic.com/avatar/1.jpg","fans":100,"freezeLevel":0,"host":"test"};
+        var TT_PLAYER_CONF = {"app":"live"};
+    </script>"#;
+
+    // 手写的 profileRoom 响应，第二条线路缺少 lPresenterUid
+    const PROFILE_ROOM: &str = r#"{
+        "status": 200,
+        "message": "",
+        "data": {
+            "liveStatus": "ON",
+            "profileInfo": {"uid": 1199512345678, "yyid": 1234567, "nick": "测试主播"},
+            "stream": {"baseSteamInfoList": [
+                {"sCdnType": "AL", "lChannelId": 1199512345678, "lSubChannelId": 1199512345678, "lPresenterUid": 1199512345678},
+                {"sCdnType": "TX", "lChannelId": 1199512345678, "lSubChannelId": 1199512345678}
+            ]}
+        }
+    }"#;
 
-    Ok((WS_URL.to_owned(), b.as_ref().to_vec()))
-}
+    #[test]
+    fn reads_ayyuid_from_profile_info() {
+        assert_eq!(
+            ayyuid_from_page(ROOM_PAGE).unwrap().as_deref(),
+            Some("1199512345678")
+        );
+    }
 
-fn decode_msg_tars(data: &[u8]) -> anyhow::Result<Option<(String, String)>> {
-    let mut ret: Option<(String, String)> = None;
-    let mut ios = TarsDecoder::from(data);
-    let top = ios.read_int32(0, false, -1)?;
-    if top != 7 {
-        println!("[Huya Danmaku] ignore msg: top_cmd={}", top);
-        info!("[Huya Danmaku] ignore msg: top_cmd={}", top);
-        return Ok(ret);
+    #[test]
+    fn falls_back_to_embedded_json_fields() {
+        let page = r#"window.HNF_GLOBAL_INIT = "{\"roomInfo\":{\"tProfileInfo\":{\"lp\":\"1199500000001\"}}}";"#;
+        assert_eq!(
+            ayyuid_from_page(page).unwrap().as_deref(),
+            Some("1199500000001")
+        );
+        let page = r#"var data = "{\"yyuid\":\"42\",\"ayyuid\":\"43\"}";"#;
+        assert_eq!(ayyuid_from_page(page).unwrap().as_deref(), Some("43"));
+        assert_eq!(ayyuid_from_page("<html></html>").unwrap(), None);
     }
-    let b1 = ios.read_bytes(1, false, Default::default())?;
-    let mut inner = TarsDecoder::from(b1.as_ref());
-    let nested = inner.read_int32(1, false, -1).unwrap_or(-1);
-    let b2 = inner.read_bytes(2, false, Default::default())?;
-    println!("[Huya Danmaku] nested={} payload_len={}", nested, b2.len());
-    info!("[Huya Danmaku] nested={} payload_len={}", nested, b2.len());
-    let mut payload = TarsDecoder::from(b2.as_ref());
-
-    if nested == 1400 {
-        let user = payload
-            .read_struct(
-                0,
-                false,
-                HuyaUser {
-                    _uid: -1,
-                    _imid: -1,
-                    name: "".to_owned(),
-                    _gender: 1,
-                },
-            )
-            .unwrap_or(HuyaUser {
-                _uid: -1,
-                _imid: -1,
-                name: "".to_owned(),
-                _gender: 1,
-            });
-        let text = payload
-            .read_string(3, false, "".to_owned())
-            .unwrap_or_default();
-        let fmt = payload
-            .read_struct(6, false, HuyaDanmakuFmt { color: 16777215 })
-            .unwrap_or(HuyaDanmakuFmt { color: 16777215 });
-        if !text.is_empty() {
-            let nick = if !user.name.is_empty() {
-                user.name
-            } else {
-                "匿名".to_string()
-            };
-            let _color_hex = format!("{:06x}", if fmt.color <= 0 { 16777215 } else { fmt.color });
-            println!(
-                "[Huya Danmaku] decoded nested=1400 nick={} text={}",
-                nick, text
-            );
-            info!(
-                "[Huya Danmaku] decoded nested=1400 nick={} text={}",
-                nick, text
-            );
-            ret = Some((nick, text));
-        } else {
-            println!("[Huya Danmaku] empty text in nested=1400");
-            info!("[Huya Danmaku] empty text in nested=1400");
-        }
-    } else {
-        println!("[Huya Danmaku] non-chat nested={}, skip", nested);
-        info!("[Huya Danmaku] non-chat nested={}, skip", nested);
+
+    #[test]
+    fn parses_channel_ids_from_profile_room() {
+        let v: serde_json::Value = serde_json::from_str(PROFILE_ROOM).unwrap();
+        assert_eq!(
+            parse_profile_room_ids(&v).unwrap(),
+            (1234567, 1199512345678, 1199512345678)
+        );
+
+        let offline = serde_json::json!({"status": 422, "message": "主播未开播"});
+        assert!(parse_profile_room_ids(&offline).is_err());
+        let no_stream = serde_json::json!({"status": 200, "data": {"profileInfo": {"yyid": 1}}});
+        assert!(parse_profile_room_ids(&no_stream).is_err());
+    }
+
+    // 返回给前端的注册包与监听器发送的一致，且能按 WebSocketCommand 结构解回 topics
+    #[test]
+    fn register_payload_round_trips() {
+        let payload = build_register_payload("1199512345678").unwrap();
+        assert_eq!(
+            payload,
+            tars::encode_register_group(&join_topics("1199512345678")).unwrap()
+        );
+
+        let mut outer = TarsDecoder::from(payload.as_slice());
+        assert_eq!(
+            outer.read_int32(0, true, 0).unwrap(),
+            tars::CMD_REGISTER_GROUP_REQ
+        );
+        let body = outer.read_bytes(1, true, Default::default()).unwrap();
+        let mut req = TarsDecoder::from(body.as_ref());
+        let topics: Vec<String> = req.read_list(0, true, Vec::new()).unwrap();
+        assert_eq!(topics, join_topics("1199512345678"));
+        assert_eq!(
+            topics,
+            vec![
+                "live:1199512345678".to_string(),
+                "chat:1199512345678".to_string()
+            ]
+        );
+        assert_eq!(req.read_string(1, false, "x".to_string()).unwrap(), "");
     }
-    Ok(ret)
 }
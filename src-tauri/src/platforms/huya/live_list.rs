@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
-use tauri::command;
+use tauri::{command, AppHandle};
 
 use crate::platforms::common::http_client::HttpClient;
+use crate::platforms::common::list_cache::{is_unavailable_status, load_list, save_list};
+use crate::proxy::proxied_image_url;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HuyaStreamerFrontend {
@@ -19,6 +21,12 @@ pub struct HuyaLiveListFrontendResponse {
     pub error: i32,
     pub msg: Option<String>,
     pub data: Option<Vec<HuyaStreamerFrontend>>, // simple list, frontend can decide pagination by page size
+    // 网络失败时返回的离线缓存数据会标记为 stale
+    #[serde(default)]
+    pub stale: bool,
+    // 是否还有下一页：优先按接口的 iTotalPage 判断，缺失时按本页是否满页判断
+    #[serde(default)]
+    pub has_more: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -67,15 +75,49 @@ fn map_huya_item_to_frontend(item: &serde_json::Value) -> Option<HuyaStreamerFro
         room_id: l_profile_room,
         title: s_intro,
         nickname: s_nick,
-        avatar: s_avatar_180,
-        room_cover: s_screenshot,
+        avatar: proxied_image_url(&s_avatar_180),
+        room_cover: proxied_image_url(&s_screenshot),
         viewer_count_str,
         platform: "huya".to_string(),
     })
 }
 
+// i_gid 为分类（游戏）id，可从 fetch_huya_categories 获取
 #[command]
 pub async fn fetch_huya_live_list(
+    app_handle: AppHandle,
+    i_gid: String,
+    i_page_no: u32,
+    i_page_size: u32,
+) -> HuyaLiveListFrontendResponse {
+    let cache_key = format!("huya_{}_{}_{}", i_gid, i_page_no, i_page_size);
+    let response = fetch_huya_live_list_remote(i_gid, i_page_no, i_page_size).await;
+    if response.error == 0 && response.data.is_some() {
+        save_list(&app_handle, &cache_key, &response);
+        return response;
+    }
+    // 只有网络不可达时才回退到缓存，接口返回的错误照常交给前端
+    if !is_unavailable_status(response.error) {
+        return response;
+    }
+    match load_list::<HuyaLiveListFrontendResponse>(&app_handle, &cache_key) {
+        Some(mut cached) => {
+            eprintln!(
+                "[Huya Backend] Live request failed ({:?}), serving cached list for {}",
+                response.msg, cache_key
+            );
+            for streamer in cached.data.iter_mut().flatten() {
+                streamer.avatar = proxied_image_url(&streamer.avatar);
+                streamer.room_cover = proxied_image_url(&streamer.room_cover);
+            }
+            cached.stale = true;
+            cached
+        }
+        None => response,
+    }
+}
+
+async fn fetch_huya_live_list_remote(
     i_gid: String,
     i_page_no: u32,
     i_page_size: u32,
@@ -96,6 +138,8 @@ pub async fn fetch_huya_live_list(
                 error: 500,
                 msg: Some(e),
                 data: None,
+                stale: false,
+                has_more: false,
             };
         }
     };
@@ -109,6 +153,8 @@ pub async fn fetch_huya_live_list(
                 error: 500,
                 msg: Some(e),
                 data: None,
+                stale: false,
+                has_more: false,
             };
         }
     };
@@ -126,21 +172,34 @@ pub async fn fetch_huya_live_list(
                 .cloned()
         });
 
+    let total_page = resp_value
+        .get("iTotalPage")
+        .or_else(|| resp_value.get("data").and_then(|d| d.get("iTotalPage")))
+        .and_then(|v| v.as_u64());
+
     if let Some(arr) = v_list_opt {
         let mapped: Vec<HuyaStreamerFrontend> = arr
             .iter()
             .filter_map(|item| map_huya_item_to_frontend(item))
             .collect();
+        let has_more = match total_page {
+            Some(total_page) => u64::from(i_page_no) < total_page,
+            None => !mapped.is_empty() && mapped.len() >= i_page_size as usize,
+        };
         HuyaLiveListFrontendResponse {
             error: 0,
             msg: Some("Success".to_string()),
             data: Some(mapped),
+            stale: false,
+            has_more,
         }
     } else {
         HuyaLiveListFrontendResponse {
             error: -1,
             msg: Some("No vList in response".to_string()),
             data: None,
+            stale: false,
+            has_more: false,
         }
     }
 }
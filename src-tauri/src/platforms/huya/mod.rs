@@ -1,7 +1,9 @@
+pub mod categories;
 pub mod danmaku;
 pub mod live_list;
 pub mod search;
 pub mod stream_url;
+pub mod tars;
 
 #[allow(unused_imports)]
 pub use danmaku::fetch_huya_join_params;
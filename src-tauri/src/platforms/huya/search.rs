@@ -3,6 +3,8 @@ use reqwest::header::{
 };
 use serde::Serialize;
 
+use crate::proxy::proxied_image_url;
+
 #[derive(Debug, Serialize)]
 pub struct HuyaAnchorItem {
     pub room_id: String,
@@ -16,8 +18,21 @@ pub struct HuyaAnchorItem {
 pub async fn search_huya_anchors(
     keyword: String,
     page: Option<usize>,
+    page_size: Option<usize>,
 ) -> Result<Vec<HuyaAnchorItem>, String> {
-    let client = reqwest::Client::builder()
+    search_huya_page(&keyword, page.unwrap_or(1), page_size.unwrap_or(20))
+        .await
+        .map(|(items, _)| items)
+}
+
+// 返回 (本页结果, 是否还有下一页)；总数取自 response["1"].numFound
+pub(crate) async fn search_huya_page(
+    keyword: &str,
+    page: usize,
+    page_size: usize,
+) -> Result<(Vec<HuyaAnchorItem>, bool), String> {
+    let client = crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
         .build()
         .map_err(|e| e.to_string())?;
     let url = "https://search.cdn.huya.com/";
@@ -31,7 +46,7 @@ pub async fn search_huya_anchors(
     headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
     headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("zh-CN,zh;q=0.9"));
 
-    let page_num = page.unwrap_or(1);
+    let start = (page.max(1) - 1) * page_size;
 
     let resp = client
         .get(url)
@@ -39,13 +54,13 @@ pub async fn search_huya_anchors(
         .query(&[
             ("m", "Search"),
             ("do", "getSearchContent"),
-            ("q", &keyword),
+            ("q", keyword),
             ("uid", "0"),
             ("v", "1"),
             ("typ", "-5"),
             ("livestate", "0"),
-            ("rows", "20"),
-            ("start", &((page_num - 1) * 20).to_string()),
+            ("rows", &page_size.to_string()),
+            ("start", &start.to_string()),
         ])
         .send()
         .await
@@ -54,11 +69,19 @@ pub async fn search_huya_anchors(
         .map_err(|e| e.to_string())?;
 
     let text = resp.text().await.map_err(|e| e.to_string())?;
-    let v: serde_json::Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    parse_huya_search(&text, start, page_size)
+}
+
+// start 为本页首条的偏移（rows * (page - 1)）；没有 numFound 时本页条数达到 page_size 即视为还有下一页
+fn parse_huya_search(
+    text: &str,
+    start: usize,
+    page_size: usize,
+) -> Result<(Vec<HuyaAnchorItem>, bool), String> {
+    let v: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
     let mut items = vec![];
-    if let Some(list) = v
-        .get("response")
-        .and_then(|r| r.get("1"))
+    let result_block = v.get("response").and_then(|r| r.get("1"));
+    if let Some(list) = result_block
         .and_then(|d| d.get("docs"))
         .and_then(|a| a.as_array())
     {
@@ -69,11 +92,11 @@ pub async fn search_huya_anchors(
                     .and_then(|v| v.as_i64())
                     .unwrap_or(0)
                     .to_string(),
-                avatar: item
-                    .get("game_avatarUrl180")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string(),
+                avatar: proxied_image_url(
+                    item.get("game_avatarUrl180")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or(""),
+                ),
                 user_name: item
                     .get("game_nick")
                     .and_then(|v| v.as_str())
@@ -92,5 +115,59 @@ pub async fn search_huya_anchors(
             items.push(anchor);
         }
     }
-    Ok(items)
+    let has_more = match result_block
+        .and_then(|d| d.get("numFound"))
+        .and_then(|n| n.as_u64())
+    {
+        Some(found) => ((start + items.len()) as u64) < found,
+        None => items.len() >= page_size,
+    };
+    Ok((items, has_more))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // search.cdn.huya.com getSearchContent 的两页示例响应（人工编写），rows = 2，共 3 条
+    const PAGE_1: &str = r#"{"response": {"1": {"numFound": 3, "start": 0, "docs": [
+        {"room_id": 1199, "game_nick": "主播A", "game_avatarUrl180": "https://huyaimg.msstatic.com/avatar/a.jpg",
+         "gameLiveOn": true, "live_intro": "第一页 A"},
+        {"room_id": 660000, "game_nick": "主播B", "game_avatarUrl180": "",
+         "gameLiveOn": false, "live_intro": ""}
+    ]}}}"#;
+    const PAGE_2: &str = r#"{"response": {"1": {"numFound": 3, "start": 2, "docs": [
+        {"room_id": 880000, "game_nick": "主播C", "game_avatarUrl180": "",
+         "gameLiveOn": true, "live_intro": "第二页 C"}
+    ]}}}"#;
+
+    fn room_ids(items: &[HuyaAnchorItem]) -> Vec<&str> {
+        items.iter().map(|item| item.room_id.as_str()).collect()
+    }
+
+    #[test]
+    fn second_page_differs_from_first() {
+        let (first, first_more) = parse_huya_search(PAGE_1, 0, 2).unwrap();
+        let (second, second_more) = parse_huya_search(PAGE_2, 2, 2).unwrap();
+        assert_eq!(room_ids(&first), ["1199", "660000"]);
+        assert_eq!(room_ids(&second), ["880000"]);
+        assert!(first_more);
+        assert!(!second_more);
+
+        assert_eq!(first[0].user_name, "主播A");
+        assert_eq!(first[0].title, "第一页 A");
+        assert!(first[0].live_status);
+        assert!(!first[1].live_status);
+    }
+
+    #[test]
+    fn has_more_without_num_found() {
+        let text = r#"{"response": {"1": {"docs": [{"room_id": 1}, {"room_id": 2}]}}}"#;
+        assert!(parse_huya_search(text, 0, 2).unwrap().1);
+        assert!(!parse_huya_search(text, 0, 20).unwrap().1);
+        let (items, has_more) = parse_huya_search(r#"{"response": {}}"#, 0, 20).unwrap();
+        assert!(items.is_empty());
+        assert!(!has_more);
+        assert!(parse_huya_search("<html>", 0, 20).is_err());
+    }
 }
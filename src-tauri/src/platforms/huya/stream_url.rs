@@ -3,6 +3,7 @@ use std::error::Error;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use base64::{engine::general_purpose, Engine as _};
+use futures_util::FutureExt;
 use md5::{Digest, Md5};
 use rand::Rng;
 use regex::Regex;
@@ -13,10 +14,18 @@ use serde::Serialize;
 use serde_json::Value;
 use tauri::State;
 
+use crate::platforms::common::cn_number::parse_cn_number;
+use crate::platforms::common::request_id::{new_request_id, tag_error};
+use crate::platforms::common::resolver_chain::{first_playable, Resolver};
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::text_decode::read_text;
+use crate::platforms::common::user_agent::user_agent_for;
 use crate::platforms::common::FollowHttpClient;
+use crate::platforms::common::{Quality, SupportedPlatformRust};
 
 const IOS_MOBILE_UA: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1";
-const DESKTOP_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:123.0) Gecko/20100101 Firefox/123.0";
+pub(crate) const DESKTOP_UA: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:123.0) Gecko/20100101 Firefox/123.0";
 
 #[derive(Clone, Debug, Serialize)]
 #[allow(non_snake_case)]
@@ -37,6 +46,23 @@ pub struct HuyaUnifiedResponse {
     pub is_live: bool,
     pub flv_tx_urls: Vec<HuyaUnifiedStreamEntry>,
     pub selected_url: Option<String>,
+    // 实际使用的 CDN 线路与房间可用线路（tx/al/hs 等）
+    pub selected_line: Option<String>,
+    pub lines: Vec<String>,
+    // 防盗链参数 wsTime 对应的过期时间（Unix 秒），前端/后端可据此在失效前主动刷新
+    pub expires_at: Option<i64>,
+    // 产出播放地址的解析策略：web_page / web_page_mobile / profile_room
+    pub resolver: Option<String>,
+    // 为 true 时只能经本地代理播放（虎牙 CDN 校验 Referer）；没有播放地址时为 None
+    pub needs_proxy: Option<bool>,
+    // 房间提供的低延迟线路（见 LOW_LATENCY_LINE），没有时为 None，前端据此决定是否展示低延迟开关
+    pub low_latency_line: Option<String>,
+    // 请求低延迟模式时是否生效：房间没有低延迟线路或指定了其他线路时按常规线路播放并返回 false，
+    // 未请求时为 None
+    pub low_latency: Option<bool>,
+    // profileRoom 原始响应，仅在开启 set_debug_raw_responses 时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Value>,
 }
 
 fn md5_hex(input: &str) -> String {
@@ -68,6 +94,18 @@ fn url_decode(s: &str) -> String {
         .unwrap_or_else(|| s.to_string())
 }
 
+// 从播放地址的防盗链参数中取出 wsTime（十六进制 Unix 秒），即地址的过期时间
+pub fn anti_code_expiry(url: &str) -> Option<i64> {
+    let query = url.split_once('?')?.1.replace("&amp;", "&");
+    let ws_time = parse_query(&query).remove("wsTime")?;
+    i64::from_str_radix(ws_time.trim(), 16).ok()
+}
+
+// 距离过期还剩多少秒；已过期返回 0
+pub fn remaining_validity_secs(expires_at: i64) -> i64 {
+    (expires_at - current_millis() / 1000).max(0)
+}
+
 fn enforce_https(url: &str) -> String {
     if url.starts_with("https://") {
         url.to_string()
@@ -161,7 +199,7 @@ async fn check_live_status(
     headers.insert(USER_AGENT, HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; WOW64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/102.0.0.0 Safari/537.36"));
 
     let resp = client.get(&url).headers(headers).send().await?;
-    let text = resp.text().await?;
+    let text = read_text(resp).await?;
 
     let re = Regex::new(r"window\\.HNF_GLOBAL_INIT.=.\{(.*?)\}\s*</script>").unwrap();
     if let Some(caps) = re.captures(&text) {
@@ -184,6 +222,11 @@ struct RoomDetail {
     title: Option<String>,
     nick: Option<String>,
     avatar180: Option<String>,
+    // profileRoom 中的 stream.baseSteamInfoList，网页解析失效时作为兜底线路来源
+    stream_items: Vec<Value>,
+    // liveData 中的在线人数（userCount/totalCount）
+    viewers: Option<u64>,
+    raw: Option<Value>,
 }
 
 #[derive(Clone, Debug)]
@@ -196,6 +239,8 @@ struct WebStreamCandidate {
 struct HuyaWebStreamData {
     is_live: bool,
     candidates: Vec<WebStreamCandidate>,
+    // 房间页下发的 cookie，部分 CDN 线路拉流时需要
+    cookies: Vec<crate::proxy::StreamCookie>,
 }
 
 async fn fetch_room_detail(
@@ -223,6 +268,9 @@ async fn fetch_room_detail(
             title: None,
             nick: None,
             avatar180: None,
+            stream_items: Vec::new(),
+            viewers: None,
+            raw: crate::platforms::common::debug_raw::capture(|| v.clone()),
         });
     }
 
@@ -232,6 +280,9 @@ async fn fetch_room_detail(
             title: None,
             nick: None,
             avatar180: None,
+            stream_items: Vec::new(),
+            viewers: None,
+            raw: crate::platforms::common::debug_raw::capture(|| v.clone()),
         });
     };
 
@@ -253,32 +304,33 @@ async fn fetch_room_detail(
         .and_then(|x| x.as_str())
         .map(|s| s.to_string());
 
+    let stream_items = data
+        .pointer("/stream/baseSteamInfoList")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let viewers = ["/liveData/userCount", "/liveData/totalCount"]
+        .iter()
+        .find_map(|path| match data.pointer(path)? {
+            Value::Number(n) => n.as_u64(),
+            Value::String(s) => parse_cn_number(s),
+            _ => None,
+        });
+
     Ok(RoomDetail {
         status: stream_ok,
         title,
         nick,
         avatar180,
+        stream_items,
+        viewers,
+        raw: crate::platforms::common::debug_raw::capture(|| v.clone()),
     })
 }
 
-async fn fetch_web_stream_data(
-    client: &reqwest::Client,
-    room_id: &str,
-) -> Result<HuyaWebStreamData, Box<dyn Error + Send + Sync>> {
-    match fetch_web_stream_data_with_headers(client, room_id, false).await {
-        Ok(data) if !data.candidates.is_empty() => Ok(data),
-        Ok(_) => {
-            println!("[Huya] Desktop UA response contained no stream candidates, retrying with mobile headers.");
-            fetch_web_stream_data_with_headers(client, room_id, true).await
-        }
-        Err(err) => {
-            eprintln!(
-                "[Huya] Desktop UA request failed ({:?}), retrying with mobile headers.",
-                err
-            );
-            fetch_web_stream_data_with_headers(client, room_id, true).await
-        }
-    }
+fn playable_stream(data: HuyaWebStreamData) -> Option<HuyaWebStreamData> {
+    Some(data).filter(|d| !d.candidates.is_empty())
 }
 
 async fn fetch_web_stream_data_with_headers(
@@ -292,24 +344,24 @@ async fn fetch_web_stream_data_with_headers(
         headers.insert(USER_AGENT, HeaderValue::from_static(IOS_MOBILE_UA));
         headers.insert(
             ACCEPT,
-            HeaderValue::from_static("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"),
+            HeaderValue::from_static(
+                "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+            ),
         );
+        headers.insert(REFERER, HeaderValue::from_static("https://m.huya.com/"));
+    } else {
         headers.insert(
-            REFERER,
-            HeaderValue::from_static("https://m.huya.com/"),
+            USER_AGENT,
+            HeaderValue::from_str(&user_agent_for(&SupportedPlatformRust::Huya))
+                .unwrap_or_else(|_| HeaderValue::from_static(DESKTOP_UA)),
         );
-    } else {
-        headers.insert(USER_AGENT, HeaderValue::from_static(DESKTOP_UA));
         headers.insert(
             ACCEPT,
             HeaderValue::from_static(
                 "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8",
             ),
         );
-        headers.insert(
-            REFERER,
-            HeaderValue::from_static("https://www.huya.com/"),
-        );
+        headers.insert(REFERER, HeaderValue::from_static("https://www.huya.com/"));
     }
     headers.insert(
         ACCEPT_LANGUAGE,
@@ -321,13 +373,15 @@ async fn fetch_web_stream_data_with_headers(
     );
 
     let resp = client.get(&url).headers(headers).send().await?;
-    let html = resp.text().await?;
+    let cookies = crate::proxy::cookies_from_response(&resp);
+    let html = read_text(resp).await?;
 
     let re = Regex::new(r#"(?s)stream:\s*(\{"data".*?),"iWebDefaultBitRate""#)?;
     let Some(caps) = re.captures(&html) else {
         return Ok(HuyaWebStreamData {
             is_live: false,
             candidates: Vec::new(),
+            cookies,
         });
     };
     let json_fragment = caps.get(1).map(|m| m.as_str()).unwrap_or("");
@@ -340,6 +394,7 @@ async fn fetch_web_stream_data_with_headers(
             return Ok(HuyaWebStreamData {
                 is_live: false,
                 candidates: Vec::new(),
+                cookies,
             })
         }
     };
@@ -352,11 +407,23 @@ async fn fetch_web_stream_data_with_headers(
             return Ok(HuyaWebStreamData {
                 is_live: false,
                 candidates: Vec::new(),
+                cookies,
             })
         }
     };
 
-    let mut stream_items = stream_info_list;
+    let candidates = candidates_from_stream_items(stream_info_list)?;
+    Ok(HuyaWebStreamData {
+        is_live: !candidates.is_empty(),
+        candidates,
+        cookies,
+    })
+}
+
+// 网页 gameStreamInfoList 与 profileRoom baseSteamInfoList 的条目结构相同（sFlvUrl/sStreamName/...）
+fn candidates_from_stream_items(
+    mut stream_items: Vec<Value>,
+) -> Result<Vec<WebStreamCandidate>, String> {
     stream_items.sort_by_key(|item| {
         let cdn = item
             .get("sCdnType")
@@ -399,7 +466,7 @@ async fn fetch_web_stream_data_with_headers(
 
         let anti_params = match generate_web_anti_code(stream_name, anti_code) {
             Ok(v) => v,
-            Err(err) => return Err(format!("failed to generate Huya anti code: {err}").into()),
+            Err(err) => return Err(format!("failed to generate Huya anti code: {err}")),
         };
 
         let base_flv = enforce_https(&format!(
@@ -409,12 +476,7 @@ async fn fetch_web_stream_data_with_headers(
         candidates.push(WebStreamCandidate { base_flv, cdn });
     }
 
-    let candidates = prioritize_candidates(candidates);
-
-    Ok(HuyaWebStreamData {
-        is_live: !candidates.is_empty(),
-        candidates,
-    })
+    Ok(prioritize_candidates(candidates))
 }
 
 fn cdn_priority(cdn: &str) -> usize {
@@ -449,6 +511,17 @@ fn normalize_huya_line(input: Option<&str>) -> Option<String> {
         .filter(|s| matches!(s.as_str(), "tx" | "al" | "hs"))
 }
 
+// 低延迟模式使用的线路：火山（hs）
+const LOW_LATENCY_LINE: &str = "hs";
+
+// 显式指定的线路优先；否则请求低延迟且房间有对应线路时改用该线路
+fn preferred_line_for(line: Option<&str>, low_latency: bool, lines: &[String]) -> Option<String> {
+    normalize_huya_line(line).or_else(|| {
+        (low_latency && lines.iter().any(|l| l == LOW_LATENCY_LINE))
+            .then(|| LOW_LATENCY_LINE.to_string())
+    })
+}
+
 fn prioritize_candidates(candidates: Vec<WebStreamCandidate>) -> Vec<WebStreamCandidate> {
     if candidates.is_empty() {
         return candidates;
@@ -488,22 +561,13 @@ fn prioritize_candidates(candidates: Vec<WebStreamCandidate>) -> Vec<WebStreamCa
     remaining
 }
 
+// 原画不限码率（None）；其余档位换算为虎牙的 ratio 参数，无法识别的写法按原画处理
 fn resolve_ratio(quality: Option<&str>) -> Option<i32> {
-    if let Some(q) = quality {
-        let trimmed = q.trim();
-        let lower = trimmed.to_ascii_lowercase();
-        if trimmed.contains("标清") || lower == "sd" || lower == "ld" || lower == "2000" {
-            return Some(2000);
-        }
-        if trimmed.contains("高清") || lower == "hd" || lower == "4000" {
-            return Some(4000);
-        }
-        if trimmed.contains("原画") || lower == "source" || lower == "uhd" {
-            return None;
-        }
-        return Some(4000);
-    }
-    None
+    let ratio = Quality::from_user_or_default(&SupportedPlatformRust::Huya, quality?)
+        .to_platform_param(&SupportedPlatformRust::Huya)
+        .parse::<i32>()
+        .unwrap_or(0);
+    (ratio > 0).then_some(ratio)
 }
 
 fn pick_stream_url(
@@ -569,20 +633,86 @@ pub async fn get_huya_unified_cmd(
     room_id: String,
     quality: Option<String>,
     line: Option<String>,
+    low_latency: Option<bool>,
     follow_http: State<'_, FollowHttpClient>,
 ) -> Result<HuyaUnifiedResponse, String> {
-    let client = &follow_http.0.inner;
+    let room_id = validate_room_id(&SupportedPlatformRust::Huya, &room_id)?;
+    let follow_client = follow_http.client();
+    let client = &follow_client.inner;
+    let request_id = new_request_id();
+    println!(
+        "[Huya][req:{}] Resolving room {} with quality {:?}, line {:?}",
+        request_id, room_id, quality, line
+    );
 
     let detail = fetch_room_detail(client, &room_id)
         .await
-        .map_err(|e| e.to_string())?;
-
-    let web_stream = fetch_web_stream_data(client, &room_id)
+        .map_err(|e| tag_error(&request_id, e))?;
+
+    // 依次尝试：桌面网页 -> 移动端网页 -> profileRoom 接口中的线路
+    let resolvers: Vec<Resolver<'_, HuyaWebStreamData>> = vec![
+        (
+            "web_page",
+            async {
+                fetch_web_stream_data_with_headers(client, &room_id, false)
+                    .await
+                    .map(playable_stream)
+                    .map_err(|e| e.to_string())
+            }
+            .boxed(),
+        ),
+        (
+            "web_page_mobile",
+            async {
+                fetch_web_stream_data_with_headers(client, &room_id, true)
+                    .await
+                    .map(playable_stream)
+                    .map_err(|e| e.to_string())
+            }
+            .boxed(),
+        ),
+        (
+            "profile_room",
+            async {
+                candidates_from_stream_items(detail.stream_items.clone()).map(|candidates| {
+                    playable_stream(HuyaWebStreamData {
+                        is_live: !candidates.is_empty(),
+                        candidates,
+                        cookies: Vec::new(),
+                    })
+                })
+            }
+            .boxed(),
+        ),
+    ];
+    let context = format!("[Huya][req:{}] room {}", request_id, room_id);
+    let (web_stream, resolver) = match first_playable(&context, resolvers)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| tag_error(&request_id, e))?
+    {
+        Some((data, name)) => (data, Some(name.to_string())),
+        None => (
+            HuyaWebStreamData {
+                is_live: false,
+                candidates: Vec::new(),
+                cookies: Vec::new(),
+            },
+            None,
+        ),
+    };
 
     let ratio = resolve_ratio(quality.as_deref());
-    let preferred_line = normalize_huya_line(line.as_deref());
+    let lines: Vec<String> = web_stream
+        .candidates
+        .iter()
+        .map(|c| c.cdn.to_ascii_lowercase())
+        .collect();
+    let low_latency_requested = low_latency.unwrap_or(false);
+    let preferred_line = preferred_line_for(line.as_deref(), low_latency_requested, &lines);
+    let low_latency_line = lines
+        .iter()
+        .any(|l| l == LOW_LATENCY_LINE)
+        .then(|| LOW_LATENCY_LINE.to_string());
     let selection = pick_stream_url(&web_stream.candidates, ratio, preferred_line.as_deref());
     let (selected_url, selected_index) = match selection {
         Some(value) => value,
@@ -596,13 +726,36 @@ pub async fn get_huya_unified_cmd(
                 is_live: detail.status || web_stream.is_live,
                 flv_tx_urls: Vec::new(),
                 selected_url: None,
+                selected_line: None,
+                lines,
+                expires_at: None,
+                resolver,
+                needs_proxy: None,
+                low_latency_line,
+                low_latency: low_latency.map(|_| false),
+                raw: detail.raw.clone(),
             });
         }
     };
+    crate::proxy::remember_resolved_cookies(&selected_url, web_stream.cookies.clone());
+    crate::proxy::remember_resolved_referer(
+        &selected_url,
+        &format!("https://www.huya.com/{}", room_id),
+    );
     let tx_entries = build_flv_tx_urls(web_stream.candidates.get(selected_index));
     let is_live = detail.status || web_stream.is_live;
+    let expires_at = anti_code_expiry(&selected_url);
+    if let Some(expires_at) = expires_at {
+        println!(
+            "[Huya][req:{}] stream url expires at {} ({}s left)",
+            request_id,
+            expires_at,
+            remaining_validity_secs(expires_at)
+        );
+    }
     println!(
-        "[Huya] requested quality: {:?}, resolved ratio: {:?}, preferred line: {:?}, selected line: {:?}",
+        "[Huya][req:{}] requested quality: {:?}, resolved ratio: {:?}, preferred line: {:?}, selected line: {:?}",
+        request_id,
         quality,
         ratio,
         preferred_line,
@@ -612,6 +765,18 @@ pub async fn get_huya_unified_cmd(
             .map(|c| c.cdn.clone())
     );
 
+    let needs_proxy = Some(crate::proxy::upstream_needs_proxy(&selected_url));
+    let selected_line = web_stream
+        .candidates
+        .get(selected_index)
+        .map(|c| c.cdn.to_ascii_lowercase());
+    let low_latency_applied = selected_line.as_deref() == Some(LOW_LATENCY_LINE);
+    if low_latency_requested && !low_latency_applied {
+        println!(
+            "[Huya][req:{}] low latency requested but line {:?} selected, using regular line",
+            request_id, selected_line
+        );
+    }
     Ok(HuyaUnifiedResponse {
         title: detail.title.clone(),
         nick: detail.nick.clone(),
@@ -621,7 +786,137 @@ pub async fn get_huya_unified_cmd(
         is_live,
         flv_tx_urls: tx_entries,
         selected_url: Some(selected_url),
+        selected_line,
+        lines,
+        expires_at,
+        resolver,
+        needs_proxy,
+        low_latency_line,
+        low_latency: low_latency.map(|requested| requested && low_latency_applied),
+        raw: detail.raw.clone(),
     })
 }
+// 预览卡片用：只请求 profileRoom，不解析流地址
+pub(crate) async fn peek_huya_room(
+    client: &reqwest::Client,
+    room_id: &str,
+) -> Result<crate::platforms::common::LiveStreamInfo, String> {
+    let detail = fetch_room_detail(client, room_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(crate::platforms::common::LiveStreamInfo {
+        title: detail.title,
+        anchor_name: detail.nick,
+        avatar: detail
+            .avatar180
+            .map(|u| crate::proxy::proxied_image_url(&u)),
+        status: Some(if detail.status { 1 } else { 0 }),
+        ..Default::default()
+    })
+}
+
 #[allow(dead_code)]
 const HEARTBEAT_BASE64: &str = "ABQdAAwsNgBM"; // same as Python
+
+// 轻量开播状态查询：仅请求 profileRoom，不解析 web 端流地址
+pub async fn fetch_huya_live_status(
+    client: &reqwest::Client,
+    room_id: &str,
+) -> Result<bool, String> {
+    fetch_room_detail(client, room_id)
+        .await
+        .map(|detail| detail.status)
+        .map_err(|e| e.to_string())
+}
+
+// 在线人数（同样只请求 profileRoom）；返回 (是否开播, 人数)
+pub async fn fetch_huya_viewers(
+    client: &reqwest::Client,
+    room_id: &str,
+) -> Result<(bool, Option<u64>), String> {
+    fetch_room_detail(client, room_id)
+        .await
+        .map(|detail| (detail.status, detail.viewers))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 按直播间页面防盗链参数的格式编造的取值；wsTime=6553f100 即 1700000000
+    const SAMPLE_ANTI_CODE: &str = "wsSecret=0123456789abcdef0123456789abcdef&amp;wsTime=6553f100&amp;fm=RFdxOEJjSjNoNkRKdDZUWV8kMF8kMV8kMl8kMw%3D%3D&amp;ctype=huya_live&amp;fs=bgct&amp;t=100";
+
+    #[test]
+    fn resolve_ratio_falls_back_to_origin() {
+        assert_eq!(resolve_ratio(None), None);
+        assert_eq!(resolve_ratio(Some("原画")), None);
+        assert_eq!(resolve_ratio(Some("高清")), Some(4000));
+        assert_eq!(resolve_ratio(Some("2000")), Some(2000));
+        assert_eq!(resolve_ratio(Some("8K")), None);
+    }
+
+    #[test]
+    fn low_latency_prefers_hs_line_unless_a_line_is_given() {
+        let lines = vec!["tx".to_string(), "al".to_string(), "hs".to_string()];
+        assert_eq!(
+            preferred_line_for(None, true, &lines).as_deref(),
+            Some("hs")
+        );
+        assert_eq!(preferred_line_for(None, false, &lines), None);
+        // 显式指定的线路优先于低延迟
+        assert_eq!(
+            preferred_line_for(Some("AL"), true, &lines).as_deref(),
+            Some("al")
+        );
+        // 房间没有 hs 线路时按常规顺序挑选
+        assert_eq!(preferred_line_for(None, true, &lines[..2]), None);
+    }
+
+    #[test]
+    fn parses_expiry_from_sample_anti_code() {
+        let url = format!(
+            "https://al.flv.huya.com/src/1199-yg.flv?{}",
+            SAMPLE_ANTI_CODE
+        );
+        assert_eq!(anti_code_expiry(&url), Some(1_700_000_000));
+    }
+
+    #[test]
+    fn missing_or_invalid_ws_time_has_no_expiry() {
+        assert_eq!(
+            anti_code_expiry("https://al.flv.huya.com/src/1199.flv"),
+            None
+        );
+        assert_eq!(
+            anti_code_expiry("https://al.flv.huya.com/src/1199.flv?fs=bgct"),
+            None
+        );
+        assert_eq!(
+            anti_code_expiry("https://al.flv.huya.com/src/1199.flv?wsTime=zz"),
+            None
+        );
+    }
+
+    #[test]
+    fn remaining_validity_is_clamped() {
+        let now = current_millis() / 1000;
+        assert_eq!(remaining_validity_secs(now - 60), 0);
+        let remaining = remaining_validity_secs(now + 600);
+        assert!((599..=600).contains(&remaining), "{}", remaining);
+    }
+
+    #[test]
+    fn generated_anti_code_expires_shortly_after_now() {
+        let query = generate_web_anti_code("1199-yg", SAMPLE_ANTI_CODE).unwrap();
+        let expires_at = anti_code_expiry(&format!(
+            "https://al.flv.huya.com/src/1199-yg.flv?{}",
+            query
+        ))
+        .unwrap();
+        // 网页端 SDK 的 wsTime 约为当前时间 + 110 秒
+        let remaining = remaining_validity_secs(expires_at);
+        assert!((100..=111).contains(&remaining), "{}", remaining);
+        assert!(generate_web_anti_code("1199-yg", "wsTime=6553f100").is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+
+use tars_stream::prelude::*;
+
+// 虎牙 WebSocket 协议（Tars/JCE 编码）的编解码工具，弹幕监听与加入参数共用。
+// 外层为 WebSocketCommand { iCmdType(0), vData(1) }；服务端推送 iCmdType == 7，
+// vData 为 WSPushMessage { iUri(1), sMsg(2) }，sMsg 再按 iUri 解成具体消息
+
+pub const CMD_REGISTER_GROUP_REQ: i32 = 16;
+pub const CMD_MSG_PUSH: i32 = 7;
+
+pub const URI_CHAT: i64 = 1400;
+pub const URI_GIFT: i64 = 6501;
+
+// 虎牙消息字段几乎都是可选的：单个字段解析失败时取默认值，不让整条消息丢弃
+pub fn read_int32(decoder: &mut TarsDecoder, tag: u8, default: i32) -> i32 {
+    decoder.read_int32(tag, false, default).unwrap_or(default)
+}
+
+pub fn read_int64(decoder: &mut TarsDecoder, tag: u8, default: i64) -> i64 {
+    decoder.read_int64(tag, false, default).unwrap_or(default)
+}
+
+pub fn read_string(decoder: &mut TarsDecoder, tag: u8) -> String {
+    decoder
+        .read_string(tag, false, String::new())
+        .unwrap_or_default()
+}
+
+pub fn read_struct<T: StructFromTars + Default>(decoder: &mut TarsDecoder, tag: u8) -> T {
+    decoder
+        .read_struct(tag, false, T::default())
+        .unwrap_or_default()
+}
+
+#[allow(dead_code)]
+pub fn read_map<K, V>(decoder: &mut TarsDecoder, tag: u8) -> BTreeMap<K, V>
+where
+    K: DecodeTars + Ord,
+    V: DecodeTars,
+{
+    decoder
+        .read_map(tag, false, BTreeMap::new())
+        .unwrap_or_default()
+}
+
+// 外层命令包：vData 为内层结构编码后的字节
+pub fn encode_ws_command(cmd: i32, body: TarsEncoder) -> Result<Vec<u8>, String> {
+    let mut encoder = TarsEncoder::new();
+    encoder.write_int32(0, cmd).map_err(|e| e.to_string())?;
+    encoder
+        .write_bytes(1, &body.to_bytes())
+        .map_err(|e| e.to_string())?;
+    Ok(encoder.to_bytes().as_ref().to_vec())
+}
+
+// WS 注册包：WSRegisterGroupReq { vGroupId(0), sToken(1) } 包在 cmd=16 的 WebSocketCommand 中
+pub fn encode_register_group(topics: &[String]) -> Result<Vec<u8>, String> {
+    let mut req = TarsEncoder::new();
+    req.write_list(0, &topics.to_vec())
+        .map_err(|e| e.to_string())?;
+    req.write_string(1, &String::new())
+        .map_err(|e| e.to_string())?;
+    encode_ws_command(CMD_REGISTER_GROUP_REQ, req)
+}
+
+// 只读取命令号，用于日志；解析失败的层级返回 None
+pub fn peek_cmds(data: &[u8]) -> (Option<i32>, Option<i64>) {
+    let mut ios = TarsDecoder::from(data);
+    let top_cmd = ios.read_int32(0, false, -1).ok();
+    let nested_cmd = ios
+        .read_bytes(1, false, Default::default())
+        .ok()
+        .and_then(|b1| {
+            let mut inner = TarsDecoder::from(b1.as_ref());
+            inner.read_int32(1, false, -1).ok().map(|v| v as i64)
+        });
+    (top_cmd, nested_cmd)
+}
+
+pub struct PushMessage {
+    pub uri: i64,
+    pub msg: Vec<u8>,
+}
+
+// 非推送命令（心跳回包、注册回包等）返回 None
+pub fn decode_push_message(data: &[u8]) -> Result<Option<PushMessage>, DecodeErr> {
+    let mut ios = TarsDecoder::from(data);
+    if ios.read_int32(0, false, -1)? != CMD_MSG_PUSH {
+        return Ok(None);
+    }
+    let push = ios.read_bytes(1, false, Default::default())?;
+    let mut inner = TarsDecoder::from(push.as_ref());
+    let uri = inner.read_int32(1, false, -1).unwrap_or(-1) as i64;
+    let msg = inner.read_bytes(2, false, Default::default())?;
+    Ok(Some(PushMessage {
+        uri,
+        msg: msg.as_ref().to_vec(),
+    }))
+}
+
+pub struct HuyaUser {
+    pub _uid: i64,
+    pub _imid: i64,
+    pub name: String,
+    pub _gender: i32,
+}
+
+impl Default for HuyaUser {
+    fn default() -> Self {
+        HuyaUser {
+            _uid: -1,
+            _imid: -1,
+            name: String::new(),
+            _gender: 1,
+        }
+    }
+}
+
+impl StructFromTars for HuyaUser {
+    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
+        Ok(HuyaUser {
+            _uid: read_int64(decoder, 0, -1),
+            _imid: read_int64(decoder, 1, -1),
+            name: read_string(decoder, 2),
+            _gender: read_int32(decoder, 3, -1),
+        })
+    }
+}
+
+// iUri == 1400 的 MessageNotice：tUserInfo(0) / sContent(3)；
+// 弹幕颜色在 tBulletFormat(6) 中，前端暂不展示，未解析
+pub struct HuyaChat {
+    pub user: HuyaUser,
+    pub content: String,
+}
+
+impl HuyaChat {
+    pub fn decode(msg: &[u8]) -> Self {
+        let mut decoder = TarsDecoder::from(msg);
+        HuyaChat {
+            user: read_struct(&mut decoder, 0),
+            content: read_string(&mut decoder, 3),
+        }
+    }
+}
+
+// iUri == 6501 的 SendItemSubBroadcastPacket：iItemType(0) / iItemCount(2) / sSenderNick(6)。
+// 包内没有礼物名称，只有礼物类型 ID
+pub struct HuyaGift {
+    pub item_type: i32,
+    pub count: i32,
+    pub sender_nick: String,
+}
+
+impl HuyaGift {
+    pub fn decode(msg: &[u8]) -> Self {
+        let mut decoder = TarsDecoder::from(msg);
+        HuyaGift {
+            item_type: read_int32(&mut decoder, 0, 0),
+            count: read_int32(&mut decoder, 2, 0),
+            sender_nick: read_string(&mut decoder, 6),
+        }
+    }
+}
+
+pub enum HuyaMessage {
+    Chat(HuyaChat),
+    Gift(HuyaGift),
+}
+
+// 解出弹幕/礼物消息；其他推送类型或空消息返回 Ok(None)
+pub fn decode_message(data: &[u8]) -> Result<Option<HuyaMessage>, DecodeErr> {
+    let Some(push) = decode_push_message(data)? else {
+        return Ok(None);
+    };
+    let message = match push.uri {
+        URI_CHAT => Some(HuyaChat::decode(&push.msg))
+            .filter(|chat| !chat.content.is_empty())
+            .map(HuyaMessage::Chat),
+        URI_GIFT => Some(HuyaGift::decode(&push.msg))
+            .filter(|gift| gift.count > 0)
+            .map(HuyaMessage::Gift),
+        _ => None,
+    };
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 按协议手工编码的服务端推送帧：
+    // WebSocketCommand{7, WSPushMessage{1400, MessageNotice{SenderInfo{12345, 0, "虎牙用户", 1}, "主播666"}}}
+    const CHAT_PUSH: &[u8] = b"\x00\x07\x1d\x00\x00\x28\x11\x05\x78\x2d\x00\x00\x21\x0a\x01\x30\x39\x1c\x26\x0c\xe8\x99\x8e\xe7\x89\x99\xe7\x94\xa8\xe6\x88\xb7\x30\x01\x0b\x36\x09\xe4\xb8\xbb\xe6\x92\xad\x36\x36\x36";
+    // WebSocketCommand{7, WSPushMessage{6501, SendItemSubBroadcastPacket{iItemType 4, iItemCount 10, sSenderNick "送礼用户"}}}
+    const GIFT_PUSH: &[u8] = b"\x00\x07\x1d\x00\x00\x19\x11\x19\x65\x2d\x00\x00\x12\x00\x04\x20\x0a\x66\x0c\xe9\x80\x81\xe7\xa4\xbc\xe7\x94\xa8\xe6\x88\xb7";
+    // 心跳回包 WebSocketCommand{iCmdType 2}
+    const HEARTBEAT_ACK: &[u8] = b"\x00\x02";
+
+    fn push_frame(uri: i32, msg: TarsEncoder) -> Vec<u8> {
+        let mut push = TarsEncoder::new();
+        push.write_int32(1, uri).unwrap();
+        push.write_bytes(2, &msg.to_bytes()).unwrap();
+        encode_ws_command(CMD_MSG_PUSH, push).unwrap()
+    }
+
+    #[test]
+    fn primitives_round_trip() {
+        let mut encoder = TarsEncoder::new();
+        encoder.write_int32(0, 300).unwrap();
+        encoder.write_int64(1, 1 << 40).unwrap();
+        encoder.write_string(2, &"弹幕".to_string()).unwrap();
+        let mut map = BTreeMap::new();
+        map.insert("room".to_string(), "1199".to_string());
+        map.insert("uid".to_string(), "0".to_string());
+        encoder.write_map(3, &map).unwrap();
+        let bytes = encoder.to_bytes();
+
+        let mut decoder = TarsDecoder::from(bytes.as_ref());
+        assert_eq!(read_int32(&mut decoder, 0, -1), 300);
+        assert_eq!(read_int64(&mut decoder, 1, -1), 1 << 40);
+        assert_eq!(read_string(&mut decoder, 2), "弹幕");
+        assert_eq!(read_map::<String, String>(&mut decoder, 3), map);
+    }
+
+    #[test]
+    fn missing_or_mistyped_fields_fall_back_to_defaults() {
+        let mut encoder = TarsEncoder::new();
+        encoder
+            .write_string(0, &"not a number".to_string())
+            .unwrap();
+        let bytes = encoder.to_bytes();
+
+        assert_eq!(read_int32(&mut TarsDecoder::from(bytes.as_ref()), 0, 7), 7);
+        assert_eq!(
+            read_int64(&mut TarsDecoder::from(bytes.as_ref()), 5, -1),
+            -1
+        );
+        assert_eq!(read_string(&mut TarsDecoder::from(bytes.as_ref()), 5), "");
+        let user: HuyaUser = read_struct(&mut TarsDecoder::from(bytes.as_ref()), 5);
+        assert_eq!((user._uid, user.name.as_str()), (-1, ""));
+        assert!(read_map::<String, String>(&mut TarsDecoder::from(bytes.as_ref()), 5).is_empty());
+    }
+
+    #[test]
+    fn decodes_chat_push() {
+        assert_eq!(peek_cmds(CHAT_PUSH), (Some(CMD_MSG_PUSH), Some(URI_CHAT)));
+        let push = decode_push_message(CHAT_PUSH).unwrap().unwrap();
+        assert_eq!(push.uri, URI_CHAT);
+        let Some(HuyaMessage::Chat(chat)) = decode_message(CHAT_PUSH).unwrap() else {
+            panic!("expected a chat message");
+        };
+        assert_eq!(chat.user._uid, 12345);
+        assert_eq!(chat.user._imid, 0);
+        assert_eq!(chat.user.name, "虎牙用户");
+        assert_eq!(chat.user._gender, 1);
+        assert_eq!(chat.content, "主播666");
+    }
+
+    #[test]
+    fn decodes_gift_push() {
+        assert_eq!(peek_cmds(GIFT_PUSH), (Some(CMD_MSG_PUSH), Some(URI_GIFT)));
+        let Some(HuyaMessage::Gift(gift)) = decode_message(GIFT_PUSH).unwrap() else {
+            panic!("expected a gift message");
+        };
+        assert_eq!(gift.item_type, 4);
+        assert_eq!(gift.count, 10);
+        assert_eq!(gift.sender_nick, "送礼用户");
+    }
+
+    #[test]
+    fn non_push_and_empty_messages_are_skipped() {
+        assert_eq!(peek_cmds(HEARTBEAT_ACK).0, Some(2));
+        assert!(decode_push_message(HEARTBEAT_ACK).unwrap().is_none());
+        assert!(decode_message(HEARTBEAT_ACK).unwrap().is_none());
+
+        let mut empty_chat = TarsEncoder::new();
+        empty_chat.write_string(3, &String::new()).unwrap();
+        assert!(decode_message(&push_frame(1400, empty_chat))
+            .unwrap()
+            .is_none());
+
+        let mut zero_gift = TarsEncoder::new();
+        zero_gift.write_int32(0, 4).unwrap();
+        assert!(decode_message(&push_frame(6501, zero_gift))
+            .unwrap()
+            .is_none());
+
+        let mut other = TarsEncoder::new();
+        other.write_string(0, &"公告".to_string()).unwrap();
+        let frame = push_frame(8006, other);
+        assert_eq!(decode_push_message(&frame).unwrap().unwrap().uri, 8006);
+        assert!(decode_message(&frame).unwrap().is_none());
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use tauri::State;
+
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::ttl_cache::TtlCache;
+use crate::platforms::common::{FollowHttpClient, SupportedPlatformRust};
+
+// 关注列表轮询会在短时间内重复查询同一房间，这里缓存几秒避免重复请求
+const LIVE_STATUS_TTL_SECONDS: u64 = 10;
+
+static LIVE_STATUS_CACHE: Lazy<TtlCache<bool>> =
+    Lazy::new(|| TtlCache::new(Duration::from_secs(LIVE_STATUS_TTL_SECONDS)));
+
+pub async fn fetch_live_status(
+    platform: &SupportedPlatformRust,
+    room_id: &str,
+    follow_http: &FollowHttpClient,
+) -> Result<bool, String> {
+    let room_id = validate_room_id(platform, room_id)?;
+    let room_id = room_id.as_str();
+
+    let cache_key = format!("{}:{}", platform.as_str(), room_id);
+    if let Some(is_live) = LIVE_STATUS_CACHE.get(&cache_key) {
+        return Ok(is_live);
+    }
+
+    let is_live = match platform {
+        SupportedPlatformRust::Douyu => crate::platforms::douyu::fetch_douyu_live_status(room_id)
+            .await
+            .map_err(|e| e.to_string())?,
+        SupportedPlatformRust::Huya => {
+            crate::platforms::huya::stream_url::fetch_huya_live_status(
+                &follow_http.client().inner,
+                room_id,
+            )
+            .await?
+        }
+        SupportedPlatformRust::Bilibili => {
+            crate::platforms::bilibili::stream_url::fetch_bilibili_live_status(room_id).await?
+        }
+        SupportedPlatformRust::Douyin => {
+            crate::platforms::douyin::douyin_streamer_detail::fetch_douyin_live_status(room_id)
+                .await?
+        }
+    };
+
+    LIVE_STATUS_CACHE.insert(cache_key, is_live);
+    Ok(is_live)
+}
+
+#[tauri::command]
+pub async fn is_room_live(
+    platform: String,
+    room_id: String,
+    follow_http: State<'_, FollowHttpClient>,
+) -> Result<bool, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    fetch_live_status(&platform, &room_id, follow_http.inner())
+        .await
+        .map_err(|e| {
+            eprintln!(
+                "[Live Status] Failed to check {} room {}: {}",
+                platform.as_str(),
+                room_id,
+                e
+            );
+            e
+        })
+}
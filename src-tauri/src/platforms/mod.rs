@@ -2,7 +2,12 @@ pub mod bilibili;
 pub mod common;
 pub mod douyin;
 pub mod douyu;
+pub mod follow_polling;
 pub mod huya;
+pub mod live_status;
+pub mod recommend;
+pub mod search;
+pub mod viewers;
 
 // pub use douyu::*; // Removed to avoid ambiguity and encourage explicit paths
 // pub use common::*; // Removed for consistency
@@ -0,0 +1,406 @@
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::platforms::common::cn_number::{format_cn_number, parse_cn_number};
+use crate::platforms::common::json_value::scalar_string;
+use crate::platforms::common::user_agent::user_agent_for;
+use crate::platforms::common::{SupportedPlatformRust, UnifiedRoom};
+use crate::proxy::proxied_image_url;
+
+const RECOMMEND_TIMEOUT_SECONDS: u64 = 10;
+const DEFAULT_RECOMMEND_LIMIT: usize = 20;
+const MAX_RECOMMEND_LIMIT: usize = 60;
+// 抖音没有独立推荐接口，使用“热门”分区
+const DOUYIN_HOT_PARTITION: &str = "720";
+const DOUYIN_HOT_PARTITION_TYPE: &str = "1";
+
+fn str_field(item: &Value, key: &str) -> String {
+    item.get(key).and_then(scalar_string).unwrap_or_default()
+}
+
+fn build_client(platform: &SupportedPlatformRust) -> Result<reqwest::Client, String> {
+    crate::network::api_proxy()
+        .apply(reqwest::Client::builder())
+        .user_agent(user_agent_for(platform))
+        .timeout(Duration::from_secs(RECOMMEND_TIMEOUT_SECONDS))
+        .build()
+        .map_err(|e| format!("Failed to build client: {}", e))
+}
+
+async fn get_json(client: &reqwest::Client, url: &str, referer: &str) -> Result<Value, String> {
+    client
+        .get(url)
+        .header("Referer", referer)
+        .send()
+        .await
+        .map_err(|e| format!("Request {} failed: {}", url, e))?
+        .json::<Value>()
+        .await
+        .map_err(|e| format!("Failed to parse JSON from {}: {}", url, e))
+}
+
+// 推荐接口返回的是平台 CDN 原图地址，统一改写为本地图片代理
+fn proxy_images(mut room: UnifiedRoom) -> UnifiedRoom {
+    room.avatar = proxied_image_url(&room.avatar);
+    room.cover = proxied_image_url(&room.cover);
+    room
+}
+
+async fn fetch_douyu_recommended(limit: usize) -> Result<Vec<UnifiedRoom>, String> {
+    let client = build_client(&SupportedPlatformRust::Douyu)?;
+    let json = get_json(
+        &client,
+        "https://www.douyu.com/japi/weblist/apinc/allpage/6/1",
+        "https://www.douyu.com/",
+    )
+    .await?;
+    parse_douyu_recommended(&json, limit)
+}
+
+fn parse_douyu_recommended(json: &Value, limit: usize) -> Result<Vec<UnifiedRoom>, String> {
+    let platform = SupportedPlatformRust::Douyu;
+    let list = json
+        .pointer("/data/rl")
+        .and_then(|v| v.as_array())
+        .ok_or("Douyu recommend response has no data.rl")?;
+    Ok(list
+        .iter()
+        .filter(|item| item.get("type").and_then(|v| v.as_i64()).unwrap_or(1) == 1)
+        .take(limit)
+        .map(|item| {
+            let viewers = item.get("ol").and_then(|v| v.as_u64());
+            let av = str_field(item, "av");
+            let avatar = if av.is_empty() || av.starts_with("http") {
+                av
+            } else {
+                format!("https://apic.douyucdn.cn/upload/{}_middle.jpg", av)
+            };
+            proxy_images(UnifiedRoom {
+                platform: platform.as_str().to_string(),
+                room_id: str_field(item, "rid"),
+                title: str_field(item, "rn"),
+                nickname: str_field(item, "nn"),
+                avatar,
+                cover: str_field(item, "rs16"),
+                viewer_count: viewers,
+                viewer_count_str: viewers.map(format_cn_number).unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+async fn fetch_bilibili_recommended(limit: usize) -> Result<Vec<UnifiedRoom>, String> {
+    let client = build_client(&SupportedPlatformRust::Bilibili)?;
+    let json = get_json(
+        &client,
+        "https://api.live.bilibili.com/xlive/web-interface/v1/webMain/getMoreRecList?platform=web",
+        "https://live.bilibili.com/",
+    )
+    .await?;
+    parse_bilibili_recommended(&json, limit)
+}
+
+fn parse_bilibili_recommended(json: &Value, limit: usize) -> Result<Vec<UnifiedRoom>, String> {
+    let platform = SupportedPlatformRust::Bilibili;
+    let list = json
+        .pointer("/data/recommend_room_list")
+        .and_then(|v| v.as_array())
+        .ok_or("Bilibili recommend response has no data.recommend_room_list")?;
+    Ok(list
+        .iter()
+        .take(limit)
+        .map(|item| {
+            let viewers = item
+                .pointer("/watched_show/num")
+                .and_then(|v| v.as_u64())
+                .or_else(|| item.get("online").and_then(|v| v.as_u64()));
+            let cover = item
+                .get("cover")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .unwrap_or_else(|| str_field(item, "keyframe"));
+            proxy_images(UnifiedRoom {
+                platform: platform.as_str().to_string(),
+                room_id: str_field(item, "roomid"),
+                title: str_field(item, "title"),
+                nickname: str_field(item, "uname"),
+                avatar: str_field(item, "face"),
+                cover,
+                viewer_count: viewers,
+                viewer_count_str: viewers.map(format_cn_number).unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+async fn fetch_huya_recommended(limit: usize) -> Result<Vec<UnifiedRoom>, String> {
+    let client = build_client(&SupportedPlatformRust::Huya)?;
+    let json = get_json(
+        &client,
+        "https://www.huya.com/cache.php?m=LiveList&do=getLiveListByPage&tagAll=0&page=1",
+        "https://www.huya.com/",
+    )
+    .await?;
+    parse_huya_recommended(&json, limit)
+}
+
+fn parse_huya_recommended(json: &Value, limit: usize) -> Result<Vec<UnifiedRoom>, String> {
+    let platform = SupportedPlatformRust::Huya;
+    let list = json
+        .pointer("/data/datas")
+        .and_then(|v| v.as_array())
+        .ok_or("Huya recommend response has no data.datas")?;
+    Ok(list
+        .iter()
+        .take(limit)
+        .map(|item| {
+            let viewers_str = str_field(item, "totalCount");
+            let viewers = parse_cn_number(&viewers_str);
+            let title = item
+                .get("roomName")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .unwrap_or_else(|| str_field(item, "introduction"));
+            proxy_images(UnifiedRoom {
+                platform: platform.as_str().to_string(),
+                room_id: str_field(item, "profileRoom"),
+                title,
+                nickname: str_field(item, "nick"),
+                avatar: str_field(item, "avatar180"),
+                cover: str_field(item, "screenshot"),
+                viewer_count: viewers,
+                viewer_count_str: viewers.map(format_cn_number).unwrap_or(viewers_str),
+            })
+        })
+        .collect())
+}
+
+async fn fetch_douyin_recommended(limit: usize) -> Result<Vec<UnifiedRoom>, String> {
+    let platform = SupportedPlatformRust::Douyin;
+    let response =
+        crate::platforms::douyin::douyin_streamer_list::fetch_douyin_partition_rooms_remote(
+            DOUYIN_HOT_PARTITION.to_string(),
+            DOUYIN_HOT_PARTITION_TYPE.to_string(),
+            0,
+            crate::platforms::douyin::danmu::signature::generate_douyin_ms_token(),
+        )
+        .await?;
+    // 分区列表里的图片已经过本地代理改写，这里直接沿用
+    Ok(response
+        .rooms
+        .into_iter()
+        .take(limit)
+        .map(|room| UnifiedRoom {
+            platform: platform.as_str().to_string(),
+            room_id: room.web_rid,
+            title: room.title,
+            nickname: room.owner_nickname,
+            avatar: room.avatar_url,
+            cover: room.cover_url,
+            viewer_count: parse_cn_number(&room.user_count_str),
+            viewer_count_str: room.user_count_str,
+        })
+        .collect())
+}
+
+async fn fetch_platform_recommended(
+    platform: &SupportedPlatformRust,
+    limit: usize,
+) -> Result<Vec<UnifiedRoom>, String> {
+    match platform {
+        SupportedPlatformRust::Douyu => fetch_douyu_recommended(limit).await,
+        SupportedPlatformRust::Bilibili => fetch_bilibili_recommended(limit).await,
+        SupportedPlatformRust::Huya => fetch_huya_recommended(limit).await,
+        SupportedPlatformRust::Douyin => fetch_douyin_recommended(limit).await,
+    }
+}
+
+// 首页“推荐”：platform 为具体平台时返回该平台推荐位；为 "all" 时四个平台并发拉取，
+// 按在线人数混排（interleave=false 时按平台顺序拼接），合并后同样最多返回 limit 条。单个平台失败不影响其他平台
+#[tauri::command]
+pub async fn fetch_recommended(
+    platform: String,
+    limit: Option<usize>,
+    interleave: Option<bool>,
+) -> Result<Vec<UnifiedRoom>, String> {
+    let limit = limit
+        .unwrap_or(DEFAULT_RECOMMEND_LIMIT)
+        .clamp(1, MAX_RECOMMEND_LIMIT);
+    if !platform.trim().eq_ignore_ascii_case("all") {
+        let platform = SupportedPlatformRust::parse(&platform)?;
+        return fetch_platform_recommended(&platform, limit).await;
+    }
+
+    let platforms = [
+        SupportedPlatformRust::Douyu,
+        SupportedPlatformRust::Bilibili,
+        SupportedPlatformRust::Huya,
+        SupportedPlatformRust::Douyin,
+    ];
+    let results = futures_util::future::join_all(
+        platforms
+            .iter()
+            .map(|platform| fetch_platform_recommended(platform, limit)),
+    )
+    .await;
+
+    merge_recommended(
+        platforms.iter().zip(results),
+        limit,
+        interleave.unwrap_or(true),
+    )
+}
+
+// 合并各平台结果：混排时按在线人数排序，最终只保留 limit 条；全部失败时返回各平台错误
+fn merge_recommended<'a>(
+    results: impl IntoIterator<Item = (&'a SupportedPlatformRust, Result<Vec<UnifiedRoom>, String>)>,
+    limit: usize,
+    interleave: bool,
+) -> Result<Vec<UnifiedRoom>, String> {
+    let mut rooms = Vec::new();
+    let mut errors = Vec::new();
+    for (platform, result) in results {
+        match result {
+            Ok(list) => rooms.extend(list),
+            Err(e) => {
+                eprintln!("[Recommend] {} failed: {}", platform.as_str(), e);
+                errors.push(format!("{}: {}", platform.as_str(), e));
+            }
+        }
+    }
+    if rooms.is_empty() && !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    if interleave {
+        rooms.sort_by_key(|room| std::cmp::Reverse(room.viewer_count.unwrap_or(0)));
+    }
+    rooms.truncate(limit);
+    println!(
+        "[Recommend] merged {} rooms ({} platform errors)",
+        rooms.len(),
+        errors.len()
+    );
+    Ok(rooms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn room(platform: &str, room_id: &str, viewers: u64) -> UnifiedRoom {
+        UnifiedRoom {
+            platform: platform.to_string(),
+            room_id: room_id.to_string(),
+            viewer_count: Some(viewers),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merged_rooms_are_truncated_to_limit() {
+        let douyu = SupportedPlatformRust::Douyu;
+        let huya = SupportedPlatformRust::Huya;
+        let results = vec![
+            (
+                &douyu,
+                Ok(vec![room("douyu", "1", 10), room("douyu", "2", 300)]),
+            ),
+            (
+                &huya,
+                Ok(vec![room("huya", "3", 200), room("huya", "4", 5)]),
+            ),
+        ];
+        let merged = merge_recommended(results, 3, true).unwrap();
+        let ids: Vec<&str> = merged.iter().map(|r| r.room_id.as_str()).collect();
+        assert_eq!(ids, ["2", "3", "1"]);
+
+        let results = vec![
+            (
+                &douyu,
+                Ok(vec![room("douyu", "1", 10), room("douyu", "2", 300)]),
+            ),
+            (&huya, Ok(vec![room("huya", "3", 200)])),
+        ];
+        let merged = merge_recommended(results, 2, false).unwrap();
+        let ids: Vec<&str> = merged.iter().map(|r| r.room_id.as_str()).collect();
+        assert_eq!(ids, ["1", "2"]);
+    }
+
+    #[test]
+    fn merge_fails_only_when_every_platform_fails() {
+        let douyu = SupportedPlatformRust::Douyu;
+        let huya = SupportedPlatformRust::Huya;
+        let partial = vec![
+            (&douyu, Err("timeout".to_string())),
+            (&huya, Ok(vec![room("huya", "3", 200)])),
+        ];
+        assert_eq!(merge_recommended(partial, 20, true).unwrap().len(), 1);
+
+        let failed = vec![
+            (&douyu, Err("timeout".to_string())),
+            (&huya, Err("403".to_string())),
+        ];
+        let err = merge_recommended(failed, 20, true).unwrap_err();
+        assert!(err.contains("douyu: timeout") && err.contains("huya: 403"));
+    }
+
+    // 以下响应均为按各平台接口结构手写的合成数据
+    #[test]
+    fn parses_douyu_recommend_list() {
+        let json = json!({"data": {"rl": [
+            {"type": 1, "rid": 288016, "rn": "标题", "nn": "主播", "av": "avatar/abc", "rs16": "https://rpic.douyucdn.cn/c.jpg", "ol": 123456},
+            {"type": 2, "rid": 1, "rn": "广告位"},
+            {"rid": "9999", "rn": "无人数", "av": "https://apic.douyucdn.cn/a.jpg"}
+        ]}});
+        let rooms = parse_douyu_recommended(&json, 20).unwrap();
+        assert_eq!(rooms.len(), 2);
+        assert_eq!(rooms[0].room_id, "288016");
+        assert_eq!(
+            rooms[0].avatar,
+            "https://apic.douyucdn.cn/upload/avatar/abc_middle.jpg"
+        );
+        assert_eq!(rooms[0].viewer_count, Some(123456));
+        assert_eq!(rooms[0].viewer_count_str, format_cn_number(123456));
+        assert_eq!(rooms[1].room_id, "9999");
+        assert_eq!(rooms[1].avatar, "https://apic.douyucdn.cn/a.jpg");
+        assert_eq!(rooms[1].viewer_count, None);
+
+        assert_eq!(parse_douyu_recommended(&json, 1).unwrap().len(), 1);
+        assert!(parse_douyu_recommended(&json!({"data": {}}), 20).is_err());
+    }
+
+    #[test]
+    fn parses_bilibili_recommend_list() {
+        let json = json!({"data": {"recommend_room_list": [
+            {"roomid": 7734200, "title": "标题", "uname": "主播", "face": "https://i0.hdslb.com/f.jpg", "cover": "", "keyframe": "https://i0.hdslb.com/k.jpg", "watched_show": {"num": 5000}},
+            {"roomid": "21", "title": "旧字段", "cover": "https://i0.hdslb.com/c.jpg", "online": 42}
+        ]}});
+        let rooms = parse_bilibili_recommended(&json, 20).unwrap();
+        assert_eq!(rooms[0].room_id, "7734200");
+        assert_eq!(rooms[0].cover, "https://i0.hdslb.com/k.jpg");
+        assert_eq!(rooms[0].viewer_count, Some(5000));
+        assert_eq!(rooms[1].cover, "https://i0.hdslb.com/c.jpg");
+        assert_eq!(rooms[1].viewer_count, Some(42));
+        assert!(parse_bilibili_recommended(&json!({"code": -352}), 20).is_err());
+    }
+
+    #[test]
+    fn parses_huya_recommend_list() {
+        let json = json!({"data": {"datas": [
+            {"profileRoom": "11342412", "roomName": "", "introduction": "简介", "nick": "主播", "avatar180": "https://huyaimg.msstatic.com/a.jpg", "screenshot": "https://live-cover.msstatic.com/s.jpg", "totalCount": "1.2万"},
+            {"profileRoom": 880201, "roomName": "房间名", "totalCount": "不是数字"}
+        ]}});
+        let rooms = parse_huya_recommended(&json, 20).unwrap();
+        assert_eq!(rooms[0].title, "简介");
+        assert_eq!(rooms[0].viewer_count, Some(12000));
+        assert_eq!(rooms[1].room_id, "880201");
+        assert_eq!(rooms[1].title, "房间名");
+        assert_eq!(rooms[1].viewer_count, None);
+        assert_eq!(rooms[1].viewer_count_str, "不是数字");
+        assert!(parse_huya_recommended(&json!({"data": null}), 20).is_err());
+    }
+}
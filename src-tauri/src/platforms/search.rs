@@ -0,0 +1,258 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::platforms::common::SupportedPlatformRust;
+use crate::proxy::proxied_image_url;
+
+const DEFAULT_SEARCH_PAGE_SIZE: u32 = 20;
+const MAX_SEARCH_PAGE_SIZE: u32 = 50;
+
+// 与前端 SearchResultItem 对应的统一搜索结果
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResultItem {
+    pub platform: String,
+    pub room_id: String,
+    pub user_name: String,
+    pub room_title: Option<String>,
+    pub avatar: Option<String>,
+    pub live_status: bool,
+    pub fans_count: Option<String>,
+    pub category: Option<String>,
+}
+
+// next_page 为下一页页码，原样传回 search_rooms 的 page 即可；没有更多结果时为 None
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchPage {
+    pub items: Vec<SearchResultItem>,
+    pub page: u32,
+    pub page_size: u32,
+    pub has_more: bool,
+    pub next_page: Option<u32>,
+}
+
+impl SearchPage {
+    fn new(items: Vec<SearchResultItem>, page: u32, page_size: u32, has_more: bool) -> Self {
+        Self {
+            items,
+            page,
+            page_size,
+            has_more,
+            next_page: has_more.then_some(page + 1),
+        }
+    }
+}
+
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|v| !v.is_empty())
+}
+
+fn json_string(item: &Value, key: &str) -> String {
+    match item.get(key) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) => n.to_string(),
+        _ => String::new(),
+    }
+}
+
+// 斗鱼搜索接口返回原始 JSON：data.relateUser 中 type == 1 的为主播，
+// isLive == 1 且不是录播轮播（videoLoop == 1）才算开播。
+// 接口不给总数，本页条数（过滤前）达到 page_size 即视为还有下一页
+fn parse_douyu_search(text: &str, page_size: u32) -> Result<(Vec<SearchResultItem>, bool), String> {
+    let json: Value = serde_json::from_str(text)
+        .map_err(|e| format!("Failed to parse Douyu search JSON: {}", e))?;
+    if json.get("error").and_then(|v| v.as_i64()) != Some(0) {
+        return Err(format!(
+            "Douyu search failed: {}",
+            json.get("msg")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown error")
+        ));
+    }
+    let users = json
+        .pointer("/data/relateUser")
+        .and_then(|v| v.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let items = users
+        .iter()
+        .filter(|item| item.get("type").and_then(|v| v.as_i64()) == Some(1))
+        .filter_map(|item| item.get("anchorInfo"))
+        .map(|anchor| SearchResultItem {
+            platform: SupportedPlatformRust::Douyu.as_str().to_string(),
+            room_id: json_string(anchor, "rid"),
+            user_name: json_string(anchor, "nickName"),
+            room_title: non_empty(json_string(anchor, "roomName"))
+                .or_else(|| non_empty(json_string(anchor, "description"))),
+            avatar: non_empty(proxied_image_url(&json_string(anchor, "avatar"))),
+            live_status: anchor.get("isLive").and_then(|v| v.as_i64()) == Some(1)
+                && anchor.get("videoLoop").and_then(|v| v.as_i64()) != Some(1),
+            fans_count: non_empty(json_string(anchor, "fansNumStr")),
+            category: non_empty(json_string(anchor, "cateName")),
+        })
+        .filter(|item| !item.room_id.is_empty())
+        .collect();
+    Ok((items, users.len() >= page_size as usize))
+}
+
+async fn search_platform_page(
+    platform: &SupportedPlatformRust,
+    keyword: &str,
+    page: u32,
+    page_size: u32,
+    cookie: Option<String>,
+) -> Result<(Vec<SearchResultItem>, bool), String> {
+    match platform {
+        SupportedPlatformRust::Douyu => {
+            let text =
+                crate::platforms::douyu::perform_anchor_search_page(keyword, page, page_size)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            parse_douyu_search(&text, page_size)
+        }
+        SupportedPlatformRust::Huya => {
+            let (anchors, has_more) = crate::platforms::huya::search::search_huya_page(
+                keyword,
+                page as usize,
+                page_size as usize,
+            )
+            .await?;
+            let items = anchors
+                .into_iter()
+                .map(|anchor| SearchResultItem {
+                    platform: platform.as_str().to_string(),
+                    room_id: anchor.room_id,
+                    user_name: anchor.user_name,
+                    room_title: non_empty(anchor.title),
+                    avatar: non_empty(anchor.avatar),
+                    live_status: anchor.live_status,
+                    fans_count: None,
+                    category: None,
+                })
+                .collect();
+            Ok((items, has_more))
+        }
+        SupportedPlatformRust::Bilibili => {
+            let (rooms, has_more) = crate::platforms::bilibili::search::search_bilibili_page(
+                keyword, page, page_size, cookie,
+            )
+            .await?;
+            let items = rooms
+                .into_iter()
+                .map(|room| SearchResultItem {
+                    platform: platform.as_str().to_string(),
+                    room_id: room.room_id,
+                    user_name: room.anchor,
+                    room_title: non_empty(room.title),
+                    avatar: non_empty(room.avatar),
+                    live_status: room.is_live,
+                    fans_count: non_empty(room.watching),
+                    category: non_empty(room.area),
+                })
+                .collect();
+            Ok((items, has_more))
+        }
+        SupportedPlatformRust::Douyin => Err("Douyin search is not supported".to_string()),
+    }
+}
+
+// 跨平台统一的分页搜索：结果统一为 SearchResultItem，前端按 next_page 继续加载实现无限滚动。
+// 原有的各平台搜索命令保持不变
+#[tauri::command]
+pub async fn search_rooms(
+    platform: String,
+    keyword: String,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    cookie: Option<String>,
+) -> Result<SearchPage, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let page = page.unwrap_or(1).max(1);
+    let page_size = page_size
+        .unwrap_or(DEFAULT_SEARCH_PAGE_SIZE)
+        .clamp(1, MAX_SEARCH_PAGE_SIZE);
+    let keyword = keyword.trim();
+    if keyword.is_empty() {
+        return Ok(SearchPage::new(Vec::new(), page, page_size, false));
+    }
+
+    let (items, has_more) =
+        search_platform_page(&platform, keyword, page, page_size, cookie).await?;
+    println!(
+        "[Search] {} '{}' page {}: {} items, has_more={}",
+        platform.as_str(),
+        keyword,
+        page,
+        items.len(),
+        has_more
+    );
+    Ok(SearchPage::new(items, page, page_size, has_more))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 斗鱼 searchUser 的两页响应，内容为测试编写，page_size = 2
+    const DOUYU_PAGE_1: &str = r#"{"error": 0, "msg": "ok", "data": {"relateUser": [
+        {"type": 1, "anchorInfo": {"rid": 9999, "nickName": "主播A", "roomName": "第一页 A",
+            "avatar": "", "isLive": 1, "videoLoop": 0, "fansNumStr": "12.3万", "cateName": "英雄联盟"}},
+        {"type": 1, "anchorInfo": {"rid": "5720533", "nickName": "主播B", "roomName": "",
+            "description": "录播中", "avatar": "", "isLive": 1, "videoLoop": 1}}
+    ]}}"#;
+    const DOUYU_PAGE_2: &str = r#"{"error": 0, "msg": "ok", "data": {"relateUser": [
+        {"type": 1, "anchorInfo": {"rid": 288016, "nickName": "主播C", "roomName": "第二页 C",
+            "avatar": "", "isLive": 0}}
+    ]}}"#;
+
+    fn room_ids(items: &[SearchResultItem]) -> Vec<&str> {
+        items.iter().map(|item| item.room_id.as_str()).collect()
+    }
+
+    #[test]
+    fn douyu_second_page_differs_from_first() {
+        let (first, first_more) = parse_douyu_search(DOUYU_PAGE_1, 2).unwrap();
+        let (second, second_more) = parse_douyu_search(DOUYU_PAGE_2, 2).unwrap();
+        assert_eq!(room_ids(&first), ["9999", "5720533"]);
+        assert_eq!(room_ids(&second), ["288016"]);
+        assert!(first_more);
+        assert!(!second_more);
+
+        assert_eq!(first[0].platform, "douyu");
+        assert_eq!(first[0].fans_count.as_deref(), Some("12.3万"));
+        assert_eq!(first[0].category.as_deref(), Some("英雄联盟"));
+        assert!(first[0].live_status);
+        // 录播轮播不算开播；没有房间名时用简介
+        assert!(!first[1].live_status);
+        assert_eq!(first[1].room_title.as_deref(), Some("录播中"));
+        assert_eq!(first[1].avatar, None);
+    }
+
+    #[test]
+    fn douyu_non_anchor_entries_still_count_toward_a_full_page() {
+        let text = r#"{"error": 0, "data": {"relateUser": [
+            {"type": 2, "anchorInfo": {"rid": 1}},
+            {"type": 1, "anchorInfo": {"rid": 2, "nickName": "主播"}}
+        ]}}"#;
+        let (items, has_more) = parse_douyu_search(text, 2).unwrap();
+        assert_eq!(room_ids(&items), ["2"]);
+        assert!(has_more);
+        assert_eq!(
+            parse_douyu_search(r#"{"error": -1, "msg": "关键词为空"}"#, 2).unwrap_err(),
+            "Douyu search failed: 关键词为空"
+        );
+    }
+
+    #[test]
+    fn next_page_token_follows_has_more() {
+        let page = SearchPage::new(Vec::new(), 1, 20, true);
+        assert_eq!(page.next_page, Some(2));
+        let page = SearchPage::new(Vec::new(), 3, 20, false);
+        assert_eq!(page.next_page, None);
+        let json = serde_json::to_value(SearchPage::new(Vec::new(), 2, 10, true)).unwrap();
+        assert_eq!(json["nextPage"], 3);
+        assert_eq!(json["hasMore"], true);
+        assert_eq!(json["pageSize"], 10);
+    }
+}
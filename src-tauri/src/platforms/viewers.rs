@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tauri::State;
+
+use crate::platforms::common::cn_number::format_cn_number;
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::ttl_cache::TtlCache;
+use crate::platforms::common::{FollowHttpClient, SupportedPlatformRust};
+
+// 前端会对未连接弹幕的房间轮询人数，缓存稍长于开播状态
+const VIEWERS_TTL_SECONDS: u64 = 15;
+
+static VIEWERS_CACHE: Lazy<TtlCache<ViewerCount>> =
+    Lazy::new(|| TtlCache::new(Duration::from_secs(VIEWERS_TTL_SECONDS)));
+
+// 未开播时 viewer_count 为 None、viewer_count_str 为空；斗鱼为热度值而非真实在线人数
+#[derive(Serialize, Clone, Debug)]
+pub struct ViewerCount {
+    pub is_live: bool,
+    pub viewer_count: Option<u64>,
+    pub viewer_count_str: String,
+}
+
+#[tauri::command]
+pub async fn get_viewers(
+    platform: String,
+    room_id: String,
+    follow_http: State<'_, FollowHttpClient>,
+) -> Result<ViewerCount, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let room_id = validate_room_id(&platform, &room_id)?;
+
+    let cache_key = format!("{}:{}", platform.as_str(), room_id);
+    if let Some(cached) = VIEWERS_CACHE.get(&cache_key) {
+        return Ok(cached);
+    }
+
+    let result = match platform {
+        SupportedPlatformRust::Douyu => {
+            crate::platforms::douyu::fetch_douyu_room_info(room_id.clone(), follow_http.clone())
+                .await
+                .map(|info| info.viewers())
+        }
+        SupportedPlatformRust::Huya => {
+            crate::platforms::huya::stream_url::fetch_huya_viewers(
+                &follow_http.client().inner,
+                &room_id,
+            )
+            .await
+        }
+        SupportedPlatformRust::Bilibili => {
+            crate::platforms::bilibili::stream_url::fetch_bilibili_viewers(&room_id).await
+        }
+        SupportedPlatformRust::Douyin => {
+            crate::platforms::douyin::douyin_streamer_detail::fetch_douyin_viewers(&room_id).await
+        }
+    };
+    let (is_live, viewers) = result.map_err(|e| {
+        eprintln!(
+            "[Viewers] Failed to fetch {} room {}: {}",
+            platform.as_str(),
+            room_id,
+            e
+        );
+        e
+    })?;
+
+    let viewer_count = viewers.filter(|_| is_live);
+    let count = ViewerCount {
+        is_live,
+        viewer_count,
+        viewer_count_str: viewer_count.map(format_cn_number).unwrap_or_default(),
+    };
+    VIEWERS_CACHE.insert(cache_key, count.clone());
+    Ok(count)
+}
@@ -1,33 +1,977 @@
-use actix_web::{dev::ServerHandle, web, App, HttpRequest, HttpResponse, HttpServer, Responder};
-use futures_util::TryStreamExt;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServerHandle, Service, ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Responder};
+use bytes::Bytes;
+use futures_util::stream::LocalBoxStream;
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use once_cell::sync::Lazy;
 use reqwest::Client;
 // awc removed for now due to API differences; using reqwest streaming
+use crate::flv_metadata::{FlvMetadata, FlvMetadataSniffer, SniffResult};
+use crate::image_resize::{make_thumbnail, ThumbnailSpec};
+use crate::platforms::common::events::{emit_event, DtvEvent};
+use crate::platforms::common::ttl_cache::TtlCache;
 use crate::StreamUrlStore;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::ErrorKind;
 use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Mutex as StdMutex;
-use std::time::Duration;
-use tauri::{AppHandle, State};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, Runtime, State};
 use url::Url;
 
+// 代理服务的停止 handle 与其所在任务的 JoinHandle：停止后再 abort 任务，保证不会残留占用端口的僵尸任务
+pub struct ProxyServerTask {
+    pub handle: ServerHandle,
+    pub join: tauri::async_runtime::JoinHandle<()>,
+    pub port: u16,
+    // 区分同一端口上先后启动的服务器，监督任务据此确认 state 中记录的仍是自己
+    pub id: u64,
+}
+
+impl ProxyServerTask {
+    pub async fn shutdown(self) {
+        self.handle.stop(false).await;
+        self.join.abort();
+        println!(
+            "[Rust/proxy.rs] Proxy server task on port {} stopped and aborted.",
+            self.port
+        );
+    }
+}
+
 // Define a struct to hold the server handle in a Tauri managed state
 #[derive(Default)]
-pub struct ProxyServerHandle(pub StdMutex<Option<ServerHandle>>);
+pub struct ProxyServerHandle(pub StdMutex<Option<ProxyServerTask>>);
+
+// 静态图片/HLS 代理（固定端口）单独记录，不与 FLV 主代理的 handle 混用
+#[derive(Default)]
+pub struct StaticProxyServerHandle(pub StdMutex<Option<ProxyServerTask>>);
+
+// FLV 主代理使用的固定端口
+pub const FLV_PROXY_PORT: u16 = 34719;
+// 静态图片/HLS 代理使用的固定端口
+pub const STATIC_PROXY_PORT: u16 = 34721;
+static STATIC_PROXY_RUNNING: AtomicBool = AtomicBool::new(false);
+// 串行化静态代理的启动/重启，重启过程中不会有并发的启动把半关闭的旧服务器当成“已运行”
+static STATIC_PROXY_LIFECYCLE: Lazy<tokio::sync::Mutex<()>> =
+    Lazy::new(|| tokio::sync::Mutex::new(()));
+// FLV 主代理的启动/停止与监督任务的重建同样串行化，stop_proxy 不会和重建交错而留下无人管理的服务器
+static FLV_PROXY_LIFECYCLE: Lazy<tokio::sync::Mutex<()>> =
+    Lazy::new(|| tokio::sync::Mutex::new(()));
+static PROXY_TASK_ID: AtomicU64 = AtomicU64::new(0);
+
+fn local_image_prefix() -> String {
+    format!("http://127.0.0.1:{}/image?url=", STATIC_PROXY_PORT)
+}
+
+// 列表/房间/搜索结果中的图片地址统一改写为本地图片代理地址（静态代理未启动时原样返回）
+pub fn proxied_image_url(raw: &str) -> String {
+    rewrite_image_url(raw, STATIC_PROXY_RUNNING.load(Ordering::Relaxed))
+}
+
+fn rewrite_image_url(raw: &str, proxy_running: bool) -> String {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() || !proxy_running || trimmed.starts_with(&local_image_prefix()) {
+        return trimmed.to_string();
+    }
+    let absolute = if trimmed.starts_with("//") {
+        format!("https:{}", trimmed)
+    } else {
+        trimmed.to_string()
+    };
+    if !absolute.starts_with("http://") && !absolute.starts_with("https://") {
+        return absolute;
+    }
+    format!("{}{}", local_image_prefix(), urlencoding::encode(&absolute))
+}
+
+pub(crate) fn is_local_image_url(url: &str) -> bool {
+    url.starts_with(&local_image_prefix())
+}
+
+// 前端对已代理地址再次包装时，解开多余的一层，避免代理请求自身
+pub(crate) fn unwrap_local_image_url(url: &str) -> String {
+    let mut current = url.to_string();
+    while let Some(inner) = current.strip_prefix(&local_image_prefix()) {
+        match urlencoding::decode(inner) {
+            Ok(decoded) => current = decoded.into_owned(),
+            Err(_) => break,
+        }
+    }
+    current
+}
+
+// 上游域名白名单（按后缀匹配），None 表示未启用，任何域名都放行
+static UPSTREAM_ALLOWLIST: Lazy<RwLock<Option<Vec<String>>>> = Lazy::new(|| RwLock::new(None));
+
+// 四个平台已知的 API / 图片 / 直播 CDN 域名
+const DEFAULT_UPSTREAM_ALLOWLIST: &[&str] = &[
+    // Bilibili
+    "bilibili.com",
+    "hdslb.com",
+    "bilivideo.com",
+    "bilivideo.cn",
+    "biliimg.com",
+    "szbdyd.com",
+    // Huya
+    "huya.com",
+    "hy-cdn.com",
+    "huyaimg.com",
+    "msstatic.com",
+    "huyalive.com",
+    // Douyu
+    "douyu.com",
+    "douyucdn.cn",
+    "douyucdn2.cn",
+    "douyuscdn.com",
+    // Douyin
+    "douyin.com",
+    "douyinpic.com",
+    "douyincdn.com",
+    "douyinvod.com",
+    "douyinliving.com",
+    "bytecdn.cn",
+    "byteimg.com",
+    "pstatp.com",
+    "amemv.com",
+];
+
+fn normalize_host_suffix(suffix: &str) -> String {
+    suffix.trim().trim_start_matches('.').to_ascii_lowercase()
+}
+
+fn upstream_host_allowed(url: &str) -> bool {
+    let allowlist = UPSTREAM_ALLOWLIST.read().unwrap_or_else(|e| e.into_inner());
+    let Some(suffixes) = allowlist.as_ref() else {
+        return true;
+    };
+    let Some(host) = Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_ascii_lowercase()))
+    else {
+        return false;
+    };
+    suffixes
+        .iter()
+        .any(|suffix| host == *suffix || host.ends_with(&format!(".{}", suffix)))
+}
+
+// 与 reqwest 默认策略相同的跳数上限
+const MAX_UPSTREAM_REDIRECTS: usize = 10;
+
+// 上游重定向的每一跳都重新校验主机白名单，避免经 302 被引到白名单之外的主机
+fn upstream_redirect_policy() -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_UPSTREAM_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if upstream_host_allowed(attempt.url().as_str()) {
+            attempt.follow()
+        } else {
+            let message = format!("Redirect to disallowed host: {}", attempt.url());
+            attempt.error(message)
+        }
+    })
+}
+
+#[derive(Serialize)]
+struct ProxyErrorBody<'a> {
+    error: String,
+    // 上游返回的 HTTP 状态码；连接失败或本地校验失败时为 null
+    upstream_status: Option<u16>,
+    url: &'a str,
+}
+
+// /image、/hls、/live.flv 统一的 JSON 错误响应，前端可以结构化解析失败原因
+fn proxy_error_response(
+    status: actix_web::http::StatusCode,
+    error: String,
+    upstream_status: Option<u16>,
+    url: &str,
+) -> HttpResponse {
+    HttpResponse::build(status).json(ProxyErrorBody {
+        error,
+        upstream_status,
+        url,
+    })
+}
+
+// 上游非 2xx：沿用上游状态码，无法映射时退回 500
+fn upstream_status_error(
+    upstream_status: reqwest::StatusCode,
+    error: String,
+    url: &str,
+) -> HttpResponse {
+    UPSTREAM_ERRORS.fetch_add(1, Ordering::Relaxed);
+    let status = actix_web::http::StatusCode::from_u16(upstream_status.as_u16())
+        .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR);
+    proxy_error_response(status, error, Some(upstream_status.as_u16()), url)
+}
+
+fn forbidden_upstream(url: &str) -> HttpResponse {
+    eprintln!(
+        "[Rust/proxy.rs] Rejected upstream not in allowlist: {}",
+        url
+    );
+    proxy_error_response(
+        actix_web::http::StatusCode::FORBIDDEN,
+        format!("Upstream host is not allowed: {}", url),
+        None,
+        url,
+    )
+}
+
+// 启用/关闭上游白名单；启用时以内置列表为基础，再追加 extra_hosts
+#[tauri::command]
+pub fn set_upstream_allowlist(
+    enabled: bool,
+    extra_hosts: Option<Vec<String>>,
+) -> Result<(), String> {
+    let mut allowlist = UPSTREAM_ALLOWLIST
+        .write()
+        .unwrap_or_else(|e| e.into_inner());
+    if !enabled {
+        *allowlist = None;
+        println!("[Rust/proxy.rs] Upstream allowlist disabled");
+        return Ok(());
+    }
+    let mut suffixes: Vec<String> = DEFAULT_UPSTREAM_ALLOWLIST
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    for host in extra_hosts.unwrap_or_default() {
+        let host = normalize_host_suffix(&host);
+        if !host.is_empty() && !suffixes.contains(&host) {
+            suffixes.push(host);
+        }
+    }
+    println!(
+        "[Rust/proxy.rs] Upstream allowlist enabled with {} host suffixes",
+        suffixes.len()
+    );
+    *allowlist = Some(suffixes);
+    Ok(())
+}
+
+// 在已启用的白名单上追加域名（未启用时先以内置列表启用）
+#[tauri::command]
+pub fn add_upstream_allowed_host(host: String) -> Result<(), String> {
+    let host = normalize_host_suffix(&host);
+    if host.is_empty() {
+        return Err("Host must not be empty".to_string());
+    }
+    let mut allowlist = UPSTREAM_ALLOWLIST
+        .write()
+        .unwrap_or_else(|e| e.into_inner());
+    let suffixes = allowlist.get_or_insert_with(|| {
+        DEFAULT_UPSTREAM_ALLOWLIST
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    });
+    if !suffixes.contains(&host) {
+        suffixes.push(host);
+    }
+    Ok(())
+}
+
+// FLV 转发限速（字节/秒），0 表示不限速
+static FLV_RATE_LIMIT_BYTES_PER_SEC: AtomicU64 = AtomicU64::new(0);
+
+// 按累计字节数对转发节奏做整形：超出配额时异步 sleep，不阻塞 actix worker
+fn throttle_stream<S, E>(stream: S, bytes_per_sec: u64) -> impl Stream<Item = Result<Bytes, E>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    let started = Instant::now();
+    let mut sent: u64 = 0;
+    stream.then(move |item| {
+        if let Ok(chunk) = &item {
+            sent += chunk.len() as u64;
+        }
+        let target = Duration::from_secs_f64(sent as f64 / bytes_per_sec as f64);
+        let elapsed = started.elapsed();
+        async move {
+            if target > elapsed {
+                tokio::time::sleep(target - elapsed).await;
+            }
+            item
+        }
+    })
+}
+
+// 转发链路是拉取式的：actix 只在写缓冲低于水位时才 poll body，reqwest 也只在被 poll 时读取 socket，
+// 慢速客户端会把压力一路传回上游 TCP 窗口，代理内存不会随直播时长增长。
+// 唯一的放大点是单个超大 chunk（actix 会整块写入缓冲后才检查水位），这里把它切成有上限的小块
+const MAX_FORWARD_CHUNK_BYTES: usize = 64 * 1024;
+
+fn bounded_chunks<S, E>(stream: S) -> impl Stream<Item = Result<Bytes, E>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    stream.flat_map(|item| {
+        let pieces: Vec<Result<Bytes, E>> = match item {
+            Ok(chunk) if chunk.len() > MAX_FORWARD_CHUNK_BYTES => (0..chunk.len())
+                .step_by(MAX_FORWARD_CHUNK_BYTES)
+                .map(|start| {
+                    let end = (start + MAX_FORWARD_CHUNK_BYTES).min(chunk.len());
+                    // Bytes::slice 共享底层内存，不产生拷贝
+                    Ok(chunk.slice(start..end))
+                })
+                .collect(),
+            other => vec![other],
+        };
+        futures_util::stream::iter(pieces)
+    })
+}
+
+// 按 request_id（播放存储的请求或 stream_token）分别统计 FLV 转发字节数，供 /stats 与码率上报采样，
+// 同时播放多路流时互不干扰。同一 request_id 的新连接替换旧计数器（旧连接尚未断开时写入的是被替换的计数器，
+// 不计入新流），连接结束时移除自己登记的计数器
+static FLV_STREAM_BYTES: Lazy<StdMutex<HashMap<String, Arc<AtomicU64>>>> =
+    Lazy::new(|| StdMutex::new(HashMap::new()));
+// 每次启动/停止代理递增，旧的码率上报任务据此退出
+static BITRATE_MONITOR_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+struct FlvStreamCounter {
+    request_id: String,
+    bytes: Arc<AtomicU64>,
+}
+
+impl FlvStreamCounter {
+    fn register(request_id: &str) -> Self {
+        let bytes = Arc::new(AtomicU64::new(0));
+        FLV_STREAM_BYTES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(request_id.to_string(), bytes.clone());
+        Self {
+            request_id: request_id.to_string(),
+            bytes,
+        }
+    }
+
+    fn add(&self, len: usize) {
+        self.bytes.fetch_add(len as u64, Ordering::Relaxed);
+    }
+}
+
+impl Drop for FlvStreamCounter {
+    fn drop(&mut self) {
+        let mut streams = FLV_STREAM_BYTES.lock().unwrap_or_else(|e| e.into_inner());
+        if streams
+            .get(&self.request_id)
+            .is_some_and(|bytes| Arc::ptr_eq(bytes, &self.bytes))
+        {
+            streams.remove(&self.request_id);
+        }
+    }
+}
+
+fn flv_stream_bytes(request_id: &str) -> u64 {
+    FLV_STREAM_BYTES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(request_id)
+        .map_or(0, |bytes| bytes.load(Ordering::Relaxed))
+}
+
+fn flv_stream_counters() -> Vec<(String, Arc<AtomicU64>)> {
+    FLV_STREAM_BYTES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(request_id, bytes)| (request_id.clone(), bytes.clone()))
+        .collect()
+}
+
+const BITRATE_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+// EMA 平滑系数：约等于最近 3~5 秒的加权平均
+const BITRATE_EMA_ALPHA: f64 = 0.3;
+
+#[derive(Serialize, Clone, Debug)]
+struct StreamMetadataPayload {
+    request_id: String,
+    #[serde(flatten)]
+    metadata: FlvMetadata,
+}
+
+// FLV 上游连接被截断（Early-EOF）时通知前端重连，而不是静默结束
+#[derive(Serialize, Clone, Debug)]
+struct StreamReconnectPayload {
+    request_id: String,
+    reason: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct BitratePayload {
+    request_id: String,
+    instant_kbps: f64,
+    average_kbps: f64,
+    total_bytes: u64,
+}
+
+// 每路流的采样状态；计数器被替换（同一 request_id 重新拉流）时重新开始平滑
+struct BitrateSample {
+    bytes: Arc<AtomicU64>,
+    last_bytes: u64,
+    ema_kbps: Option<f64>,
+}
+
+// 定时采样各路流的字节计数器计算码率，通过 "bitrate" 事件按 request_id 分别发给前端
+fn spawn_bitrate_monitor(app_handle: AppHandle) {
+    let generation = BITRATE_MONITOR_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(BITRATE_SAMPLE_INTERVAL);
+        let mut samples: HashMap<String, BitrateSample> = HashMap::new();
+        let mut last_sample = Instant::now();
+        loop {
+            interval.tick().await;
+            if BITRATE_MONITOR_GENERATION.load(Ordering::SeqCst) != generation {
+                break;
+            }
+            let elapsed = last_sample.elapsed().as_secs_f64();
+            last_sample = Instant::now();
+            let counters = flv_stream_counters();
+            samples.retain(|request_id, _| counters.iter().any(|(id, _)| id == request_id));
+            for (request_id, bytes) in counters {
+                let total_bytes = bytes.load(Ordering::Relaxed);
+                let sample = match samples.get_mut(&request_id) {
+                    Some(sample) if Arc::ptr_eq(&sample.bytes, &bytes) => sample,
+                    _ => {
+                        samples.insert(
+                            request_id,
+                            BitrateSample {
+                                bytes,
+                                last_bytes: total_bytes,
+                                ema_kbps: None,
+                            },
+                        );
+                        continue;
+                    }
+                };
+                let delta = total_bytes.saturating_sub(sample.last_bytes);
+                sample.last_bytes = total_bytes;
+                if total_bytes == 0 || elapsed <= 0.0 {
+                    continue;
+                }
+                let instant_kbps = delta as f64 * 8.0 / 1000.0 / elapsed;
+                let average_kbps = match sample.ema_kbps {
+                    Some(prev) => {
+                        BITRATE_EMA_ALPHA * instant_kbps + (1.0 - BITRATE_EMA_ALPHA) * prev
+                    }
+                    None => instant_kbps,
+                };
+                sample.ema_kbps = Some(average_kbps);
+                emit_event(
+                    &app_handle,
+                    DtvEvent::Bitrate(DtvEvent::json(BitratePayload {
+                        request_id,
+                        instant_kbps,
+                        average_kbps,
+                        total_bytes,
+                    })),
+                );
+            }
+        }
+    });
+}
+
+fn stop_bitrate_monitor() {
+    BITRATE_MONITOR_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+// /metrics 使用的计数器：与 /stats 共用字节计数，另外按路由统计请求数、上游错误与图片缓存命中
+static METRICS_ENABLED: AtomicBool = AtomicBool::new(false);
+static FLV_BYTES_TOTAL: AtomicU64 = AtomicU64::new(0);
+static ACTIVE_FLV_CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+static UPSTREAM_ERRORS: AtomicU64 = AtomicU64::new(0);
+static FLV_CLIENT_DISCONNECTS: AtomicU64 = AtomicU64::new(0);
+static IMAGE_CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static IMAGE_CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+const METRIC_ROUTES: [&str; 5] = ["/live.flv", "/image", "/hls", "/stats", "/metrics"];
+// 最后一格统计未匹配的路由
+static ROUTE_REQUESTS: [AtomicU64; METRIC_ROUTES.len() + 1] =
+    [const { AtomicU64::new(0) }; METRIC_ROUTES.len() + 1];
+
+fn record_route_request(path: &str) {
+    let index = METRIC_ROUTES
+        .iter()
+        .position(|route| *route == path)
+        .unwrap_or(METRIC_ROUTES.len());
+    ROUTE_REQUESTS[index].fetch_add(1, Ordering::Relaxed);
+}
+
+// 随 FLV 响应流一起释放，用于统计当前活跃的播放连接
+struct ActiveFlvConnection;
+
+impl ActiveFlvConnection {
+    fn open() -> Self {
+        ACTIVE_FLV_CONNECTIONS.fetch_add(1, Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for ActiveFlvConnection {
+    fn drop(&mut self) {
+        ACTIVE_FLV_CONNECTIONS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// 播放心跳：前端对正在播放的 stream_token 定期调用 stream_keepalive。
+// 调用过心跳的 token 超时未再调用（页面刷新/跳转后连接未正常关闭）时，FLV 代理结束响应并丢弃上游请求；
+// 从未调用过心跳的 token 不受影响
+const STREAM_KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(30);
+const STREAM_KEEPALIVE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+static STREAM_KEEPALIVES: Lazy<StdMutex<HashMap<String, Instant>>> =
+    Lazy::new(|| StdMutex::new(HashMap::new()));
+
+#[tauri::command]
+pub fn stream_keepalive(stream_token: String) {
+    STREAM_KEEPALIVES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(stream_token, Instant::now());
+}
+
+fn keepalive_expired(token: &str) -> bool {
+    STREAM_KEEPALIVES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(token)
+        .is_some_and(|last| last.elapsed() > STREAM_KEEPALIVE_TIMEOUT)
+}
+
+// 心跳超时时返回，作为 FLV 响应流的 take_until 条件
+async fn keepalive_lost(token: String) {
+    let mut ticker = tokio::time::interval(STREAM_KEEPALIVE_CHECK_INTERVAL);
+    loop {
+        ticker.tick().await;
+        if keepalive_expired(&token) {
+            STREAM_KEEPALIVES
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&token);
+            println!(
+                "[Rust/proxy.rs handler][req:{}] no keepalive for {:?}, closing FLV stream",
+                token, STREAM_KEEPALIVE_TIMEOUT
+            );
+            return;
+        }
+    }
+}
+
+// 播放端断开时 actix 直接丢弃响应体，上游 bytes_stream 随之被丢弃，reqwest 请求与 CDN 连接一并取消。
+// 这里记录未读到上游结尾就被丢弃的响应（心跳超时主动结束的不算），确认上游拉流确实已停止
+struct FlvClientWatch {
+    request_id: String,
+    bytes_sent: u64,
+    ended: bool,
+}
+
+impl Drop for FlvClientWatch {
+    fn drop(&mut self) {
+        if !self.ended {
+            FLV_CLIENT_DISCONNECTS.fetch_add(1, Ordering::Relaxed);
+            println!(
+                "[Rust/proxy.rs handler][req:{}] client disconnected after {} bytes, upstream FLV fetch aborted",
+                self.request_id, self.bytes_sent
+            );
+        }
+    }
+}
+
+fn watch_client_disconnect<S>(
+    mut stream: S,
+    request_id: String,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>>
+where
+    S: Stream<Item = Result<Bytes, actix_web::Error>> + Unpin,
+{
+    let mut watch = FlvClientWatch {
+        request_id,
+        bytes_sent: 0,
+        ended: false,
+    };
+    futures_util::stream::poll_fn(move |cx| {
+        let polled = stream.poll_next_unpin(cx);
+        match &polled {
+            std::task::Poll::Ready(Some(Ok(chunk))) => watch.bytes_sent += chunk.len() as u64,
+            std::task::Poll::Ready(Some(Err(_))) | std::task::Poll::Ready(None) => {
+                watch.ended = true
+            }
+            std::task::Poll::Pending => {}
+        }
+        polled
+    })
+}
+
+fn render_metrics() -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+        out.push_str(&format!(
+            "# HELP {} {}\n# TYPE {} {}\n",
+            name, help, name, kind
+        ));
+        for (labels, value) in samples {
+            out.push_str(&format!("{}{} {}\n", name, labels, value));
+        }
+    };
+    let single = |value: u64| vec![(String::new(), value.to_string())];
+
+    metric(
+        "dtv_proxy_flv_bytes_forwarded_total",
+        "counter",
+        "FLV bytes forwarded to players since start.",
+        single(FLV_BYTES_TOTAL.load(Ordering::Relaxed)),
+    );
+    let mut stream_bytes: Vec<(String, String)> = flv_stream_counters()
+        .into_iter()
+        .map(|(request_id, bytes)| {
+            (
+                format!("{{request_id=\"{}\"}}", request_id),
+                bytes.load(Ordering::Relaxed).to_string(),
+            )
+        })
+        .collect();
+    stream_bytes.sort();
+    metric(
+        "dtv_proxy_flv_stream_bytes",
+        "gauge",
+        "FLV bytes forwarded per active stream.",
+        stream_bytes,
+    );
+    metric(
+        "dtv_proxy_active_connections",
+        "gauge",
+        "FLV player connections currently streaming.",
+        single(ACTIVE_FLV_CONNECTIONS.load(Ordering::Relaxed)),
+    );
+    let mut requests: Vec<(String, String)> = METRIC_ROUTES
+        .iter()
+        .enumerate()
+        .map(|(i, route)| {
+            (
+                format!("{{route=\"{}\"}}", route),
+                ROUTE_REQUESTS[i].load(Ordering::Relaxed).to_string(),
+            )
+        })
+        .collect();
+    requests.push((
+        "{route=\"other\"}".to_string(),
+        ROUTE_REQUESTS[METRIC_ROUTES.len()]
+            .load(Ordering::Relaxed)
+            .to_string(),
+    ));
+    metric(
+        "dtv_proxy_requests_total",
+        "counter",
+        "Requests received per route.",
+        requests,
+    );
+    metric(
+        "dtv_proxy_upstream_errors_total",
+        "counter",
+        "Upstream connection failures and non-2xx responses.",
+        single(UPSTREAM_ERRORS.load(Ordering::Relaxed)),
+    );
+    metric(
+        "dtv_proxy_client_disconnects_total",
+        "counter",
+        "FLV responses dropped by the player before the upstream stream ended.",
+        single(FLV_CLIENT_DISCONNECTS.load(Ordering::Relaxed)),
+    );
+    let hits = IMAGE_CACHE_HITS.load(Ordering::Relaxed);
+    let misses = IMAGE_CACHE_MISSES.load(Ordering::Relaxed);
+    metric(
+        "dtv_proxy_image_cache_hits_total",
+        "counter",
+        "Image requests served from the in-memory cache.",
+        single(hits),
+    );
+    metric(
+        "dtv_proxy_image_cache_misses_total",
+        "counter",
+        "Image requests fetched from upstream.",
+        single(misses),
+    );
+    let ratio = if hits + misses > 0 {
+        hits as f64 / (hits + misses) as f64
+    } else {
+        0.0
+    };
+    metric(
+        "dtv_proxy_image_cache_hit_ratio",
+        "gauge",
+        "Image cache hit ratio.",
+        vec![(String::new(), format!("{:.4}", ratio))],
+    );
+    metric(
+        "dtv_proxy_restarts_total",
+        "counter",
+        "Automatic proxy server restarts.",
+        single(PROXY_RESTART_COUNT.load(Ordering::Relaxed)),
+    );
+    out
+}
+
+// Prometheus 文本格式；默认关闭，通过 start_proxy 的 enable_metrics 开启
+async fn metrics_handler() -> impl Responder {
+    if !METRICS_ENABLED.load(Ordering::Relaxed) {
+        return proxy_error_response(
+            actix_web::http::StatusCode::NOT_FOUND,
+            "metrics endpoint is disabled; enable it via start_proxy".to_string(),
+            None,
+            "/metrics",
+        );
+    }
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(render_metrics())
+}
+
+async fn stats_handler(stream_url_store: web::Data<StreamUrlStore>) -> impl Responder {
+    let request_id = stream_url_store
+        .request_id
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    HttpResponse::Ok().json(serde_json::json!({
+        "request_id": request_id,
+        "bytes_forwarded": flv_stream_bytes(&request_id),
+        "proxy_restarts": PROXY_RESTART_COUNT.load(Ordering::Relaxed),
+    }))
+}
+
+// kbps 为码率单位（千比特/秒，与码率统计的 instant_kbps 一致），换算为字节/秒。
+// 向上取整：0 表示不限速，任何非零限速都不能被换算成 0
+fn kbps_to_bytes_per_sec(kbps: u64) -> u64 {
+    kbps.saturating_mul(1000).div_ceil(8)
+}
+
+#[tauri::command]
+pub fn set_flv_rate_limit(kbps: Option<u64>) -> Result<(), String> {
+    let kbps = kbps.unwrap_or(0);
+    let bytes_per_sec = kbps_to_bytes_per_sec(kbps);
+    FLV_RATE_LIMIT_BYTES_PER_SEC.store(bytes_per_sec, Ordering::Relaxed);
+    println!(
+        "[Rust/proxy.rs] FLV rate limit set to {}",
+        if bytes_per_sec == 0 {
+            "unlimited".to_string()
+        } else {
+            format!("{} kbps ({} B/s)", kbps, bytes_per_sec)
+        }
+    );
+    Ok(())
+}
 
 async fn find_free_port() -> u16 {
     // Using a fixed port as requested by the user for easier debugging
-    34719
+    FLV_PROXY_PORT
 }
 
 #[derive(Deserialize)]
 struct ImageQuery {
     url: String,
+    // 可选缩略图参数：给出 w/h 时解码后缩放并重新编码（format: jpeg/webp，默认 jpeg）
+    w: Option<u32>,
+    h: Option<u32>,
+    format: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct HlsQuery {
     url: String,
+    // 所属流的 token，用于附带该流在解析阶段拿到的 cookie
+    token: Option<String>,
+}
+
+// 部分 CDN 用解析阶段下发的会话 cookie 鉴权分片请求；cookie 按 stream token 隔离，
+// 只在 host 匹配时附带，避免串到无关请求
+#[derive(Clone, Debug)]
+pub struct StreamCookie {
+    domain: String,
+    pair: String,
+}
+
+// 解析完成但尚未接入代理的 cookie，按解析出的上游地址暂存，接入代理时再绑定到 token
+static RESOLVED_COOKIES: Lazy<TtlCache<Vec<StreamCookie>>> =
+    Lazy::new(|| TtlCache::new(Duration::from_secs(300)));
+static STREAM_COOKIES: Lazy<StdMutex<HashMap<String, Vec<StreamCookie>>>> =
+    Lazy::new(|| StdMutex::new(HashMap::new()));
+
+// 从解析请求的响应中提取 Set-Cookie；未声明 Domain 时以响应所在 host 为作用域
+pub fn cookies_from_response(response: &reqwest::Response) -> Vec<StreamCookie> {
+    let default_domain = response.url().host_str().unwrap_or_default().to_string();
+    response
+        .headers()
+        .get_all(reqwest::header::SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|raw| {
+            let mut parts = raw.split(';');
+            let pair = parts.next()?.trim();
+            if !pair.contains('=') {
+                return None;
+            }
+            let domain = parts
+                .filter_map(|attr| {
+                    let (key, value) = attr.trim().split_once('=')?;
+                    key.eq_ignore_ascii_case("domain")
+                        .then(|| value.trim().trim_start_matches('.').to_ascii_lowercase())
+                })
+                .next()
+                .unwrap_or_else(|| default_domain.clone());
+            Some(StreamCookie {
+                domain,
+                pair: pair.to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn remember_resolved_cookies(stream_url: &str, cookies: Vec<StreamCookie>) {
+    if !cookies.is_empty() {
+        RESOLVED_COOKIES.insert(stream_url.to_string(), cookies);
+    }
+}
+
+// 接入代理时调用：把该上游地址解析时拿到的 cookie 与 Referer 绑定到 token（没有则清空旧的绑定）。
+// 绑定不会消耗 RESOLVED_COOKIES / RESOLVED_REFERERS 中的暂存项：它们保留到 TTL 过期，
+// 同一地址可以重复绑定（如先用探测 token 验证候选地址、再绑定到正式播放 token）。
+// 代价是暂存项最多多留 5 分钟，期间重新解析同一地址会覆盖为最新的值
+pub fn bind_stream_cookies(token: &str, stream_url: &str) {
+    let mut registry = STREAM_COOKIES.lock().unwrap_or_else(|e| e.into_inner());
+    match RESOLVED_COOKIES.get(stream_url) {
+        Some(cookies) => {
+            registry.insert(token.to_string(), cookies);
+        }
+        None => {
+            registry.remove(token);
+        }
+    }
+    let mut referers = STREAM_REFERERS.lock().unwrap_or_else(|e| e.into_inner());
+    match RESOLVED_REFERERS.get(stream_url) {
+        Some(referer) => {
+            referers.insert(token.to_string(), referer);
+        }
+        None => {
+            referers.remove(token);
+        }
+    }
+}
+
+pub fn forget_stream_cookies(token: &str) {
+    STREAM_COOKIES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(token);
+    STREAM_REFERERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(token);
+    STREAM_KEEPALIVES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(token);
+    TOKEN_STREAMS.remove(token);
+}
+
+// 个别防盗链要求具体房间页作为 Referer，按 host 推断的平台首页不够用：
+// 解析时按上游地址暂存，接入代理时与 cookie 一起绑定到 token
+static RESOLVED_REFERERS: Lazy<TtlCache<String>> =
+    Lazy::new(|| TtlCache::new(Duration::from_secs(300)));
+static STREAM_REFERERS: Lazy<StdMutex<HashMap<String, String>>> =
+    Lazy::new(|| StdMutex::new(HashMap::new()));
+
+pub fn remember_resolved_referer(stream_url: &str, referer: &str) {
+    if !referer.is_empty() {
+        RESOLVED_REFERERS.insert(stream_url.to_string(), referer.to_string());
+    }
+}
+
+// token 绑定了 Referer 时覆盖按 host 设置的默认值（RequestBuilder::headers 会替换同名头）
+pub(crate) fn apply_stream_referer(
+    req: reqwest::RequestBuilder,
+    token: &str,
+) -> reqwest::RequestBuilder {
+    let referer = STREAM_REFERERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(token)
+        .cloned();
+    let Some(value) = referer.and_then(|r| reqwest::header::HeaderValue::from_str(&r).ok()) else {
+        return req;
+    };
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(reqwest::header::REFERER, value);
+    req.headers(headers)
+}
+
+pub(crate) fn stream_cookie_header(token: &str, url: &str) -> Option<String> {
+    let host = Url::parse(url).ok()?.host_str()?.to_ascii_lowercase();
+    let registry = STREAM_COOKIES.lock().unwrap_or_else(|e| e.into_inner());
+    let pairs: Vec<&str> = registry
+        .get(token)?
+        .iter()
+        .filter(|c| host == c.domain || host.ends_with(&format!(".{}", c.domain)))
+        .map(|c| c.pair.as_str())
+        .collect();
+    (!pairs.is_empty()).then(|| pairs.join("; "))
+}
+
+// 判断是否为 chunked 响应被提前截断（Windows 下常见的 Early-EOF / IncompleteMessage）
+fn is_early_eof(err: &reqwest::Error) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(err);
+    while let Some(e) = source {
+        if let Some(io_err) = e.downcast_ref::<std::io::Error>() {
+            if io_err.kind() == ErrorKind::UnexpectedEof {
+                return true;
+            }
+        }
+        let msg = e.to_string().to_ascii_lowercase();
+        if msg.contains("early eof")
+            || msg.contains("unexpected eof")
+            || msg.contains("incomplete message")
+            || msg.contains("connection closed before message completed")
+        {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+// Windows 下 HLS 分片整包读取，遇到 Early-EOF 时重新请求一次；分片有限长，缓冲开销可接受
+// 重试请求与首次请求使用相同的请求头（通用头、token 的 Referer、Cookie）
+async fn fetch_segment_buffered(
+    client: &Client,
+    url: &str,
+    token: Option<&str>,
+    cookie: Option<&str>,
+    first_response: reqwest::Response,
+) -> Result<Bytes, reqwest::Error> {
+    match first_response.bytes().await {
+        Ok(body) => Ok(body),
+        Err(e) if is_early_eof(&e) => {
+            eprintln!(
+                "[Rust/proxy.rs hls] Early-EOF reading segment, retrying buffered: {}",
+                url
+            );
+            let mut req = apply_common_headers(client.get(url), url);
+            if let Some(token) = token {
+                req = apply_stream_referer(req, token);
+            }
+            if let Some(cookie) = cookie {
+                req = req.header(reqwest::header::COOKIE, cookie);
+            }
+            req.send().await?.error_for_status()?.bytes().await
+        }
+        Err(e) => Err(e),
+    }
 }
 
 fn apply_common_headers(mut req: reqwest::RequestBuilder, url: &str) -> reqwest::RequestBuilder {
@@ -44,7 +988,8 @@ fn apply_common_headers(mut req: reqwest::RequestBuilder, url: &str) -> reqwest:
         req = req
             .header("Referer", "https://live.bilibili.com/")
             .header("Origin", "https://live.bilibili.com");
-    } else if url.contains("huya.com") || url.contains("hy-cdn.com") || url.contains("huyaimg.com") {
+    } else if url.contains("huya.com") || url.contains("hy-cdn.com") || url.contains("huyaimg.com")
+    {
         req = req
             .header("Referer", "https://www.huya.com/")
             .header("Origin", "https://www.huya.com");
@@ -54,41 +999,251 @@ fn apply_common_headers(mut req: reqwest::RequestBuilder, url: &str) -> reqwest:
     req
 }
 
-async fn image_proxy_handler(
-    query: web::Query<ImageQuery>,
-    client: web::Data<Client>,
-) -> impl Responder {
-    let url = query.url.clone();
-    if url.is_empty() {
-        return HttpResponse::BadRequest().body("Missing url query parameter");
+// 按文件头识别常见图片格式
+fn sniff_image_content_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        Some("image/png")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
     }
+}
 
-    let mut req = apply_common_headers(client.get(&url), &url).header(
-        "Accept",
-        "image/avif,image/webp,image/apng,image/*;q=0.8,*/*;q=0.5",
-    );
+// 上游给出合理的 image/* 类型时保留；缺失或误标为 octet-stream 等时按文件头纠正
+fn resolve_image_content_type(upstream: Option<&str>, bytes: &[u8]) -> String {
+    match upstream {
+        Some(ct) if ct.trim().to_ascii_lowercase().starts_with("image/") => ct.to_string(),
+        other => sniff_image_content_type(bytes)
+            .map(str::to_string)
+            .unwrap_or_else(|| other.unwrap_or("application/octet-stream").to_string()),
+    }
+}
 
-    match req.send().await {
-        Ok(upstream_response) => {
-            let content_type = upstream_response
-                .headers()
-                .get(reqwest::header::CONTENT_TYPE)
-                .and_then(|v| v.to_str().ok())
-                .unwrap_or("application/octet-stream")
-                .to_string();
+// 图片内存缓存：列表封面/头像短时间内会被反复请求，命中后直接返回
+#[derive(Clone)]
+struct CachedImage {
+    content_type: String,
+    bytes: Bytes,
+}
 
-            // 为避免 Windows 下 chunked 传输的 Early-EOF，改为一次性读取 bytes 并返回
-            if upstream_response.status().is_success() {
-                match upstream_response.bytes().await {
-                    Ok(bytes) => HttpResponse::Ok()
-                        .content_type(content_type)
-                        .insert_header(("Content-Length", bytes.len().to_string()))
-                        .insert_header(("Cache-Control", "no-store"))
-                        .body(bytes),
+static IMAGE_CACHE: Lazy<TtlCache<CachedImage>> = Lazy::new(|| {
+    TtlCache::bounded(
+        Duration::from_secs(10 * 60),
+        IMAGE_CACHE_MAX_ENTRIES,
+        IMAGE_CACHE_MAX_BYTES,
+        |image| image.bytes.len(),
+    )
+});
+// 超过该大小的图片不缓存，预取时直接视为失败
+const MAX_CACHED_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+// 图片缓存整体上限，超出后淘汰最早写入的图片
+const IMAGE_CACHE_MAX_ENTRIES: usize = 1024;
+const IMAGE_CACHE_MAX_BYTES: usize = 128 * 1024 * 1024;
+const PREFETCH_IMAGE_CONCURRENCY: usize = 6;
+const PREFETCH_IMAGE_TIMEOUT: Duration = Duration::from_secs(15);
+
+fn cache_image(url: &str, content_type: &str, bytes: &Bytes) {
+    if bytes.len() <= MAX_CACHED_IMAGE_BYTES {
+        IMAGE_CACHE.insert(
+            url.to_string(),
+            CachedImage {
+                content_type: content_type.to_string(),
+                bytes: bytes.clone(),
+            },
+        );
+    }
+}
+
+// 读取图片响应体：声明的长度或实际读到的数据超过 MAX_CACHED_IMAGE_BYTES 时中止，不把整个响应读进内存
+async fn read_image_body(resp: reqwest::Response) -> Result<Bytes, String> {
+    if resp
+        .content_length()
+        .is_some_and(|len| len as usize > MAX_CACHED_IMAGE_BYTES)
+    {
+        return Err("Image exceeds cache size limit".to_string());
+    }
+    let mut body = Vec::new();
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| e.to_string())?;
+        if body.len() + chunk.len() > MAX_CACHED_IMAGE_BYTES {
+            return Err("Image exceeds cache size limit".to_string());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(body))
+}
+
+fn cached_image_response(image: CachedImage) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(image.content_type)
+        .insert_header(("Content-Length", image.bytes.len().to_string()))
+        .insert_header(("Cache-Control", "no-store"))
+        .body(image.bytes)
+}
+
+// 缩略图：原图与缩放结果分别缓存；解码失败（格式不支持/尺寸超限）时退回原图
+async fn thumbnail_image_response(client: &Client, url: &str, spec: ThumbnailSpec) -> HttpResponse {
+    let key = spec.cache_key(url);
+    if let Some(cached) = IMAGE_CACHE.get(&key) {
+        IMAGE_CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+        return cached_image_response(cached);
+    }
+    IMAGE_CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+
+    let original = match IMAGE_CACHE.get(url) {
+        Some(cached) => cached,
+        None => {
+            let req = apply_common_headers(client.get(url), url).header(
+                "Accept",
+                "image/avif,image/webp,image/apng,image/*;q=0.8,*/*;q=0.5",
+            );
+            let resp = match req.send().await {
+                Ok(resp) => resp,
+                Err(e) => {
+                    UPSTREAM_ERRORS.fetch_add(1, Ordering::Relaxed);
+                    return proxy_error_response(
+                        actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Error connecting to upstream IMAGE: {}", e),
+                        None,
+                        url,
+                    );
+                }
+            };
+            if !resp.status().is_success() {
+                return upstream_status_error(
+                    resp.status(),
+                    "Error fetching IMAGE from upstream for thumbnail".to_string(),
+                    url,
+                );
+            }
+            let upstream_content_type = resp
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let bytes = match read_image_body(resp).await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    return proxy_error_response(
+                        actix_web::http::StatusCode::BAD_GATEWAY,
+                        format!("Failed to read image bytes: {}", e),
+                        None,
+                        url,
+                    )
+                }
+            };
+            let content_type = resolve_image_content_type(upstream_content_type.as_deref(), &bytes);
+            cache_image(url, &content_type, &bytes);
+            CachedImage {
+                content_type,
+                bytes,
+            }
+        }
+    };
+
+    // 解码/编码较耗 CPU，放到阻塞线程池执行，避免卡住代理的事件循环
+    let source = original.bytes.clone();
+    match web::block(move || make_thumbnail(&source, spec)).await {
+        // 缩放后反而不比原图小（如无损 WebP 重编码 JPEG），直接返回原图
+        Ok(Ok(None)) => {
+            cache_image(&key, &original.content_type, &original.bytes);
+            cached_image_response(original)
+        }
+        Ok(Ok(Some(encoded))) => {
+            let bytes = Bytes::from(encoded);
+            cache_image(&key, spec.format.content_type(), &bytes);
+            cached_image_response(CachedImage {
+                content_type: spec.format.content_type().to_string(),
+                bytes,
+            })
+        }
+        Ok(Err(e)) => {
+            eprintln!(
+                "[Rust/proxy.rs image] Thumbnail failed for {}, serving original: {}",
+                url, e
+            );
+            cached_image_response(original)
+        }
+        Err(e) => {
+            eprintln!(
+                "[Rust/proxy.rs image] Thumbnail task failed for {}: {}",
+                url, e
+            );
+            cached_image_response(original)
+        }
+    }
+}
+
+async fn image_proxy_handler(
+    query: web::Query<ImageQuery>,
+    clients: web::Data<SegmentClients>,
+) -> impl Responder {
+    let client = clients.current();
+    let url = unwrap_local_image_url(&query.url);
+    if url.is_empty() {
+        return proxy_error_response(
+            actix_web::http::StatusCode::BAD_REQUEST,
+            "Missing url query parameter".to_string(),
+            None,
+            &url,
+        );
+    }
+    if !upstream_host_allowed(&url) {
+        return forbidden_upstream(&url);
+    }
+    match ThumbnailSpec::from_query(query.w, query.h, query.format.as_deref()) {
+        Ok(Some(spec)) => return thumbnail_image_response(client, &url, spec).await,
+        Ok(None) => {}
+        Err(e) => {
+            return proxy_error_response(actix_web::http::StatusCode::BAD_REQUEST, e, None, &url)
+        }
+    }
+    if let Some(cached) = IMAGE_CACHE.get(&url) {
+        IMAGE_CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+        return cached_image_response(cached);
+    }
+    IMAGE_CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+
+    let mut req = apply_common_headers(client.get(&url), &url).header(
+        "Accept",
+        "image/avif,image/webp,image/apng,image/*;q=0.8,*/*;q=0.5",
+    );
+
+    match req.send().await {
+        Ok(upstream_response) => {
+            let upstream_content_type = upstream_response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+
+            // 为避免 Windows 下 chunked 传输的 Early-EOF，改为一次性读取 bytes 并返回
+            if upstream_response.status().is_success() {
+                match upstream_response.bytes().await {
+                    Ok(bytes) => {
+                        let content_type =
+                            resolve_image_content_type(upstream_content_type.as_deref(), &bytes);
+                        cache_image(&url, &content_type, &bytes);
+                        HttpResponse::Ok()
+                            .content_type(content_type)
+                            .insert_header(("Content-Length", bytes.len().to_string()))
+                            .insert_header(("Cache-Control", "no-store"))
+                            .body(bytes)
+                    }
                     Err(e) => {
                         eprintln!("[Rust/proxy.rs image] Failed to read bytes: {}", e);
-                        HttpResponse::InternalServerError()
-                            .body(format!("Failed to read image bytes: {}", e))
+                        proxy_error_response(
+                            actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+                            format!("Failed to read image bytes: {}", e),
+                            None,
+                            &url,
+                        )
                     }
                 }
             } else {
@@ -101,14 +1256,14 @@ async fn image_proxy_handler(
                     "[Rust/proxy.rs image] Upstream request to {} failed with status: {}. Body: {}",
                     url, status_from_reqwest, error_text
                 );
-                let actix_status_code =
-                    actix_web::http::StatusCode::from_u16(status_from_reqwest.as_u16())
-                        .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR);
-
-                HttpResponse::build(actix_status_code).body(format!(
-                    "Error fetching IMAGE from upstream (reqwest): {}. Status: {}. Details: {}",
-                    url, status_from_reqwest, error_text
-                ))
+                upstream_status_error(
+                    status_from_reqwest,
+                    format!(
+                        "Error fetching IMAGE from upstream (reqwest). Details: {}",
+                        error_text
+                    ),
+                    &url,
+                )
             }
         }
         Err(e) => {
@@ -116,393 +1271,2677 @@ async fn image_proxy_handler(
                 "[Rust/proxy.rs image] Failed to send request to upstream {}: {}",
                 url, e
             );
-            HttpResponse::InternalServerError()
-                .body(format!("Error connecting to upstream IMAGE {}: {}", url, e))
+            UPSTREAM_ERRORS.fetch_add(1, Ordering::Relaxed);
+            proxy_error_response(
+                actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error connecting to upstream IMAGE: {}", e),
+                None,
+                &url,
+            )
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PrefetchImageResult {
+    pub url: String,
+    pub ok: bool,
+    // 请求前已在缓存中
+    pub cached: bool,
+    pub error: Option<String>,
+}
+
+async fn prefetch_image(client: &Client, raw_url: String) -> PrefetchImageResult {
+    let url = unwrap_local_image_url(&raw_url);
+    let mut result = PrefetchImageResult {
+        url: raw_url,
+        ok: false,
+        cached: false,
+        error: None,
+    };
+    if url.is_empty() || !upstream_host_allowed(&url) {
+        result.error = Some(format!("Upstream host is not allowed: {}", url));
+        return result;
+    }
+    if IMAGE_CACHE.get(&url).is_some() {
+        result.ok = true;
+        result.cached = true;
+        return result;
+    }
+
+    let req = apply_common_headers(client.get(&url), &url).header(
+        "Accept",
+        "image/avif,image/webp,image/apng,image/*;q=0.8,*/*;q=0.5",
+    );
+    let fetched = tokio::time::timeout(PREFETCH_IMAGE_TIMEOUT, async {
+        let resp = req.send().await.map_err(|e| e.to_string())?;
+        if !resp.status().is_success() {
+            return Err(format!("Upstream status {}", resp.status()));
+        }
+        let content_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let bytes = read_image_body(resp).await?;
+        Ok((content_type, bytes))
+    })
+    .await
+    .unwrap_or_else(|_| Err("Timed out".to_string()));
+
+    match fetched {
+        Ok((content_type, bytes)) => {
+            let content_type = resolve_image_content_type(content_type.as_deref(), &bytes);
+            cache_image(&url, &content_type, &bytes);
+            result.ok = true;
+        }
+        Err(e) => result.error = Some(e),
+    }
+    result
+}
+
+// 批量预热图片缓存（有限并发），之后 /image 命中缓存直接返回；结果顺序与输入一致
+#[tauri::command]
+pub async fn prefetch_images(urls: Vec<String>) -> Result<Vec<PrefetchImageResult>, String> {
+    let (_, clients) = shared_proxy_clients();
+    let client = clients.current().clone();
+    let results: Vec<PrefetchImageResult> = futures_util::stream::iter(urls)
+        .map(|url| {
+            let client = client.clone();
+            async move { prefetch_image(&client, url).await }
+        })
+        .buffered(PREFETCH_IMAGE_CONCURRENCY)
+        .collect()
+        .await;
+    let ok = results.iter().filter(|r| r.ok).count();
+    println!(
+        "[Rust/proxy.rs image] Prefetched {}/{} images",
+        ok,
+        results.len()
+    );
+    Ok(results)
+}
+
+fn hls_proxy_path(resolved: &str, token: Option<&str>) -> String {
+    match token {
+        Some(token) => format!(
+            "/hls?url={}&token={}",
+            urlencoding::encode(resolved),
+            urlencoding::encode(token)
+        ),
+        None => format!("/hls?url={}", urlencoding::encode(resolved)),
+    }
+}
+
+fn rewrite_attribute_uri(line: &str, base: &Url, token: Option<&str>) -> String {
+    // 处理常见 tag：#EXT-X-KEY / #EXT-X-MAP 里的 URI="..."
+    let key = "URI=\"";
+    let Some(start) = line.find(key) else {
+        return line.to_string();
+    };
+    let rest = &line[start + key.len()..];
+    let Some(end) = rest.find('"') else {
+        return line.to_string();
+    };
+    let raw_uri = &rest[..end];
+    let resolved = base
+        .join(raw_uri)
+        .map(|u| u.to_string())
+        .unwrap_or_else(|_| raw_uri.to_string());
+    let proxied = hls_proxy_path(&resolved, token);
+    let mut out = String::new();
+    out.push_str(&line[..start + key.len()]);
+    out.push_str(&proxied);
+    out.push('"');
+    out.push_str(&rest[end + 1..]);
+    out
+}
+
+async fn hls_proxy_handler<R: Runtime>(
+    query: web::Query<HlsQuery>,
+    clients: web::Data<SegmentClients>,
+    app_handle: web::Data<AppHandle<R>>,
+) -> impl Responder {
+    let client = clients.current();
+    let url = query.url.clone();
+    if url.is_empty() {
+        return proxy_error_response(
+            actix_web::http::StatusCode::BAD_REQUEST,
+            "Missing url query parameter".to_string(),
+            None,
+            &url,
+        );
+    }
+
+    let upstream_url = match Url::parse(&url) {
+        Ok(u) => u,
+        Err(e) => {
+            return proxy_error_response(
+                actix_web::http::StatusCode::BAD_REQUEST,
+                format!("Invalid url: {}", e),
+                None,
+                &url,
+            )
+        }
+    };
+    if !upstream_host_allowed(upstream_url.as_str()) {
+        return forbidden_upstream(upstream_url.as_str());
+    }
+
+    let token = query.token.as_deref();
+    let cookie = token.and_then(|t| stream_cookie_header(t, upstream_url.as_str()));
+    let mut req = apply_common_headers(client.get(upstream_url.as_str()), upstream_url.as_str());
+    if let Some(token) = token {
+        req = apply_stream_referer(req, token);
+    }
+    if let Some(cookie) = cookie.as_deref() {
+        req = req.header(reqwest::header::COOKIE, cookie);
+    }
+
+    match req.send().await {
+        Ok(upstream_response) => {
+            let status_from_reqwest = upstream_response.status();
+            let content_type = upstream_response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("application/octet-stream")
+                .to_string();
+
+            if !status_from_reqwest.is_success() {
+                let error_text = upstream_response
+                    .text()
+                    .await
+                    .unwrap_or_else(|e| format!("Failed to read error body from upstream: {}", e));
+                return upstream_status_error(
+                    status_from_reqwest,
+                    format!(
+                        "Error fetching HLS resource from upstream. Details: {}",
+                        error_text
+                    ),
+                    &url,
+                );
+            }
+
+            let is_m3u8 = upstream_url.path().to_ascii_lowercase().ends_with(".m3u8")
+                || content_type.to_ascii_lowercase().contains("mpegurl")
+                || content_type.to_ascii_lowercase().contains("m3u8");
+
+            if is_m3u8 {
+                let text = match upstream_response.text().await {
+                    Ok(t) => t,
+                    Err(e) => {
+                        eprintln!("[Rust/proxy.rs hls] Failed to read playlist text: {}", e);
+                        return proxy_error_response(
+                            actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+                            format!("Failed to read playlist text: {}", e),
+                            None,
+                            &url,
+                        );
+                    }
+                };
+                // 记录 token 对应的媒体播放列表（非 master），供 seek_live 重新拉取直播边缘
+                if let Some(token) = token {
+                    if text.contains("#EXTINF") {
+                        LIVE_PLAYLISTS
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .insert(token.to_string(), upstream_url.to_string());
+                    }
+                }
+                // CDN 边缘节点卡住时会反复返回同一份列表，播放器因此停顿：超过阈值通知前端重新解析。
+                // 按 token 记录（随 forget_live_playlist 清理），无 token 的请求不做判断
+                let media_playlist = token.zip(parse_media_playlist(&text));
+                if let Some((key, info)) = media_playlist {
+                    if let Some(stalled) = check_playlist_freshness(key, &info) {
+                        eprintln!(
+                            "[Rust/proxy.rs hls] Playlist {} stale for {:.1}s at sequence {}",
+                            key,
+                            stalled.as_secs_f64(),
+                            info.media_sequence
+                        );
+                        emit_event(
+                            app_handle.get_ref(),
+                            DtvEvent::StreamReconnect(DtvEvent::json(StreamReconnectPayload {
+                                request_id: key.to_string(),
+                                reason: format!(
+                                    "stale HLS playlist: media sequence {} unchanged for {:.1}s",
+                                    info.media_sequence,
+                                    stalled.as_secs_f64()
+                                ),
+                            })),
+                        );
+                    }
+                }
+
+                let base_for_resolve = upstream_url.clone();
+                let rewritten = text
+                    .lines()
+                    .map(|line| {
+                        let trimmed = line.trim();
+                        if trimmed.is_empty() {
+                            return line.to_string();
+                        }
+                        if trimmed.starts_with('#') {
+                            // tag line: try rewrite URI="..."
+                            return rewrite_attribute_uri(line, &base_for_resolve, token);
+                        }
+
+                        let resolved = base_for_resolve
+                            .join(trimmed)
+                            .map(|u| u.to_string())
+                            .unwrap_or_else(|_| trimmed.to_string());
+                        hls_proxy_path(&resolved, token)
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n");
+
+                return HttpResponse::Ok()
+                    .content_type("application/vnd.apple.mpegurl")
+                    .insert_header(("Cache-Control", "no-store"))
+                    .body(rewritten);
+            }
+
+            // 非 m3u8：按二进制流转发（ts/mp4/key 等）
+            let mut response_builder = HttpResponse::Ok();
+            response_builder
+                .content_type(content_type)
+                .insert_header(("Cache-Control", "no-store"));
+
+            // Windows 下 chunked 流式转发容易被截断，改为整包读取（失败重试一次）；其他平台保持流式
+            if cfg!(target_os = "windows") {
+                return match fetch_segment_buffered(
+                    client,
+                    upstream_url.as_str(),
+                    token,
+                    cookie.as_deref(),
+                    upstream_response,
+                )
+                .await
+                {
+                    Ok(body) => response_builder.body(body),
+                    Err(e) => {
+                        eprintln!("[Rust/proxy.rs hls] Failed to read segment: {}", e);
+                        proxy_error_response(
+                            actix_web::http::StatusCode::BAD_GATEWAY,
+                            format!("Failed to read segment: {}", e),
+                            None,
+                            &url,
+                        )
+                    }
+                };
+            }
+
+            let byte_stream = upstream_response.bytes_stream().map_err(|e| {
+                eprintln!("[Rust/proxy.rs hls] Upstream stream error: {}", e);
+                actix_web::error::ErrorInternalServerError(format!("Upstream stream error: {}", e))
+            });
+            response_builder.streaming(bounded_chunks(byte_stream))
+        }
+        Err(e) => {
+            eprintln!(
+                "[Rust/proxy.rs hls] Failed to send request to upstream {}: {}",
+                url, e
+            );
+            UPSTREAM_ERRORS.fetch_add(1, Ordering::Relaxed);
+            proxy_error_response(
+                actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error connecting to upstream HLS: {}", e),
+                None,
+                &url,
+            )
+        }
+    }
+}
+
+// 判断上游地址能否由 webview 直接播放：虎牙/B 站 CDN 校验 Referer，解析时带回 Cookie 的地址
+// 需要附带 Cookie，HLS 需要代理改写分片地址并补 CORS，http 地址在 https 页面中会被拦截
+pub fn upstream_needs_proxy(url: &str) -> bool {
+    let Ok(parsed) = Url::parse(url) else {
+        return true;
+    };
+    let host = parsed.host_str().unwrap_or_default().to_ascii_lowercase();
+    // 按主机名后缀匹配，避免查询参数或路径中出现这些域名时误判
+    let host_matches = |suffix: &str| host == suffix || host.ends_with(&format!(".{}", suffix));
+    let needs_referer = [
+        "huya.com",
+        "hy-cdn.com",
+        "huyaimg.com",
+        "bilibili.com",
+        "hdslb.com",
+    ]
+    .iter()
+    .any(|suffix| host_matches(suffix))
+        || host.contains("bilivideo");
+    needs_referer
+        || parsed.path().ends_with(".m3u8")
+        || parsed.scheme() == "http"
+        || RESOLVED_COOKIES.get(url).is_some()
+}
+
+// FLV 上游请求头：/live.flv、prewarm_stream 与录制共用，保证命中的是同一条 CDN 路径
+pub(crate) fn flv_upstream_request(client: &Client, url: &str) -> reqwest::RequestBuilder {
+    let mut req = client
+        .get(url)
+        .header(
+            "User-Agent",
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+        )
+        .header("Accept", "video/x-flv,application/octet-stream,*/*")
+        .header("Range", "bytes=0-")
+        .header("Connection", "keep-alive");
+
+    // 如果是虎牙域名，添加必要的 Referer/Origin 头
+    if url.contains("huya.com") || url.contains("hy-cdn.com") || url.contains("huyaimg.com") {
+        req = req
+            .header("Referer", "https://www.huya.com/")
+            .header("Origin", "https://www.huya.com");
+    }
+    // 如果是B站域名，添加必要的 Referer 头
+    if url.contains("bilivideo") || url.contains("bilibili.com") || url.contains("hdslb.com") {
+        req = req.header("Referer", "https://live.bilibili.com/");
+    }
+    req
+}
+
+// 上游 Content-Type 只在确实是 FLV 时沿用（保留 video/flv 等写法），
+// 其他类型（常见的 application/octet-stream、text/plain）一律改写为 video/x-flv
+fn is_flv_content_type(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    matches!(
+        essence.to_ascii_lowercase().as_str(),
+        "video/x-flv" | "video/flv" | "application/x-flv" | "flv-application/octet-stream"
+    )
+}
+
+// 透传给播放器的上游响应头：只包含无副作用的描述性头与 CDN 调试头，
+// 长度/编码/连接相关的头由 actix 按实际转发的流重新生成
+const FORWARDED_FLV_HEADERS: [&str; 6] = [
+    "accept-ranges",
+    "x-cache",
+    "x-cache-lookup",
+    "x-request-id",
+    "via",
+    "server-timing",
+];
+
+fn forward_flv_headers(
+    upstream: &reqwest::header::HeaderMap,
+    response_builder: &mut actix_web::HttpResponseBuilder,
+) {
+    let content_type = upstream
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| is_flv_content_type(v))
+        .unwrap_or("video/x-flv");
+    response_builder.content_type(content_type.to_string());
+    for name in FORWARDED_FLV_HEADERS {
+        if let Some(value) = upstream.get(name).and_then(|v| v.to_str().ok()) {
+            response_builder.insert_header((name, value.to_string()));
+        }
+    }
+    if !upstream.contains_key(reqwest::header::ACCEPT_RANGES) {
+        response_builder.insert_header(("Accept-Ranges", "bytes"));
+    }
+}
+
+// Your actual proxy logic - this is a simplified placeholder
+async fn flv_proxy_handler<R: Runtime>(
+    req: HttpRequest,
+    stream_url_store: web::Data<StreamUrlStore>,
+    client: web::Data<Client>,
+    app_handle: web::Data<AppHandle<R>>,
+) -> impl Responder {
+    let token_stream = token_stream_url(&req);
+    let (url, request_id) = token_stream.unwrap_or_else(|| {
+        (
+            stream_url_store
+                .url
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+            stream_url_store
+                .request_id
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        )
+    });
+    if url.is_empty() {
+        return proxy_error_response(
+            actix_web::http::StatusCode::NOT_FOUND,
+            "Stream URL is not set or empty.".to_string(),
+            None,
+            &url,
+        );
+    }
+    if !upstream_host_allowed(&url) {
+        return forbidden_upstream(&url);
+    }
+
+    println!(
+        "[Rust/proxy.rs handler][req:{}] Incoming FLV proxy request -> {}",
+        request_id, url
+    );
+
+    let mut req = apply_stream_referer(flv_upstream_request(&client, &url), &request_id);
+    if let Some(cookie) = stream_cookie_header(&request_id, &url) {
+        req = req.header(reqwest::header::COOKIE, cookie);
+    }
+
+    match req.send().await {
+        Ok(upstream_response) => {
+            if upstream_response.status().is_success() {
+                let mut response_builder = HttpResponse::Ok();
+                response_builder
+                    .insert_header(("Connection", "keep-alive"))
+                    .insert_header(("Cache-Control", "no-store"));
+                forward_flv_headers(upstream_response.headers(), &mut response_builder);
+
+                // 每个连接按 request_id 登记自己的字节计数，重新拉流时从零开始
+                let stream_counter = FlvStreamCounter::register(&request_id);
+                let active_connection = ActiveFlvConnection::open();
+                // 从首批数据中解析 onMetaData，拿到后通过 stream-metadata 事件上报，数据原样转发
+                let mut metadata_sniffer = Some(FlvMetadataSniffer::new());
+                let metadata_request_id = request_id.clone();
+                let reconnect_app_handle = app_handle.clone();
+                let reconnect_request_id = request_id.clone();
+
+                let byte_stream = upstream_response
+                    .bytes_stream()
+                    .inspect(move |item| {
+                        let _active_connection = &active_connection;
+                        if let Ok(chunk) = item {
+                            FLV_BYTES_TOTAL.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                            stream_counter.add(chunk.len());
+                            if let Some(sniffer) = metadata_sniffer.as_mut() {
+                                match sniffer.push(chunk) {
+                                    SniffResult::NeedMore => {}
+                                    SniffResult::Found(metadata) => {
+                                        println!(
+                                            "[Rust/proxy.rs handler][req:{}] FLV metadata: {:?}",
+                                            metadata_request_id, metadata
+                                        );
+                                        emit_event(
+                                            app_handle.get_ref(),
+                                            DtvEvent::StreamMetadata(DtvEvent::json(
+                                                StreamMetadataPayload {
+                                                    request_id: metadata_request_id.clone(),
+                                                    metadata,
+                                                },
+                                            )),
+                                        );
+                                        metadata_sniffer = None;
+                                    }
+                                    SniffResult::Absent => metadata_sniffer = None,
+                                }
+                            }
+                        }
+                    })
+                    .map_err(move |e| {
+                        eprintln!(
+                            "[Rust/proxy.rs handler] Error reading bytes from upstream: {}",
+                            e
+                        );
+                        // FLV 是无限长的直播流，无法整包重试：截断时让前端重新拉流
+                        if is_early_eof(&e) {
+                            emit_event(
+                                reconnect_app_handle.get_ref(),
+                                DtvEvent::StreamReconnect(DtvEvent::json(StreamReconnectPayload {
+                                    request_id: reconnect_request_id.clone(),
+                                    reason: format!("upstream early EOF: {}", e),
+                                })),
+                            );
+                        }
+                        actix_web::error::ErrorInternalServerError(format!(
+                            "Upstream stream error: {}",
+                            e
+                        ))
+                    });
+
+                let byte_stream = bounded_chunks(byte_stream);
+                let rate_limit = FLV_RATE_LIMIT_BYTES_PER_SEC.load(Ordering::Relaxed);
+                let byte_stream: LocalBoxStream<'static, Result<Bytes, actix_web::Error>> =
+                    if rate_limit > 0 {
+                        throttle_stream(byte_stream, rate_limit).boxed_local()
+                    } else {
+                        byte_stream.boxed_local()
+                    };
+                let byte_stream = byte_stream
+                    .take_until(keepalive_lost(request_id.clone()))
+                    .boxed_local();
+                let byte_stream = watch_client_disconnect(byte_stream, request_id.clone());
+
+                response_builder.streaming(byte_stream)
+            } else {
+                let status_from_reqwest = upstream_response.status(); // Renamed for clarity
+                let error_text = upstream_response
+                    .text()
+                    .await
+                    .unwrap_or_else(|e| format!("Failed to read error body from upstream: {}", e));
+                eprintln!(
+                    "[Rust/proxy.rs handler] Upstream request to {} failed with status: {}. Body: {}",
+                    url, status_from_reqwest, error_text
+                );
+                upstream_status_error(
+                    status_from_reqwest,
+                    format!(
+                        "Error fetching FLV stream from upstream (reqwest). Details: {}",
+                        error_text
+                    ),
+                    &url,
+                )
+            }
+        }
+        Err(e) => {
+            eprintln!(
+                "[Rust/proxy.rs handler] Failed to send request to upstream {} with reqwest: {}",
+                url, e
+            );
+            UPSTREAM_ERRORS.fetch_add(1, Ordering::Relaxed);
+            proxy_error_response(
+                actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!(
+                    "Error connecting to upstream FLV stream with reqwest: {}",
+                    e
+                ),
+                None,
+                &url,
+            )
+        }
+    }
+}
+
+// /hls 与 /image 是否允许 HTTP/2（FLV 长连接始终使用 HTTP/1.1）
+static SEGMENT_HTTP2_ENABLED: AtomicBool = AtomicBool::new(false);
+
+// HLS 分片/图片客户端：同时准备 HTTP/1.1 与 HTTP/2 两个实例，按开关在请求时选择，切换无需重启代理
+struct SegmentClients {
+    http1: Client,
+    http2: Client,
+}
+
+impl SegmentClients {
+    fn new() -> Self {
+        Self {
+            http1: build_proxy_client(),
+            http2: build_segment_http2_client(),
+        }
+    }
+
+    fn current(&self) -> &Client {
+        if SEGMENT_HTTP2_ENABLED.load(Ordering::Relaxed) {
+            &self.http2
+        } else {
+            &self.http1
+        }
+    }
+}
+
+// 只限制建连（DNS/TCP/TLS）阶段：CDN 节点不可达时快速失败，不影响已建立连接上的长时间流式读取
+const PROXY_CONNECT_TIMEOUT: Duration = Duration::from_secs(8);
+
+// 允许通过 ALPN 协商 HTTP/2；图片与 m3u8 仍是整包读取后返回，不会引入 Windows 下 chunked 的 Early-EOF
+fn build_segment_http2_client() -> Client {
+    crate::network::stream_proxy()
+        .apply(Client::builder())
+        .redirect(upstream_redirect_policy())
+        .gzip(false)
+        .brotli(false)
+        .no_deflate()
+        .pool_idle_timeout(Duration::from_secs(90))
+        .pool_max_idle_per_host(4)
+        .tcp_keepalive(Duration::from_secs(60))
+        .connect_timeout(PROXY_CONNECT_TIMEOUT)
+        .timeout(Duration::from_secs(60))
+        .build()
+        .expect("failed to build client")
+}
+
+fn build_proxy_client() -> Client {
+    crate::network::stream_proxy()
+        .apply(Client::builder())
+        .redirect(upstream_redirect_policy())
+        .http1_only()
+        .gzip(false)
+        .brotli(false)
+        .no_deflate()
+        .pool_idle_timeout(None)
+        .pool_max_idle_per_host(4)
+        .tcp_keepalive(Duration::from_secs(60))
+        .connect_timeout(PROXY_CONNECT_TIMEOUT)
+        .timeout(Duration::from_secs(7200))
+        .build()
+        .expect("failed to build client")
+}
+
+// 上游客户端进程内共享：切换房间、重启代理服务时沿用同一连接池，同一 CDN 主机的 keep-alive
+// 连接不会因为重建服务器而断开。流链路代理设置（或 no_proxy 开关）变化后下次取用时重建
+struct SharedProxyClients {
+    proxy: crate::network::UpstreamProxy,
+    no_proxy: bool,
+    flv: web::Data<Client>,
+    segments: web::Data<SegmentClients>,
+}
+
+static SHARED_PROXY_CLIENTS: Lazy<StdMutex<Option<SharedProxyClients>>> =
+    Lazy::new(|| StdMutex::new(None));
+
+fn shared_proxy_clients() -> (web::Data<Client>, web::Data<SegmentClients>) {
+    let proxy = crate::network::stream_proxy();
+    let no_proxy = crate::network::is_no_proxy_enabled();
+    let mut shared = SHARED_PROXY_CLIENTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let reusable = shared
+        .as_ref()
+        .filter(|clients| clients.proxy == proxy && clients.no_proxy == no_proxy);
+    if let Some(clients) = reusable {
+        return (clients.flv.clone(), clients.segments.clone());
+    }
+    println!(
+        "[Rust/proxy.rs] Building shared upstream clients (stream proxy: {:?}, no_proxy: {})",
+        proxy, no_proxy
+    );
+    let clients = SharedProxyClients {
+        proxy,
+        no_proxy,
+        flv: web::Data::new(build_proxy_client()),
+        segments: web::Data::new(SegmentClients::new()),
+    };
+    let handles = (clients.flv.clone(), clients.segments.clone());
+    *shared = Some(clients);
+    handles
+}
+
+// FLV 代理与静态图片/HLS 代理共用同一套路由与客户端配置，新增路由只需改这里。
+// 对运行时泛型，测试中可以用 MockRuntime 的 AppHandle 启动同一套路由
+fn build_proxy_app<R: Runtime>(
+    stream_data: web::Data<StreamUrlStore>,
+    app_handle: web::Data<AppHandle<R>>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    let (app_data_reqwest_client, app_data_segment_clients) = shared_proxy_clients();
+    App::new()
+        .app_data(stream_data)
+        .app_data(app_data_reqwest_client)
+        .app_data(app_data_segment_clients)
+        .app_data(app_handle)
+        .wrap(actix_cors::Cors::permissive())
+        .wrap_fn(|req, srv| {
+            record_route_request(req.path());
+            srv.call(req)
+        })
+        .route("/live.flv", web::get().to(flv_proxy_handler::<R>))
+        .route("/image", web::get().to(image_proxy_handler))
+        .route("/hls", web::get().to(hls_proxy_handler::<R>))
+        .route("/stats", web::get().to(stats_handler))
+        .route("/metrics", web::get().to(metrics_handler))
+}
+
+// 绑定端口并在 tauri 运行时中启动服务，返回可用于停止服务的 handle 与任务 JoinHandle
+fn bind_proxy_server(
+    port: u16,
+    stream_data: web::Data<StreamUrlStore>,
+    app_handle: web::Data<AppHandle>,
+) -> std::io::Result<actix_web::dev::Server> {
+    Ok(
+        HttpServer::new(move || build_proxy_app(stream_data.clone(), app_handle.clone()))
+            .keep_alive(Duration::from_secs(120))
+            .bind(("127.0.0.1", port))?
+            .run(),
+    )
+}
+
+// 服务器异常退出后的自动重启次数（进程级累计），通过 /stats 暴露
+static PROXY_RESTART_COUNT: AtomicU64 = AtomicU64::new(0);
+// 单个代理任务内最多连续重启次数，避免端口被占用等永久性错误导致死循环
+const MAX_PROXY_RESTARTS: u32 = 5;
+const PROXY_RESTART_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Serialize, Clone, Debug)]
+struct ProxyRestartedPayload {
+    proxy_url: String,
+    restarts: u64,
+    reason: String,
+}
+
+// FLV 主代理与静态图片/HLS 代理共用监督任务，差异（state 中记录的 handle、对外地址、
+// 异常退出后是否值得重启）按代理类型区分
+#[derive(Clone, Copy, Debug)]
+enum ProxyKind {
+    Flv,
+    Static,
+}
+
+impl ProxyKind {
+    fn proxy_url(&self, port: u16) -> String {
+        match self {
+            Self::Flv => format!("http://127.0.0.1:{}/live.flv", port),
+            Self::Static => format!("http://127.0.0.1:{}", port),
+        }
+    }
+
+    fn lifecycle(&self) -> &'static tokio::sync::Mutex<()> {
+        match self {
+            Self::Flv => &FLV_PROXY_LIFECYCLE,
+            Self::Static => &STATIC_PROXY_LIFECYCLE,
+        }
+    }
+
+    fn task_slot<'a>(&self, app_handle: &'a AppHandle) -> &'a StdMutex<Option<ProxyServerTask>> {
+        match self {
+            Self::Flv => &app_handle.state::<ProxyServerHandle>().inner().0,
+            Self::Static => &app_handle.state::<StaticProxyServerHandle>().inner().0,
+        }
+    }
+
+    // FLV 代理只在仍有流在播放时重启；静态代理只要未被主动停止就重启（图片/HLS 随时会用到）
+    fn should_restart(&self, stream_data: &StreamUrlStore) -> bool {
+        match self {
+            Self::Flv => !stream_data
+                .url
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .is_empty(),
+            Self::Static => STATIC_PROXY_RUNNING.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// 监督任务重建服务器前调用（调用方已持有该代理的生命周期锁）：state 中记录的仍是本任务时才重建，
+// 并在同一把锁内换上新的停止 handle；已被 stop 取走或被新的 start 替换时返回 None。
+// 重建失败时把本任务从 state 中移除，之后的 start 不会把它当成仍在运行
+fn rebind_if_current(
+    slot: &StdMutex<Option<ProxyServerTask>>,
+    id: u64,
+    rebind: impl FnOnce() -> std::io::Result<actix_web::dev::Server>,
+) -> Option<std::io::Result<actix_web::dev::Server>> {
+    let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+    if slot.as_ref().map(|task| task.id) != Some(id) {
+        return None;
+    }
+    let result = rebind();
+    match &result {
+        Ok(server) => {
+            if let Some(task) = slot.as_mut() {
+                task.handle = server.handle();
+            }
+        }
+        Err(_) => {
+            slot.take();
+        }
+    }
+    Some(result)
+}
+
+// 监督任务放弃重启时，若 state 中仍是本任务则移除，静态代理同时标记为未运行
+fn forget_task(kind: ProxyKind, app_handle: &AppHandle, id: u64) {
+    let mut slot = kind
+        .task_slot(app_handle)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if slot.as_ref().map(|task| task.id) == Some(id) {
+        slot.take();
+        if matches!(kind, ProxyKind::Static) {
+            STATIC_PROXY_RUNNING.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+fn spawn_proxy_server(
+    kind: ProxyKind,
+    port: u16,
+    stream_data: web::Data<StreamUrlStore>,
+    app_handle: AppHandle,
+) -> std::io::Result<ProxyServerTask> {
+    let app_handle_data = web::Data::new(app_handle.clone());
+    let server = bind_proxy_server(port, stream_data.clone(), app_handle_data.clone())?;
+
+    let handle = server.handle();
+    let id = PROXY_TASK_ID.fetch_add(1, Ordering::Relaxed) + 1;
+
+    // 监督任务：服务器返回错误且该代理仍在使用时，在同一端口重建服务器，对外地址保持不变。
+    // 重建在生命周期锁内进行，与 start/stop/restart 命令互斥
+    let join = tauri::async_runtime::spawn(async move {
+        let mut server = server;
+        let mut attempts: u32 = 0;
+        loop {
+            let error = match server.await {
+                Ok(()) => {
+                    println!("[Rust/proxy.rs] Proxy server on port {} shut down.", port);
+                    break;
+                }
+                Err(e) => e,
+            };
+            eprintln!(
+                "[Rust/proxy.rs] {:?} proxy server run error: {}",
+                kind, error
+            );
+            let in_use = kind.should_restart(&stream_data);
+            if !in_use || attempts >= MAX_PROXY_RESTARTS {
+                eprintln!(
+                    "[Rust/proxy.rs] Not restarting {:?} proxy on port {} (in use: {}, attempts: {})",
+                    kind, port, in_use, attempts
+                );
+                forget_task(kind, &app_handle, id);
+                break;
+            }
+            attempts += 1;
+            tokio::time::sleep(PROXY_RESTART_DELAY).await;
+            let _lifecycle = kind.lifecycle().lock().await;
+            let rebound = rebind_if_current(kind.task_slot(&app_handle), id, || {
+                bind_proxy_server(port, stream_data.clone(), app_handle_data.clone())
+            });
+            server = match rebound {
+                Some(Ok(server)) => server,
+                Some(Err(e)) => {
+                    eprintln!(
+                        "[Rust/proxy.rs] Failed to rebind {:?} proxy on port {}: {}",
+                        kind, port, e
+                    );
+                    if matches!(kind, ProxyKind::Static) {
+                        STATIC_PROXY_RUNNING.store(false, Ordering::Relaxed);
+                    }
+                    break;
+                }
+                None => {
+                    println!(
+                        "[Rust/proxy.rs] {:?} proxy on port {} was stopped or replaced, not restarting",
+                        kind, port
+                    );
+                    break;
+                }
+            };
+            let restarts = PROXY_RESTART_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+            println!(
+                "[Rust/proxy.rs] {:?} proxy server restarted on port {} (attempt {}, total {})",
+                kind, port, attempts, restarts
+            );
+            emit_event(
+                &app_handle,
+                DtvEvent::ProxyRestarted(DtvEvent::json(ProxyRestartedPayload {
+                    proxy_url: kind.proxy_url(port),
+                    restarts,
+                    reason: error.to_string(),
+                })),
+            );
+        }
+        println!(
+            "[Rust/proxy.rs] Proxy server task on port {} completed.",
+            port
+        );
+    });
+
+    Ok(ProxyServerTask {
+        handle,
+        join,
+        port,
+        id,
+    })
+}
+
+#[tauri::command]
+pub async fn start_proxy(
+    app_handle: AppHandle,
+    server_handle_state: State<'_, ProxyServerHandle>,
+    stream_url_store: State<'_, StreamUrlStore>,
+    allow_http2: Option<bool>,
+    enable_metrics: Option<bool>,
+) -> Result<String, String> {
+    if let Some(enabled) = allow_http2 {
+        SEGMENT_HTTP2_ENABLED.store(enabled, Ordering::Relaxed);
+        println!("[Rust/proxy.rs] HTTP/2 for /hls and /image: {}", enabled);
+    }
+    if let Some(enabled) = enable_metrics {
+        METRICS_ENABLED.store(enabled, Ordering::Relaxed);
+        println!("[Rust/proxy.rs] /metrics endpoint enabled: {}", enabled);
+    }
+    let port = find_free_port().await;
+    let current_stream_url = stream_url_store
+        .url
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+
+    if current_stream_url.is_empty() {
+        return Err("Stream URL is not set in store. Cannot start proxy.".to_string());
+    }
+
+    // stream_url_data_for_actix can be created once and cloned, as StreamUrlStore is Arc based and Send + Sync
+    let stream_url_data_for_actix = web::Data::new(stream_url_store.inner().clone());
+
+    let _lifecycle = FLV_PROXY_LIFECYCLE.lock().await;
+    // Ensure MutexGuard is dropped before .await
+    let existing_task_to_stop = {
+        server_handle_state
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    };
+    if let Some(existing_task) = existing_task_to_stop {
+        existing_task.shutdown().await;
+    }
+
+    let server_task_for_state = match spawn_proxy_server(
+        ProxyKind::Flv,
+        port,
+        stream_url_data_for_actix,
+        app_handle.clone(),
+    ) {
+        Ok(task) => task,
+        Err(e) => {
+            let err_msg = format!(
+                "[Rust/proxy.rs] Failed to bind server to port {}: {}",
+                port, e
+            );
+            eprintln!("{}", err_msg);
+            return Err(err_msg);
+        }
+    };
+    *server_handle_state
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = Some(server_task_for_state);
+    spawn_bitrate_monitor(app_handle);
+
+    Ok(ProxyKind::Flv.proxy_url(port))
+}
+
+// FLV 代理正在运行时返回其播放地址，不重启服务器
+pub fn running_flv_proxy_url(server_handle: &ProxyServerHandle) -> Option<String> {
+    server_handle
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|task| ProxyKind::Flv.proxy_url(task.port))
+}
+
+#[tauri::command]
+pub async fn start_static_proxy_server(
+    app_handle: AppHandle,
+    stream_url_store: State<'_, StreamUrlStore>,
+) -> Result<String, String> {
+    let _lifecycle = STATIC_PROXY_LIFECYCLE.lock().await;
+    start_static_proxy_locked(app_handle, stream_url_store).await
+}
+
+// 调用方需持有 STATIC_PROXY_LIFECYCLE
+async fn start_static_proxy_locked(
+    app_handle: AppHandle,
+    stream_url_store: State<'_, StreamUrlStore>,
+) -> Result<String, String> {
+    // Use a dedicated port for static image proxy to avoid interfering with FLV stream proxy
+    let port: u16 = STATIC_PROXY_PORT;
+
+    // If the server is already running, just return the base URL (idempotent behavior)
+    if TcpStream::connect(("127.0.0.1", port)).is_ok() {
+        STATIC_PROXY_RUNNING.store(true, Ordering::Relaxed);
+        return Ok(format!("http://127.0.0.1:{}", port));
+    }
+
+    let stream_url_data_for_actix = web::Data::new(stream_url_store.inner().clone());
+
+    // Do NOT overwrite the main proxy server handle; run static proxy independently
+    match spawn_proxy_server(
+        ProxyKind::Static,
+        port,
+        stream_url_data_for_actix,
+        app_handle.clone(),
+    ) {
+        Ok(task) => {
+            let previous = app_handle
+                .state::<StaticProxyServerHandle>()
+                .0
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .replace(task);
+            if let Some(previous) = previous {
+                previous.shutdown().await;
+            }
+        }
+        // If address already in use, assume server is running and return OK base URL
+        Err(e) if e.kind() == ErrorKind::AddrInUse => {
+            eprintln!(
+                "[Rust/proxy.rs] Port {} already in use; assuming static proxy running.",
+                port
+            );
+            STATIC_PROXY_RUNNING.store(true, Ordering::Relaxed);
+            return Ok(format!("http://127.0.0.1:{}", port));
+        }
+        Err(e) => {
+            let err_msg = format!(
+                "[Rust/proxy.rs] Failed to bind server to port {}: {}",
+                port, e
+            );
+            eprintln!("{}", err_msg);
+            return Err(err_msg);
+        }
+    }
+    STATIC_PROXY_RUNNING.store(true, Ordering::Relaxed);
+
+    Ok(format!("http://127.0.0.1:{}", port))
+}
+
+// 重启静态图片/HLS 代理（例如应用新的请求头规则或清空图片缓存）：停止本进程记录的服务器，
+// 等固定端口释放后重新启动并返回基础地址。重启期间图片地址不再改写为本地代理地址
+#[tauri::command]
+pub async fn restart_static_proxy(
+    app_handle: AppHandle,
+    stream_url_store: State<'_, StreamUrlStore>,
+) -> Result<String, String> {
+    let _lifecycle = STATIC_PROXY_LIFECYCLE.lock().await;
+    STATIC_PROXY_RUNNING.store(false, Ordering::Relaxed);
+    let task_to_stop = {
+        app_handle
+            .state::<StaticProxyServerHandle>()
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    };
+    match task_to_stop {
+        Some(task) => task.shutdown().await,
+        None => println!(
+            "[Rust/proxy.rs] restart_static_proxy: no tracked static proxy, starting fresh."
+        ),
+    }
+    if let Err(busy) = wait_for_ports_released(&[STATIC_PROXY_PORT]).await {
+        return Err(format!(
+            "Static proxy port still in use after {:?}: {:?}",
+            PORT_RELEASE_TIMEOUT, busy
+        ));
+    }
+    IMAGE_CACHE.clear();
+
+    let base_url = start_static_proxy_locked(app_handle, stream_url_store).await?;
+    println!(
+        "[Rust/proxy.rs] restart_static_proxy: static proxy restarted at {}",
+        base_url
+    );
+    Ok(base_url)
+}
+
+#[tauri::command]
+pub async fn stop_proxy(server_handle_state: State<'_, ProxyServerHandle>) -> Result<(), String> {
+    let _lifecycle = FLV_PROXY_LIFECYCLE.lock().await;
+    // Ensure MutexGuard is dropped before .await
+    let task_to_stop = {
+        server_handle_state
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    };
+    stop_bitrate_monitor();
+
+    if let Some(task) = task_to_stop {
+        task.shutdown().await; // Changed to non-graceful shutdown
+        println!("[Rust/proxy.rs] stop_proxy: Initiated non-graceful shutdown.");
+    } else {
+        println!("[Rust/proxy.rs] stop_proxy command: No proxy server was running or handle already taken.");
+    }
+    Ok(())
+}
+
+// 停止静态图片/HLS 代理；从未启动或已停止时直接返回 false
+#[tauri::command]
+pub async fn stop_static_proxy(
+    static_handle_state: State<'_, StaticProxyServerHandle>,
+) -> Result<bool, String> {
+    let _lifecycle = STATIC_PROXY_LIFECYCLE.lock().await;
+    let task_to_stop = {
+        static_handle_state
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    };
+    STATIC_PROXY_RUNNING.store(false, Ordering::Relaxed);
+    match task_to_stop {
+        Some(task) => {
+            task.shutdown().await;
+            println!("[Rust/proxy.rs] stop_static_proxy: static proxy stopped.");
+            Ok(true)
+        }
+        None => {
+            println!(
+                "[Rust/proxy.rs] stop_static_proxy: static proxy was not started by this process."
+            );
+            Ok(false)
+        }
+    }
+}
+
+// 依次停止 FLV 主代理与静态代理：先断开正在播放的长连接，再停图片/HLS 服务
+#[tauri::command]
+pub async fn shutdown_all(app_handle: AppHandle) -> Result<(), String> {
+    stop_proxy(app_handle.state::<ProxyServerHandle>()).await?;
+    stop_static_proxy(app_handle.state::<StaticProxyServerHandle>()).await?;
+    Ok(())
+}
+
+// 不占用播放存储的 FLV 流（导出的播放列表等）：按 token 登记上游地址，
+// 经 /live.flv?token= 访问；未登记的 token 仍回落到播放存储。
+// 导出的播放列表没有对应的关闭操作，登记项按 TTL 过期（上游签名地址通常也在这之前失效），
+// 数量超出上限时淘汰最早的登记；forget_stream_cookies 时同时移除
+const TOKEN_STREAM_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+const MAX_TOKEN_STREAMS: usize = 256;
+static TOKEN_STREAMS: Lazy<TtlCache<String>> =
+    Lazy::new(|| TtlCache::bounded(TOKEN_STREAM_TTL, MAX_TOKEN_STREAMS, usize::MAX, |_| 0));
+
+pub fn register_token_stream(token: &str, upstream_url: &str) {
+    TOKEN_STREAMS.insert(token.to_string(), upstream_url.to_string());
+}
+
+// token 对应的 FLV 上游：先查按 token 登记的流，再查播放存储
+pub fn flv_upstream_for_token(token: &str, store: &StreamUrlStore) -> Option<String> {
+    TOKEN_STREAMS.get(token).or_else(|| {
+        let url = store.url.lock().unwrap_or_else(|e| e.into_inner());
+        let request_id = store.request_id.lock().unwrap_or_else(|e| e.into_inner());
+        (!url.is_empty() && *request_id == token).then(|| url.clone())
+    })
+}
+
+fn token_stream_url(req: &HttpRequest) -> Option<(String, String)> {
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).ok()?;
+    let token = query.get("token")?;
+    let url = TOKEN_STREAMS.get(token)?;
+    Some((url, token.clone()))
+}
+
+const PORT_RELEASE_TIMEOUT: Duration = Duration::from_secs(5);
+const PORT_RELEASE_POLL: Duration = Duration::from_millis(50);
+
+fn port_is_free(port: u16) -> bool {
+    std::net::TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+// 轮询直到给定端口都可绑定；超时后返回仍被占用的端口
+async fn wait_for_ports_released(ports: &[u16]) -> Result<(), Vec<u16>> {
+    let deadline = Instant::now() + PORT_RELEASE_TIMEOUT;
+    loop {
+        let busy: Vec<u16> = ports
+            .iter()
+            .copied()
+            .filter(|port| !port_is_free(*port))
+            .collect();
+        if busy.is_empty() {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(busy);
+        }
+        tokio::time::sleep(PORT_RELEASE_POLL).await;
+    }
+}
+
+// 停止两个代理并等待固定端口真正释放后才返回，避免随后重新绑定时出现 AddrInUse。
+// 端口被其他进程占用（或本进程之外的实例）时在超时后报错
+#[tauri::command]
+pub async fn stop_all_proxies(app_handle: AppHandle) -> Result<(), String> {
+    shutdown_all(app_handle).await?;
+
+    match wait_for_ports_released(&[FLV_PROXY_PORT, STATIC_PROXY_PORT]).await {
+        Ok(()) => {
+            println!("[Rust/proxy.rs] stop_all_proxies: both proxy ports released.");
+            Ok(())
+        }
+        Err(busy) => Err(format!(
+            "Proxy ports still in use after {:?}: {:?}",
+            PORT_RELEASE_TIMEOUT, busy
+        )),
+    }
+}
+
+const PREWARM_TIMEOUT: Duration = Duration::from_secs(5);
+const PREWARM_READ_BYTES: usize = 16 * 1024;
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct PrewarmResult {
+    pub ok: bool,
+    // 发出请求到收到响应头的耗时（DNS + TCP + TLS + CDN 调度）
+    pub handshake_ms: Option<u64>,
+    pub first_bytes_ms: Option<u64>,
+    pub bytes_read: usize,
+    pub error: Option<String>,
+}
+
+// 在播放器连接 /live.flv 之前先连一次上游：读几 KB 后断开，让 DNS 缓存与 CDN 边缘节点提前就绪。
+// 尽力而为：失败只体现在返回值里，不影响后续播放
+#[tauri::command]
+pub async fn prewarm_stream(
+    stream_url_store: State<'_, StreamUrlStore>,
+    stream_token: String,
+) -> Result<PrewarmResult, String> {
+    let (url, request_id) = {
+        let url = stream_url_store
+            .url
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let request_id = stream_url_store
+            .request_id
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        (url, request_id)
+    };
+    if url.is_empty() {
+        return Err("Stream URL is not set in store. Nothing to prewarm.".to_string());
+    }
+    if request_id != stream_token {
+        return Err(format!(
+            "Stream token {} is not the active stream ({})",
+            stream_token, request_id
+        ));
+    }
+    prewarm_upstream(&url, &request_id).await
+}
+
+// 预热任意上游地址（切换清晰度时在替换存储前先预热新地址）
+pub(crate) async fn prewarm_upstream(url: &str, request_id: &str) -> Result<PrewarmResult, String> {
+    if !upstream_host_allowed(url) {
+        return Err(format!("Upstream host not allowed: {}", url));
+    }
+
+    // 使用 /live.flv 的共享客户端，预热建立的 keep-alive 连接才能被随后的播放复用
+    let (client, _) = shared_proxy_clients();
+
+    let started = Instant::now();
+    let mut result = PrewarmResult::default();
+    let mut req = apply_stream_referer(flv_upstream_request(&client, url), request_id)
+        .timeout(PREWARM_TIMEOUT);
+    if let Some(cookie) = stream_cookie_header(request_id, url) {
+        req = req.header(reqwest::header::COOKIE, cookie);
+    }
+    match req.send().await {
+        Ok(resp) if resp.status().is_success() => {
+            result.handshake_ms = Some(started.elapsed().as_millis() as u64);
+            let mut stream = resp.bytes_stream();
+            while result.bytes_read < PREWARM_READ_BYTES {
+                match stream.next().await {
+                    Some(Ok(chunk)) => {
+                        if result.first_bytes_ms.is_none() {
+                            result.first_bytes_ms = Some(started.elapsed().as_millis() as u64);
+                        }
+                        result.bytes_read += chunk.len();
+                    }
+                    Some(Err(e)) => {
+                        result.error = Some(e.to_string());
+                        break;
+                    }
+                    None => break,
+                }
+            }
+            result.ok = result.bytes_read > 0;
+        }
+        Ok(resp) => {
+            result.handshake_ms = Some(started.elapsed().as_millis() as u64);
+            result.error = Some(format!("Upstream returned status {}", resp.status()));
+        }
+        Err(e) => result.error = Some(e.to_string()),
+    }
+
+    println!(
+        "[Rust/proxy.rs][req:{}] prewarm ok={} handshake={:?}ms first_bytes={:?}ms read={}B error={:?}",
+        request_id,
+        result.ok,
+        result.handshake_ms,
+        result.first_bytes_ms,
+        result.bytes_read,
+        result.error
+    );
+    Ok(result)
+}
+
+const THROUGHPUT_PROBE_TIMEOUT: Duration = Duration::from_secs(4);
+const THROUGHPUT_PROBE_BYTES: usize = 2 * 1024 * 1024;
+// 首字节之后读到的数据少于该值时测得的速率不可信
+const THROUGHPUT_PROBE_MIN_BYTES: usize = 64 * 1024;
+// 直播 FLV 建连后先按链路速度下发缓存的 GOP，之后按码率推流：相邻数据块的间隔超过该值
+// 视为突发段结束，后续按码率到达的数据反映的是源站码率而不是带宽，不再计入
+const THROUGHPUT_BURST_GAP: Duration = Duration::from_millis(100);
+
+// 估算可用带宽（kbps）：FLV 只统计建连后首个 GOP 的突发段；HLS 下载列表中最新的分片（固定大小的对象）。
+// 只统计首个数据块之后的传输；数据过少（如上游限速、突发段太短）时返回 None，由调用方按“无法判断”处理
+pub(crate) async fn probe_throughput_kbps(
+    url: &str,
+    request_id: &str,
+) -> Result<Option<u64>, String> {
+    if !upstream_host_allowed(url) {
+        return Err(format!("Upstream host not allowed: {}", url));
+    }
+
+    // 与 /live.flv 共用连接池：测速建立的连接可被随后的播放直接复用
+    let (client, _) = shared_proxy_clients();
+    let deadline = tokio::time::Instant::now() + THROUGHPUT_PROBE_TIMEOUT;
+    let target = if url.contains(".m3u8") {
+        match latest_hls_segment(&client, url, request_id, deadline).await? {
+            Some(segment) => segment,
+            None => return Ok(None),
+        }
+    } else {
+        url.to_string()
+    };
+    if !upstream_host_allowed(&target) {
+        return Err(format!("Upstream host not allowed: {}", target));
+    }
+
+    let mut req = apply_stream_referer(flv_upstream_request(&client, &target), request_id);
+    if let Some(cookie) = stream_cookie_header(request_id, &target) {
+        req = req.header(reqwest::header::COOKIE, cookie);
+    }
+    let resp = match tokio::time::timeout_at(deadline, req.send()).await {
+        Ok(Ok(resp)) if resp.status().is_success() => resp,
+        Ok(Ok(resp)) => return Err(format!("Upstream returned status {}", resp.status())),
+        Ok(Err(e)) => return Err(e.to_string()),
+        Err(_) => return Ok(None),
+    };
+
+    let (bytes_after_first, elapsed) = measure_burst(
+        resp.bytes_stream(),
+        deadline,
+        THROUGHPUT_BURST_GAP,
+        request_id,
+    )
+    .await;
+    if bytes_after_first < THROUGHPUT_PROBE_MIN_BYTES || elapsed.is_zero() {
+        println!(
+            "[Rust/proxy.rs][req:{}] throughput probe inconclusive: {}B in {:?}",
+            request_id, bytes_after_first, elapsed
+        );
+        return Ok(None);
+    }
+    let kbps = (bytes_after_first as f64 * 8.0 / 1000.0 / elapsed.as_secs_f64()) as u64;
+    println!(
+        "[Rust/proxy.rs][req:{}] throughput probe: {}B in {:?} = {}kbps",
+        request_id, bytes_after_first, elapsed, kbps
+    );
+    Ok(Some(kbps))
+}
+
+// 读取连续到达的数据，返回首个数据块之后的字节数与耗时（截至最后一个计入的数据块）
+async fn measure_burst<S, E>(
+    stream: S,
+    deadline: tokio::time::Instant,
+    gap: Duration,
+    request_id: &str,
+) -> (usize, Duration)
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: std::fmt::Display,
+{
+    let mut stream = std::pin::pin!(stream);
+    let first_chunk_at = match tokio::time::timeout_at(deadline, stream.next()).await {
+        Ok(Some(Ok(_))) => Instant::now(),
+        _ => return (0, Duration::ZERO),
+    };
+    let mut last_chunk_at = first_chunk_at;
+    let mut bytes_after_first = 0usize;
+    while bytes_after_first < THROUGHPUT_PROBE_BYTES {
+        let until = deadline.min(tokio::time::Instant::from_std(last_chunk_at + gap));
+        match tokio::time::timeout_at(until, stream.next()).await {
+            Ok(Some(Ok(chunk))) => {
+                bytes_after_first += chunk.len();
+                last_chunk_at = Instant::now();
+            }
+            Ok(Some(Err(e))) => {
+                eprintln!(
+                    "[Rust/proxy.rs][req:{}] throughput probe read error: {}",
+                    request_id, e
+                );
+                break;
+            }
+            Ok(None) | Err(_) => break,
+        }
+    }
+    (bytes_after_first, last_chunk_at - first_chunk_at)
+}
+
+// 直播列表中最新的分片地址；嵌套的多码率主列表不再展开，按无法测速处理
+async fn latest_hls_segment(
+    client: &Client,
+    playlist_url: &str,
+    request_id: &str,
+    deadline: tokio::time::Instant,
+) -> Result<Option<String>, String> {
+    let mut req = apply_stream_referer(flv_upstream_request(client, playlist_url), request_id);
+    if let Some(cookie) = stream_cookie_header(request_id, playlist_url) {
+        req = req.header(reqwest::header::COOKIE, cookie);
+    }
+    let text = match tokio::time::timeout_at(deadline, async {
+        let resp = req.send().await?.error_for_status()?;
+        resp.text().await
+    })
+    .await
+    {
+        Ok(Ok(text)) => text,
+        Ok(Err(e)) => return Err(e.to_string()),
+        Err(_) => return Ok(None),
+    };
+    let Some(uri) = text
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+    else {
+        return Ok(None);
+    };
+    if uri.split('?').next().unwrap_or(uri).ends_with(".m3u8") {
+        return Ok(None);
+    }
+    Url::parse(playlist_url)
+        .and_then(|base| base.join(uri))
+        .map(|segment| Some(segment.to_string()))
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct LatencyBreakdown {
+    // reqwest 不暴露连接阶段的耗时，DNS/TCP 由单独的解析与连接测得
+    pub dns_ms: Option<u64>,
+    pub connect_ms: Option<u64>,
+    pub headers_ms: Option<u64>,
+    pub first_byte_ms: Option<u64>,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ProxyLatencyReport {
+    pub stream_token: String,
+    pub direct: LatencyBreakdown,
+    // FLV 代理未运行时为 None
+    pub proxied: Option<LatencyBreakdown>,
+    // 经代理与直连的首字节耗时差，正数表示代理带来的额外延迟
+    pub overhead_ms: Option<i64>,
+}
+
+async fn measure_connect(url: &str, result: &mut LatencyBreakdown) {
+    let Ok(parsed) = Url::parse(url) else {
+        return;
+    };
+    let Some(host) = parsed.host_str() else {
+        return;
+    };
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let started = Instant::now();
+    let lookup = tokio::net::lookup_host((host, port));
+    let addr = match tokio::time::timeout(PREWARM_TIMEOUT, lookup).await {
+        Ok(Ok(mut addrs)) => addrs.next(),
+        _ => None,
+    };
+    let Some(addr) = addr else {
+        return;
+    };
+    result.dns_ms = Some(started.elapsed().as_millis() as u64);
+    let started = Instant::now();
+    if let Ok(Ok(_)) =
+        tokio::time::timeout(PREWARM_TIMEOUT, tokio::net::TcpStream::connect(addr)).await
+    {
+        result.connect_ms = Some(started.elapsed().as_millis() as u64);
+    }
+}
+
+async fn measure_first_byte(req: reqwest::RequestBuilder) -> LatencyBreakdown {
+    let mut result = LatencyBreakdown::default();
+    let started = Instant::now();
+    match req.send().await {
+        Ok(resp) => {
+            result.headers_ms = Some(started.elapsed().as_millis() as u64);
+            result.status = Some(resp.status().as_u16());
+            if !resp.status().is_success() {
+                result.error = Some(format!("Upstream returned status {}", resp.status()));
+                return result;
+            }
+            match resp.bytes_stream().next().await {
+                Some(Ok(_)) => result.first_byte_ms = Some(started.elapsed().as_millis() as u64),
+                Some(Err(e)) => result.error = Some(e.to_string()),
+                None => result.error = Some("Stream ended before the first byte".to_string()),
+            }
+        }
+        Err(e) => result.error = Some(e.to_string()),
+    }
+    result
+}
+
+// 一次性诊断：分别测量直连上游与经本地 FLV 代理拉流的首字节耗时，用于判断代理带来的开销
+// （配合 needs_proxy 决定能否直接播放上游地址）。两次测量串行进行，互不争抢带宽
+#[tauri::command]
+pub async fn measure_proxy_latency(
+    stream_url_store: State<'_, StreamUrlStore>,
+    server_handle: State<'_, ProxyServerHandle>,
+    stream_token: String,
+) -> Result<ProxyLatencyReport, String> {
+    let url = {
+        let url = stream_url_store
+            .url
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let request_id = stream_url_store
+            .request_id
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        if url.is_empty() || request_id != stream_token {
+            return Err(format!(
+                "Stream token {} is not the active stream",
+                stream_token
+            ));
+        }
+        url
+    };
+    if !upstream_host_allowed(&url) {
+        return Err(format!("Upstream host not allowed: {}", url));
+    }
+
+    let upstream_client = crate::network::stream_proxy()
+        .apply(Client::builder())
+        .redirect(upstream_redirect_policy())
+        .http1_only()
+        .timeout(PREWARM_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to build client: {}", e))?;
+    let mut req = apply_stream_referer(flv_upstream_request(&upstream_client, &url), &stream_token);
+    if let Some(cookie) = stream_cookie_header(&stream_token, &url) {
+        req = req.header(reqwest::header::COOKIE, cookie);
+    }
+    let mut direct = measure_first_byte(req).await;
+    measure_connect(&url, &mut direct).await;
+
+    let proxied = match running_flv_proxy_url(&server_handle) {
+        Some(proxy_url) => {
+            let local_client = Client::builder()
+                .no_proxy()
+                .timeout(PREWARM_TIMEOUT)
+                .build()
+                .map_err(|e| format!("Failed to build client: {}", e))?;
+            let mut proxied = measure_first_byte(local_client.get(&proxy_url)).await;
+            measure_connect(&proxy_url, &mut proxied).await;
+            Some(proxied)
+        }
+        None => None,
+    };
+
+    let proxied_first_byte = proxied.as_ref().and_then(|p| p.first_byte_ms);
+    let overhead_ms = match (direct.first_byte_ms, proxied_first_byte) {
+        (Some(direct_ms), Some(proxied_ms)) => Some(proxied_ms as i64 - direct_ms as i64),
+        _ => None,
+    };
+    println!(
+        "[Rust/proxy.rs][req:{}] latency direct={:?}ms proxied={:?}ms overhead={:?}ms",
+        stream_token, direct.first_byte_ms, proxied_first_byte, overhead_ms
+    );
+    Ok(ProxyLatencyReport {
+        stream_token,
+        direct,
+        proxied,
+        overhead_ms,
+    })
+}
+
+// 各 stream token 最近一次经代理拉取的 HLS 媒体播放列表地址
+static LIVE_PLAYLISTS: Lazy<StdMutex<HashMap<String, String>>> =
+    Lazy::new(|| StdMutex::new(HashMap::new()));
+
+pub fn forget_live_playlist(token: &str) {
+    LIVE_PLAYLISTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(token);
+    PLAYLIST_FRESHNESS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(token);
+}
+
+// 直播媒体播放列表最近一次 EXT-X-MEDIA-SEQUENCE 变化的时间；signalled 保证每次卡住只通知一次
+struct PlaylistFreshness {
+    media_sequence: u64,
+    changed_at: Instant,
+    signalled: bool,
+}
+
+static PLAYLIST_FRESHNESS: Lazy<StdMutex<HashMap<String, PlaylistFreshness>>> =
+    Lazy::new(|| StdMutex::new(HashMap::new()));
+
+// 判定为卡住的最短时间；target duration 较长的列表按三个分片时长计算
+const MIN_STALE_PLAYLIST: Duration = Duration::from_secs(10);
+
+// 序号长时间不变时返回已卡住的时长（仅首次超过阈值时返回），已结束的列表不做判断
+fn check_playlist_freshness(key: &str, info: &MediaPlaylistInfo) -> Option<Duration> {
+    check_playlist_freshness_at(key, info, Instant::now())
+}
+
+fn check_playlist_freshness_at(
+    key: &str,
+    info: &MediaPlaylistInfo,
+    now: Instant,
+) -> Option<Duration> {
+    if info.ended {
+        return None;
+    }
+    let threshold = info
+        .target_duration
+        .map(|d| Duration::from_secs_f64((d * 3.0).max(0.0)))
+        .unwrap_or_default()
+        .max(MIN_STALE_PLAYLIST);
+    let mut freshness = PLAYLIST_FRESHNESS.lock().unwrap_or_else(|e| e.into_inner());
+    let entry = freshness
+        .entry(key.to_string())
+        .or_insert_with(|| PlaylistFreshness {
+            media_sequence: info.media_sequence,
+            changed_at: now,
+            signalled: false,
+        });
+    if entry.media_sequence != info.media_sequence {
+        entry.media_sequence = info.media_sequence;
+        entry.changed_at = now;
+        entry.signalled = false;
+        return None;
+    }
+    let stalled = now.saturating_duration_since(entry.changed_at);
+    if entry.signalled || stalled < threshold {
+        return None;
+    }
+    entry.signalled = true;
+    Some(stalled)
+}
+
+#[derive(Debug, Default)]
+struct MediaPlaylistInfo {
+    media_sequence: u64,
+    segment_count: usize,
+    target_duration: Option<f64>,
+    window_seconds: f64,
+    ended: bool,
+}
+
+// 只解析媒体播放列表；没有 #EXTINF 的（master 列表）返回 None
+fn parse_media_playlist(text: &str) -> Option<MediaPlaylistInfo> {
+    let mut info = MediaPlaylistInfo::default();
+    for line in text.lines().map(str::trim) {
+        if let Some(value) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+            info.media_sequence = value.trim().parse().unwrap_or(0);
+        } else if let Some(value) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+            info.target_duration = value.trim().parse().ok();
+        } else if let Some(value) = line.strip_prefix("#EXTINF:") {
+            let duration = value.split(',').next().unwrap_or("").trim();
+            info.window_seconds += duration.parse::<f64>().unwrap_or(0.0);
+            info.segment_count += 1;
+        } else if line == "#EXT-X-ENDLIST" {
+            info.ended = true;
+        }
+    }
+    (info.segment_count > 0).then_some(info)
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SeekLiveResult {
+    pub supported: bool,
+    pub reason: Option<String>,
+    pub media_sequence: Option<u64>,
+    // 直播边缘处最新分片的序号
+    pub latest_sequence: Option<u64>,
+    pub segment_count: usize,
+    pub target_duration: Option<f64>,
+    // 播放列表覆盖的 DVR 窗口长度（秒）
+    pub window_seconds: f64,
+    // 建议跳转的位置（相对窗口起点，秒）：留出三个 target duration，避免追到尚未生成的分片
+    pub live_edge_seconds: Option<f64>,
+    pub ended: bool,
+}
+
+impl SeekLiveResult {
+    fn from_playlist(info: &MediaPlaylistInfo) -> Self {
+        let holdback = info.target_duration.unwrap_or(0.0) * 3.0;
+        SeekLiveResult {
+            supported: true,
+            reason: None,
+            media_sequence: Some(info.media_sequence),
+            latest_sequence: Some(info.media_sequence + info.segment_count as u64 - 1),
+            segment_count: info.segment_count,
+            target_duration: info.target_duration,
+            window_seconds: info.window_seconds,
+            live_edge_seconds: Some((info.window_seconds - holdback).max(0.0)),
+            ended: info.ended,
+        }
+    }
+}
+
+// 暂停后追回直播：HLS 重新拉取媒体播放列表，返回最新分片序号与直播边缘位置；FLV 无法 seek
+#[tauri::command]
+pub async fn seek_live(
+    stream_url_store: State<'_, StreamUrlStore>,
+    stream_token: String,
+) -> Result<SeekLiveResult, String> {
+    let playlist_url = LIVE_PLAYLISTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&stream_token)
+        .cloned();
+    let Some(playlist_url) = playlist_url else {
+        let is_flv = *stream_url_store
+            .request_id
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            == stream_token;
+        if is_flv {
+            return Ok(SeekLiveResult {
+                reason: Some("FLV 直播流不支持 seek，重新拉流即可回到直播".to_string()),
+                ..Default::default()
+            });
+        }
+        return Err(format!(
+            "Stream token {} has no HLS playlist loaded through the proxy",
+            stream_token
+        ));
+    };
+
+    let client = crate::network::stream_proxy()
+        .apply(Client::builder())
+        .redirect(upstream_redirect_policy())
+        .timeout(PREWARM_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to build client: {}", e))?;
+    let mut req = apply_stream_referer(
+        apply_common_headers(client.get(&playlist_url), &playlist_url),
+        &stream_token,
+    );
+    if let Some(cookie) = stream_cookie_header(&stream_token, &playlist_url) {
+        req = req.header(reqwest::header::COOKIE, cookie);
+    }
+    let text = req
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to refresh playlist: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read playlist: {}", e))?;
+
+    let Some(info) = parse_media_playlist(&text) else {
+        return Ok(SeekLiveResult {
+            reason: Some("播放列表中没有分片".to_string()),
+            ..Default::default()
+        });
+    };
+    let result = SeekLiveResult::from_playlist(&info);
+    println!(
+        "[Rust/proxy.rs][req:{}] seek_live seq={:?} latest={:?} window={:.1}s edge={:?}",
+        stream_token,
+        result.media_sequence,
+        result.latest_sequence,
+        result.window_seconds,
+        result.live_edge_seconds
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platforms::bilibili::stream_url::{resolve_bilibili_upstream, TEST_LIVE_API_BASE};
+    use crate::platforms::common::types::{GetStreamUrlArgs, GetStreamUrlPayload};
+
+    const ROOM_ID: &str = "7734200";
+    // FLV 文件头 + 首个 PreviousTagSize
+    const FLV_BODY: &[u8] = b"FLV\x01\x05\x00\x00\x00\x09\x00\x00\x00\x00";
+
+    // 限速后的实际速率不超过上限：按累计字节计算，首块也要等到配额允许才放行
+    #[tokio::test]
+    async fn throttled_stream_stays_under_cap() {
+        const CHUNK: usize = 16 * 1024;
+        const CHUNKS: usize = 8;
+        const CAP: u64 = 256 * 1024;
+        let chunks = (0..CHUNKS).map(|_| Ok::<_, std::io::Error>(Bytes::from(vec![0u8; CHUNK])));
+        let started = Instant::now();
+        let received: Vec<Bytes> = throttle_stream(futures_util::stream::iter(chunks), CAP)
+            .try_collect()
+            .await
+            .unwrap();
+        let elapsed = started.elapsed().as_secs_f64();
+        let total: usize = received.iter().map(|c| c.len()).sum();
+        assert_eq!(total, CHUNK * CHUNKS);
+        let rate = total as f64 / elapsed;
+        assert!(
+            rate <= CAP as f64,
+            "effective rate {:.0} B/s over cap {} B/s",
+            rate,
+            CAP
+        );
+        // 128 KiB / 256 KiB/s = 0.5s，留出调度余量
+        assert!(elapsed < 1.5, "throttle took {:.2}s", elapsed);
+    }
+
+    #[tokio::test]
+    async fn oversized_chunks_are_split_without_copying() {
+        let big = Bytes::from((0..200 * 1024).map(|i| i as u8).collect::<Vec<u8>>());
+        let pieces: Vec<Bytes> = bounded_chunks(futures_util::stream::iter(vec![
+            Ok::<_, std::io::Error>(big.clone()),
+            Ok(Bytes::from_static(b"tail")),
+        ]))
+        .try_collect()
+        .await
+        .unwrap();
+        let sizes: Vec<usize> = pieces.iter().map(|p| p.len()).collect();
+        assert_eq!(sizes, vec![65536, 65536, 65536, 8192, 4]);
+        assert_eq!(pieces[..4].concat(), big.as_ref());
+        // 切片与原 chunk 共享同一块内存
+        assert_eq!(pieces[1].as_ptr(), big[65536..].as_ptr());
+    }
+
+    // 慢速消费者：上游只在被 poll 时才产出数据，代理持有的未转发数据不超过一个上游 chunk
+    #[tokio::test]
+    async fn slow_consumer_does_not_buffer_upstream() {
+        const UPSTREAM_CHUNK: usize = 256 * 1024;
+        let pulled = Arc::new(AtomicU64::new(0));
+        let counter = pulled.clone();
+        let upstream = futures_util::stream::repeat_with(move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+            Ok::<_, std::io::Error>(Bytes::from(vec![0u8; UPSTREAM_CHUNK]))
+        });
+        let mut forwarded = Box::pin(bounded_chunks(upstream));
+
+        let mut consumed = 0usize;
+        for _ in 0..10 {
+            let piece = forwarded.next().await.unwrap().unwrap();
+            assert!(piece.len() <= MAX_FORWARD_CHUNK_BYTES);
+            consumed += piece.len();
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            let buffered = pulled.load(Ordering::Relaxed) as usize * UPSTREAM_CHUNK - consumed;
+            assert!(
+                buffered < UPSTREAM_CHUNK,
+                "{} bytes read ahead of the consumer",
+                buffered
+            );
+        }
+        assert_eq!(pulled.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn rewrites_image_urls_to_the_local_proxy() {
+        let prefix = local_image_prefix();
+        assert_eq!(
+            rewrite_image_url(" https://img.example.com/a.jpg?w=1&h=2 ", true),
+            format!(
+                "{}https%3A%2F%2Fimg.example.com%2Fa.jpg%3Fw%3D1%26h%3D2",
+                prefix
+            )
+        );
+        // 协议相对地址补成 https
+        assert_eq!(
+            rewrite_image_url("//img.example.com/b.png", true),
+            format!("{}https%3A%2F%2Fimg.example.com%2Fb.png", prefix)
+        );
+        // 已代理的地址不再包装
+        let proxied = rewrite_image_url("https://img.example.com/c.png", true);
+        assert_eq!(rewrite_image_url(&proxied, true), proxied);
+        assert!(is_local_image_url(&proxied));
+        assert_eq!(
+            unwrap_local_image_url(&format!("{}{}", prefix, urlencoding::encode(&proxied))),
+            "https://img.example.com/c.png"
+        );
+    }
+
+    #[test]
+    fn leaves_image_urls_alone_when_not_proxyable() {
+        assert_eq!(
+            rewrite_image_url("https://img.example.com/a.jpg", false),
+            "https://img.example.com/a.jpg"
+        );
+        assert_eq!(rewrite_image_url("  ", true), "");
+        assert_eq!(
+            rewrite_image_url("data:image/png;base64,AAAA", true),
+            "data:image/png;base64,AAAA"
+        );
+        assert_eq!(rewrite_image_url("/static/a.png", true), "/static/a.png");
+    }
+
+    #[test]
+    fn sniffs_each_image_magic_number() {
+        let cases: [(&[u8], &str); 5] = [
+            (
+                &[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F'],
+                "image/jpeg",
+            ),
+            (b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR", "image/png"),
+            (b"GIF87a\x01\x00\x01\x00", "image/gif"),
+            (b"GIF89a\x01\x00\x01\x00", "image/gif"),
+            (b"RIFF\x24\x00\x00\x00WEBPVP8 ", "image/webp"),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(sniff_image_content_type(bytes), Some(expected));
+        }
+    }
+
+    #[test]
+    fn unknown_or_truncated_bytes_are_not_sniffed() {
+        assert_eq!(sniff_image_content_type(b""), None);
+        assert_eq!(sniff_image_content_type(&[0xFF, 0xD8]), None);
+        assert_eq!(sniff_image_content_type(b"RIFF\x24\x00\x00\x00WAVE"), None);
+        assert_eq!(sniff_image_content_type(b"RIFFWEBP"), None);
+        assert_eq!(sniff_image_content_type(b"<html></html>"), None);
+    }
+
+    #[test]
+    fn resolves_image_content_type() {
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00";
+        // 合理的 image/* 原样保留（即使与文件头不一致）
+        assert_eq!(
+            resolve_image_content_type(Some("image/avif"), png),
+            "image/avif"
+        );
+        assert_eq!(
+            resolve_image_content_type(Some("Image/PNG"), png),
+            "Image/PNG"
+        );
+        // 缺失或误标时按文件头纠正
+        assert_eq!(resolve_image_content_type(None, png), "image/png");
+        assert_eq!(
+            resolve_image_content_type(Some("application/octet-stream"), png),
+            "image/png"
+        );
+        assert_eq!(
+            resolve_image_content_type(Some("text/plain"), png),
+            "image/png"
+        );
+        // 识别不了时沿用上游类型，没有则为 octet-stream
+        assert_eq!(
+            resolve_image_content_type(Some("text/html"), b"<html>"),
+            "text/html"
+        );
+        assert_eq!(
+            resolve_image_content_type(None, b"\x00\x01"),
+            "application/octet-stream"
+        );
+    }
+
+    fn forwarded(upstream: &[(&'static str, &'static str)]) -> HttpResponse {
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in upstream {
+            headers.insert(*name, reqwest::header::HeaderValue::from_static(*value));
         }
+        let mut builder = HttpResponse::Ok();
+        forward_flv_headers(&headers, &mut builder);
+        builder.finish()
     }
-}
 
-fn rewrite_attribute_uri(line: &str, base: &Url) -> String {
-    // 处理常见 tag：#EXT-X-KEY / #EXT-X-MAP 里的 URI="..."
-    let key = "URI=\"";
-    let Some(start) = line.find(key) else {
-        return line.to_string();
-    };
-    let rest = &line[start + key.len()..];
-    let Some(end) = rest.find('"') else {
-        return line.to_string();
-    };
-    let raw_uri = &rest[..end];
-    let resolved = base.join(raw_uri).map(|u| u.to_string()).unwrap_or_else(|_| raw_uri.to_string());
-    let proxied = format!("/hls?url={}", urlencoding::encode(&resolved));
-    let mut out = String::new();
-    out.push_str(&line[..start + key.len()]);
-    out.push_str(&proxied);
-    out.push('"');
-    out.push_str(&rest[end + 1..]);
-    out
-}
+    fn header<'a>(response: &'a HttpResponse, name: &str) -> Option<&'a str> {
+        response.headers().get(name).and_then(|v| v.to_str().ok())
+    }
 
-async fn hls_proxy_handler(query: web::Query<HlsQuery>, client: web::Data<Client>) -> impl Responder {
-    let url = query.url.clone();
-    if url.is_empty() {
-        return HttpResponse::BadRequest().body("Missing url query parameter");
+    #[test]
+    fn flv_content_type_variants_are_forwarded() {
+        let response = forwarded(&[
+            ("content-type", "video/flv; charset=binary"),
+            ("x-cache", "HIT from cdn"),
+            ("via", "1.1 edge"),
+            ("server-timing", "cdn-cache;desc=HIT"),
+            ("set-cookie", "sid=1"),
+            ("content-length", "1024"),
+            ("transfer-encoding", "chunked"),
+        ]);
+        assert_eq!(
+            header(&response, "content-type"),
+            Some("video/flv; charset=binary")
+        );
+        assert_eq!(header(&response, "x-cache"), Some("HIT from cdn"));
+        assert_eq!(header(&response, "via"), Some("1.1 edge"));
+        assert_eq!(
+            header(&response, "server-timing"),
+            Some("cdn-cache;desc=HIT")
+        );
+        assert_eq!(header(&response, "accept-ranges"), Some("bytes"));
+        // 有副作用或与转发后的流不一致的头不透传
+        assert_eq!(header(&response, "set-cookie"), None);
+        assert_eq!(header(&response, "content-length"), None);
+        assert_eq!(header(&response, "transfer-encoding"), None);
     }
 
-    let upstream_url = match Url::parse(&url) {
-        Ok(u) => u,
-        Err(e) => return HttpResponse::BadRequest().body(format!("Invalid url: {}", e)),
-    };
+    #[test]
+    fn mislabelled_content_type_is_overridden() {
+        let response = forwarded(&[
+            ("content-type", "application/octet-stream"),
+            ("accept-ranges", "none"),
+        ]);
+        assert_eq!(header(&response, "content-type"), Some("video/x-flv"));
+        // 上游明确给出的 Accept-Ranges 原样保留
+        assert_eq!(header(&response, "accept-ranges"), Some("none"));
 
-    let req = apply_common_headers(client.get(upstream_url.as_str()), upstream_url.as_str());
+        let response = forwarded(&[("content-type", "text/plain")]);
+        assert_eq!(header(&response, "content-type"), Some("video/x-flv"));
+        let response = forwarded(&[]);
+        assert_eq!(header(&response, "content-type"), Some("video/x-flv"));
+        assert_eq!(header(&response, "accept-ranges"), Some("bytes"));
 
-    match req.send().await {
-        Ok(upstream_response) => {
-            let status_from_reqwest = upstream_response.status();
-            let content_type = upstream_response
-                .headers()
-                .get(reqwest::header::CONTENT_TYPE)
-                .and_then(|v| v.to_str().ok())
-                .unwrap_or("application/octet-stream")
-                .to_string();
+        assert!(is_flv_content_type("Video/X-FLV"));
+        assert!(is_flv_content_type("application/x-flv"));
+        assert!(!is_flv_content_type("video/mp4"));
+        assert!(!is_flv_content_type(""));
+    }
 
-            if !status_from_reqwest.is_success() {
-                let error_text = upstream_response
-                    .text()
-                    .await
-                    .unwrap_or_else(|e| format!("Failed to read error body from upstream: {}", e));
-                let actix_status_code =
-                    actix_web::http::StatusCode::from_u16(status_from_reqwest.as_u16())
-                        .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR);
-                return HttpResponse::build(actix_status_code).body(format!(
-                    "Error fetching HLS resource from upstream: {}. Status: {}. Details: {}",
-                    url, status_from_reqwest, error_text
-                ));
+    // mock 上游收到的流请求 Referer
+    #[derive(Default)]
+    struct UpstreamLog {
+        stream_referer: StdMutex<Option<String>>,
+    }
+
+    async fn mock_room_init() -> HttpResponse {
+        HttpResponse::Ok().json(serde_json::json!({
+            "code": 0,
+            "message": "ok",
+            "data": {
+                "room_id": 7734200,
+                "uid": 1,
+                "live_status": 1,
+                "is_locked": false,
+                "is_hidden": false,
+                "encrypted": false,
+                "title": "mock room",
+                "uname": "mock anchor"
             }
+        }))
+    }
 
-            let is_m3u8 = upstream_url
-                .path()
-                .to_ascii_lowercase()
-                .ends_with(".m3u8")
-                || content_type.to_ascii_lowercase().contains("mpegurl")
-                || content_type.to_ascii_lowercase().contains("m3u8");
+    // 流地址指回 mock 上游自身
+    async fn mock_play_info(req: HttpRequest) -> HttpResponse {
+        let host = format!("http://{}", req.connection_info().host());
+        HttpResponse::Ok().json(serde_json::json!({
+            "code": 0,
+            "data": {"playurl_info": {"playurl": {
+                "g_qn_desc": [{"qn": 10000, "desc": "原画"}, {"qn": 400, "desc": "蓝光"}],
+                "stream": [{
+                    "protocol_name": "http_stream",
+                    "format": [{
+                        "format_name": "flv",
+                        "codec": [{
+                            "codec_name": "avc",
+                            "current_qn": 10000,
+                            "accept_qn": [10000, 400],
+                            "base_url": "/live-bvc/mock/live_7734200.flv",
+                            "url_info": [{"host": host, "extra": "?expires=1"}]
+                        }]
+                    }]
+                }]
+            }}}
+        }))
+    }
 
-            if is_m3u8 {
-                let text = match upstream_response.text().await {
-                    Ok(t) => t,
-                    Err(e) => {
-                        eprintln!("[Rust/proxy.rs hls] Failed to read playlist text: {}", e);
-                        return HttpResponse::InternalServerError()
-                            .body(format!("Failed to read playlist text: {}", e));
-                    }
-                };
+    async fn mock_stream(req: HttpRequest, log: web::Data<UpstreamLog>) -> HttpResponse {
+        *log.stream_referer.lock().unwrap() = req
+            .headers()
+            .get("referer")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        HttpResponse::Ok()
+            .content_type("video/x-flv")
+            .insert_header(("x-cache", "HIT mock"))
+            .body(FLV_BODY)
+    }
 
-                let base_for_resolve = upstream_url.clone();
-                let rewritten = text
-                    .lines()
-                    .map(|line| {
-                        let trimmed = line.trim();
-                        if trimmed.is_empty() {
-                            return line.to_string();
-                        }
-                        if trimmed.starts_with('#') {
-                            // tag line: try rewrite URI="..."
-                            return rewrite_attribute_uri(line, &base_for_resolve);
-                        }
+    fn spawn_mock_upstream(log: web::Data<UpstreamLog>) -> std::net::SocketAddr {
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(log.clone())
+                .route("/room/v1/Room/room_init", web::get().to(mock_room_init))
+                .route(
+                    "/xlive/web-room/v2/index/getRoomPlayInfo",
+                    web::get().to(mock_play_info),
+                )
+                .route(
+                    "/live-bvc/mock/live_7734200.flv",
+                    web::get().to(mock_stream),
+                )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        addr
+    }
 
-                        let resolved = base_for_resolve
-                            .join(trimmed)
-                            .map(|u| u.to_string())
-                            .unwrap_or_else(|_| trimmed.to_string());
-                        format!("/hls?url={}", urlencoding::encode(&resolved))
-                    })
-                    .collect::<Vec<String>>()
-                    .join("\n");
+    // 解析 -> 写入播放存储 -> /live.flv 转发，全程只访问本地 mock 上游
+    #[actix_web::test]
+    async fn resolved_bilibili_stream_is_served_on_live_flv() {
+        crate::network::apply_no_proxy(true);
+        let log = web::Data::new(UpstreamLog::default());
+        let upstream = spawn_mock_upstream(log.clone());
+        *TEST_LIVE_API_BASE.lock().unwrap() = Some(format!("http://{}", upstream));
 
-                return HttpResponse::Ok()
-                    .content_type("application/vnd.apple.mpegurl")
-                    .insert_header(("Cache-Control", "no-store"))
-                    .body(rewritten);
-            }
+        let request_id = "req-e2e";
+        let payload = GetStreamUrlPayload {
+            args: GetStreamUrlArgs {
+                room_id_str: ROOM_ID.to_string(),
+            },
+        };
+        let info = resolve_bilibili_upstream(payload, "原画", None, request_id)
+            .await
+            .unwrap();
+        assert_eq!(info.status, Some(1));
+        assert_eq!(info.current_qn, Some(10000));
+        let upstream_url = info.upstream_url.expect("resolver selected a stream");
+        assert_eq!(
+            upstream_url,
+            format!(
+                "http://{}/live-bvc/mock/live_7734200.flv?expires=1",
+                upstream
+            )
+        );
 
-            // 非 m3u8：按二进制流转发（ts/mp4/key 等）
-            let mut response_builder = HttpResponse::Ok();
-            response_builder
-                .content_type(content_type)
-                .insert_header(("Cache-Control", "no-store"));
+        // 与 room::ensure_proxy 相同：绑定解析时的 Referer，写入播放存储
+        bind_stream_cookies(request_id, &upstream_url);
+        let store = StreamUrlStore::default();
+        *store.url.lock().unwrap() = upstream_url;
+        *store.request_id.lock().unwrap() = request_id.to_string();
 
-            let byte_stream = upstream_response.bytes_stream().map_err(|e| {
-                eprintln!("[Rust/proxy.rs hls] Upstream stream error: {}", e);
-                actix_web::error::ErrorInternalServerError(format!("Upstream stream error: {}", e))
-            });
-            response_builder.streaming(byte_stream)
-        }
-        Err(e) => {
-            eprintln!(
-                "[Rust/proxy.rs hls] Failed to send request to upstream {}: {}",
-                url, e
-            );
-            HttpResponse::InternalServerError()
-                .body(format!("Error connecting to upstream HLS {}: {}", url, e))
-        }
+        let app = tauri::test::mock_app();
+        let proxy_addr = spawn_proxy(web::Data::new(store), app.handle().clone());
+
+        let response = Client::builder()
+            .no_proxy()
+            .build()
+            .unwrap()
+            .get(format!("http://{}/live.flv", proxy_addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let headers = response.headers().clone();
+        assert_eq!(headers["content-type"], "video/x-flv");
+        assert_eq!(headers["cache-control"], "no-store");
+        assert_eq!(headers["x-cache"], "HIT mock");
+        assert_eq!(headers["accept-ranges"], "bytes");
+        assert_eq!(response.bytes().await.unwrap().as_ref(), FLV_BODY);
+        assert_eq!(
+            log.stream_referer.lock().unwrap().as_deref(),
+            Some("https://live.bilibili.com/7734200")
+        );
+
+        *TEST_LIVE_API_BASE.lock().unwrap() = None;
+        forget_stream_cookies(request_id);
     }
-}
 
-// Your actual proxy logic - this is a simplified placeholder
-async fn flv_proxy_handler(
-    _req: HttpRequest,
-    stream_url_store: web::Data<StreamUrlStore>,
-    client: web::Data<Client>,
-) -> impl Responder {
-    let url = stream_url_store.url.lock().unwrap().clone();
-    if url.is_empty() {
-        return HttpResponse::NotFound().body("Stream URL is not set or empty.");
+    // 同一 CDN 主机上的两路流，统计 mock 上游实际建立的 TCP 连接数
+    fn spawn_counting_cdn(connects: Arc<AtomicU64>) -> std::net::SocketAddr {
+        let server = HttpServer::new(|| {
+            App::new().route(
+                "/cdn/{name}.flv",
+                web::get().to(|| async {
+                    HttpResponse::Ok()
+                        .content_type("video/x-flv")
+                        .body(&b"FLV\x01\x05\x00\x00\x00\x09\x00\x00\x00\x00"[..])
+                }),
+            )
+        })
+        .on_connect(move |_, _| {
+            connects.fetch_add(1, Ordering::SeqCst);
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        addr
     }
 
-    println!(
-        "[Rust/proxy.rs handler] Incoming FLV proxy request -> {}",
-        url
-    );
+    // 切换流地址、重启代理服务都沿用共享客户端，同一 CDN 主机只建一次连接
+    #[actix_web::test]
+    async fn stream_switch_reuses_pooled_upstream_connection() {
+        crate::network::apply_no_proxy(true);
+        let connects = Arc::new(AtomicU64::new(0));
+        let cdn = spawn_counting_cdn(connects.clone());
 
-    let mut req = client
-        .get(&url)
-        .header(
-            "User-Agent",
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
-        )
-        .header("Accept", "video/x-flv,application/octet-stream,*/*")
-        .header("Range", "bytes=0-")
-        .header("Connection", "keep-alive");
+        // 每次取客户端都相当于一次代理服务（重新）启动
+        for name in ["a", "b", "a"] {
+            let (flv, _) = shared_proxy_clients();
+            let url = format!("http://{}/cdn/{}.flv", cdn, name);
+            let body = flv_upstream_request(&flv, &url)
+                .send()
+                .await
+                .unwrap()
+                .bytes()
+                .await
+                .unwrap();
+            assert!(body.starts_with(b"FLV"));
+            // 等待上游连接归还连接池
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(connects.load(Ordering::SeqCst), 1);
 
-    // 如果是虎牙域名，添加必要的 Referer/Origin 头
-    if url.contains("huya.com") || url.contains("hy-cdn.com") || url.contains("huyaimg.com") {
-        req = req
-            .header("Referer", "https://www.huya.com/")
-            .header("Origin", "https://www.huya.com");
+        let (flv_a, _) = shared_proxy_clients();
+        let (flv_b, _) = shared_proxy_clients();
+        assert!(Arc::ptr_eq(&flv_a.into_inner(), &flv_b.into_inner()));
     }
-    // 如果是B站域名，添加必要的 Referer 头
-    if url.contains("bilivideo") || url.contains("bilibili.com") || url.contains("hdslb.com") {
-        req = req.header("Referer", "https://live.bilibili.com/");
+
+    // mock 上游的无限 FLV 流：响应体被丢弃（与代理的连接断开）时置位
+    struct UpstreamStopped(web::Data<AtomicBool>);
+
+    impl Drop for UpstreamStopped {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
     }
 
-    match req.send().await {
-        Ok(upstream_response) => {
-            if upstream_response.status().is_success() {
-                let mut response_builder = HttpResponse::Ok();
-                response_builder
-                    .content_type("video/x-flv")
-                    .insert_header(("Connection", "keep-alive"))
-                    .insert_header(("Cache-Control", "no-store"))
-                    .insert_header(("Accept-Ranges", "bytes"));
+    async fn endless_flv(stopped: web::Data<AtomicBool>) -> HttpResponse {
+        let chunks = futures_util::stream::unfold(UpstreamStopped(stopped), |guard| async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Some((
+                Ok::<_, actix_web::Error>(Bytes::from_static(&[0u8; 1024])),
+                guard,
+            ))
+        });
+        HttpResponse::Ok()
+            .content_type("video/x-flv")
+            .streaming(chunks)
+    }
 
-                let byte_stream = upstream_response.bytes_stream().map_err(|e| {
-                    eprintln!(
-                        "[Rust/proxy.rs handler] Error reading bytes from upstream: {}",
-                        e
-                    );
-                    actix_web::error::ErrorInternalServerError(format!(
-                        "Upstream stream error: {}",
-                        e
-                    ))
-                });
+    // 在随机端口上启动与 start_proxy 相同的代理应用，返回监听地址
+    fn spawn_proxy<R: Runtime>(
+        store: web::Data<StreamUrlStore>,
+        app_handle: AppHandle<R>,
+    ) -> std::net::SocketAddr {
+        let app_handle = web::Data::new(app_handle);
+        let proxy = HttpServer::new(move || build_proxy_app(store.clone(), app_handle.clone()))
+            .workers(1)
+            .bind(("127.0.0.1", 0))
+            .unwrap();
+        let addr = proxy.addrs()[0];
+        actix_web::rt::spawn(proxy.run());
+        addr
+    }
 
-                response_builder.streaming(byte_stream)
-            } else {
-                let status_from_reqwest = upstream_response.status(); // Renamed for clarity
-                let error_text = upstream_response
-                    .text()
-                    .await
-                    .unwrap_or_else(|e| format!("Failed to read error body from upstream: {}", e));
-                eprintln!(
-                    "[Rust/proxy.rs handler] Upstream request to {} failed with status: {}. Body: {}",
-                    url, status_from_reqwest, error_text
-                );
-                // Convert reqwest::StatusCode to actix_web::http::StatusCode
-                let actix_status_code =
-                    actix_web::http::StatusCode::from_u16(status_from_reqwest.as_u16())
-                        .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR);
+    // 播放端中途断开：代理丢弃响应体，上游请求随之取消，mock 上游不再继续推流
+    #[actix_web::test]
+    async fn client_disconnect_aborts_upstream_fetch() {
+        crate::network::apply_no_proxy(true);
+        let stopped = web::Data::new(AtomicBool::new(false));
+        let upstream_stopped = stopped.clone();
+        let upstream = HttpServer::new(move || {
+            App::new()
+                .app_data(upstream_stopped.clone())
+                .route("/live/endless.flv", web::get().to(endless_flv))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let upstream_addr = upstream.addrs()[0];
+        actix_web::rt::spawn(upstream.run());
 
-                HttpResponse::build(actix_status_code).body(format!(
-                    "Error fetching FLV stream from upstream (reqwest): {}. Status: {}. Details: {}",
-                    url, status_from_reqwest, error_text
-                ))
-            }
+        let store = web::Data::new(StreamUrlStore::default());
+        *store.url.lock().unwrap() = format!("http://{}/live/endless.flv", upstream_addr);
+        *store.request_id.lock().unwrap() = "req-disconnect".to_string();
+        let app = tauri::test::mock_app();
+        let proxy_addr = spawn_proxy(store, app.handle().clone());
+
+        let disconnects_before = FLV_CLIENT_DISCONNECTS.load(Ordering::Relaxed);
+        let mut response = Client::builder()
+            .no_proxy()
+            .build()
+            .unwrap()
+            .get(format!("http://{}/live.flv", proxy_addr))
+            .send()
+            .await
+            .unwrap();
+        let mut received = 0;
+        while received < 8 * 1024 {
+            received += response.chunk().await.unwrap().unwrap().len();
         }
-        Err(e) => {
-            eprintln!(
-                "[Rust/proxy.rs handler] Failed to send request to upstream {} with reqwest: {}",
-                url, e
+        assert!(!stopped.load(Ordering::SeqCst));
+        drop(response);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !stopped.load(Ordering::SeqCst) {
+            assert!(
+                Instant::now() < deadline,
+                "upstream kept streaming after the client disconnected"
             );
-            HttpResponse::InternalServerError().body(format!(
-                "Error connecting to upstream FLV stream {} with reqwest: {}",
-                url, e
-            ))
+            tokio::time::sleep(Duration::from_millis(20)).await;
         }
+        assert!(FLV_CLIENT_DISCONNECTS.load(Ordering::Relaxed) > disconnects_before);
     }
-}
 
-#[tauri::command]
-pub async fn start_proxy(
-    _app_handle: AppHandle,
-    server_handle_state: State<'_, ProxyServerHandle>,
-    stream_url_store: State<'_, StreamUrlStore>,
-) -> Result<String, String> {
-    let port = find_free_port().await;
-    let current_stream_url = stream_url_store.url.lock().unwrap().clone();
+    // 测速用的 mock 上游：burst.flv 先一次性下发 4 MiB（模拟缓存的 GOP），之后每 200ms 才推 1 KiB；
+    // trickle.flv 只按低码率推流；index.m3u8 的最新分片为 512 KiB 的固定对象
+    fn spawn_probe_upstream() -> std::net::SocketAddr {
+        fn trickle() -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+            futures_util::stream::unfold((), |_| async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                Some((Ok(Bytes::from_static(&[0u8; 1024])), ()))
+            })
+        }
+        let server = HttpServer::new(|| {
+            App::new()
+                .route(
+                    "/probe/burst.flv",
+                    web::get().to(|| async {
+                        let burst = futures_util::stream::iter(
+                            (0..256).map(|_| Ok(Bytes::from(vec![0u8; 16 * 1024]))),
+                        );
+                        HttpResponse::Ok()
+                            .content_type("video/x-flv")
+                            .streaming(burst.chain(trickle()))
+                    }),
+                )
+                .route(
+                    "/probe/trickle.flv",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .content_type("video/x-flv")
+                            .streaming(trickle())
+                    }),
+                )
+                .route(
+                    "/probe/index.m3u8",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .content_type("application/vnd.apple.mpegurl")
+                            .body("#EXTM3U\n#EXT-X-TARGETDURATION:2\n#EXTINF:2.0,\nseg-1.ts\n#EXTINF:2.0,\nseg-2.ts\n")
+                    }),
+                )
+                .route(
+                    "/probe/seg-2.ts",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .content_type("video/mp2t")
+                            .body(vec![0u8; 512 * 1024])
+                    }),
+                )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        addr
+    }
 
-    if current_stream_url.is_empty() {
-        return Err("Stream URL is not set in store. Cannot start proxy.".to_string());
+    // 只统计突发段：之后按码率到达的数据不计入，测得的速率不会被拉低到源站码率
+    #[actix_web::test]
+    async fn throughput_probe_measures_the_initial_burst() {
+        crate::network::apply_no_proxy(true);
+        let upstream = spawn_probe_upstream();
+
+        let started = Instant::now();
+        let kbps = probe_throughput_kbps(
+            &format!("http://{}/probe/burst.flv", upstream),
+            "req-probe-burst",
+        )
+        .await
+        .unwrap()
+        .expect("the burst is long enough to measure");
+        // 若把突发段之后的数据也算进去，速率会接近 1 KiB / 200ms ≈ 40kbps
+        assert!(kbps > 10_000, "measured {}kbps", kbps);
+        assert!(started.elapsed() < THROUGHPUT_PROBE_TIMEOUT);
+
+        // 没有突发段的低码率流无法判断带宽
+        let trickle = probe_throughput_kbps(
+            &format!("http://{}/probe/trickle.flv", upstream),
+            "req-probe-trickle",
+        )
+        .await
+        .unwrap();
+        assert_eq!(trickle, None);
+
+        // HLS 下载最新的分片测速
+        let hls = probe_throughput_kbps(
+            &format!("http://{}/probe/index.m3u8", upstream),
+            "req-probe-hls",
+        )
+        .await
+        .unwrap();
+        assert!(hls.is_some_and(|kbps| kbps > 10_000), "measured {:?}", hls);
     }
 
-    // stream_url_data_for_actix can be created once and cloned, as StreamUrlStore is Arc based and Send + Sync
-    let stream_url_data_for_actix = web::Data::new(stream_url_store.inner().clone());
-    // REMOVED: let awc_client_for_actix = web::Data::new(Client::default());
+    // 滑动窗口直播列表（DVR）：3 个分片，序号从 1200 开始
+    const DVR_PLAYLIST: &str = "#EXTM3U
+#EXT-X-VERSION:3
+#EXT-X-TARGETDURATION:4
+#EXT-X-MEDIA-SEQUENCE:1200
+#EXTINF:4.000,
+seg-1200.ts
+#EXTINF:4.000,
+seg-1201.ts
+#EXTINF:3.500,live
+seg-1202.ts
+";
 
-    // Ensure MutexGuard is dropped before .await
-    let existing_handle_to_stop = { server_handle_state.0.lock().unwrap().take() };
-    if let Some(existing_handle) = existing_handle_to_stop {
-        existing_handle.stop(false).await;
-    }
-
-    let server = match HttpServer::new(move || {
-        let app_data_stream_url = stream_url_data_for_actix.clone();
-        // Create reqwest::Client inside the closure for each worker thread (for images)
-        let app_data_reqwest_client = web::Data::new(
-            Client::builder()
-                .http1_only()
-                .gzip(false)
-                .brotli(false)
-                .no_deflate()
-                .pool_idle_timeout(None)
-                .pool_max_idle_per_host(4)
-                .tcp_keepalive(Duration::from_secs(60))
-                .timeout(Duration::from_secs(7200))
-                .build()
-                .expect("failed to build client"),
+    #[test]
+    fn needs_proxy_matches_hosts_not_substrings() {
+        assert!(upstream_needs_proxy(
+            "https://tx.flv.huya.com/src/1.flv?wsSecret=x"
+        ));
+        assert!(upstream_needs_proxy(
+            "https://cn-gddg-ct-01-01.bilivideo.com/live-bvc/1.flv"
+        ));
+        assert!(upstream_needs_proxy(
+            "https://cdn.example/live/index.m3u8?t=1"
+        ));
+        assert!(upstream_needs_proxy("http://cdn.example/live.flv"));
+        assert!(upstream_needs_proxy("not a url"));
+        // 查询参数或路径里出现平台域名不算
+        assert!(!upstream_needs_proxy(
+            "https://pull-flv.douyincdn.com/stage/1.flv?from=huya.com"
+        ));
+        assert!(!upstream_needs_proxy(
+            "https://cdn.example/huya.com/live.m3u8.flv"
+        ));
+    }
+
+    #[test]
+    fn token_streams_are_forgotten_with_the_stream() {
+        let store = StreamUrlStore::default();
+        register_token_stream("export-token", "https://cdn.example/live.flv");
+        assert_eq!(
+            flv_upstream_for_token("export-token", &store).as_deref(),
+            Some("https://cdn.example/live.flv")
         );
-        App::new()
-            .app_data(app_data_stream_url)
-            .app_data(app_data_reqwest_client)
-            .wrap(actix_cors::Cors::permissive())
-            .route("/live.flv", web::get().to(flv_proxy_handler))
-            .route("/image", web::get().to(image_proxy_handler))
-            .route("/hls", web::get().to(hls_proxy_handler))
-    })
-    .keep_alive(Duration::from_secs(120))
-    .bind(("127.0.0.1", port))
-    {
-        Ok(srv) => srv,
-        Err(e) => {
-            let err_msg = format!(
-                "[Rust/proxy.rs] Failed to bind server to port {}: {}",
-                port, e
-            );
-            eprintln!("{}", err_msg);
-            return Err(err_msg);
-        }
+        forget_stream_cookies("export-token");
+        assert_eq!(flv_upstream_for_token("export-token", &store), None);
     }
-    .run();
 
-    let server_handle_for_state = server.handle();
-    *server_handle_state.0.lock().unwrap() = Some(server_handle_for_state);
+    #[test]
+    fn parses_dvr_media_playlist() {
+        let info = parse_media_playlist(DVR_PLAYLIST).unwrap();
+        assert_eq!(info.media_sequence, 1200);
+        assert_eq!(info.segment_count, 3);
+        assert_eq!(info.target_duration, Some(4.0));
+        assert!((info.window_seconds - 11.5).abs() < 1e-9);
+        assert!(!info.ended);
 
-    // Use tauri::async_runtime::spawn directly
-    tauri::async_runtime::spawn(async move {
-        if let Err(e) = server.await {
-            eprintln!("[Rust/proxy.rs] Proxy server run error: {}", e);
-        } else {
-            println!("[Rust/proxy.rs] Proxy server on port {} shut down.", port);
-        }
-    });
+        let ended = format!("{}#EXT-X-ENDLIST\n", DVR_PLAYLIST);
+        assert!(parse_media_playlist(&ended).unwrap().ended);
+    }
 
-    let proxy_url = format!("http://127.0.0.1:{}/live.flv", port);
-    Ok(proxy_url)
-}
+    #[test]
+    fn master_playlist_is_not_a_media_playlist() {
+        let master = "#EXTM3U
+#EXT-X-STREAM-INF:BANDWIDTH=2000000,RESOLUTION=1280x720
+720p/index.m3u8
+";
+        assert!(parse_media_playlist(master).is_none());
+        assert!(parse_media_playlist("").is_none());
+    }
 
-#[tauri::command]
-pub async fn start_static_proxy_server(
-    _app_handle: AppHandle,
-    stream_url_store: State<'_, StreamUrlStore>,
-) -> Result<String, String> {
-    // Use a dedicated port for static image proxy to avoid interfering with FLV stream proxy
-    let port: u16 = 34721;
+    #[test]
+    fn seek_live_result_points_at_live_edge() {
+        let info = parse_media_playlist(DVR_PLAYLIST).unwrap();
+        let result = SeekLiveResult::from_playlist(&info);
+        assert!(result.supported);
+        assert_eq!(result.media_sequence, Some(1200));
+        assert_eq!(result.latest_sequence, Some(1202));
+        // 窗口 11.5s，留出 3 个 target duration（12s）后不会小于 0
+        assert_eq!(result.live_edge_seconds, Some(0.0));
 
-    // If the server is already running, just return the base URL (idempotent behavior)
-    if TcpStream::connect(("127.0.0.1", port)).is_ok() {
-        return Ok(format!("http://127.0.0.1:{}", port));
+        let long = MediaPlaylistInfo {
+            media_sequence: 10,
+            segment_count: 10,
+            target_duration: Some(2.0),
+            window_seconds: 20.0,
+            ended: false,
+        };
+        let result = SeekLiveResult::from_playlist(&long);
+        assert_eq!(result.latest_sequence, Some(19));
+        assert_eq!(result.live_edge_seconds, Some(14.0));
     }
 
-    let stream_url_data_for_actix = web::Data::new(stream_url_store.inner().clone());
+    // 同一份列表反复返回：超过阈值只提示一次，序号前进后重新计时
+    #[test]
+    fn repeated_identical_playlists_are_flagged_once() {
+        let key = "req-stale-playlist";
+        forget_live_playlist(key);
+        let info = parse_media_playlist(DVR_PLAYLIST).unwrap();
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
 
-    let server = match HttpServer::new(move || {
-        let app_data_stream_url = stream_url_data_for_actix.clone();
-        let app_data_reqwest_client = web::Data::new(
-            Client::builder()
-                .http1_only()
-                .gzip(false)
-                .brotli(false)
-                .no_deflate()
-                .pool_idle_timeout(None)
-                .pool_max_idle_per_host(4)
-                .tcp_keepalive(Duration::from_secs(60))
-                .timeout(Duration::from_secs(7200))
-                .build()
-                .expect("failed to build client"),
+        assert_eq!(check_playlist_freshness_at(key, &info, at(0)), None);
+        // 阈值为 max(3 * 4s, 10s) = 12s
+        assert_eq!(check_playlist_freshness_at(key, &info, at(6)), None);
+        assert_eq!(check_playlist_freshness_at(key, &info, at(11)), None);
+        assert_eq!(
+            check_playlist_freshness_at(key, &info, at(12)),
+            Some(Duration::from_secs(12))
         );
-        App::new()
-            .app_data(app_data_stream_url)
-            .app_data(app_data_reqwest_client)
-            .wrap(actix_cors::Cors::permissive())
-            .route("/live.flv", web::get().to(flv_proxy_handler))
-            .route("/image", web::get().to(image_proxy_handler))
-            .route("/hls", web::get().to(hls_proxy_handler))
-    })
-    .keep_alive(Duration::from_secs(120))
-    .bind(("127.0.0.1", port))
-    {
-        Ok(srv) => srv,
-        Err(e) => {
-            // If address already in use, assume server is running and return OK base URL
-            if e.kind() == ErrorKind::AddrInUse {
-                eprintln!(
-                    "[Rust/proxy.rs] Port {} already in use; assuming static proxy running.",
-                    port
-                );
-                return Ok(format!("http://127.0.0.1:{}", port));
-            }
-            let err_msg = format!(
-                "[Rust/proxy.rs] Failed to bind server to port {}: {}",
-                port, e
-            );
-            eprintln!("{}", err_msg);
-            return Err(err_msg);
-        }
+        assert_eq!(check_playlist_freshness_at(key, &info, at(20)), None);
+
+        let advanced = DVR_PLAYLIST.replace("SEQUENCE:1200", "SEQUENCE:1201");
+        let advanced = parse_media_playlist(&advanced).unwrap();
+        assert_eq!(check_playlist_freshness_at(key, &advanced, at(21)), None);
+        assert_eq!(check_playlist_freshness_at(key, &advanced, at(32)), None);
+        assert_eq!(
+            check_playlist_freshness_at(key, &advanced, at(33)),
+            Some(Duration::from_secs(12))
+        );
+        forget_live_playlist(key);
     }
-    .run();
 
-    // Do NOT overwrite the main proxy server handle; run static proxy independently
+    #[test]
+    fn ended_or_short_segment_playlists_use_their_own_rules() {
+        let key = "req-ended-playlist";
+        forget_live_playlist(key);
+        let start = Instant::now();
+        let ended = format!("{}#EXT-X-ENDLIST\n", DVR_PLAYLIST);
+        let ended = parse_media_playlist(&ended).unwrap();
+        assert_eq!(check_playlist_freshness_at(key, &ended, start), None);
+        assert_eq!(
+            check_playlist_freshness_at(key, &ended, start + Duration::from_secs(60)),
+            None
+        );
 
-    tauri::async_runtime::spawn(async move {
-        if let Err(e) = server.await {
-            eprintln!("[Rust/proxy.rs] Proxy server run error: {}", e);
-        } else {
-            println!("[Rust/proxy.rs] Proxy server on port {} shut down.", port);
+        // 2s 分片的列表按最短阈值 10s 判断
+        let short = DVR_PLAYLIST.replace("TARGETDURATION:4", "TARGETDURATION:2");
+        let short = parse_media_playlist(&short).unwrap();
+        assert_eq!(check_playlist_freshness_at(key, &short, start), None);
+        assert_eq!(
+            check_playlist_freshness_at(key, &short, start + Duration::from_secs(10)),
+            Some(Duration::from_secs(10))
+        );
+        forget_live_playlist(key);
+    }
+
+    #[test]
+    fn flv_rate_limit_is_kilobits_per_second() {
+        assert_eq!(kbps_to_bytes_per_sec(0), 0);
+        assert!((1..=7).all(|kbps| kbps_to_bytes_per_sec(kbps) > 0));
+        assert_eq!(kbps_to_bytes_per_sec(1), 125);
+        assert_eq!(kbps_to_bytes_per_sec(8), 1000);
+        assert_eq!(kbps_to_bytes_per_sec(4000), 500_000);
+        assert_eq!(kbps_to_bytes_per_sec(u64::MAX), u64::MAX / 8 + 1);
+    }
+
+    #[test]
+    fn flv_stream_bytes_are_counted_per_request() {
+        let first = FlvStreamCounter::register("bytes-test-a");
+        let other = FlvStreamCounter::register("bytes-test-b");
+        first.add(100);
+        other.add(7);
+        assert_eq!(flv_stream_bytes("bytes-test-a"), 100);
+        assert_eq!(flv_stream_bytes("bytes-test-b"), 7);
+
+        // 同一 request_id 重新拉流：新计数器从零开始，旧连接的写入与释放都不影响它
+        let second = FlvStreamCounter::register("bytes-test-a");
+        first.add(50);
+        second.add(10);
+        drop(first);
+        assert_eq!(flv_stream_bytes("bytes-test-a"), 10);
+
+        drop(second);
+        drop(other);
+        assert_eq!(flv_stream_bytes("bytes-test-a"), 0);
+        assert_eq!(flv_stream_bytes("bytes-test-b"), 0);
+    }
+
+    fn idle_server() -> actix_web::dev::Server {
+        HttpServer::new(App::new)
+            .workers(1)
+            .bind(("127.0.0.1", 0))
+            .unwrap()
+            .run()
+    }
+
+    fn tracked_task(handle: ServerHandle, id: u64) -> ProxyServerTask {
+        ProxyServerTask {
+            handle,
+            join: tauri::async_runtime::spawn(async {}),
+            port: 0,
+            id,
         }
-    });
+    }
 
-    Ok(format!("http://127.0.0.1:{}", port))
-}
+    #[actix_web::test]
+    async fn supervisor_rebinds_only_while_its_task_is_tracked() {
+        let original = idle_server();
+        let original_handle = original.handle();
+        let slot = StdMutex::new(Some(tracked_task(original.handle(), 7)));
+        let original = actix_web::rt::spawn(original);
 
-#[tauri::command]
-pub async fn stop_proxy(server_handle_state: State<'_, ProxyServerHandle>) -> Result<(), String> {
-    // Ensure MutexGuard is dropped before .await
-    let handle_to_stop = { server_handle_state.0.lock().unwrap().take() };
+        // state 中仍是本任务：重建，并换上新服务器的停止 handle
+        let rebuilt = rebind_if_current(&slot, 7, || Ok(idle_server()))
+            .expect("tracked task should be rebound")
+            .unwrap();
+        let rebuilt = actix_web::rt::spawn(rebuilt);
+        let task = slot.lock().unwrap().take().unwrap();
+        assert_eq!(task.id, 7);
+        task.handle.stop(false).await;
+        tokio::time::timeout(Duration::from_secs(5), rebuilt)
+            .await
+            .expect("the handle in state should stop the rebuilt server")
+            .unwrap()
+            .unwrap();
 
-    if let Some(handle) = handle_to_stop {
-        handle.stop(false).await; // Changed to non-graceful shutdown
-        println!("[Rust/proxy.rs] stop_proxy: Initiated non-graceful shutdown.");
-    } else {
-        println!("[Rust/proxy.rs] stop_proxy command: No proxy server was running or handle already taken.");
+        // 已被 stop_proxy 取走：不再重建
+        assert!(rebind_if_current(&slot, 7, || panic!("stopped proxy was rebound")).is_none());
+
+        // 已被新的 start 替换：不再重建，也不动新任务
+        let replacement = idle_server();
+        *slot.lock().unwrap() = Some(tracked_task(replacement.handle(), 8));
+        let replacement = actix_web::rt::spawn(replacement);
+        assert!(rebind_if_current(&slot, 7, || panic!("replaced proxy was rebound")).is_none());
+        assert_eq!(slot.lock().unwrap().as_ref().map(|task| task.id), Some(8));
+
+        // 重建失败：从 state 中移除，之后的 start 不会把它当成仍在运行
+        let handle = slot.lock().unwrap().as_ref().unwrap().handle.clone();
+        let failed = rebind_if_current(&slot, 8, || {
+            Err(std::io::Error::new(ErrorKind::AddrInUse, "port busy"))
+        });
+        assert!(matches!(failed, Some(Err(_))));
+        assert!(slot.lock().unwrap().is_none());
+
+        handle.stop(false).await;
+        replacement.await.unwrap().unwrap();
+        original_handle.stop(false).await;
+        original.await.unwrap().unwrap();
     }
-    Ok(())
 }
@@ -0,0 +1,395 @@
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::oneshot;
+
+use crate::platforms::common::events::{emit_event, DtvEvent};
+use crate::platforms::common::request_id::new_request_id;
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::SupportedPlatformRust;
+use crate::room::NowPlayingState;
+use crate::StreamUrlStore;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+const RECORDINGS_DIR: &str = "recordings";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingStatus {
+    Recording,
+    Stopped,
+    // 上游结束推流（EOF）
+    Finished,
+    Failed,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RecordingInfo {
+    pub stream_token: String,
+    pub path: String,
+    pub started_at: i64,
+    pub bytes_written: u64,
+    pub duration_secs: i64,
+    pub status: RecordingStatus,
+    pub error: Option<String>,
+}
+
+struct RecordingEntry {
+    // 录制自己持有的上游地址，不随播放存储切换房间而改变
+    upstream_url: String,
+    // token 由录制自行解析生成（而非沿用播放中的流）时，结束后一并清理 cookie 绑定
+    owns_token: bool,
+    path: PathBuf,
+    started_at: i64,
+    ended_at: Option<i64>,
+    bytes_written: Arc<AtomicU64>,
+    status: RecordingStatus,
+    error: Option<String>,
+    stop_tx: Option<oneshot::Sender<()>>,
+}
+
+impl RecordingEntry {
+    fn info(&self, stream_token: &str) -> RecordingInfo {
+        let until = self
+            .ended_at
+            .unwrap_or_else(|| chrono::Utc::now().timestamp());
+        RecordingInfo {
+            stream_token: stream_token.to_string(),
+            path: self.path.to_string_lossy().into_owned(),
+            started_at: self.started_at,
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            duration_secs: (until - self.started_at).max(0),
+            status: self.status,
+            error: self.error.clone(),
+        }
+    }
+}
+
+// 按 stream token 登记的录制任务：每个录制独立拉取上游、独立持有文件句柄，可同时录制多路。
+// 只保存进行中的录制，结束（停止/完成/失败）后移除，最终状态通过 recording-progress 事件下发
+#[derive(Default)]
+pub struct Recordings(Mutex<HashMap<String, RecordingEntry>>);
+
+fn default_recording_path(
+    app_handle: &AppHandle,
+    stream_token: &str,
+    room: Option<(&SupportedPlatformRust, &str)>,
+) -> Result<PathBuf, String> {
+    let dir = app_handle
+        .path()
+        .video_dir()
+        .or_else(|_| app_handle.path().app_data_dir())
+        .map_err(|e| format!("无法定位录制目录: {}", e))?
+        .join(RECORDINGS_DIR);
+    let name = match (
+        room,
+        app_handle
+            .state::<NowPlayingState>()
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref(),
+    ) {
+        (Some((platform, room_id)), _) => {
+            format!("{}_{}", platform.as_str().to_lowercase(), room_id)
+        }
+        (None, Some(now)) if now.stream_token == stream_token => {
+            format!("{}_{}", now.platform.to_lowercase(), now.room_id)
+        }
+        _ => "dtv".to_string(),
+    };
+    let stamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    Ok(dir.join(format!("{}_{}.flv", name, stamp)))
+}
+
+// 磁盘写满：ENOSPC (28) / Windows ERROR_HANDLE_DISK_FULL (39)、ERROR_DISK_FULL (112)
+fn is_disk_full(error: &std::io::Error) -> bool {
+    matches!(error.raw_os_error(), Some(28) | Some(39) | Some(112))
+}
+
+fn finish_recording(
+    app_handle: &AppHandle,
+    stream_token: &str,
+    status: RecordingStatus,
+    error: Option<String>,
+) {
+    let (info, owns_token) = {
+        let recordings = app_handle.state::<Recordings>();
+        let mut recordings = recordings.0.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(mut entry) = recordings.remove(stream_token) else {
+            return;
+        };
+        entry.status = status;
+        entry.error = error;
+        entry.ended_at = Some(chrono::Utc::now().timestamp());
+        (entry.info(stream_token), entry.owns_token)
+    };
+    if owns_token {
+        crate::proxy::forget_stream_cookies(stream_token);
+    }
+    println!(
+        "[Recording] {} ended: {:?} ({} bytes) {:?}",
+        stream_token, info.status, info.bytes_written, info.error
+    );
+    emit_event(
+        app_handle,
+        DtvEvent::RecordingProgress(DtvEvent::json(info)),
+    );
+}
+
+async fn run_recording(
+    app_handle: AppHandle,
+    stream_token: String,
+    path: PathBuf,
+    bytes_written: Arc<AtomicU64>,
+    mut stop_rx: oneshot::Receiver<()>,
+) {
+    let upstream_url = {
+        let recordings = app_handle.state::<Recordings>();
+        let recordings = recordings.0.lock().unwrap_or_else(|e| e.into_inner());
+        match recordings.get(&stream_token) {
+            Some(entry) => entry.upstream_url.clone(),
+            None => return,
+        }
+    };
+    let client = app_handle
+        .state::<crate::platforms::common::SharedReqwestClient>()
+        .client();
+    let mut req = crate::proxy::apply_stream_referer(
+        crate::proxy::flv_upstream_request(&client, &upstream_url),
+        &stream_token,
+    );
+    if let Some(cookie) = crate::proxy::stream_cookie_header(&stream_token, &upstream_url) {
+        req = req.header(reqwest::header::COOKIE, cookie);
+    }
+    let response = match req.send().await.and_then(|r| r.error_for_status()) {
+        Ok(response) => response,
+        Err(e) => {
+            let error = format!("连接上游失败: {}", e);
+            finish_recording(
+                &app_handle,
+                &stream_token,
+                RecordingStatus::Failed,
+                Some(error),
+            );
+            return;
+        }
+    };
+    let mut file = match tokio::fs::File::create(&path).await {
+        Ok(file) => file,
+        Err(e) => {
+            let error = format!("创建录制文件失败: {}", e);
+            finish_recording(
+                &app_handle,
+                &stream_token,
+                RecordingStatus::Failed,
+                Some(error),
+            );
+            return;
+        }
+    };
+
+    let mut stream = response.bytes_stream();
+    let mut progress = tokio::time::interval(PROGRESS_INTERVAL);
+    let (status, error) = loop {
+        tokio::select! {
+            _ = &mut stop_rx => break (RecordingStatus::Stopped, None),
+            _ = progress.tick() => {
+                let info = {
+                    let recordings = app_handle.state::<Recordings>();
+                    let recordings = recordings.0.lock().unwrap_or_else(|e| e.into_inner());
+                    recordings.get(&stream_token).map(|entry| entry.info(&stream_token))
+                };
+                if let Some(info) = info {
+                    emit_event(&app_handle, DtvEvent::RecordingProgress(DtvEvent::json(info)));
+                }
+            }
+            chunk = stream.next() => match chunk {
+                Some(Ok(chunk)) => {
+                    if let Err(e) = file.write_all(&chunk).await {
+                        let error = if is_disk_full(&e) {
+                            format!("磁盘空间不足，已停止录制: {}", e)
+                        } else {
+                            format!("写入录制文件失败: {}", e)
+                        };
+                        break (RecordingStatus::Failed, Some(error));
+                    }
+                    bytes_written.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                }
+                Some(Err(e)) => break (RecordingStatus::Failed, Some(format!("上游读取失败: {}", e))),
+                None => break (RecordingStatus::Finished, None),
+            },
+        }
+    };
+    // 显式 flush 后释放文件句柄，失败时保留已写入的数据
+    if let Err(e) = file.flush().await {
+        eprintln!("[Recording] {} flush failed: {}", stream_token, e);
+    }
+    drop(file);
+    finish_recording(&app_handle, &stream_token, status, error);
+}
+
+// 录制的上游来源：沿用已接入代理的流，或按房间单独解析（与当前播放互不影响）
+async fn resolve_recording_source(
+    app_handle: &AppHandle,
+    stream_url_store: &StreamUrlStore,
+    stream_token: Option<String>,
+    room: Option<(&SupportedPlatformRust, &str)>,
+    quality: Option<String>,
+    cookie: Option<String>,
+) -> Result<(String, String, bool), String> {
+    if let Some((platform, room_id)) = room {
+        let quality = quality.unwrap_or_else(crate::settings::default_quality);
+        let info =
+            crate::room::resolve_stream(app_handle, platform, room_id, &quality, cookie).await?;
+        let upstream_url = info.upstream_url.ok_or_else(|| {
+            info.error_message
+                .unwrap_or_else(|| "主播未开播".to_string())
+        })?;
+        if upstream_url.contains(".m3u8") {
+            return Err(format!(
+                "{} {} only offers an HLS stream; only FLV streams can be recorded",
+                platform.as_str(),
+                room_id
+            ));
+        }
+        let stream_token = new_request_id();
+        crate::proxy::bind_stream_cookies(&stream_token, &upstream_url);
+        return Ok((stream_token, upstream_url, true));
+    }
+    let stream_token = stream_token.ok_or("stream_token or platform/room_id is required")?;
+    let upstream_url = crate::proxy::flv_upstream_for_token(&stream_token, stream_url_store)
+        .ok_or_else(|| {
+            format!(
+                "Stream token {} is not an active FLV stream; only FLV streams can be recorded",
+                stream_token
+            )
+        })?;
+    Ok((stream_token, upstream_url, false))
+}
+
+// 开始录制 FLV 流：传入 stream_token 录制已接入代理的流，或传入 platform/room_id 由录制自行解析；
+// path 缺省时写入系统视频目录下的 recordings
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn start_recording(
+    app_handle: AppHandle,
+    stream_url_store: State<'_, StreamUrlStore>,
+    recordings: State<'_, Recordings>,
+    stream_token: Option<String>,
+    platform: Option<String>,
+    room_id: Option<String>,
+    quality: Option<String>,
+    cookie: Option<String>,
+    path: Option<String>,
+) -> Result<RecordingInfo, String> {
+    let room = match (platform, room_id) {
+        (Some(platform), Some(room_id)) => {
+            let platform = SupportedPlatformRust::parse(&platform)?;
+            let room_id = validate_room_id(&platform, &room_id)?;
+            Some((platform, room_id))
+        }
+        (None, None) => None,
+        _ => return Err("platform and room_id must be given together".to_string()),
+    };
+    let room_ref = room
+        .as_ref()
+        .map(|(platform, room_id)| (platform, room_id.as_str()));
+    let (stream_token, upstream_url, owns_token) = resolve_recording_source(
+        &app_handle,
+        &stream_url_store,
+        stream_token,
+        room_ref,
+        quality,
+        cookie,
+    )
+    .await?;
+    // 按房间录制时 Cookie 已绑定到新令牌，登记成功前的任何失败都要释放绑定
+    let release_token = |e: String| {
+        if owns_token {
+            crate::proxy::forget_stream_cookies(&stream_token);
+        }
+        e
+    };
+    let path = match path.filter(|p| !p.trim().is_empty()) {
+        Some(p) => PathBuf::from(p),
+        None => {
+            default_recording_path(&app_handle, &stream_token, room_ref).map_err(release_token)?
+        }
+    };
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| release_token(format!("创建录制目录失败: {}", e)))?;
+    }
+
+    let (stop_tx, stop_rx) = oneshot::channel();
+    let bytes_written = Arc::new(AtomicU64::new(0));
+    let info = {
+        let mut recordings = recordings.0.lock().unwrap_or_else(|e| e.into_inner());
+        if recordings.contains_key(&stream_token) {
+            return Err(release_token(format!(
+                "Stream {} is already being recorded",
+                stream_token
+            )));
+        }
+        let entry = RecordingEntry {
+            upstream_url,
+            owns_token,
+            path: path.clone(),
+            started_at: chrono::Utc::now().timestamp(),
+            ended_at: None,
+            bytes_written: bytes_written.clone(),
+            status: RecordingStatus::Recording,
+            error: None,
+            stop_tx: Some(stop_tx),
+        };
+        let info = entry.info(&stream_token);
+        recordings.insert(stream_token.clone(), entry);
+        info
+    };
+    println!("[Recording] {} -> {}", stream_token, path.display());
+
+    tauri::async_runtime::spawn(run_recording(
+        app_handle,
+        stream_token,
+        path,
+        bytes_written,
+        stop_rx,
+    ));
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn stop_recording(
+    recordings: State<'_, Recordings>,
+    stream_token: String,
+) -> Result<RecordingInfo, String> {
+    let mut recordings = recordings.0.lock().unwrap_or_else(|e| e.into_inner());
+    let entry = recordings
+        .get_mut(&stream_token)
+        .ok_or_else(|| format!("No recording for stream {}", stream_token))?;
+    // 文件句柄由录制任务在收到停止信号后关闭，状态随后更新为 stopped
+    if let Some(stop_tx) = entry.stop_tx.take() {
+        let _ = stop_tx.send(());
+    }
+    Ok(entry.info(&stream_token))
+}
+
+// 列出进行中的录制，按开始时间排序
+#[tauri::command]
+pub fn list_recordings(recordings: State<'_, Recordings>) -> Vec<RecordingInfo> {
+    let recordings = recordings.0.lock().unwrap_or_else(|e| e.into_inner());
+    let mut list: Vec<RecordingInfo> = recordings
+        .iter()
+        .map(|(token, entry)| entry.info(token))
+        .collect();
+    list.sort_by_key(|info| info.started_at);
+    list
+}
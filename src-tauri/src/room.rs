@@ -0,0 +1,1644 @@
+use futures_util::StreamExt;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::platforms::common::request_id::new_request_id;
+use crate::platforms::common::room_id::validate_room_id;
+use crate::platforms::common::ttl_cache::TtlCache;
+use crate::platforms::common::types::{GetStreamUrlArgs, StreamVariant};
+use crate::platforms::common::{
+    FollowHttpClient, GetStreamUrlPayload, LiveStreamInfo, Quality, SupportedPlatformRust,
+};
+use crate::proxy::ProxyServerHandle;
+use crate::StreamUrlStore;
+
+pub(crate) const DEFAULT_QUALITY: &str = "原画";
+// 按测得带宽自动选择清晰度
+const AUTO_QUALITY: &str = "auto";
+// 可用带宽需高出档位码率的倍数，留出余量应对网络波动
+const AUTO_QUALITY_HEADROOM: f64 = 1.2;
+const PEEK_TTL_SECONDS: u64 = 15;
+
+// 每个步骤的执行结果：前端据此区分“流可播但弹幕失败”等部分成功情况
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct RoomStepStatus {
+    pub ok: bool,
+    pub skipped: bool,
+    pub error: Option<String>,
+}
+
+impl RoomStepStatus {
+    fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Default::default()
+        }
+    }
+
+    fn skipped() -> Self {
+        Self {
+            skipped: true,
+            ..Default::default()
+        }
+    }
+
+    fn failed(error: String) -> Self {
+        Self {
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OpenRoomSteps {
+    pub resolve: RoomStepStatus,
+    pub proxy: RoomStepStatus,
+    pub danmaku: RoomStepStatus,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OpenRoomResponse {
+    pub proxy_url: Option<String>,
+    pub info: LiveStreamInfo,
+    pub danmaku_started: bool,
+    pub request_id: String,
+    pub steps: OpenRoomSteps,
+}
+
+// 当前正在观看的房间：刷新/录制/降级等后端功能以此为准，无需前端重复传参
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NowPlaying {
+    pub platform: String,
+    pub room_id: String,
+    pub quality: String,
+    // 斗鱼实际播放的码率编号，切换线路时沿用；其他平台为 None
+    #[serde(default)]
+    pub rate: Option<i32>,
+    pub stream_token: String,
+    pub started_at: i64,
+}
+
+#[derive(Default)]
+pub struct NowPlayingState(pub Mutex<Option<NowPlaying>>);
+
+#[tauri::command]
+pub fn get_now_playing(state: State<'_, NowPlayingState>) -> Option<NowPlaying> {
+    state.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+// 播放存储或播放地址更新后调用，让 NowPlaying 始终对应当前的 stream token
+pub(crate) fn set_now_playing(
+    app_handle: &AppHandle,
+    platform: &SupportedPlatformRust,
+    room_id: &str,
+    quality: &str,
+    rate: Option<i32>,
+    stream_token: &str,
+) {
+    *app_handle
+        .state::<NowPlayingState>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = Some(NowPlaying {
+        platform: platform.as_str().to_string(),
+        room_id: room_id.to_string(),
+        quality: quality.to_string(),
+        rate,
+        stream_token: stream_token.to_string(),
+        started_at: chrono::Utc::now().timestamp_millis(),
+    });
+}
+
+// 斗鱼解析结果中只有选中的码率档位带地址，据此取出实际生效的码率编号
+fn douyu_selected_rate(platform: &SupportedPlatformRust, info: &LiveStreamInfo) -> Option<i32> {
+    if *platform != SupportedPlatformRust::Douyu {
+        return None;
+    }
+    info.available_streams
+        .as_ref()?
+        .iter()
+        .find(|variant| !variant.url.is_empty())
+        .and_then(|variant| variant.qn)
+}
+
+// 存储被替换为无法对应到房间的地址时调用
+pub(crate) fn clear_now_playing(app_handle: &AppHandle) {
+    *app_handle
+        .state::<NowPlayingState>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+fn room_payload(room_id: &str) -> GetStreamUrlPayload {
+    GetStreamUrlPayload {
+        args: GetStreamUrlArgs {
+            room_id_str: room_id.to_string(),
+        },
+    }
+}
+
+// 解析直播流，只返回上游地址（stream_url 与 upstream_url 相同），不写入存储也不启动代理；
+// 需要播放时由调用方 ensure_proxy 接入
+pub(crate) async fn resolve_stream(
+    app_handle: &AppHandle,
+    platform: &SupportedPlatformRust,
+    room_id: &str,
+    quality: &str,
+    cookie: Option<String>,
+) -> Result<LiveStreamInfo, String> {
+    match platform {
+        SupportedPlatformRust::Douyu => {
+            let resolved =
+                crate::platforms::douyu::resolve_douyu_stream(room_id, quality, None, None)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            let url = resolved.url;
+            Ok(LiveStreamInfo {
+                stream_url: Some(url.clone()),
+                status: Some(1),
+                needs_proxy: Some(resolved.needs_proxy),
+                upstream_url: Some(url),
+                is_vertical: Some(resolved.is_vertical),
+                available_streams: Some(resolved.rates),
+                ..Default::default()
+            })
+        }
+        SupportedPlatformRust::Huya => {
+            let resp = crate::platforms::huya::stream_url::get_huya_unified_cmd(
+                room_id.to_string(),
+                Some(quality.to_string()),
+                None,
+                None,
+                app_handle.state::<FollowHttpClient>(),
+            )
+            .await?;
+            Ok(LiveStreamInfo {
+                title: resp.title,
+                anchor_name: resp.nick,
+                avatar: resp.avatar,
+                stream_url: resp.selected_url.clone(),
+                status: Some(if resp.is_live { 1 } else { 0 }),
+                needs_proxy: resp.needs_proxy,
+                upstream_url: resp.selected_url,
+                expires_at: resp.expires_at,
+                resolver: resp.resolver,
+                raw: resp.raw,
+                ..Default::default()
+            })
+        }
+        SupportedPlatformRust::Douyin => {
+            crate::platforms::douyin::get_douyin_live_stream_url_with_quality(
+                app_handle.clone(),
+                app_handle.state::<StreamUrlStore>(),
+                app_handle.state::<ProxyServerHandle>(),
+                room_payload(room_id),
+                quality.to_string(),
+            )
+            .await
+        }
+        SupportedPlatformRust::Bilibili => {
+            crate::platforms::bilibili::stream_url::resolve_bilibili_upstream(
+                room_payload(room_id),
+                quality,
+                cookie,
+                &new_request_id(),
+            )
+            .await
+        }
+    }
+}
+
+// 将上游地址接入本地代理：HLS 走静态代理 /hls，FLV 写入存储后启动 /live.flv 代理
+async fn ensure_proxy(
+    app_handle: &AppHandle,
+    upstream_url: &str,
+    request_id: &str,
+) -> Result<String, String> {
+    crate::proxy::bind_stream_cookies(request_id, upstream_url);
+    attach_proxy(app_handle, upstream_url, request_id).await
+}
+
+// 同 ensure_proxy，但不绑定 cookie：调用方已为该 token 绑定过（如预热前）
+async fn attach_proxy(
+    app_handle: &AppHandle,
+    upstream_url: &str,
+    request_id: &str,
+) -> Result<String, String> {
+    if upstream_url.contains(".m3u8") {
+        let base = crate::proxy::start_static_proxy_server(
+            app_handle.clone(),
+            app_handle.state::<StreamUrlStore>(),
+        )
+        .await?;
+        return Ok(format!(
+            "{}/hls?url={}&token={}",
+            base.trim_end_matches('/'),
+            urlencoding::encode(upstream_url),
+            urlencoding::encode(request_id)
+        ));
+    }
+    {
+        let store = app_handle.state::<StreamUrlStore>();
+        *store.url.lock().unwrap_or_else(|e| e.into_inner()) = upstream_url.to_string();
+        *store.request_id.lock().unwrap_or_else(|e| e.into_inner()) = request_id.to_string();
+    }
+    crate::proxy::start_proxy(
+        app_handle.clone(),
+        app_handle.state::<ProxyServerHandle>(),
+        app_handle.state::<StreamUrlStore>(),
+        None,
+        None,
+    )
+    .await
+}
+
+// 不占用播放存储的代理地址：经静态代理访问，FLV 按 token 登记上游地址，
+// 导出的播放列表被外部播放器打开时不会替换应用内正在播放的流
+async fn ensure_token_proxy(
+    app_handle: &AppHandle,
+    upstream_url: &str,
+    token: &str,
+) -> Result<String, String> {
+    crate::proxy::bind_stream_cookies(token, upstream_url);
+    let base = crate::proxy::start_static_proxy_server(
+        app_handle.clone(),
+        app_handle.state::<StreamUrlStore>(),
+    )
+    .await?;
+    let base = base.trim_end_matches('/');
+    if upstream_url.contains(".m3u8") {
+        return Ok(format!(
+            "{}/hls?url={}&token={}",
+            base,
+            urlencoding::encode(upstream_url),
+            urlencoding::encode(token)
+        ));
+    }
+    crate::proxy::register_token_stream(token, upstream_url);
+    Ok(format!(
+        "{}/live.flv?token={}",
+        base,
+        urlencoding::encode(token)
+    ))
+}
+
+pub(crate) async fn start_room_danmaku(
+    app_handle: &AppHandle,
+    window: tauri::Window,
+    platform: &SupportedPlatformRust,
+    room_id: &str,
+    cookie: Option<String>,
+) -> Result<(), String> {
+    match platform {
+        SupportedPlatformRust::Douyu => {
+            crate::start_danmaku_listener(
+                room_id.to_string(),
+                window,
+                app_handle.state::<crate::DouyuDanmakuHandles>(),
+            )
+            .await
+        }
+        SupportedPlatformRust::Huya => {
+            crate::platforms::huya::start_huya_danmaku_listener(
+                room_payload(room_id),
+                window,
+                app_handle.state(),
+            )
+            .await
+        }
+        SupportedPlatformRust::Douyin => {
+            crate::platforms::douyin::start_douyin_danmu_listener(
+                room_payload(room_id),
+                window,
+                app_handle.state(),
+            )
+            .await
+        }
+        SupportedPlatformRust::Bilibili => {
+            crate::platforms::bilibili::danmaku::start_bilibili_danmaku_listener(
+                room_payload(room_id),
+                cookie,
+                window,
+                app_handle.state(),
+            )
+            .await
+        }
+    }
+}
+
+pub(crate) fn is_auto_quality(quality: &str) -> bool {
+    quality.trim().eq_ignore_ascii_case(AUTO_QUALITY)
+}
+
+// 上游未给出码率时各档位的典型码率（kbps）
+fn nominal_bitrate_kbps(quality: Quality) -> u32 {
+    match quality {
+        Quality::Origin => 8000,
+        Quality::High => 4000,
+        Quality::Standard => 2000,
+    }
+}
+
+// 各档位（从高到低）的码率：优先取 available_streams 中上游给出的码率（同档取最高），
+// 缺失时按典型值估计
+fn tier_bitrates(platform: &SupportedPlatformRust, info: &LiveStreamInfo) -> Vec<(Quality, u32)> {
+    let variant_tier = |variant: &StreamVariant| {
+        variant
+            .qn
+            .and_then(|qn| Quality::from_user(platform, &qn.to_string()).ok())
+            .or_else(|| {
+                let desc = variant.desc.as_deref()?;
+                Quality::from_user(platform, desc).ok()
+            })
+    };
+    [Quality::Origin, Quality::High, Quality::Standard]
+        .into_iter()
+        .map(|tier| {
+            let parsed = info
+                .available_streams
+                .iter()
+                .flatten()
+                .filter(|variant| variant_tier(variant) == Some(tier))
+                .filter_map(|variant| variant.bitrate_kbps)
+                .max();
+            (tier, parsed.unwrap_or_else(|| nominal_bitrate_kbps(tier)))
+        })
+        .collect()
+}
+
+// tiers 按从高到低排列：选码率（含余量）不超过测得带宽的最高档，都放不下时选最低档，无测速结果时选中间档
+fn choose_tier(tiers: &[(Quality, u32)], measured_kbps: Option<u64>) -> (Quality, u32) {
+    match measured_kbps {
+        Some(kbps) => tiers
+            .iter()
+            .copied()
+            .find(|(_, bitrate)| *bitrate as f64 * AUTO_QUALITY_HEADROOM <= kbps as f64)
+            .unwrap_or(tiers[tiers.len() - 1]),
+        None => tiers[tiers.len() / 2],
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AutoQualityChoice {
+    pub quality: Quality,
+    pub label: String,
+    pub bitrate_kbps: u32,
+    pub measured_kbps: Option<u64>,
+    // 测速没有得到可信结果，按中间档位选择
+    pub inconclusive: bool,
+    // 选中原画时沿用测速前的解析结果，调用方无需再解析一次
+    #[serde(skip)]
+    pub(crate) resolved: Option<LiveStreamInfo>,
+}
+
+// 先按原画解析并下载开头一段测速，再按测得的带宽选档
+pub(crate) async fn pick_auto_quality(
+    app_handle: &AppHandle,
+    platform: &SupportedPlatformRust,
+    room_id: &str,
+    cookie: Option<String>,
+) -> Result<AutoQualityChoice, String> {
+    let info = resolve_stream(
+        app_handle,
+        platform,
+        room_id,
+        Quality::Origin.label(),
+        cookie,
+    )
+    .await?;
+    let tiers = tier_bitrates(platform, &info);
+
+    let measured_kbps = match info.upstream_url.as_ref().or(info.stream_url.as_ref()) {
+        Some(url) => {
+            let probe_token = format!("{}-auto", new_request_id());
+            crate::proxy::bind_stream_cookies(&probe_token, url);
+            let measured = crate::proxy::probe_throughput_kbps(url, &probe_token).await;
+            crate::proxy::forget_stream_cookies(&probe_token);
+            measured.unwrap_or_else(|e| {
+                eprintln!("[Room] auto quality probe failed for {}: {}", room_id, e);
+                None
+            })
+        }
+        None => None,
+    };
+
+    let (quality, bitrate_kbps) = choose_tier(&tiers, measured_kbps);
+    println!(
+        "[Room] auto quality {} {}: measured={:?}kbps -> {} ({}kbps)",
+        platform.as_str(),
+        room_id,
+        measured_kbps,
+        quality.label(),
+        bitrate_kbps
+    );
+    Ok(AutoQualityChoice {
+        quality,
+        label: quality.label().to_string(),
+        bitrate_kbps,
+        measured_kbps,
+        inconclusive: measured_kbps.is_none(),
+        resolved: (quality == Quality::Origin).then_some(info),
+    })
+}
+
+#[tauri::command]
+pub async fn resolve_auto_quality(
+    app_handle: AppHandle,
+    platform: String,
+    room_id: String,
+    cookie: Option<String>,
+) -> Result<AutoQualityChoice, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let room_id = validate_room_id(&platform, &room_id)?;
+    pick_auto_quality(&app_handle, &platform, &room_id, cookie).await
+}
+
+// 一次完成 解析 -> 写入存储/启动代理 -> (可选)启动弹幕，返回每一步的结果
+#[tauri::command]
+pub async fn open_room(
+    app_handle: AppHandle,
+    window: tauri::Window,
+    platform: String,
+    room_id: String,
+    quality: Option<String>,
+    with_danmaku: bool,
+    cookie: Option<String>,
+) -> Result<OpenRoomResponse, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let room_id = validate_room_id(&platform, &room_id)?;
+    let mut quality = quality.unwrap_or_else(crate::settings::default_quality);
+    let mut resolved = None;
+    if is_auto_quality(&quality) {
+        let choice = pick_auto_quality(&app_handle, &platform, &room_id, cookie.clone()).await?;
+        quality = choice.label;
+        resolved = choice.resolved;
+    }
+    let request_id = new_request_id();
+    println!(
+        "[Room][req:{}] open_room {} {} quality={} danmaku={}",
+        request_id,
+        platform.as_str(),
+        room_id,
+        quality,
+        with_danmaku
+    );
+
+    // 解析失败时没有任何资源被占用，直接返回错误
+    let info = match resolved {
+        Some(info) => info,
+        None => resolve_stream(&app_handle, &platform, &room_id, &quality, cookie.clone()).await?,
+    };
+    let resolve = match &info.error_message {
+        Some(e) if info.stream_url.is_none() => RoomStepStatus::failed(e.clone()),
+        _ => RoomStepStatus::ok(),
+    };
+
+    let (proxy_url, proxy) = match &info.stream_url {
+        None => (None, RoomStepStatus::skipped()),
+        Some(url) => match ensure_proxy(&app_handle, url, &request_id).await {
+            Ok(proxied) => (Some(proxied), RoomStepStatus::ok()),
+            Err(e) => {
+                eprintln!("[Room][req:{}] proxy step failed: {}", request_id, e);
+                (None, RoomStepStatus::failed(e))
+            }
+        },
+    };
+
+    if proxy_url.is_some() {
+        set_now_playing(
+            &app_handle,
+            &platform,
+            &room_id,
+            &quality,
+            douyu_selected_rate(&platform, &info),
+            &request_id,
+        );
+    }
+
+    let danmaku = if with_danmaku {
+        match start_room_danmaku(&app_handle, window, &platform, &room_id, cookie).await {
+            Ok(()) => RoomStepStatus::ok(),
+            Err(e) => {
+                eprintln!("[Room][req:{}] danmaku step failed: {}", request_id, e);
+                RoomStepStatus::failed(e)
+            }
+        }
+    } else {
+        RoomStepStatus::skipped()
+    };
+
+    Ok(OpenRoomResponse {
+        proxy_url,
+        info,
+        danmaku_started: danmaku.ok,
+        request_id,
+        steps: OpenRoomSteps {
+            resolve,
+            proxy,
+            danmaku,
+        },
+    })
+}
+
+// 首播前探测的候选地址上限与并发数：首选地址之外只探测少量备选，避免一次打开房间发出过多请求
+const MAX_VERIFY_CANDIDATES: usize = 6;
+const VERIFY_CONCURRENCY: usize = 3;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProbedCandidate {
+    pub url: String,
+    pub desc: Option<String>,
+    pub probe: crate::proxy::PrewarmResult,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct VerifiedStream {
+    pub request_id: String,
+    pub proxy_url: String,
+    pub upstream_url: String,
+    // 选中的候选在 candidates 中的下标；candidates 按优先级排列（解析选中的地址在最前）
+    pub chosen_index: usize,
+    pub candidates: Vec<ProbedCandidate>,
+    pub info: LiveStreamInfo,
+}
+
+// 所有候选地址都探测失败：在占用代理之前返回，附带每个候选的探测结果
+#[derive(Debug, Clone)]
+pub struct NoPlayableStream {
+    pub platform: &'static str,
+    pub room_id: String,
+    pub candidates: Vec<ProbedCandidate>,
+}
+
+impl std::fmt::Display for NoPlayableStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let errors: Vec<String> = self
+            .candidates
+            .iter()
+            .map(|c| {
+                c.probe
+                    .error
+                    .clone()
+                    .unwrap_or_else(|| "no data".to_string())
+            })
+            .collect();
+        write!(
+            f,
+            "[NoPlayableStream] {} 房间 {} 的 {} 个候选地址均不可用: {}",
+            self.platform,
+            self.room_id,
+            self.candidates.len(),
+            errors.join("; ")
+        )
+    }
+}
+
+impl From<NoPlayableStream> for String {
+    fn from(error: NoPlayableStream) -> Self {
+        error.to_string()
+    }
+}
+
+// 解析得到的首选地址在前，其余为 available_streams 中的变体（去重）。
+// 斗鱼的码率档位只列出名称、url 为空，这类变体无法探测，直接跳过
+fn verify_candidates(info: &LiveStreamInfo) -> Vec<(String, Option<String>)> {
+    let mut candidates: Vec<(String, Option<String>)> = Vec::new();
+    let streams = info.available_streams.iter().flatten();
+    let preferred = info.upstream_url.iter().map(|url| (url.clone(), None));
+    for (url, desc) in preferred.chain(streams.map(|v| (v.url.clone(), v.desc.clone()))) {
+        if url.trim().is_empty() || candidates.iter().any(|(existing, _)| *existing == url) {
+            continue;
+        }
+        candidates.push((url, desc));
+        if candidates.len() >= MAX_VERIFY_CANDIDATES {
+            break;
+        }
+    }
+    candidates
+}
+
+// 并发探测候选地址，结果保持候选顺序。
+// 每个候选用独立的探测 token 绑定 cookie/Referer，并发探测时互不覆盖；
+// 解析时暂存的 cookie/Referer 在绑定后仍保留到过期，选中的地址随后还要再绑定到正式 token
+async fn probe_candidates(
+    request_id: &str,
+    candidates: Vec<(String, Option<String>)>,
+) -> Vec<ProbedCandidate> {
+    futures_util::stream::iter(candidates.into_iter().enumerate().map(|(i, (url, desc))| {
+        let probe_token = format!("{}-probe{}", request_id, i);
+        async move {
+            crate::proxy::bind_stream_cookies(&probe_token, &url);
+            let probe = crate::proxy::prewarm_upstream(&url, &probe_token)
+                .await
+                .unwrap_or_else(|e| crate::proxy::PrewarmResult {
+                    error: Some(e),
+                    ..Default::default()
+                });
+            crate::proxy::forget_stream_cookies(&probe_token);
+            ProbedCandidate { url, desc, probe }
+        }
+    }))
+    .buffered(VERIFY_CONCURRENCY)
+    .collect()
+    .await
+}
+
+// 解析后先探测候选地址（读取首个数据块），只返回确认可用的地址及其代理地址，
+// 避免前端先播放失效地址再处理失败
+#[tauri::command]
+pub async fn get_verified_stream(
+    app_handle: AppHandle,
+    platform: String,
+    room_id: String,
+    quality: Option<String>,
+    cookie: Option<String>,
+) -> Result<VerifiedStream, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let room_id = validate_room_id(&platform, &room_id)?;
+    let mut quality = quality.unwrap_or_else(crate::settings::default_quality);
+    let mut resolved = None;
+    if is_auto_quality(&quality) {
+        let choice = pick_auto_quality(&app_handle, &platform, &room_id, cookie.clone()).await?;
+        quality = choice.label;
+        resolved = choice.resolved;
+    }
+    let request_id = new_request_id();
+
+    let info = match resolved {
+        Some(info) => info,
+        None => resolve_stream(&app_handle, &platform, &room_id, &quality, cookie).await?,
+    };
+    let candidates = verify_candidates(&info);
+    if candidates.is_empty() {
+        return Err(info
+            .error_message
+            .clone()
+            .unwrap_or_else(|| "未找到可用的直播流地址".to_string()));
+    }
+
+    let probes = probe_candidates(&request_id, candidates).await;
+
+    let Some(chosen_index) = probes.iter().position(|c| c.probe.ok) else {
+        return Err(NoPlayableStream {
+            platform: platform.as_str(),
+            room_id,
+            candidates: probes,
+        }
+        .into());
+    };
+    let upstream_url = probes[chosen_index].url.clone();
+    println!(
+        "[Room][req:{}] verified candidate {}/{} for {} {}: {}",
+        request_id,
+        chosen_index + 1,
+        probes.len(),
+        platform.as_str(),
+        room_id,
+        upstream_url
+    );
+
+    let proxy_url = ensure_proxy(&app_handle, &upstream_url, &request_id).await?;
+    set_now_playing(
+        &app_handle,
+        &platform,
+        &room_id,
+        &quality,
+        douyu_selected_rate(&platform, &info),
+        &request_id,
+    );
+
+    Ok(VerifiedStream {
+        request_id,
+        proxy_url,
+        upstream_url,
+        chosen_index,
+        candidates: probes,
+        info,
+    })
+}
+
+// 悬停预览等场景频繁查询同一房间，短时间内复用结果
+static PEEK_CACHE: Lazy<TtlCache<LiveStreamInfo>> =
+    Lazy::new(|| TtlCache::new(Duration::from_secs(PEEK_TTL_SECONDS)));
+
+// 只取预览卡片所需的元数据（标题/主播/头像/是否开播），不解析流地址也不启动代理；
+// status 统一为 1 开播 / 0 未开播，available_streams 仅在房间接口已顺带返回时填充
+#[tauri::command]
+pub async fn peek_room(
+    app_handle: AppHandle,
+    platform: String,
+    room_id: String,
+    cookie: Option<String>,
+) -> Result<LiveStreamInfo, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let room_id = validate_room_id(&platform, &room_id)?;
+    let cache_key = format!("{}:{}", platform.as_str(), room_id);
+    if let Some(info) = PEEK_CACHE.get(&cache_key) {
+        return Ok(info);
+    }
+
+    let info = match platform {
+        SupportedPlatformRust::Douyu => crate::platforms::douyu::fetch_douyu_room_info(
+            room_id.clone(),
+            app_handle.state::<FollowHttpClient>(),
+        )
+        .await?
+        .to_live_stream_info(),
+        SupportedPlatformRust::Huya => {
+            let follow_http = app_handle.state::<FollowHttpClient>();
+            crate::platforms::huya::stream_url::peek_huya_room(
+                &follow_http.client().inner,
+                &room_id,
+            )
+            .await?
+        }
+        SupportedPlatformRust::Douyin => {
+            crate::platforms::douyin::douyin_streamer_detail::peek_douyin_room(&room_id).await?
+        }
+        SupportedPlatformRust::Bilibili => {
+            let mut info = crate::platforms::bilibili::streamer_info::fetch_bilibili_streamer_info(
+                room_payload(&room_id),
+                cookie,
+                app_handle.state::<FollowHttpClient>(),
+            )
+            .await?;
+            if let Some(e) = info.error_message.take() {
+                return Err(e);
+            }
+            info.status = Some(if info.status == Some(1) { 1 } else { 0 });
+            info
+        }
+    };
+
+    PEEK_CACHE.insert(cache_key, info.clone());
+    Ok(info)
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CloseRoomResponse {
+    pub danmaku_stopped: bool,
+    pub stream_cleared: bool,
+    pub proxy_stopped: bool,
+}
+
+// 停止弹幕监听；只有确实存在监听器时才返回 true。
+// 其他窗口仍订阅同一房间时只移除当前窗口，保留上游连接并返回 false
+pub(crate) async fn stop_room_danmaku(
+    app_handle: &AppHandle,
+    platform: &SupportedPlatformRust,
+    room_id: &str,
+    window_label: &str,
+) -> bool {
+    // 各平台都按房间维护连接；抖音以规范化后的房间号登记
+    let room_id = match platform {
+        SupportedPlatformRust::Douyin => {
+            crate::platforms::douyin::web_api::normalize_douyin_live_id(room_id)
+        }
+        _ => room_id.to_string(),
+    };
+    if !crate::platforms::common::danmaku_subscribers::unsubscribe(
+        platform.as_str(),
+        Some(&room_id),
+        Some(window_label),
+    ) {
+        return false;
+    }
+    let sender = match platform {
+        SupportedPlatformRust::Douyu => {
+            let handles = app_handle.state::<crate::DouyuDanmakuHandles>();
+            let removed = handles
+                .0
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&room_id);
+            return removed.map(|tx| tx.send(()).is_ok()).unwrap_or(false);
+        }
+        SupportedPlatformRust::Huya => app_handle
+            .state::<crate::platforms::common::HuyaDanmakuState>()
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&room_id),
+        SupportedPlatformRust::Douyin => app_handle
+            .state::<crate::platforms::common::DouyinDanmakuState>()
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&room_id),
+        SupportedPlatformRust::Bilibili => app_handle
+            .state::<crate::platforms::common::BilibiliDanmakuState>()
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&room_id),
+    };
+    match sender {
+        Some(tx) => tx.send(()).await.is_ok(),
+        None => false,
+    }
+}
+
+// 离开房间时的统一清理：弹幕监听、存储中的流地址，以及（可选）不再使用的 FLV 代理。可重复调用。
+#[tauri::command]
+pub async fn close_room(
+    app_handle: AppHandle,
+    window: tauri::Window,
+    platform: String,
+    room_id: String,
+    stream_token: Option<String>,
+    stop_proxy: Option<bool>,
+) -> Result<CloseRoomResponse, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    // 无效房间号不可能被打开过，按“没有可关闭的内容”处理，保持重复调用安全
+    let room_id = match validate_room_id(&platform, &room_id) {
+        Ok(room_id) => room_id,
+        Err(e) => {
+            println!(
+                "[Room] close_room {}: {}, nothing to close",
+                platform.as_str(),
+                e
+            );
+            return Ok(CloseRoomResponse {
+                danmaku_stopped: false,
+                stream_cleared: false,
+                proxy_stopped: false,
+            });
+        }
+    };
+
+    let danmaku_stopped = stop_room_danmaku(&app_handle, &platform, &room_id, window.label()).await;
+    crate::platforms::common::danmaku_pause::forget(platform.as_str(), &room_id);
+
+    // 只清理属于本房间的流：必须带上匹配的 token，不匹配（或未提供）说明无法确认存储归属
+    let (stream_cleared, store_empty) = {
+        let store = app_handle.state::<StreamUrlStore>();
+        let mut url = store.url.lock().unwrap_or_else(|e| e.into_inner());
+        let mut request_id = store.request_id.lock().unwrap_or_else(|e| e.into_inner());
+        let owned = stream_token
+            .as_deref()
+            .is_some_and(|token| !token.is_empty() && *request_id == token);
+        let cleared = owned && !url.is_empty();
+        if cleared {
+            crate::proxy::forget_stream_cookies(&request_id);
+            url.clear();
+            request_id.clear();
+        }
+        (cleared, url.is_empty())
+    };
+    // HLS 流不写入存储，按前端传入的 token 清理
+    if let Some(token) = stream_token.as_deref() {
+        crate::proxy::forget_stream_cookies(token);
+        crate::proxy::forget_live_playlist(token);
+    }
+
+    {
+        let now_playing = app_handle.state::<NowPlayingState>();
+        let mut now_playing = now_playing.0.lock().unwrap_or_else(|e| e.into_inner());
+        let is_current = now_playing
+            .as_ref()
+            .map(|np| np.platform == platform.as_str() && np.room_id == room_id)
+            .unwrap_or(false);
+        if is_current {
+            *now_playing = None;
+        }
+    }
+
+    let mut proxy_stopped = false;
+    if stop_proxy.unwrap_or(true) && store_empty {
+        let running = {
+            app_handle
+                .state::<ProxyServerHandle>()
+                .0
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .is_some()
+        };
+        if running {
+            crate::proxy::stop_proxy(app_handle.state::<ProxyServerHandle>()).await?;
+            proxy_stopped = true;
+        }
+    }
+
+    println!(
+        "[Room] close_room {} {}: danmaku_stopped={} stream_cleared={} proxy_stopped={}",
+        platform.as_str(),
+        room_id,
+        danmaku_stopped,
+        stream_cleared,
+        proxy_stopped
+    );
+    Ok(CloseRoomResponse {
+        danmaku_stopped,
+        stream_cleared,
+        proxy_stopped,
+    })
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SwitchLineResponse {
+    pub proxy_url: String,
+    // 实际生效的线路：请求的线路不可用时平台会回退到其他线路
+    pub applied_line: Option<String>,
+    pub available_lines: Vec<String>,
+    pub url_changed: bool,
+    pub stream_token: String,
+}
+
+// 播放中切换 CDN 线路：按当前房间与清晰度在指定线路上重新解析，只替换存储中的上游地址；
+// FLV 代理保持运行，播放器重新请求 /live.flv 即走新线路
+#[tauri::command]
+pub async fn switch_line(
+    app_handle: AppHandle,
+    stream_token: String,
+    line: String,
+) -> Result<SwitchLineResponse, String> {
+    let now_playing = app_handle
+        .state::<NowPlayingState>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .ok_or_else(|| "当前没有正在播放的房间".to_string())?;
+    if now_playing.stream_token != stream_token {
+        return Err(format!(
+            "Stream token {} is not the active stream ({})",
+            stream_token, now_playing.stream_token
+        ));
+    }
+    let platform = SupportedPlatformRust::parse(&now_playing.platform)?;
+    let line = line.trim().to_string();
+
+    let (upstream_url, applied_line, available_lines) = match platform {
+        SupportedPlatformRust::Douyu => {
+            // 沿用当前播放的码率，避免换线后回落到按清晰度名称选出的档位
+            let resolved = crate::platforms::douyu::resolve_douyu_stream(
+                &now_playing.room_id,
+                &now_playing.quality,
+                now_playing.rate,
+                Some(&line),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+            (resolved.url, Some(resolved.cdn), resolved.cdns)
+        }
+        SupportedPlatformRust::Huya => {
+            let resp = crate::platforms::huya::stream_url::get_huya_unified_cmd(
+                now_playing.room_id.clone(),
+                Some(now_playing.quality.clone()),
+                Some(line.clone()),
+                None,
+                app_handle.state::<FollowHttpClient>(),
+            )
+            .await?;
+            let url = resp.selected_url.ok_or_else(|| "主播未开播".to_string())?;
+            (url, resp.selected_line, resp.lines)
+        }
+        SupportedPlatformRust::Douyin | SupportedPlatformRust::Bilibili => {
+            return Err(format!("{} 暂不支持切换线路", platform.as_str()));
+        }
+    };
+
+    let previous_url = app_handle
+        .state::<StreamUrlStore>()
+        .url
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    let url_changed = previous_url != upstream_url;
+
+    // FLV 代理已在运行时只替换地址；否则（或 HLS）按常规流程接入代理，沿用原 token
+    let running_proxy = if upstream_url.contains(".m3u8") {
+        None
+    } else {
+        crate::proxy::running_flv_proxy_url(&app_handle.state::<ProxyServerHandle>())
+    };
+    let proxy_url = match running_proxy {
+        Some(proxy_url) => {
+            crate::proxy::bind_stream_cookies(&stream_token, &upstream_url);
+            let store = app_handle.state::<StreamUrlStore>();
+            *store.url.lock().unwrap_or_else(|e| e.into_inner()) = upstream_url.clone();
+            *store.request_id.lock().unwrap_or_else(|e| e.into_inner()) = stream_token.clone();
+            proxy_url
+        }
+        None => ensure_proxy(&app_handle, &upstream_url, &stream_token).await?,
+    };
+    println!(
+        "[Room][req:{}] switch_line {} {} requested={} applied={:?} changed={}",
+        stream_token,
+        platform.as_str(),
+        now_playing.room_id,
+        line,
+        applied_line,
+        url_changed
+    );
+
+    Ok(SwitchLineResponse {
+        proxy_url,
+        applied_line,
+        available_lines,
+        url_changed,
+        stream_token,
+    })
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ChangeQualityResponse {
+    pub proxy_url: String,
+    pub requested_quality: String,
+    // 实际生效的清晰度：所选档位不可用时平台会回退到其他档位
+    pub applied_quality: String,
+    pub url_changed: bool,
+    // true 表示代理地址未变，前端可在播放器内直接无缝切换源；false 时需要按新地址重新加载
+    pub seamless: bool,
+    pub prewarm: Option<crate::proxy::PrewarmResult>,
+    pub stream_token: String,
+}
+
+// 播放中切换清晰度：按当前房间重新解析，（可选）先预热新地址，再一次性替换存储中的上游地址。
+// FLV 代理保持运行，返回的代理地址不变
+#[tauri::command]
+pub async fn change_quality(
+    app_handle: AppHandle,
+    stream_token: String,
+    quality: String,
+    prewarm: Option<bool>,
+    cookie: Option<String>,
+) -> Result<ChangeQualityResponse, String> {
+    let now_playing = app_handle
+        .state::<NowPlayingState>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .ok_or_else(|| "当前没有正在播放的房间".to_string())?;
+    if now_playing.stream_token != stream_token {
+        return Err(format!(
+            "Stream token {} is not the active stream ({})",
+            stream_token, now_playing.stream_token
+        ));
+    }
+    let platform = SupportedPlatformRust::parse(&now_playing.platform)?;
+
+    let previous_url = app_handle
+        .state::<StreamUrlStore>()
+        .url
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    let info = resolve_stream(
+        &app_handle,
+        &platform,
+        &now_playing.room_id,
+        &quality,
+        cookie,
+    )
+    .await?;
+    let upstream_url = info.upstream_url.clone().ok_or_else(|| {
+        info.error_message
+            .clone()
+            .unwrap_or_else(|| "主播未开播".to_string())
+    })?;
+    let url_changed = previous_url != upstream_url;
+
+    // 只绑定一次：预热与之后的代理请求共用这次绑定的 cookie/Referer
+    crate::proxy::bind_stream_cookies(&stream_token, &upstream_url);
+    let prewarm = if prewarm.unwrap_or(false) && !upstream_url.contains(".m3u8") {
+        crate::proxy::prewarm_upstream(&upstream_url, &stream_token)
+            .await
+            .ok()
+    } else {
+        None
+    };
+
+    let running_proxy = if upstream_url.contains(".m3u8") {
+        None
+    } else {
+        crate::proxy::running_flv_proxy_url(&app_handle.state::<ProxyServerHandle>())
+    };
+    let (proxy_url, seamless) = match running_proxy {
+        Some(proxy_url) => {
+            let store = app_handle.state::<StreamUrlStore>();
+            // 同时持有两把锁，保证代理不会读到新地址配旧 token
+            let mut url = store.url.lock().unwrap_or_else(|e| e.into_inner());
+            let mut request_id = store.request_id.lock().unwrap_or_else(|e| e.into_inner());
+            *url = upstream_url.clone();
+            *request_id = stream_token.clone();
+            (proxy_url, true)
+        }
+        None => (
+            attach_proxy(&app_handle, &upstream_url, &stream_token).await?,
+            false,
+        ),
+    };
+
+    let applied_quality = info
+        .current_quality_desc
+        .clone()
+        .unwrap_or_else(|| quality.trim().to_string());
+    if let Some(now) = app_handle
+        .state::<NowPlayingState>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_mut()
+        .filter(|now| now.stream_token == stream_token)
+    {
+        now.quality = quality.clone();
+        now.rate = douyu_selected_rate(&platform, &info);
+    }
+    println!(
+        "[Room][req:{}] change_quality {} {} requested={} applied={} changed={}",
+        stream_token,
+        platform.as_str(),
+        now_playing.room_id,
+        quality,
+        applied_quality,
+        url_changed
+    );
+
+    Ok(ChangeQualityResponse {
+        proxy_url,
+        requested_quality: quality,
+        applied_quality,
+        url_changed,
+        seamless,
+        prewarm,
+        stream_token,
+    })
+}
+
+// 导出 .strm / .m3u8 播放列表，指向本地代理地址，供 Kodi/Jellyfin/VLC 等外部播放器打开
+#[tauri::command]
+pub async fn export_playlist(
+    app_handle: AppHandle,
+    platform: String,
+    room_id: String,
+    quality: Option<String>,
+    path: String,
+    cookie: Option<String>,
+) -> Result<String, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let room_id = validate_room_id(&platform, &room_id)?;
+    let quality = quality.unwrap_or_else(|| DEFAULT_QUALITY.to_string());
+    let request_id = new_request_id();
+
+    let info = resolve_stream(&app_handle, &platform, &room_id, &quality, cookie).await?;
+    let stream_url = info.stream_url.clone().ok_or_else(|| {
+        info.error_message
+            .clone()
+            .unwrap_or_else(|| "主播未开播".to_string())
+    })?;
+    // 使用独立 token 接入静态代理，不替换当前播放的流；上游所需的 Referer/UA 由本地代理补齐，
+    // 外部播放器无需额外请求头
+    let proxy_url = ensure_token_proxy(&app_handle, &stream_url, &request_id).await?;
+
+    let path = std::path::PathBuf::from(path.trim());
+    let is_strm = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("strm"))
+        .unwrap_or(false);
+    let content = if is_strm {
+        format!("{}\n", proxy_url)
+    } else {
+        let title = info
+            .title
+            .clone()
+            .or_else(|| info.anchor_name.clone())
+            .unwrap_or_else(|| format!("{} {}", platform.as_str(), room_id));
+        format!(
+            "#EXTM3U\n\
+             # DTV {} {} ({}), request {}\n\
+             # 地址指向 DTV 本地代理，需保持 DTV 运行；上游请求头由代理添加\n\
+             #EXTINF:-1,{}\n\
+             {}\n",
+            platform.as_str(),
+            room_id,
+            quality,
+            request_id,
+            title,
+            proxy_url
+        )
+    };
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+    std::fs::write(&path, content).map_err(|e| format!("写入播放列表失败: {}", e))?;
+    println!(
+        "[Room][req:{}] exported playlist for {} {} -> {}",
+        request_id,
+        platform.as_str(),
+        room_id,
+        path.display()
+    );
+    Ok(path.to_string_lossy().into_owned())
+}
+
+// 斗鱼/B 站每次只返回所选清晰度的地址，预解析最多并发这么多档，避免频繁请求触发风控
+const MAX_PRERESOLVE_QUALITIES: usize = 3;
+const DOUYU_PRERESOLVE_QUALITIES: [&str; MAX_PRERESOLVE_QUALITIES] = ["原画", "高清", "标清"];
+
+// 一次性解析各清晰度的上游地址（清晰度 -> URL），前端可据此即时切换而无需每次等待解析
+#[tauri::command]
+pub async fn get_all_quality_urls(
+    app_handle: AppHandle,
+    platform: String,
+    room_id: String,
+) -> Result<HashMap<String, String>, String> {
+    let platform = SupportedPlatformRust::parse(&platform)?;
+    let room_id = validate_room_id(&platform, &room_id)?;
+    let mut urls = HashMap::new();
+
+    match platform {
+        SupportedPlatformRust::Douyin => {
+            // 抖音一次返回全部 flv_pull_url，直接复用 available_streams
+            let info =
+                resolve_stream(&app_handle, &platform, &room_id, DEFAULT_QUALITY, None).await?;
+            for variant in info.available_streams.unwrap_or_default() {
+                if let Some(desc) = variant.desc {
+                    urls.insert(desc, variant.url);
+                }
+            }
+            if let Some(url) = info.upstream_url {
+                urls.entry(DEFAULT_QUALITY.to_string()).or_insert(url);
+            }
+        }
+        SupportedPlatformRust::Huya => {
+            let resp = crate::platforms::huya::stream_url::get_huya_unified_cmd(
+                room_id.clone(),
+                None,
+                None,
+                None,
+                app_handle.state::<FollowHttpClient>(),
+            )
+            .await?;
+            for entry in resp.flv_tx_urls {
+                urls.entry(entry.quality).or_insert(entry.url);
+            }
+        }
+        SupportedPlatformRust::Douyu => {
+            let results =
+                futures_util::future::join_all(DOUYU_PRERESOLVE_QUALITIES.iter().map(|quality| {
+                    let room_id = room_id.clone();
+                    async move {
+                        let result = crate::platforms::douyu::get_stream_url_with_quality(
+                            &room_id, quality, None, None,
+                        )
+                        .await
+                        .map_err(|e| e.to_string());
+                        (quality.to_string(), result)
+                    }
+                }))
+                .await;
+            for (quality, result) in results {
+                match result {
+                    Ok(url) => {
+                        urls.insert(quality, url);
+                    }
+                    Err(e) => eprintln!(
+                        "[Room] get_all_quality_urls douyu {} {} failed: {}",
+                        room_id, quality, e
+                    ),
+                }
+            }
+        }
+        SupportedPlatformRust::Bilibili => {
+            // 先按默认清晰度解析拿到 accept_qualities，再逐档解析其余 qn。
+            // 只解析上游地址，不写入播放存储；匿名用户可能被降级，按实际下发的清晰度记录
+            let info =
+                resolve_stream(&app_handle, &platform, &room_id, DEFAULT_QUALITY, None).await?;
+            let delivered_label = |info: &LiveStreamInfo, fallback: String| {
+                info.current_quality_desc
+                    .clone()
+                    .or_else(|| info.current_qn.map(|qn| qn.to_string()))
+                    .unwrap_or(fallback)
+            };
+            let current_qn = info.current_qn;
+            let accepted: Vec<i32> = info
+                .accept_qualities
+                .iter()
+                .flatten()
+                .map(|q| q.qn)
+                .filter(|qn| Some(*qn) != current_qn)
+                .take(MAX_PRERESOLVE_QUALITIES)
+                .collect();
+            if let Some(url) = info.upstream_url.clone() {
+                urls.insert(delivered_label(&info, DEFAULT_QUALITY.to_string()), url);
+            }
+            let results = futures_util::future::join_all(accepted.into_iter().map(|qn| {
+                let room_id = room_id.clone();
+                async move {
+                    let result = crate::platforms::bilibili::stream_url::resolve_bilibili_upstream(
+                        room_payload(&room_id),
+                        &qn.to_string(),
+                        None,
+                        &new_request_id(),
+                    )
+                    .await;
+                    (qn, result)
+                }
+            }))
+            .await;
+            for (qn, result) in results {
+                match result {
+                    // 只记录确实按该 qn 下发的地址；被降级到其他 qn 时那一档已由对应的解析记录
+                    Ok(info) if info.current_qn.is_some_and(|delivered| delivered != qn) => {
+                        eprintln!(
+                            "[Room] get_all_quality_urls bilibili {} qn {} delivered as qn {:?}, skipped",
+                            room_id, qn, info.current_qn
+                        );
+                    }
+                    Ok(info) => {
+                        if let Some(url) = info.upstream_url.clone() {
+                            urls.entry(delivered_label(&info, qn.to_string()))
+                                .or_insert(url);
+                        }
+                    }
+                    Err(e) => eprintln!(
+                        "[Room] get_all_quality_urls bilibili {} qn {} failed: {}",
+                        room_id, qn, e
+                    ),
+                }
+            }
+        }
+    }
+
+    if urls.is_empty() {
+        return Err("未能解析到任何清晰度的播放地址".to_string());
+    }
+    println!(
+        "[Room] get_all_quality_urls {} {}: {} qualities",
+        platform.as_str(),
+        room_id,
+        urls.len()
+    );
+    Ok(urls)
+}
+
+// 上次会话：退出时记录正在观看的房间（需用户开启），启动后由前端决定是否恢复
+const LAST_SESSION_FILE: &str = "last_session.json";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct LastSessionFile {
+    resume_enabled: bool,
+    session: Option<NowPlaying>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LastSession {
+    pub platform: String,
+    pub room_id: String,
+    pub quality: String,
+    pub started_at: i64,
+    // 房间已下播时仍返回元数据，由前端提示而不是直接打开
+    pub is_live: bool,
+}
+
+fn last_session_path(app_handle: &AppHandle) -> Option<std::path::PathBuf> {
+    app_handle
+        .path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(LAST_SESSION_FILE))
+}
+
+fn read_last_session_file(app_handle: &AppHandle) -> LastSessionFile {
+    last_session_path(app_handle)
+        .and_then(|path| std::fs::read(path).ok())
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn write_last_session_file(app_handle: &AppHandle, file: &LastSessionFile) -> Result<(), String> {
+    let path = last_session_path(app_handle).ok_or("无法定位数据目录".to_string())?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(file).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("保存上次会话失败: {}", e))
+}
+
+// 应用退出时调用：开启恢复时写入当前房间（未在观看则清空）
+pub fn persist_last_session(app_handle: &AppHandle) {
+    let mut file = read_last_session_file(app_handle);
+    if !file.resume_enabled {
+        return;
+    }
+    file.session = app_handle
+        .state::<NowPlayingState>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    match write_last_session_file(app_handle, &file) {
+        Ok(()) => println!(
+            "[Room] persisted last session: {:?}",
+            file.session.as_ref().map(|np| (&np.platform, &np.room_id))
+        ),
+        Err(e) => eprintln!("[Room] {}", e),
+    }
+}
+
+#[tauri::command]
+pub async fn set_resume_last_session(app_handle: AppHandle, enabled: bool) -> Result<(), String> {
+    let mut file = read_last_session_file(&app_handle);
+    file.resume_enabled = enabled;
+    if !enabled {
+        file.session = None;
+    }
+    write_last_session_file(&app_handle, &file)
+}
+
+#[tauri::command]
+pub async fn get_last_session(
+    app_handle: AppHandle,
+    follow_http: State<'_, FollowHttpClient>,
+) -> Result<Option<LastSession>, String> {
+    let file = read_last_session_file(&app_handle);
+    let session = match file.session.filter(|_| file.resume_enabled) {
+        Some(session) => session,
+        None => return Ok(None),
+    };
+    let is_live = match SupportedPlatformRust::parse(&session.platform) {
+        Ok(platform) => crate::platforms::live_status::fetch_live_status(
+            &platform,
+            &session.room_id,
+            &follow_http,
+        )
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!(
+                "[Room] last session live check failed for {} {}: {}",
+                session.platform, session.room_id, e
+            );
+            false
+        }),
+        Err(_) => false,
+    };
+    Ok(Some(LastSession {
+        platform: session.platform,
+        room_id: session.room_id,
+        quality: session.quality,
+        started_at: session.started_at,
+        is_live,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn douyu_variant(rate: i32, desc: &str, bitrate_kbps: u32) -> StreamVariant {
+        StreamVariant {
+            desc: Some(desc.to_string()),
+            qn: Some(rate),
+            bitrate_kbps: Some(bitrate_kbps),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tier_bitrates_prefer_parsed_bitrates() {
+        let info = LiveStreamInfo {
+            available_streams: Some(vec![
+                douyu_variant(0, "原画 (10000kbps)", 10000),
+                douyu_variant(4, "蓝光4M (4000kbps)", 4000),
+                douyu_variant(3, "超清 (2000kbps)", 2000),
+                douyu_variant(2, "高清 (900kbps)", 900),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            tier_bitrates(&SupportedPlatformRust::Douyu, &info),
+            vec![
+                (Quality::Origin, 10000),
+                (Quality::High, 4000),
+                (Quality::Standard, 2000),
+            ]
+        );
+    }
+
+    #[test]
+    fn tier_bitrates_fall_back_to_nominal_values() {
+        let info = LiveStreamInfo {
+            available_streams: Some(vec![StreamVariant {
+                qn: Some(10000),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        assert_eq!(
+            tier_bitrates(&SupportedPlatformRust::Bilibili, &info),
+            vec![
+                (Quality::Origin, 8000),
+                (Quality::High, 4000),
+                (Quality::Standard, 2000),
+            ]
+        );
+    }
+
+    fn variant(url: &str, desc: &str) -> StreamVariant {
+        StreamVariant {
+            url: url.to_string(),
+            desc: Some(desc.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn verify_candidates_skip_empty_and_duplicate_urls() {
+        let info = LiveStreamInfo {
+            upstream_url: Some("https://cdn.example/a.flv".to_string()),
+            available_streams: Some(vec![
+                variant("https://cdn.example/a.flv", "原画"),
+                // 斗鱼码率档位只有名称
+                variant("", "蓝光4M"),
+                variant("  ", "超清"),
+                variant("https://cdn.example/b.m3u8", "原画 HLS"),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            verify_candidates(&info),
+            vec![
+                ("https://cdn.example/a.flv".to_string(), None),
+                (
+                    "https://cdn.example/b.m3u8".to_string(),
+                    Some("原画 HLS".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_candidates_are_capped() {
+        let info = LiveStreamInfo {
+            available_streams: Some(
+                (0..MAX_VERIFY_CANDIDATES + 3)
+                    .map(|i| variant(&format!("https://cdn.example/{}.flv", i), "原画"))
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        let candidates = verify_candidates(&info);
+        assert_eq!(candidates.len(), MAX_VERIFY_CANDIDATES);
+        assert_eq!(candidates[0].0, "https://cdn.example/0.flv");
+    }
+
+    async fn flv_bytes() -> actix_web::HttpResponse {
+        actix_web::HttpResponse::Ok()
+            .content_type("video/x-flv")
+            .body(&b"FLV\x01\x05\x00\x00\x00\x09"[..])
+    }
+
+    #[actix_web::test]
+    async fn probe_candidates_keeps_order_and_reports_failures() {
+        let server = actix_web::HttpServer::new(|| {
+            actix_web::App::new().route("/ok.flv", actix_web::web::get().to(flv_bytes))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let probes = probe_candidates(
+            "verify-test",
+            vec![
+                (format!("http://{}/missing.flv", addr), None),
+                (format!("http://{}/ok.flv", addr), Some("原画".to_string())),
+            ],
+        )
+        .await;
+        assert_eq!(probes.len(), 2);
+        assert!(!probes[0].probe.ok);
+        assert!(probes[0].probe.error.as_deref().unwrap().contains("404"));
+        assert!(probes[1].probe.ok);
+        assert_eq!(probes[1].desc.as_deref(), Some("原画"));
+        assert_eq!(probes.iter().position(|c| c.probe.ok), Some(1));
+    }
+
+    #[test]
+    fn choose_tier_fits_measured_bandwidth_with_headroom() {
+        let tiers = [
+            (Quality::Origin, 10000),
+            (Quality::High, 4000),
+            (Quality::Standard, 2000),
+        ];
+        assert_eq!(choose_tier(&tiers, Some(15000)), (Quality::Origin, 10000));
+        // 10000kbps 放不下 20% 余量，降一档
+        assert_eq!(choose_tier(&tiers, Some(10000)), (Quality::High, 4000));
+        assert_eq!(choose_tier(&tiers, Some(3000)), (Quality::Standard, 2000));
+        // 都放不下时选最低档
+        assert_eq!(choose_tier(&tiers, Some(500)), (Quality::Standard, 2000));
+        // 测速无结果时选中间档
+        assert_eq!(choose_tier(&tiers, None), (Quality::High, 4000));
+    }
+}
@@ -0,0 +1,446 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::network::{self, UpstreamProxy};
+use crate::platforms::common::danmaku_reconnect::{
+    self, BASE_BACKOFF_MS, DEFAULT_MAX_ATTEMPTS, DEFAULT_MAX_BACKOFF_MS,
+};
+use crate::platforms::common::{
+    FollowHttpClient, Quality, SharedReqwestClient, SupportedPlatformRust,
+};
+
+// 持久化设置：保存在数据目录的 settings.json。main 在创建 tauri::Builder 之前加载并应用
+// 需要写入进程环境变量的部分，其余部分在 setup 中下发到各子系统。
+// 不能推迟到 setup 读取：Tauri 在调用 setup 之前就创建了配置中的窗口，WebView 及其网络进程
+// 已按当时的环境变量启动，之后再注入默认代理不会对它们生效，且此时修改环境变量会与这些线程竞争。
+// 新增字段一律带 serde 默认值，旧版本文件缺字段时按默认补齐（文件里多出的未知字段会被忽略）
+const SETTINGS_FILE: &str = "settings.json";
+const SETTINGS_VERSION: u32 = 1;
+
+// 受限网络环境下的默认 HTTP 代理，仅在用户未设置 HTTP(S)_PROXY 环境变量时注入
+const DEFAULT_HTTP_PROXY: &str = "http://192.168.1.1:8118";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct DanmakuReconnectSettings {
+    pub max_attempts: u32,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for DanmakuReconnectSettings {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            max_backoff_ms: DEFAULT_MAX_BACKOFF_MS,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    // 启动时注入的默认代理，为 None 时不注入；修改后下次启动生效
+    pub default_http_proxy: Option<String>,
+    pub no_proxy: bool,
+    pub api_proxy: UpstreamProxy,
+    pub stream_proxy: UpstreamProxy,
+    // open_room / get_verified_stream 未指定清晰度时使用，可为 "auto"
+    pub default_quality: String,
+    pub danmaku_reconnect: DanmakuReconnectSettings,
+    pub follow_notifications: bool,
+    // 直播列表离线缓存：网络不可达时返回最近一次成功的列表（标记 stale），默认关闭
+    pub offline_list_cache: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            default_http_proxy: Some(DEFAULT_HTTP_PROXY.to_string()),
+            no_proxy: false,
+            api_proxy: UpstreamProxy::Env,
+            stream_proxy: UpstreamProxy::Env,
+            default_quality: crate::room::DEFAULT_QUALITY.to_string(),
+            danmaku_reconnect: DanmakuReconnectSettings::default(),
+            follow_notifications: false,
+            offline_list_cache: false,
+        }
+    }
+}
+
+impl Settings {
+    fn validate(&self) -> Result<(), String> {
+        self.api_proxy.validate()?;
+        self.stream_proxy.validate()?;
+        if let Some(url) = &self.default_http_proxy {
+            reqwest::Proxy::all(url).map_err(|e| format!("无效的代理地址 {}: {}", url, e))?;
+        }
+        if self.danmaku_reconnect.max_backoff_ms < BASE_BACKOFF_MS {
+            return Err(format!(
+                "danmaku_reconnect.max_backoff_ms must be at least {} ms",
+                BASE_BACKOFF_MS
+            ));
+        }
+        // 默认清晰度对所有平台生效，必须是每个平台都能识别的写法（如“原画”，而非斗鱼专有的 rate）
+        if !crate::room::is_auto_quality(&self.default_quality) {
+            for platform in [
+                SupportedPlatformRust::Douyu,
+                SupportedPlatformRust::Bilibili,
+                SupportedPlatformRust::Douyin,
+                SupportedPlatformRust::Huya,
+            ] {
+                Quality::from_user(&platform, &self.default_quality)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+static SETTINGS: Lazy<Mutex<Settings>> = Lazy::new(|| Mutex::new(Settings::default()));
+
+pub fn current() -> Settings {
+    SETTINGS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub fn default_quality() -> String {
+    current().default_quality
+}
+
+// <数据目录>/<identifier>/settings.json：与 app.path().app_data_dir() 相同（Tauri 同样基于
+// dirs::data_dir() 拼接 identifier），只是不需要 AppHandle
+fn settings_file_for(identifier: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(identifier).join(SETTINGS_FILE))
+}
+
+// 启动时由 load_at_startup 按应用 identifier 确定，之后的读写都使用同一路径
+static SETTINGS_PATH: OnceCell<Option<PathBuf>> = OnceCell::new();
+
+fn settings_path() -> Option<PathBuf> {
+    SETTINGS_PATH.get().cloned().flatten()
+}
+
+// 解析设置文件内容：缺失的字段按默认值补齐，未知字段忽略；version 保持文件中的值
+fn parse_settings(bytes: &[u8]) -> Result<Settings, String> {
+    let settings = serde_json::from_slice::<Settings>(bytes)
+        .map_err(|e| format!("Failed to parse {}: {}", SETTINGS_FILE, e))?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+// 文件不存在或损坏时返回默认设置；旧版本文件升级后写回
+fn load_settings() -> Settings {
+    let Some(bytes) = settings_path().and_then(|path| fs::read(path).ok()) else {
+        return Settings::default();
+    };
+    let mut settings = match parse_settings(&bytes) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("[Settings] Invalid saved settings, using defaults: {}", e);
+            return Settings::default();
+        }
+    };
+    if settings.version < SETTINGS_VERSION {
+        println!(
+            "[Settings] Migrating settings from version {} to {}",
+            settings.version, SETTINGS_VERSION
+        );
+        settings.version = SETTINGS_VERSION;
+        if let Err(e) = save_settings(&settings) {
+            eprintln!("[Settings] {}", e);
+        }
+    }
+    settings
+}
+
+pub fn save_settings(settings: &Settings) -> Result<(), String> {
+    let path = settings_path().ok_or("无法定位数据目录".to_string())?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(settings).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("保存设置失败: {}", e))
+}
+
+fn apply_default_http_proxy(proxy: &str) {
+    if env::var("HTTP_PROXY").is_err() && env::var("http_proxy").is_err() {
+        env::set_var("HTTP_PROXY", proxy);
+    }
+    if env::var("HTTPS_PROXY").is_err() && env::var("https_proxy").is_err() {
+        env::set_var("HTTPS_PROXY", proxy);
+    }
+    if env::var("ALL_PROXY").is_err() && env::var("all_proxy").is_err() {
+        env::set_var("ALL_PROXY", proxy);
+    }
+}
+
+// main 中在创建 tauri::Builder 之前调用：此时进程只有主线程，修改环境变量是安全的。
+// identifier 取自 tauri.conf.json，设置文件位于 <平台数据目录>/<identifier>/settings.json
+pub fn load_at_startup(identifier: &str) {
+    let _ = SETTINGS_PATH.set(settings_file_for(identifier));
+    let settings = load_settings();
+    if let Some(proxy) = &settings.default_http_proxy {
+        apply_default_http_proxy(proxy);
+    }
+    println!("[Settings] Loaded settings (version {})", settings.version);
+    *SETTINGS.lock().unwrap_or_else(|e| e.into_inner()) = settings;
+}
+
+// setup 中调用，下发不涉及环境变量的设置；必须早于任何 HTTP 客户端的创建，代理配置才能对它们生效
+pub fn init() {
+    let settings = current();
+    network::apply_no_proxy(settings.no_proxy);
+    network::apply_upstream_proxies(settings.api_proxy.clone(), settings.stream_proxy.clone());
+    let reconnect = &settings.danmaku_reconnect;
+    if let Err(e) =
+        danmaku_reconnect::apply_reconnect_policy(reconnect.max_attempts, reconnect.max_backoff_ms)
+    {
+        eprintln!("[Settings] {}", e);
+    }
+    crate::platforms::follow_polling::set_follow_notifications(settings.follow_notifications);
+    crate::platforms::common::list_cache::set_list_cache_enabled(settings.offline_list_cache);
+}
+
+// 对象逐字段合并，其余值整体替换；null 只对可选字段有效（如关闭 default_http_proxy）
+fn merge_patch(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge_patch(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+#[tauri::command]
+pub fn get_settings() -> Settings {
+    current()
+}
+
+// 把补丁合并到 previous 上，得到校验过的完整设置
+fn patched_settings(previous: &Settings, patch: Value) -> Result<Settings, String> {
+    if !patch.is_object() {
+        return Err("settings patch must be a JSON object".to_string());
+    }
+    let mut merged = serde_json::to_value(previous).map_err(|e| e.to_string())?;
+    merge_patch(&mut merged, patch);
+    let mut settings: Settings =
+        serde_json::from_value(merged).map_err(|e| format!("无效的设置: {}", e))?;
+    settings.version = SETTINGS_VERSION;
+    settings.validate()?;
+    Ok(settings)
+}
+
+// 先应用到运行时再保存：任一步失败都按 settings -> previous 反向应用一次，恢复修改前的状态
+fn commit_settings(
+    previous: &Settings,
+    settings: &Settings,
+    mut apply: impl FnMut(&Settings, &Settings) -> Result<(), String>,
+    save: impl FnOnce(&Settings) -> Result<(), String>,
+) -> Result<(), String> {
+    let applied = apply(previous, settings).and_then(|_| save(settings));
+    if let Err(e) = applied {
+        if let Err(rollback) = apply(settings, previous) {
+            eprintln!(
+                "[Settings] Failed to restore previous settings: {}",
+                rollback
+            );
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+// 把 previous -> settings 的变化应用到运行中的各子系统；只处理有变化的字段，
+// 未涉及代理的修改不会覆盖 set_api_proxy / set_stream_proxy 在运行时设置的代理
+fn apply_runtime_changes(
+    previous: &Settings,
+    settings: &Settings,
+    http_client: &SharedReqwestClient,
+    follow_http: &FollowHttpClient,
+) -> Result<(), String> {
+    if settings.danmaku_reconnect != previous.danmaku_reconnect {
+        let reconnect = &settings.danmaku_reconnect;
+        danmaku_reconnect::apply_reconnect_policy(
+            reconnect.max_attempts,
+            reconnect.max_backoff_ms,
+        )?;
+    }
+    if settings.follow_notifications != previous.follow_notifications {
+        crate::platforms::follow_polling::set_follow_notifications(settings.follow_notifications);
+    }
+    if settings.offline_list_cache != previous.offline_list_cache {
+        crate::platforms::common::list_cache::set_list_cache_enabled(settings.offline_list_cache);
+    }
+    if settings.no_proxy != previous.no_proxy {
+        network::apply_no_proxy(settings.no_proxy);
+        http_client.rebuild()?;
+    }
+    if settings.api_proxy != previous.api_proxy {
+        network::apply_api_proxy(settings.api_proxy.clone());
+    }
+    if settings.stream_proxy != previous.stream_proxy {
+        network::apply_stream_proxy(settings.stream_proxy.clone());
+    }
+    if settings.no_proxy != previous.no_proxy || settings.api_proxy != previous.api_proxy {
+        follow_http.rebuild()?;
+    }
+    Ok(())
+}
+
+// 日志中的设置：代理地址可能带有 user:pass@，打印前脱敏
+fn masked_for_log(settings: &Settings) -> String {
+    let mut value = serde_json::to_value(settings).unwrap_or(Value::Null);
+    crate::diagnostics::mask_credentials(&mut value);
+    value.to_string()
+}
+
+// 只需传入要修改的字段，例如 {"default_quality": "auto"}；返回更新后的完整设置。
+// 先应用到运行时再写文件：任一步失败都恢复到修改前的状态，文件与运行时保持一致。
+// set_api_proxy 等运行时命令不写入设置文件，重启后以这里保存的值为准
+#[tauri::command]
+pub async fn update_settings(
+    patch: Value,
+    http_client: State<'_, SharedReqwestClient>,
+    follow_http: State<'_, FollowHttpClient>,
+) -> Result<Settings, String> {
+    let mut guard = SETTINGS.lock().unwrap_or_else(|e| e.into_inner());
+    let previous = guard.clone();
+    let settings = patched_settings(&previous, patch)?;
+    commit_settings(
+        &previous,
+        &settings,
+        |from, to| apply_runtime_changes(from, to, &http_client, &follow_http),
+        save_settings,
+    )?;
+    *guard = settings.clone();
+
+    println!("[Settings] Updated settings: {}", masked_for_log(&settings));
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn settings_file_lives_in_the_tauri_app_data_dir() {
+        use tauri::Manager;
+        let app = tauri::test::mock_app();
+        let expected = app.path().app_data_dir().unwrap().join(SETTINGS_FILE);
+        assert_eq!(settings_file_for(&app.config().identifier), Some(expected));
+    }
+
+    #[test]
+    fn old_files_get_defaults_for_missing_fields() {
+        let settings = parse_settings(
+            br#"{"version": 0, "no_proxy": true, "danmaku_reconnect": {"max_attempts": 3}, "removed_field": 1}"#,
+        )
+        .unwrap();
+        let defaults = Settings::default();
+        assert_eq!(settings.version, 0);
+        assert!(settings.no_proxy);
+        assert_eq!(settings.api_proxy, UpstreamProxy::Env);
+        assert_eq!(settings.default_quality, defaults.default_quality);
+        assert_eq!(settings.default_http_proxy, defaults.default_http_proxy);
+        assert_eq!(settings.danmaku_reconnect.max_attempts, 3);
+        assert_eq!(
+            settings.danmaku_reconnect.max_backoff_ms,
+            DEFAULT_MAX_BACKOFF_MS
+        );
+
+        assert_eq!(parse_settings(b"{}").unwrap(), defaults);
+        assert!(parse_settings(b"not json").is_err());
+        assert!(
+            parse_settings(br#"{"api_proxy": {"mode": "custom", "url": "http://[::1"}}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn patch_merges_fields_and_nulls_optional_ones() {
+        let previous = Settings::default();
+        let settings = patched_settings(
+            &previous,
+            json!({
+                "default_http_proxy": null,
+                "danmaku_reconnect": {"max_attempts": 2},
+                "api_proxy": {"mode": "custom", "url": "http://127.0.0.1:7890"}
+            }),
+        )
+        .unwrap();
+        assert_eq!(settings.default_http_proxy, None);
+        assert_eq!(settings.danmaku_reconnect.max_attempts, 2);
+        assert_eq!(
+            settings.danmaku_reconnect.max_backoff_ms,
+            previous.danmaku_reconnect.max_backoff_ms
+        );
+        assert_eq!(
+            settings.api_proxy,
+            UpstreamProxy::Custom("http://127.0.0.1:7890".to_string())
+        );
+        assert_eq!(settings.stream_proxy, previous.stream_proxy);
+
+        // null 只对可选字段有效
+        assert!(patched_settings(&previous, json!({"no_proxy": null})).is_err());
+        assert!(patched_settings(&previous, json!(["no_proxy"])).is_err());
+    }
+
+    #[test]
+    fn validate_rejects_bad_values() {
+        let reject = |patch: Value| patched_settings(&Settings::default(), patch).unwrap_err();
+        reject(json!({"api_proxy": {"mode": "custom", "url": "http://[::1"}}));
+        reject(json!({"stream_proxy": {"mode": "custom", "url": "http://[::1"}}));
+        reject(json!({"default_http_proxy": "http://[::1"}));
+        reject(json!({"danmaku_reconnect": {"max_backoff_ms": 0}}));
+        // 默认清晰度必须在所有平台上都能识别：斗鱼的 rate 编号不行
+        reject(json!({"default_quality": "4"}));
+        reject(json!({"default_quality": "8K"}));
+
+        let accept = |quality: &str| {
+            patched_settings(&Settings::default(), json!({"default_quality": quality})).unwrap()
+        };
+        assert_eq!(accept("auto").default_quality, "auto");
+        assert_eq!(accept("高清").default_quality, "高清");
+    }
+
+    #[test]
+    fn failed_save_rolls_runtime_back() {
+        let previous = Settings::default();
+        let settings = patched_settings(&previous, json!({"no_proxy": true})).unwrap();
+
+        let mut applied = Vec::new();
+        let result = commit_settings(
+            &previous,
+            &settings,
+            |from, to| {
+                applied.push((from.no_proxy, to.no_proxy));
+                Ok(())
+            },
+            |_| Err("disk full".to_string()),
+        );
+        assert_eq!(result, Err("disk full".to_string()));
+        assert_eq!(applied, vec![(false, true), (true, false)]);
+
+        let mut applied = Vec::new();
+        commit_settings(
+            &previous,
+            &settings,
+            |from, to| {
+                applied.push((from.no_proxy, to.no_proxy));
+                Ok(())
+            },
+            |_| Ok(()),
+        )
+        .unwrap();
+        assert_eq!(applied, vec![(false, true)]);
+    }
+}
@@ -0,0 +1,122 @@
+use std::fs;
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager};
+
+// macOS 窗口毛玻璃材质：偏好保存在配置目录，启动时读取，默认沿用 HudWindow
+const MATERIAL_PREF_FILE: &str = "window_material.txt";
+pub const DEFAULT_WINDOW_MATERIAL: &str = "HudWindow";
+
+// "None" 表示关闭毛玻璃效果；其余名称对应 NSVisualEffectMaterial 的非废弃变体
+const KNOWN_MATERIALS: &[&str] = &[
+    "None",
+    "Titlebar",
+    "Selection",
+    "Menu",
+    "Popover",
+    "Sidebar",
+    "HeaderView",
+    "Sheet",
+    "WindowBackground",
+    "HudWindow",
+    "FullScreenUI",
+    "Tooltip",
+    "ContentBackground",
+    "UnderWindowBackground",
+    "UnderPageBackground",
+];
+
+// 忽略大小写匹配材质名，返回规范写法
+fn canonical_material(name: &str) -> Option<&'static str> {
+    let name = name.trim();
+    KNOWN_MATERIALS
+        .iter()
+        .copied()
+        .find(|known| known.eq_ignore_ascii_case(name))
+}
+
+fn pref_file(app_handle: &AppHandle) -> Option<PathBuf> {
+    app_handle
+        .path()
+        .app_config_dir()
+        .ok()
+        .map(|dir| dir.join(MATERIAL_PREF_FILE))
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn load_material_preference(app_handle: &AppHandle) -> &'static str {
+    let saved = pref_file(app_handle).and_then(|path| fs::read_to_string(path).ok());
+    match saved {
+        Some(name) => canonical_material(&name).unwrap_or_else(|| {
+            eprintln!(
+                "[Window] Unknown saved window material '{}', falling back to {}",
+                name.trim(),
+                DEFAULT_WINDOW_MATERIAL
+            );
+            DEFAULT_WINDOW_MATERIAL
+        }),
+        None => DEFAULT_WINDOW_MATERIAL,
+    }
+}
+
+fn save_material_preference(app_handle: &AppHandle, material: &str) -> Result<(), String> {
+    let path = pref_file(app_handle).ok_or("无法定位配置目录".to_string())?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(&path, material).map_err(|e| format!("保存窗口材质失败: {}", e))
+}
+
+#[cfg(target_os = "macos")]
+pub fn apply_window_material(window: &tauri::WebviewWindow, material: &str) -> Result<(), String> {
+    use window_vibrancy::{apply_vibrancy, clear_vibrancy, NSVisualEffectMaterial};
+    let ns_material = match material {
+        "None" => {
+            return clear_vibrancy(window)
+                .map(|_| ())
+                .map_err(|e| format!("vibrancy error: {:?}", e));
+        }
+        "Titlebar" => NSVisualEffectMaterial::Titlebar,
+        "Selection" => NSVisualEffectMaterial::Selection,
+        "Menu" => NSVisualEffectMaterial::Menu,
+        "Popover" => NSVisualEffectMaterial::Popover,
+        "Sidebar" => NSVisualEffectMaterial::Sidebar,
+        "HeaderView" => NSVisualEffectMaterial::HeaderView,
+        "Sheet" => NSVisualEffectMaterial::Sheet,
+        "WindowBackground" => NSVisualEffectMaterial::WindowBackground,
+        "FullScreenUI" => NSVisualEffectMaterial::FullScreenUI,
+        "Tooltip" => NSVisualEffectMaterial::Tooltip,
+        "ContentBackground" => NSVisualEffectMaterial::ContentBackground,
+        "UnderWindowBackground" => NSVisualEffectMaterial::UnderWindowBackground,
+        "UnderPageBackground" => NSVisualEffectMaterial::UnderPageBackground,
+        _ => NSVisualEffectMaterial::HudWindow,
+    };
+    // 切换材质前先清掉旧的 effect view，避免叠加
+    let _ = clear_vibrancy(window);
+    apply_vibrancy(window, ns_material, None, None).map_err(|e| format!("vibrancy error: {:?}", e))
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn apply_window_material(
+    _window: &tauri::WebviewWindow,
+    _material: &str,
+) -> Result<(), String> {
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_window_material(app_handle: AppHandle, material: String) -> Result<(), String> {
+    let material = canonical_material(&material).ok_or_else(|| {
+        format!(
+            "Unknown window material '{}'. Supported: {}",
+            material,
+            KNOWN_MATERIALS.join(", ")
+        )
+    })?;
+    if let Some(window) = app_handle.get_webview_window("main") {
+        apply_window_material(&window, material)?;
+    }
+    save_material_preference(&app_handle, material)?;
+    println!("[Window] window material set to {}", material);
+    Ok(())
+}
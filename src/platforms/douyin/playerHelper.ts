@@ -83,7 +83,7 @@ export async function fetchAndPrepareDouyinStreamConfig(roomId: string, quality:
     let finalStreamUrl = sanitizedStreamUrl;
     if (streamAvailable && sanitizedStreamUrl && !sanitizedStreamUrl.startsWith('http://127.0.0.1')) {
       try {
-        await invoke('set_stream_url_cmd', { url: sanitizedStreamUrl });
+        await invoke('set_stream_url_cmd', { url: sanitizedStreamUrl, platform: 'douyin', roomId, quality });
         const proxyUrl = await invoke<string>('start_proxy');
         if (proxyUrl) {
           finalStreamUrl = proxyUrl;
@@ -82,8 +82,8 @@ export async function fetchDouyuStreamUrlRaw(roomId: string): Promise<string> {
     return Promise.reject('Room ID is required to fetch stream URL.');
   }
   try {
-    // Assuming get_stream_url_cmd returns a simple string URL
-    const url = await invoke<string>('get_stream_url_cmd', { roomId }); 
+    // get_stream_url_cmd 返回 { url, needs_proxy }
+    const { url } = await invoke<{ url: string; needs_proxy: boolean }>('get_stream_url_cmd', { roomId });
     if (!url) {
       console.error(`[Douyu API] fetchDouyuStreamUrlRaw: Received empty URL for room ${roomId}`);
       return Promise.reject('Empty stream URL received');
@@ -22,18 +22,20 @@ export async function getDouyuStreamConfig(
 ): Promise<{ streamUrl: string, streamType: string | undefined }> {
   let finalStreamUrl: string | null = null;
   let streamType: string | undefined = undefined;
+  let resolvedRate: number | null = null;
   const MAX_STREAM_FETCH_ATTEMPTS = 2;
 
   for (let attempt = 1; attempt <= MAX_STREAM_FETCH_ATTEMPTS; attempt++) {
     try {
-      const streamUrl = await invoke<string>('get_stream_url_with_quality_cmd', {
+      const resolved = await invoke<{ url: string; is_vertical: boolean; rate: number }>('get_stream_url_with_quality_cmd', {
         roomId: roomId,
         quality: quality,
         line: line ?? null,
       });
       
-      if (streamUrl) {
-        finalStreamUrl = enforceHttps(streamUrl);
+      if (resolved?.url) {
+        finalStreamUrl = enforceHttps(resolved.url);
+        resolvedRate = resolved.rate;
         streamType = 'flv';
         break;
       } else {
@@ -70,7 +72,7 @@ export async function getDouyuStreamConfig(
   }
 
   try {
-    await invoke('set_stream_url_cmd', { url: finalStreamUrl });
+    await invoke('set_stream_url_cmd', { url: finalStreamUrl, platform: 'douyu', roomId, quality, rate: resolvedRate });
     const proxyUrl = await invoke<string>('start_proxy');
     douyuProxyActive = true;
     return { streamUrl: proxyUrl, streamType };
@@ -24,7 +24,7 @@ export async function getHuyaStreamConfig(
         let finalUrl = sanitizedUrl;
         try {
           if (!sanitizedUrl.startsWith('http://127.0.0.1')) {
-            await invoke('set_stream_url_cmd', { url: sanitizedUrl });
+            await invoke('set_stream_url_cmd', { url: sanitizedUrl, platform: 'huya', roomId, quality });
             const proxyUrl = await invoke<string>('start_proxy');
             if (proxyUrl) {
               finalUrl = proxyUrl;